@@ -1,4 +1,6 @@
-use crate::{DataType, ScalarType};
+use std::rc::Rc;
+
+use crate::{DataType, ScalarType, StructDecl, StructMember};
 
 #[derive(
     Clone, Copy, Debug, PartialEq, Eq, Hash, strum::AsRefStr, strum::EnumIter, strum::EnumString,
@@ -17,7 +19,16 @@ pub enum BuiltinFn {
     Atanh,
     Atan2,
     AtomicAdd,
+    AtomicAnd,
+    AtomicCompareExchangeWeak,
+    AtomicExchange,
     AtomicLoad,
+    AtomicMax,
+    AtomicMin,
+    AtomicOr,
+    AtomicStore,
+    AtomicSub,
+    AtomicXor,
     Ceil,
     Clamp,
     Cos,
@@ -27,6 +38,7 @@ pub enum BuiltinFn {
     CountTrailingZeros,
     Cross,
     Degrees,
+    Determinant,
     Distance,
     Dot,
     Exp,
@@ -66,6 +78,7 @@ pub enum BuiltinFn {
     Step,
     Tan,
     Tanh,
+    Transpose,
     Trunc,
 }
 
@@ -102,8 +115,13 @@ impl BuiltinFn {
             All => Bool.into(),
             Any => Bool.into(),
             ArrayLength => U32.into(),
-            AtomicAdd => param(2)?,
-            AtomicLoad => param(1)?,
+            AtomicAdd | AtomicAnd | AtomicExchange | AtomicLoad | AtomicMax | AtomicMin
+            | AtomicOr | AtomicSub | AtomicXor => atomic_value_type(&param(1)?)?.into(),
+            AtomicCompareExchangeWeak => DataType::Struct(atomic_compare_exchange_result(
+                atomic_value_type(&param(1)?)?,
+            )),
+            // `atomicStore` has no return value, so it can only be called as a statement
+            AtomicStore => return None,
             Ceil => param(1)?,
             Clamp => param(1)?,
             Cos => param(1)?,
@@ -113,6 +131,7 @@ impl BuiltinFn {
             CountTrailingZeros => param(1)?,
             Cross => param(1)?,
             Degrees => param(1)?,
+            Determinant => param(1)?.as_scalar()?.into(),
             Distance => F32.into(),
             Dot => param(1)?.as_scalar()?.into(),
             ExtractBits => param(1)?,
@@ -152,9 +171,41 @@ impl BuiltinFn {
             Step => param(1)?,
             Tan => param(1)?,
             Tanh => param(1)?,
+            Transpose => match param(1)? {
+                DataType::Matrix(c, r, t) => DataType::Matrix(r, c, t),
+                _ => return None,
+            },
             Trunc => param(1)?,
         };
 
         Some(ret)
     }
 }
+
+/// Returns the type stored in the atomic pointed to by `ptr`, e.g. `u32` for
+/// `ptr<storage, atomic<u32>, read_write>`.
+fn atomic_value_type(ptr: &DataType) -> Option<ScalarType> {
+    let DataType::Ptr(view) = ptr else {
+        return None;
+    };
+
+    match view.inner.as_ref() {
+        DataType::Scalar(ScalarType::AU32) => Some(ScalarType::U32),
+        DataType::Scalar(ScalarType::AI32) => Some(ScalarType::I32),
+        _ => None,
+    }
+}
+
+/// Returns the result type of `atomicCompareExchangeWeak` for an atomic of type `ty`.
+///
+/// This is a predeclared struct which cannot be named in WGSL, so it never appears in the list of
+/// structs of a module.
+pub fn atomic_compare_exchange_result(ty: ScalarType) -> Rc<StructDecl> {
+    StructDecl::new(
+        format!("__atomic_compare_exchange_result_{ty}"),
+        vec![
+            StructMember::new(vec![], "old_value", ty.into()),
+            StructMember::new(vec![], "exchanged", ScalarType::Bool.into()),
+        ],
+    )
+}
@@ -141,18 +141,35 @@ pub enum BinOp {
 
 impl BinOp {
     /// Determines the return type of a binary operator given its operand types.
-    pub fn type_eval(&self, left: &DataType, #[allow(unused)] right: &DataType) -> DataType {
+    pub fn type_eval(&self, left: &DataType, right: &DataType) -> DataType {
         let left = if let DataType::Ref(view) = left {
             view.inner.as_ref()
         } else {
             left
         };
 
+        let right = if let DataType::Ref(view) = right {
+            view.inner.as_ref()
+        } else {
+            right
+        };
+
         match self {
+            // Multiplication involving a matrix follows the rules of linear algebra, so the result
+            // type depends on both operands.
+            BinOp::Times => match (left, right) {
+                (DataType::Matrix(_, r, t), DataType::Vector(..)) => DataType::Vector(*r, *t),
+                (DataType::Vector(..), DataType::Matrix(c, _, t)) => DataType::Vector(*c, *t),
+                (DataType::Matrix(_, r, t), DataType::Matrix(c, _, _)) => {
+                    DataType::Matrix(*c, *r, *t)
+                }
+                (DataType::Scalar(_), DataType::Matrix(..)) => right.clone(),
+                _ => left.clone(),
+            },
+
             // These operators produce the same result type as the first operand.
             | BinOp::Plus
             | BinOp::Minus
-            | BinOp::Times
             | BinOp::Divide
             | BinOp::Mod
             | BinOp::BitAnd
@@ -225,6 +242,7 @@ impl Postfix {
         match self {
             Postfix::Index(_) => match ty {
                 DataType::Vector(_, t) => DataType::Scalar(*t),
+                DataType::Matrix(_, r, t) => DataType::Vector(*r, *t),
                 DataType::Array(t, _) => (**t).clone(),
                 ty => panic!("index operator cannot be applied to type `{ty}`"),
             },
@@ -0,0 +1,398 @@
+//! Owned transformation of the AST.
+//!
+//! Each `fold_*` method of [`Fold`] takes a node by value and returns its replacement. The default
+//! implementations call the corresponding `walk_*` function in this module, which rebuilds the
+//! node from its folded children. Implementations override the methods for the nodes they want to
+//! rewrite, typically calling the `walk_*` function first so that children are rewritten before
+//! the node itself.
+
+use crate::*;
+
+pub trait Fold {
+    fn fold_module(&mut self, module: Module) -> Module {
+        walk_module(self, module)
+    }
+
+    fn fold_global_const_decl(&mut self, decl: GlobalConstDecl) -> GlobalConstDecl {
+        walk_global_const_decl(self, decl)
+    }
+
+    fn fold_global_override_decl(&mut self, decl: GlobalOverrideDecl) -> GlobalOverrideDecl {
+        walk_global_override_decl(self, decl)
+    }
+
+    fn fold_global_var_decl(&mut self, decl: GlobalVarDecl) -> GlobalVarDecl {
+        walk_global_var_decl(self, decl)
+    }
+
+    fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+        walk_fn_decl(self, decl)
+    }
+
+    /// Folds a list of statements forming a block, e.g. a function body or the body of a
+    /// compound statement.
+    fn fold_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        walk_block(self, block)
+    }
+
+    fn fold_stmt(&mut self, stmt: Statement) -> Statement {
+        walk_stmt(self, stmt)
+    }
+
+    fn fold_let_decl_stmt(&mut self, stmt: LetDeclStatement) -> LetDeclStatement {
+        walk_let_decl_stmt(self, stmt)
+    }
+
+    fn fold_var_decl_stmt(&mut self, stmt: VarDeclStatement) -> VarDeclStatement {
+        walk_var_decl_stmt(self, stmt)
+    }
+
+    fn fold_assignment_stmt(&mut self, stmt: AssignmentStatement) -> AssignmentStatement {
+        walk_assignment_stmt(self, stmt)
+    }
+
+    fn fold_assignment_lhs(&mut self, lhs: AssignmentLhs) -> AssignmentLhs {
+        walk_assignment_lhs(self, lhs)
+    }
+
+    fn fold_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
+        walk_lhs_expr(self, node)
+    }
+
+    fn fold_if_stmt(&mut self, stmt: IfStatement) -> IfStatement {
+        walk_if_stmt(self, stmt)
+    }
+
+    fn fold_else(&mut self, els: Else) -> Else {
+        walk_else(self, els)
+    }
+
+    fn fold_return_stmt(&mut self, stmt: ReturnStatement) -> ReturnStatement {
+        walk_return_stmt(self, stmt)
+    }
+
+    fn fold_loop_stmt(&mut self, stmt: LoopStatement) -> LoopStatement {
+        walk_loop_stmt(self, stmt)
+    }
+
+    fn fold_continuing_stmt(&mut self, stmt: ContinuingStatement) -> ContinuingStatement {
+        walk_continuing_stmt(self, stmt)
+    }
+
+    fn fold_while_stmt(&mut self, stmt: WhileStatement) -> WhileStatement {
+        walk_while_stmt(self, stmt)
+    }
+
+    fn fold_switch_stmt(&mut self, stmt: SwitchStatement) -> SwitchStatement {
+        walk_switch_stmt(self, stmt)
+    }
+
+    fn fold_switch_case(&mut self, case: SwitchCase) -> SwitchCase {
+        walk_switch_case(self, case)
+    }
+
+    fn fold_for_loop_stmt(&mut self, stmt: ForLoopStatement) -> ForLoopStatement {
+        walk_for_loop_stmt(self, stmt)
+    }
+
+    fn fold_for_loop_header(&mut self, header: ForLoopHeader) -> ForLoopHeader {
+        walk_for_loop_header(self, header)
+    }
+
+    fn fold_fn_call_stmt(&mut self, stmt: FnCallStatement) -> FnCallStatement {
+        walk_fn_call_stmt(self, stmt)
+    }
+
+    fn fold_const_assert_stmt(&mut self, stmt: ConstAssertStatement) -> ConstAssertStatement {
+        walk_const_assert_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        walk_expr(self, node)
+    }
+
+    fn fold_postfix(&mut self, postfix: Postfix) -> Postfix {
+        walk_postfix(self, postfix)
+    }
+}
+
+pub fn walk_module<F: Fold + ?Sized>(f: &mut F, module: Module) -> Module {
+    Module {
+        consts: module
+            .consts
+            .into_iter()
+            .map(|it| f.fold_global_const_decl(it))
+            .collect(),
+        overrides: module
+            .overrides
+            .into_iter()
+            .map(|it| f.fold_global_override_decl(it))
+            .collect(),
+        vars: module
+            .vars
+            .into_iter()
+            .map(|it| f.fold_global_var_decl(it))
+            .collect(),
+        const_asserts: module
+            .const_asserts
+            .into_iter()
+            .map(|it| f.fold_const_assert_stmt(it))
+            .collect(),
+        functions: module
+            .functions
+            .into_iter()
+            .map(|it| f.fold_fn_decl(it))
+            .collect(),
+        ..module
+    }
+}
+
+pub fn walk_global_const_decl<F: Fold + ?Sized>(
+    f: &mut F,
+    decl: GlobalConstDecl,
+) -> GlobalConstDecl {
+    GlobalConstDecl {
+        initializer: f.fold_expr(decl.initializer),
+        ..decl
+    }
+}
+
+pub fn walk_global_override_decl<F: Fold + ?Sized>(
+    f: &mut F,
+    decl: GlobalOverrideDecl,
+) -> GlobalOverrideDecl {
+    GlobalOverrideDecl {
+        initializer: decl.initializer.map(|it| f.fold_expr(it)),
+        ..decl
+    }
+}
+
+pub fn walk_global_var_decl<F: Fold + ?Sized>(f: &mut F, decl: GlobalVarDecl) -> GlobalVarDecl {
+    GlobalVarDecl {
+        initializer: decl.initializer.map(|it| f.fold_expr(it)),
+        ..decl
+    }
+}
+
+pub fn walk_fn_decl<F: Fold + ?Sized>(f: &mut F, decl: FnDecl) -> FnDecl {
+    FnDecl {
+        body: f.fold_block(decl.body),
+        ..decl
+    }
+}
+
+pub fn walk_block<F: Fold + ?Sized>(f: &mut F, block: Vec<Statement>) -> Vec<Statement> {
+    block.into_iter().map(|it| f.fold_stmt(it)).collect()
+}
+
+pub fn walk_stmt<F: Fold + ?Sized>(f: &mut F, stmt: Statement) -> Statement {
+    match stmt {
+        Statement::LetDecl(stmt) => f.fold_let_decl_stmt(stmt).into(),
+        Statement::VarDecl(stmt) => f.fold_var_decl_stmt(stmt).into(),
+        Statement::Assignment(stmt) => f.fold_assignment_stmt(stmt).into(),
+        Statement::Compound(stmts) => Statement::Compound(f.fold_block(stmts)),
+        Statement::If(stmt) => f.fold_if_stmt(stmt).into(),
+        Statement::Return(stmt) => f.fold_return_stmt(stmt).into(),
+        Statement::Loop(stmt) => f.fold_loop_stmt(stmt).into(),
+        Statement::Break => Statement::Break,
+        Statement::Continue => Statement::Continue,
+        Statement::Switch(stmt) => f.fold_switch_stmt(stmt).into(),
+        Statement::Fallthrough => Statement::Fallthrough,
+        Statement::ForLoop(stmt) => f.fold_for_loop_stmt(stmt).into(),
+        Statement::FnCall(stmt) => f.fold_fn_call_stmt(stmt).into(),
+        Statement::While(stmt) => f.fold_while_stmt(stmt).into(),
+        Statement::ConstAssert(stmt) => f.fold_const_assert_stmt(stmt).into(),
+    }
+}
+
+pub fn walk_let_decl_stmt<F: Fold + ?Sized>(f: &mut F, stmt: LetDeclStatement) -> LetDeclStatement {
+    LetDeclStatement {
+        initializer: f.fold_expr(stmt.initializer),
+        ..stmt
+    }
+}
+
+pub fn walk_var_decl_stmt<F: Fold + ?Sized>(f: &mut F, stmt: VarDeclStatement) -> VarDeclStatement {
+    VarDeclStatement {
+        initializer: stmt.initializer.map(|it| f.fold_expr(it)),
+        ..stmt
+    }
+}
+
+pub fn walk_assignment_stmt<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: AssignmentStatement,
+) -> AssignmentStatement {
+    AssignmentStatement {
+        lhs: f.fold_assignment_lhs(stmt.lhs),
+        rhs: f.fold_expr(stmt.rhs),
+        ..stmt
+    }
+}
+
+pub fn walk_assignment_lhs<F: Fold + ?Sized>(f: &mut F, lhs: AssignmentLhs) -> AssignmentLhs {
+    match lhs {
+        AssignmentLhs::Phony => AssignmentLhs::Phony,
+        AssignmentLhs::Expr(node) => AssignmentLhs::Expr(f.fold_lhs_expr(node)),
+    }
+}
+
+pub fn walk_lhs_expr<F: Fold + ?Sized>(f: &mut F, node: LhsExprNode) -> LhsExprNode {
+    let expr = match node.expr {
+        LhsExpr::Ident(ident) => LhsExpr::Ident(ident),
+        LhsExpr::Postfix(inner, postfix) => {
+            LhsExpr::Postfix(Box::new(f.fold_lhs_expr(*inner)), f.fold_postfix(postfix))
+        }
+        LhsExpr::Deref(inner) => LhsExpr::Deref(Box::new(f.fold_lhs_expr(*inner))),
+        LhsExpr::AddressOf(inner) => LhsExpr::AddressOf(Box::new(f.fold_lhs_expr(*inner))),
+    };
+
+    LhsExprNode { expr, ..node }
+}
+
+pub fn walk_if_stmt<F: Fold + ?Sized>(f: &mut F, stmt: IfStatement) -> IfStatement {
+    IfStatement {
+        condition: f.fold_expr(stmt.condition),
+        body: f.fold_block(stmt.body),
+        else_: stmt.else_.map(|els| Box::new(f.fold_else(*els))),
+    }
+}
+
+pub fn walk_else<F: Fold + ?Sized>(f: &mut F, els: Else) -> Else {
+    match els {
+        Else::If(stmt) => Else::If(f.fold_if_stmt(stmt)),
+        Else::Else(stmts) => Else::Else(f.fold_block(stmts)),
+    }
+}
+
+pub fn walk_return_stmt<F: Fold + ?Sized>(f: &mut F, stmt: ReturnStatement) -> ReturnStatement {
+    ReturnStatement {
+        value: stmt.value.map(|it| f.fold_expr(it)),
+    }
+}
+
+pub fn walk_loop_stmt<F: Fold + ?Sized>(f: &mut F, stmt: LoopStatement) -> LoopStatement {
+    LoopStatement {
+        body: f.fold_block(stmt.body),
+        continuing: stmt.continuing.map(|it| f.fold_continuing_stmt(it)),
+    }
+}
+
+pub fn walk_continuing_stmt<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: ContinuingStatement,
+) -> ContinuingStatement {
+    ContinuingStatement {
+        body: f.fold_block(stmt.body),
+        break_if: stmt.break_if.map(|it| f.fold_expr(it)),
+    }
+}
+
+pub fn walk_while_stmt<F: Fold + ?Sized>(f: &mut F, stmt: WhileStatement) -> WhileStatement {
+    WhileStatement {
+        condition: f.fold_expr(stmt.condition),
+        body: f.fold_block(stmt.body),
+    }
+}
+
+pub fn walk_switch_stmt<F: Fold + ?Sized>(f: &mut F, stmt: SwitchStatement) -> SwitchStatement {
+    SwitchStatement {
+        selector: f.fold_expr(stmt.selector),
+        cases: stmt
+            .cases
+            .into_iter()
+            .map(|it| f.fold_switch_case(it))
+            .collect(),
+        default_selectors: stmt
+            .default_selectors
+            .into_iter()
+            .map(|it| f.fold_expr(it))
+            .collect(),
+        default: f.fold_block(stmt.default),
+    }
+}
+
+pub fn walk_switch_case<F: Fold + ?Sized>(f: &mut F, case: SwitchCase) -> SwitchCase {
+    SwitchCase {
+        selectors: case
+            .selectors
+            .into_iter()
+            .map(|it| f.fold_expr(it))
+            .collect(),
+        body: f.fold_block(case.body),
+    }
+}
+
+pub fn walk_for_loop_stmt<F: Fold + ?Sized>(f: &mut F, stmt: ForLoopStatement) -> ForLoopStatement {
+    ForLoopStatement {
+        header: Box::new(f.fold_for_loop_header(*stmt.header)),
+        body: f.fold_block(stmt.body),
+    }
+}
+
+pub fn walk_for_loop_header<F: Fold + ?Sized>(f: &mut F, header: ForLoopHeader) -> ForLoopHeader {
+    ForLoopHeader {
+        init: header.init.map(|init| match init {
+            ForLoopInit::VarDecl(stmt) => ForLoopInit::VarDecl(f.fold_var_decl_stmt(stmt)),
+        }),
+        condition: header.condition.map(|it| f.fold_expr(it)),
+        update: header.update.map(|update| match update {
+            ForLoopUpdate::Assignment(stmt) => {
+                ForLoopUpdate::Assignment(f.fold_assignment_stmt(stmt))
+            }
+        }),
+    }
+}
+
+pub fn walk_fn_call_stmt<F: Fold + ?Sized>(f: &mut F, stmt: FnCallStatement) -> FnCallStatement {
+    FnCallStatement {
+        args: stmt.args.into_iter().map(|it| f.fold_expr(it)).collect(),
+        ..stmt
+    }
+}
+
+pub fn walk_const_assert_stmt<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: ConstAssertStatement,
+) -> ConstAssertStatement {
+    ConstAssertStatement {
+        expr: f.fold_expr(stmt.expr),
+    }
+}
+
+pub fn walk_expr<F: Fold + ?Sized>(f: &mut F, node: ExprNode) -> ExprNode {
+    let expr = match node.expr {
+        Expr::Lit(lit) => Expr::Lit(lit),
+        Expr::TypeCons(expr) => Expr::TypeCons(TypeConsExpr {
+            args: expr.args.into_iter().map(|it| f.fold_expr(it)).collect(),
+            ..expr
+        }),
+        Expr::Var(expr) => Expr::Var(expr),
+        Expr::Postfix(expr) => Expr::Postfix(PostfixExpr {
+            inner: Box::new(f.fold_expr(*expr.inner)),
+            postfix: f.fold_postfix(expr.postfix),
+        }),
+        Expr::UnOp(expr) => Expr::UnOp(UnOpExpr {
+            inner: Box::new(f.fold_expr(*expr.inner)),
+            ..expr
+        }),
+        Expr::BinOp(expr) => Expr::BinOp(BinOpExpr {
+            left: Box::new(f.fold_expr(*expr.left)),
+            right: Box::new(f.fold_expr(*expr.right)),
+            ..expr
+        }),
+        Expr::FnCall(expr) => Expr::FnCall(FnCallExpr {
+            args: expr.args.into_iter().map(|it| f.fold_expr(it)).collect(),
+            ..expr
+        }),
+    };
+
+    ExprNode { expr, ..node }
+}
+
+pub fn walk_postfix<F: Fold + ?Sized>(f: &mut F, postfix: Postfix) -> Postfix {
+    match postfix {
+        Postfix::Index(index) => Postfix::Index(Box::new(f.fold_expr(*index))),
+        Postfix::Member(member) => Postfix::Member(member),
+    }
+}
@@ -22,7 +22,18 @@ pub enum FnAttr {
     #[display(fmt = "workgroup_size({_0})")]
     LitWorkgroupSize(u32),
     #[display(fmt = "workgroup_size({_0})")]
-    VarWorkgroupSize(String)
+    VarWorkgroupSize(String),
+    /// `@workgroup_size` with explicit y (and optionally z) dimensions.
+    #[display(fmt = "workgroup_size({})", "CommaSeparated(_0)")]
+    MultiWorkgroupSize(Vec<WorkgroupSizeArg>),
+}
+
+/// A single dimension of a `@workgroup_size` attribute, which is either a literal or the name of
+/// a module scope constant.
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+pub enum WorkgroupSizeArg {
+    Lit(u32),
+    Var(String),
 }
 
 #[derive(Clone, Debug, Display, PartialEq, Eq)]
@@ -77,6 +88,22 @@ pub struct FnDecl {
     pub body: Vec<Statement>,
 }
 
+struct CommaSeparated<'a, T>(&'a [T]);
+
+impl<'a, T: Display> Display for CommaSeparated<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+
+            item.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
 struct InlineAttrs<'a, T>(&'a [T]);
 
 impl<'a, T: Display> Display for InlineAttrs<'a, T> {
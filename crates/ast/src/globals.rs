@@ -90,3 +90,24 @@ pub struct GlobalConstDecl {
     pub data_type: DataType,
     pub initializer: ExprNode,
 }
+
+#[derive(Clone, Debug, Display, PartialEq, Eq)]
+pub enum OverrideAttr {
+    #[display(fmt = "id({_0})")]
+    Id(u32),
+}
+
+/// A pipeline-overridable constant (`override` declaration).
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlobalOverrideDecl {
+    pub attrs: Vec<OverrideAttr>,
+    pub name: String,
+    pub data_type: DataType,
+    pub initializer: Option<ExprNode>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeAliasDecl {
+    pub name: String,
+    pub data_type: DataType,
+}
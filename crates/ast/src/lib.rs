@@ -1,4 +1,7 @@
+pub mod fold;
 pub mod types;
+pub mod visit;
+pub mod visit_mut;
 pub mod writer;
 
 mod builtins;
@@ -21,9 +24,14 @@ pub use types::{DataType, ScalarType};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Module {
+    /// Extensions enabled by `enable` directives.
+    pub enables: Vec<String>,
+    pub aliases: Vec<TypeAliasDecl>,
     pub structs: Vec<Rc<StructDecl>>,
     pub consts: Vec<GlobalConstDecl>,
+    pub overrides: Vec<GlobalOverrideDecl>,
     pub vars: Vec<GlobalVarDecl>,
+    pub const_asserts: Vec<ConstAssertStatement>,
     pub functions: Vec<FnDecl>,
 }
 
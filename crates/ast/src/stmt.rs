@@ -4,12 +4,14 @@ use derive_more::{Display, From};
 use indenter::indented;
 
 use crate::types::DataType;
-use crate::{ExprNode, Postfix};
+use crate::{BinOp, ExprNode, Postfix};
 
-#[derive(Clone, Debug, Display, PartialEq)]
-#[display(fmt = "let {ident} = {initializer}")]
+#[derive(Clone, Debug, PartialEq)]
 pub struct LetDeclStatement {
     pub ident: String,
+    /// Whether the declaration uses `const` rather than `let`.
+    pub is_const: bool,
+    pub data_type: Option<DataType>,
     pub initializer: ExprNode,
 }
 
@@ -17,6 +19,8 @@ impl LetDeclStatement {
     pub fn new(ident: impl Into<String>, initializer: impl Into<ExprNode>) -> Self {
         Self {
             ident: ident.into(),
+            is_const: false,
+            data_type: None,
             initializer: initializer.into(),
         }
     }
@@ -24,11 +28,32 @@ impl LetDeclStatement {
     pub fn inferred_type(&self) -> &DataType {
         // If the type of the initializer expression is a reference, then we infer the declaration
         // type to be the target type of the reference. Otherwise it is simply the type of the initializer.
-        if let DataType::Ref(view) = &self.initializer.data_type {
-            view.inner.as_ref()
-        } else {
-            &self.initializer.data_type
+        self.data_type.as_ref().unwrap_or_else(|| {
+            if let DataType::Ref(view) = &self.initializer.data_type {
+                view.inner.as_ref()
+            } else {
+                &self.initializer.data_type
+            }
+        })
+    }
+}
+
+impl Display for LetDeclStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let LetDeclStatement {
+            ident,
+            is_const,
+            data_type,
+            initializer,
+        } = self;
+
+        write!(f, "{} {ident}", if *is_const { "const" } else { "let" })?;
+
+        if let Some(data_type) = data_type {
+            write!(f, ": {data_type}")?;
         }
+
+        write!(f, " = {initializer}")
     }
 }
 
@@ -116,9 +141,9 @@ pub enum LhsExpr {
     Ident(String),
     #[display(fmt = "({_0}){_1}")]
     Postfix(Box<LhsExprNode>, Postfix),
-    #[display(fmt = "*(_0)")]
+    #[display(fmt = "*({_0})")]
     Deref(Box<LhsExprNode>),
-    #[display(fmt = "&(_0)")]
+    #[display(fmt = "&({_0})")]
     AddressOf(Box<LhsExprNode>),
 }
 
@@ -148,10 +173,12 @@ impl LhsExprNode {
             .as_memory_view()
             .expect("lhs expression must be a reference type");
 
-        let element_type = if let DataType::Array(ty, _) = mem_view.inner.as_ref() {
-            DataType::Ref(mem_view.clone_with_type(ty.as_ref().clone()))
-        } else {
-            panic!("expected array, got `{}`", mem_view.inner)
+        let element_type = match mem_view.inner.as_ref() {
+            DataType::Array(ty, _) => DataType::Ref(mem_view.clone_with_type(ty.as_ref().clone())),
+            DataType::Matrix(_, r, ty) => {
+                DataType::Ref(mem_view.clone_with_type(DataType::Vector(*r, *ty)))
+            }
+            ty => panic!("expected array or matrix, got `{ty}`"),
         };
 
         LhsExprNode {
@@ -206,6 +233,30 @@ pub enum AssignmentOp {
     Or,
     #[display(fmt = "^=")]
     Xor,
+    #[display(fmt = "<<=")]
+    LShift,
+    #[display(fmt = ">>=")]
+    RShift,
+}
+
+impl AssignmentOp {
+    /// Returns the binary operator applied by a compound assignment, or `None` for a simple
+    /// assignment.
+    pub fn bin_op(&self) -> Option<BinOp> {
+        match self {
+            AssignmentOp::Simple => None,
+            AssignmentOp::Plus => Some(BinOp::Plus),
+            AssignmentOp::Minus => Some(BinOp::Minus),
+            AssignmentOp::Times => Some(BinOp::Times),
+            AssignmentOp::Divide => Some(BinOp::Divide),
+            AssignmentOp::Mod => Some(BinOp::Mod),
+            AssignmentOp::And => Some(BinOp::BitAnd),
+            AssignmentOp::Or => Some(BinOp::BitOr),
+            AssignmentOp::Xor => Some(BinOp::BitXOr),
+            AssignmentOp::LShift => Some(BinOp::LShift),
+            AssignmentOp::RShift => Some(BinOp::RShift),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Display, PartialEq)]
@@ -337,11 +388,20 @@ impl Display for ReturnStatement {
 #[derive(Clone, Debug, PartialEq)]
 pub struct LoopStatement {
     pub body: Vec<Statement>,
+    pub continuing: Option<ContinuingStatement>,
 }
 
 impl LoopStatement {
     pub fn new(body: Vec<Statement>) -> Self {
-        Self { body }
+        Self {
+            body,
+            continuing: None,
+        }
+    }
+
+    pub fn with_continuing(mut self, continuing: impl Into<Option<ContinuingStatement>>) -> Self {
+        self.continuing = continuing.into();
+        self
     }
 }
 
@@ -353,6 +413,67 @@ impl Display for LoopStatement {
             writeln!(indented(f), "{}", stmt)?;
         }
 
+        if let Some(continuing) = &self.continuing {
+            writeln!(indented(f), "{continuing}")?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+/// The `continuing` block at the end of a loop, which is executed at the end of each iteration
+/// and may optionally end with a `break if` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContinuingStatement {
+    pub body: Vec<Statement>,
+    pub break_if: Option<ExprNode>,
+}
+
+impl ContinuingStatement {
+    pub fn new(body: Vec<Statement>, break_if: Option<ExprNode>) -> Self {
+        Self { body, break_if }
+    }
+}
+
+impl Display for ContinuingStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "continuing {{")?;
+
+        for stmt in &self.body {
+            writeln!(indented(f), "{}", stmt)?;
+        }
+
+        if let Some(condition) = &self.break_if {
+            writeln!(indented(f), "break if {condition};")?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhileStatement {
+    pub condition: ExprNode,
+    pub body: Vec<Statement>,
+}
+
+impl WhileStatement {
+    pub fn new(condition: impl Into<ExprNode>, body: Vec<Statement>) -> Self {
+        Self {
+            condition: condition.into(),
+            body,
+        }
+    }
+}
+
+impl Display for WhileStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "while ({}) {{", self.condition)?;
+
+        for stmt in &self.body {
+            writeln!(indented(f), "{}", stmt)?;
+        }
+
         write!(f, "}}")
     }
 }
@@ -361,6 +482,8 @@ impl Display for LoopStatement {
 pub struct SwitchStatement {
     pub selector: ExprNode,
     pub cases: Vec<SwitchCase>,
+    /// Selectors which share a clause with `default`, e.g. `3` in `case 3, default`.
+    pub default_selectors: Vec<ExprNode>,
     pub default: Vec<Statement>,
 }
 
@@ -369,6 +492,7 @@ impl SwitchStatement {
         Self {
             selector,
             cases,
+            default_selectors: vec![],
             default,
         }
     }
@@ -379,13 +503,24 @@ impl Display for SwitchStatement {
         let SwitchStatement {
             selector,
             cases,
+            default_selectors,
             default,
         } = self;
 
         writeln!(f, "switch ({selector}) {{")?;
 
-        for SwitchCase { selector, body } in cases {
-            writeln!(indented(f), "case {selector}: {{")?;
+        for SwitchCase { selectors, body } in cases {
+            write!(indented(f), "case ")?;
+
+            for (i, selector) in selectors.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+
+                write!(f, "{selector}")?;
+            }
+
+            writeln!(f, ": {{")?;
 
             for stmt in body {
                 writeln!(indented(&mut indented(f)), "{}", stmt)?;
@@ -394,7 +529,17 @@ impl Display for SwitchStatement {
             writeln!(indented(f), "}}")?;
         }
 
-        writeln!(indented(f), "default: {{")?;
+        if default_selectors.is_empty() {
+            writeln!(indented(f), "default: {{")?;
+        } else {
+            write!(indented(f), "case ")?;
+
+            for selector in default_selectors {
+                write!(f, "{selector}, ")?;
+            }
+
+            writeln!(f, "default: {{")?;
+        }
 
         for stmt in default {
             writeln!(indented(&mut indented(f)), "{}", stmt)?;
@@ -408,10 +553,19 @@ impl Display for SwitchStatement {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct SwitchCase {
-    pub selector: ExprNode,
+    pub selectors: Vec<ExprNode>,
     pub body: Vec<Statement>,
 }
 
+impl SwitchCase {
+    pub fn new(selector: impl Into<ExprNode>, body: Vec<Statement>) -> Self {
+        Self {
+            selectors: vec![selector.into()],
+            body,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ForLoopInit {
     VarDecl(VarDeclStatement),
@@ -507,6 +661,18 @@ impl Display for FnCallStatement {
     }
 }
 
+#[derive(Clone, Debug, Display, PartialEq)]
+#[display(fmt = "const_assert {expr}")]
+pub struct ConstAssertStatement {
+    pub expr: ExprNode,
+}
+
+impl ConstAssertStatement {
+    pub fn new(expr: impl Into<ExprNode>) -> Self {
+        Self { expr: expr.into() }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, From)]
 pub enum Statement {
     LetDecl(LetDeclStatement),
@@ -522,6 +688,8 @@ pub enum Statement {
     Fallthrough,
     ForLoop(ForLoopStatement),
     FnCall(FnCallStatement),
+    While(WhileStatement),
+    ConstAssert(ConstAssertStatement),
 }
 
 impl Statement {
@@ -560,6 +728,8 @@ impl Display for Statement {
             Statement::Switch(stmt) => stmt.fmt(f),
             Statement::ForLoop(stmt) => stmt.fmt(f),
             Statement::FnCall(stmt) => write!(f, "{stmt};"),
+            Statement::While(stmt) => stmt.fmt(f),
+            Statement::ConstAssert(stmt) => write!(f, "{stmt};"),
         }
     }
 }
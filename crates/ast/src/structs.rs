@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::rc::Rc;
 
@@ -33,13 +34,24 @@ impl StructMember {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct StructDecl {
     pub name: String,
     pub members: Vec<Rc<StructMember>>,
     accessors: HashMap<DataType, Vec<Rc<StructMember>>>,
 }
 
+impl Debug for StructDecl {
+    // The accessors are derived from the members and stored in hash maps, so they are left out to
+    // keep the output deterministic
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StructDecl")
+            .field("name", &self.name)
+            .field("members", &self.members)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Hash for StructDecl {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.name.hash(state);
@@ -103,26 +115,40 @@ fn collect_struct_accessors(
         map.entry(ty.clone()).or_default().insert(member.clone());
     }
 
-    for member in members {
-        insert(&mut accessors, &member.data_type, member);
-
-        match &member.data_type {
+    /// Inserts the types which are accessible from a value of type `ty` within `member`.
+    fn insert_accessible(
+        map: &mut HashMap<DataType, HashSet<Rc<StructMember>>>,
+        ty: &DataType,
+        member: &Rc<StructMember>,
+    ) {
+        match ty {
             DataType::Scalar(_) => {}
             DataType::Vector(n, ty) => {
                 // Access to component type
-                insert(&mut accessors, &DataType::Scalar(*ty), member);
+                insert(map, &DataType::Scalar(*ty), member);
 
                 // Access to subvectors via swizzling
                 for i in 2..*n {
-                    insert(&mut accessors, &DataType::Vector(i, *ty), member);
+                    insert(map, &DataType::Vector(i, *ty), member);
+                }
+            }
+            DataType::Matrix(_, r, ty) => {
+                // Access to column vectors via indexing, and to their components
+                insert(map, &DataType::Vector(*r, *ty), member);
+                insert(map, &DataType::Scalar(*ty), member);
+
+                for i in 2..*r {
+                    insert(map, &DataType::Vector(i, *ty), member);
                 }
             }
-            DataType::Array(_, _) => {
-                // TODO
+            DataType::Array(element_type, _) => {
+                // Access to elements via indexing, and to anything accessible from them
+                insert(map, element_type, member);
+                insert_accessible(map, element_type, member);
             }
             DataType::Struct(decl) => {
                 for ty in decl.accessible_types() {
-                    insert(&mut accessors, ty, member);
+                    insert(map, ty, member);
                 }
             }
             DataType::Ptr(_) => unreachable!("pointers are not storable"),
@@ -130,6 +156,11 @@ fn collect_struct_accessors(
         }
     }
 
+    for member in members {
+        insert(&mut accessors, &member.data_type, member);
+        insert_accessible(&mut accessors, &member.data_type, member);
+    }
+
     // Convert the sets into vectors
     // We use vectors for more efficient random selection later on
     accessors
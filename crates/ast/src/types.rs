@@ -3,7 +3,7 @@ use std::rc::Rc;
 
 use derive_more::Display;
 
-use crate::{AccessMode, StorageClass, StructDecl};
+use crate::{AccessMode, StorageClass, StructDecl, StructMember};
 
 #[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
 pub enum ScalarType {
@@ -59,6 +59,8 @@ impl Display for MemoryViewType {
 pub enum DataType {
     Scalar(ScalarType),
     Vector(u8, ScalarType),
+    /// Matrix with the given number of columns and rows (in that order).
+    Matrix(u8, u8, ScalarType),
     Array(Rc<DataType>, Option<u32>),
     Struct(Rc<StructDecl>),
     Ptr(MemoryViewType),
@@ -66,19 +68,37 @@ pub enum DataType {
 }
 
 impl DataType {
-
-    pub fn size_bytes(&self) -> usize {
-      match self {
-        // all currently supported scalars are four bytes
-        DataType::Scalar(_) => 4,
-        _ => unimplemented!()
-      }
-    }
-
     pub fn array(element_type: impl Into<DataType>, size: impl Into<Option<u32>>) -> DataType {
         DataType::Array(Rc::new(element_type.into()), size.into())
     }
 
+    /// Replaces the runtime-sized array in this type, which may either be the type itself or the
+    /// last member of a struct, with a fixed-size array of `len` elements.
+    ///
+    /// Returns `None` if the type does not contain a runtime-sized array.
+    pub fn with_runtime_array_len(&self, len: u32) -> Option<DataType> {
+        match self {
+            DataType::Array(element_type, None) => {
+                Some(DataType::Array(element_type.clone(), Some(len)))
+            }
+            DataType::Struct(decl) => {
+                let (last, init) = decl.members.split_last()?;
+                let data_type = last.data_type.with_runtime_array_len(len)?;
+                let mut members = init.to_vec();
+                members.push(StructMember::new(
+                    last.attrs.clone(),
+                    last.name.clone(),
+                    data_type,
+                ));
+                Some(DataType::Struct(StructDecl::new(
+                    decl.name.clone(),
+                    members,
+                )))
+            }
+            _ => None,
+        }
+    }
+
     pub fn map(&self, scalar: ScalarType) -> DataType {
         match self {
             DataType::Scalar(_) => DataType::Scalar(scalar),
@@ -91,6 +111,7 @@ impl DataType {
         match self {
             DataType::Scalar(ty) => Some(*ty),
             DataType::Vector(_, ty) => Some(*ty),
+            DataType::Matrix(_, _, ty) => Some(*ty),
             DataType::Ref(view) => view.inner.as_scalar(),
             _ => None,
         }
@@ -131,6 +152,14 @@ impl DataType {
         matches!(self, Self::Vector(..))
     }
 
+    /// Returns `true` if the data type is [`Matrix`].
+    ///
+    /// [`Matrix`]: DataType::Matrix
+    #[must_use]
+    pub fn is_matrix(&self) -> bool {
+        matches!(self, Self::Matrix(..))
+    }
+
     /// Returns `true` if the data type is a scalar or vector of integers.
     pub fn is_integer(&self) -> bool {
         matches!(self.as_scalar(), Some(ScalarType::I32 | ScalarType::U32))
@@ -142,6 +171,12 @@ impl fmt::Debug for DataType {
         match self {
             Self::Scalar(arg0) => f.debug_tuple("Scalar").field(arg0).finish(),
             Self::Vector(arg0, arg1) => f.debug_tuple("Vector").field(arg0).field(arg1).finish(),
+            Self::Matrix(arg0, arg1, arg2) => f
+                .debug_tuple("Matrix")
+                .field(arg0)
+                .field(arg1)
+                .field(arg2)
+                .finish(),
             Self::Array(arg0, arg1) => f.debug_tuple("Array").field(arg0).field(arg1).finish(),
             Self::Struct(arg0) => f.debug_tuple("Struct").field(&arg0.name).finish(),
             Self::Ptr(arg0) => f.debug_tuple("Ptr").field(arg0).finish(),
@@ -155,6 +190,7 @@ impl Display for DataType {
         match self {
             DataType::Scalar(t) => write!(f, "{}", t),
             DataType::Vector(n, t) => write!(f, "vec{}<{}>", n, t),
+            DataType::Matrix(c, r, t) => write!(f, "mat{}x{}<{}>", c, r, t),
             DataType::Array(inner, n) => {
                 write!(f, "array<{inner}")?;
                 if let Some(n) = n {
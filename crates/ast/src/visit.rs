@@ -0,0 +1,348 @@
+//! Read-only traversal over the AST.
+//!
+//! Each `visit_*` method of [`Visit`] defaults to calling the corresponding `walk_*` function in
+//! this module, which visits the children of the node. Implementations override the methods for
+//! the nodes they care about, and call the `walk_*` function to continue into the children.
+
+use crate::*;
+
+pub trait Visit {
+    fn visit_module(&mut self, module: &Module) {
+        walk_module(self, module)
+    }
+
+    fn visit_global_const_decl(&mut self, decl: &GlobalConstDecl) {
+        walk_global_const_decl(self, decl)
+    }
+
+    fn visit_global_override_decl(&mut self, decl: &GlobalOverrideDecl) {
+        walk_global_override_decl(self, decl)
+    }
+
+    fn visit_global_var_decl(&mut self, decl: &GlobalVarDecl) {
+        walk_global_var_decl(self, decl)
+    }
+
+    fn visit_fn_decl(&mut self, decl: &FnDecl) {
+        walk_fn_decl(self, decl)
+    }
+
+    /// Visits a list of statements forming a block, e.g. a function body or the body of a
+    /// compound statement.
+    fn visit_block(&mut self, block: &[Statement]) {
+        walk_block(self, block)
+    }
+
+    fn visit_stmt(&mut self, stmt: &Statement) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_let_decl_stmt(&mut self, stmt: &LetDeclStatement) {
+        walk_let_decl_stmt(self, stmt)
+    }
+
+    fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStatement) {
+        walk_var_decl_stmt(self, stmt)
+    }
+
+    fn visit_assignment_stmt(&mut self, stmt: &AssignmentStatement) {
+        walk_assignment_stmt(self, stmt)
+    }
+
+    fn visit_assignment_lhs(&mut self, lhs: &AssignmentLhs) {
+        walk_assignment_lhs(self, lhs)
+    }
+
+    fn visit_lhs_expr(&mut self, node: &LhsExprNode) {
+        walk_lhs_expr(self, node)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStatement) {
+        walk_if_stmt(self, stmt)
+    }
+
+    fn visit_else(&mut self, els: &Else) {
+        walk_else(self, els)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStatement) {
+        walk_return_stmt(self, stmt)
+    }
+
+    fn visit_loop_stmt(&mut self, stmt: &LoopStatement) {
+        walk_loop_stmt(self, stmt)
+    }
+
+    fn visit_continuing_stmt(&mut self, stmt: &ContinuingStatement) {
+        walk_continuing_stmt(self, stmt)
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStatement) {
+        walk_while_stmt(self, stmt)
+    }
+
+    fn visit_switch_stmt(&mut self, stmt: &SwitchStatement) {
+        walk_switch_stmt(self, stmt)
+    }
+
+    fn visit_switch_case(&mut self, case: &SwitchCase) {
+        walk_switch_case(self, case)
+    }
+
+    fn visit_for_loop_stmt(&mut self, stmt: &ForLoopStatement) {
+        walk_for_loop_stmt(self, stmt)
+    }
+
+    fn visit_for_loop_header(&mut self, header: &ForLoopHeader) {
+        walk_for_loop_header(self, header)
+    }
+
+    fn visit_fn_call_stmt(&mut self, stmt: &FnCallStatement) {
+        walk_fn_call_stmt(self, stmt)
+    }
+
+    fn visit_const_assert_stmt(&mut self, stmt: &ConstAssertStatement) {
+        walk_const_assert_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, node: &ExprNode) {
+        walk_expr(self, node)
+    }
+
+    fn visit_postfix(&mut self, postfix: &Postfix) {
+        walk_postfix(self, postfix)
+    }
+}
+
+pub fn walk_module<V: Visit + ?Sized>(v: &mut V, module: &Module) {
+    for decl in &module.consts {
+        v.visit_global_const_decl(decl);
+    }
+
+    for decl in &module.overrides {
+        v.visit_global_override_decl(decl);
+    }
+
+    for decl in &module.vars {
+        v.visit_global_var_decl(decl);
+    }
+
+    for stmt in &module.const_asserts {
+        v.visit_const_assert_stmt(stmt);
+    }
+
+    for decl in &module.functions {
+        v.visit_fn_decl(decl);
+    }
+}
+
+pub fn walk_global_const_decl<V: Visit + ?Sized>(v: &mut V, decl: &GlobalConstDecl) {
+    v.visit_expr(&decl.initializer);
+}
+
+pub fn walk_global_override_decl<V: Visit + ?Sized>(v: &mut V, decl: &GlobalOverrideDecl) {
+    if let Some(initializer) = &decl.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_global_var_decl<V: Visit + ?Sized>(v: &mut V, decl: &GlobalVarDecl) {
+    if let Some(initializer) = &decl.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_fn_decl<V: Visit + ?Sized>(v: &mut V, decl: &FnDecl) {
+    v.visit_block(&decl.body);
+}
+
+pub fn walk_block<V: Visit + ?Sized>(v: &mut V, block: &[Statement]) {
+    for stmt in block {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::LetDecl(stmt) => v.visit_let_decl_stmt(stmt),
+        Statement::VarDecl(stmt) => v.visit_var_decl_stmt(stmt),
+        Statement::Assignment(stmt) => v.visit_assignment_stmt(stmt),
+        Statement::Compound(stmts) => v.visit_block(stmts),
+        Statement::If(stmt) => v.visit_if_stmt(stmt),
+        Statement::Return(stmt) => v.visit_return_stmt(stmt),
+        Statement::Loop(stmt) => v.visit_loop_stmt(stmt),
+        Statement::Break => {}
+        Statement::Continue => {}
+        Statement::Switch(stmt) => v.visit_switch_stmt(stmt),
+        Statement::Fallthrough => {}
+        Statement::ForLoop(stmt) => v.visit_for_loop_stmt(stmt),
+        Statement::FnCall(stmt) => v.visit_fn_call_stmt(stmt),
+        Statement::While(stmt) => v.visit_while_stmt(stmt),
+        Statement::ConstAssert(stmt) => v.visit_const_assert_stmt(stmt),
+    }
+}
+
+pub fn walk_let_decl_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &LetDeclStatement) {
+    v.visit_expr(&stmt.initializer);
+}
+
+pub fn walk_var_decl_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &VarDeclStatement) {
+    if let Some(initializer) = &stmt.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_assignment_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &AssignmentStatement) {
+    v.visit_assignment_lhs(&stmt.lhs);
+    v.visit_expr(&stmt.rhs);
+}
+
+pub fn walk_assignment_lhs<V: Visit + ?Sized>(v: &mut V, lhs: &AssignmentLhs) {
+    match lhs {
+        AssignmentLhs::Phony => {}
+        AssignmentLhs::Expr(node) => v.visit_lhs_expr(node),
+    }
+}
+
+pub fn walk_lhs_expr<V: Visit + ?Sized>(v: &mut V, node: &LhsExprNode) {
+    match &node.expr {
+        LhsExpr::Ident(_) => {}
+        LhsExpr::Postfix(inner, postfix) => {
+            v.visit_lhs_expr(inner);
+            v.visit_postfix(postfix);
+        }
+        LhsExpr::Deref(inner) => v.visit_lhs_expr(inner),
+        LhsExpr::AddressOf(inner) => v.visit_lhs_expr(inner),
+    }
+}
+
+pub fn walk_if_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &IfStatement) {
+    v.visit_expr(&stmt.condition);
+    v.visit_block(&stmt.body);
+
+    if let Some(els) = &stmt.else_ {
+        v.visit_else(els);
+    }
+}
+
+pub fn walk_else<V: Visit + ?Sized>(v: &mut V, els: &Else) {
+    match els {
+        Else::If(stmt) => v.visit_if_stmt(stmt),
+        Else::Else(stmts) => v.visit_block(stmts),
+    }
+}
+
+pub fn walk_return_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &ReturnStatement) {
+    if let Some(value) = &stmt.value {
+        v.visit_expr(value);
+    }
+}
+
+pub fn walk_loop_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &LoopStatement) {
+    v.visit_block(&stmt.body);
+
+    if let Some(continuing) = &stmt.continuing {
+        v.visit_continuing_stmt(continuing);
+    }
+}
+
+pub fn walk_continuing_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &ContinuingStatement) {
+    v.visit_block(&stmt.body);
+
+    if let Some(break_if) = &stmt.break_if {
+        v.visit_expr(break_if);
+    }
+}
+
+pub fn walk_while_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &WhileStatement) {
+    v.visit_expr(&stmt.condition);
+    v.visit_block(&stmt.body);
+}
+
+pub fn walk_switch_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &SwitchStatement) {
+    v.visit_expr(&stmt.selector);
+
+    for case in &stmt.cases {
+        v.visit_switch_case(case);
+    }
+
+    for selector in &stmt.default_selectors {
+        v.visit_expr(selector);
+    }
+
+    v.visit_block(&stmt.default);
+}
+
+pub fn walk_switch_case<V: Visit + ?Sized>(v: &mut V, case: &SwitchCase) {
+    for selector in &case.selectors {
+        v.visit_expr(selector);
+    }
+
+    v.visit_block(&case.body);
+}
+
+pub fn walk_for_loop_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &ForLoopStatement) {
+    v.visit_for_loop_header(&stmt.header);
+    v.visit_block(&stmt.body);
+}
+
+pub fn walk_for_loop_header<V: Visit + ?Sized>(v: &mut V, header: &ForLoopHeader) {
+    if let Some(init) = &header.init {
+        match init {
+            ForLoopInit::VarDecl(stmt) => v.visit_var_decl_stmt(stmt),
+        }
+    }
+
+    if let Some(condition) = &header.condition {
+        v.visit_expr(condition);
+    }
+
+    if let Some(update) = &header.update {
+        match update {
+            ForLoopUpdate::Assignment(stmt) => v.visit_assignment_stmt(stmt),
+        }
+    }
+}
+
+pub fn walk_fn_call_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &FnCallStatement) {
+    for arg in &stmt.args {
+        v.visit_expr(arg);
+    }
+}
+
+pub fn walk_const_assert_stmt<V: Visit + ?Sized>(v: &mut V, stmt: &ConstAssertStatement) {
+    v.visit_expr(&stmt.expr);
+}
+
+pub fn walk_expr<V: Visit + ?Sized>(v: &mut V, node: &ExprNode) {
+    match &node.expr {
+        Expr::Lit(_) => {}
+        Expr::TypeCons(expr) => {
+            for arg in &expr.args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::Var(_) => {}
+        Expr::Postfix(expr) => {
+            v.visit_expr(&expr.inner);
+            v.visit_postfix(&expr.postfix);
+        }
+        Expr::UnOp(expr) => v.visit_expr(&expr.inner),
+        Expr::BinOp(expr) => {
+            v.visit_expr(&expr.left);
+            v.visit_expr(&expr.right);
+        }
+        Expr::FnCall(expr) => {
+            for arg in &expr.args {
+                v.visit_expr(arg);
+            }
+        }
+    }
+}
+
+pub fn walk_postfix<V: Visit + ?Sized>(v: &mut V, postfix: &Postfix) {
+    match postfix {
+        Postfix::Index(index) => v.visit_expr(index),
+        Postfix::Member(_) => {}
+    }
+}
@@ -0,0 +1,348 @@
+//! In-place mutable traversal over the AST.
+//!
+//! This mirrors [`crate::visit`], but each node is passed by mutable reference so that it can be
+//! modified while walking the tree. Blocks are passed as `&mut Vec<Statement>` so that statements
+//! can be inserted or removed.
+
+use crate::*;
+
+pub trait VisitMut {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module(self, module)
+    }
+
+    fn visit_global_const_decl_mut(&mut self, decl: &mut GlobalConstDecl) {
+        walk_global_const_decl(self, decl)
+    }
+
+    fn visit_global_override_decl_mut(&mut self, decl: &mut GlobalOverrideDecl) {
+        walk_global_override_decl(self, decl)
+    }
+
+    fn visit_global_var_decl_mut(&mut self, decl: &mut GlobalVarDecl) {
+        walk_global_var_decl(self, decl)
+    }
+
+    fn visit_fn_decl_mut(&mut self, decl: &mut FnDecl) {
+        walk_fn_decl(self, decl)
+    }
+
+    /// Visits a list of statements forming a block, e.g. a function body or the body of a
+    /// compound statement.
+    fn visit_block_mut(&mut self, block: &mut Vec<Statement>) {
+        walk_block(self, block)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_let_decl_stmt_mut(&mut self, stmt: &mut LetDeclStatement) {
+        walk_let_decl_stmt(self, stmt)
+    }
+
+    fn visit_var_decl_stmt_mut(&mut self, stmt: &mut VarDeclStatement) {
+        walk_var_decl_stmt(self, stmt)
+    }
+
+    fn visit_assignment_stmt_mut(&mut self, stmt: &mut AssignmentStatement) {
+        walk_assignment_stmt(self, stmt)
+    }
+
+    fn visit_assignment_lhs_mut(&mut self, lhs: &mut AssignmentLhs) {
+        walk_assignment_lhs(self, lhs)
+    }
+
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        walk_lhs_expr(self, node)
+    }
+
+    fn visit_if_stmt_mut(&mut self, stmt: &mut IfStatement) {
+        walk_if_stmt(self, stmt)
+    }
+
+    fn visit_else_mut(&mut self, els: &mut Else) {
+        walk_else(self, els)
+    }
+
+    fn visit_return_stmt_mut(&mut self, stmt: &mut ReturnStatement) {
+        walk_return_stmt(self, stmt)
+    }
+
+    fn visit_loop_stmt_mut(&mut self, stmt: &mut LoopStatement) {
+        walk_loop_stmt(self, stmt)
+    }
+
+    fn visit_continuing_stmt_mut(&mut self, stmt: &mut ContinuingStatement) {
+        walk_continuing_stmt(self, stmt)
+    }
+
+    fn visit_while_stmt_mut(&mut self, stmt: &mut WhileStatement) {
+        walk_while_stmt(self, stmt)
+    }
+
+    fn visit_switch_stmt_mut(&mut self, stmt: &mut SwitchStatement) {
+        walk_switch_stmt(self, stmt)
+    }
+
+    fn visit_switch_case_mut(&mut self, case: &mut SwitchCase) {
+        walk_switch_case(self, case)
+    }
+
+    fn visit_for_loop_stmt_mut(&mut self, stmt: &mut ForLoopStatement) {
+        walk_for_loop_stmt(self, stmt)
+    }
+
+    fn visit_for_loop_header_mut(&mut self, header: &mut ForLoopHeader) {
+        walk_for_loop_header(self, header)
+    }
+
+    fn visit_fn_call_stmt_mut(&mut self, stmt: &mut FnCallStatement) {
+        walk_fn_call_stmt(self, stmt)
+    }
+
+    fn visit_const_assert_stmt_mut(&mut self, stmt: &mut ConstAssertStatement) {
+        walk_const_assert_stmt(self, stmt)
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        walk_expr(self, node)
+    }
+
+    fn visit_postfix_mut(&mut self, postfix: &mut Postfix) {
+        walk_postfix(self, postfix)
+    }
+}
+
+pub fn walk_module<V: VisitMut + ?Sized>(v: &mut V, module: &mut Module) {
+    for decl in &mut module.consts {
+        v.visit_global_const_decl_mut(decl);
+    }
+
+    for decl in &mut module.overrides {
+        v.visit_global_override_decl_mut(decl);
+    }
+
+    for decl in &mut module.vars {
+        v.visit_global_var_decl_mut(decl);
+    }
+
+    for stmt in &mut module.const_asserts {
+        v.visit_const_assert_stmt_mut(stmt);
+    }
+
+    for decl in &mut module.functions {
+        v.visit_fn_decl_mut(decl);
+    }
+}
+
+pub fn walk_global_const_decl<V: VisitMut + ?Sized>(v: &mut V, decl: &mut GlobalConstDecl) {
+    v.visit_expr_mut(&mut decl.initializer);
+}
+
+pub fn walk_global_override_decl<V: VisitMut + ?Sized>(v: &mut V, decl: &mut GlobalOverrideDecl) {
+    if let Some(initializer) = &mut decl.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_global_var_decl<V: VisitMut + ?Sized>(v: &mut V, decl: &mut GlobalVarDecl) {
+    if let Some(initializer) = &mut decl.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_fn_decl<V: VisitMut + ?Sized>(v: &mut V, decl: &mut FnDecl) {
+    v.visit_block_mut(&mut decl.body);
+}
+
+pub fn walk_block<V: VisitMut + ?Sized>(v: &mut V, block: &mut Vec<Statement>) {
+    for stmt in block {
+        v.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut Statement) {
+    match stmt {
+        Statement::LetDecl(stmt) => v.visit_let_decl_stmt_mut(stmt),
+        Statement::VarDecl(stmt) => v.visit_var_decl_stmt_mut(stmt),
+        Statement::Assignment(stmt) => v.visit_assignment_stmt_mut(stmt),
+        Statement::Compound(stmts) => v.visit_block_mut(stmts),
+        Statement::If(stmt) => v.visit_if_stmt_mut(stmt),
+        Statement::Return(stmt) => v.visit_return_stmt_mut(stmt),
+        Statement::Loop(stmt) => v.visit_loop_stmt_mut(stmt),
+        Statement::Break => {}
+        Statement::Continue => {}
+        Statement::Switch(stmt) => v.visit_switch_stmt_mut(stmt),
+        Statement::Fallthrough => {}
+        Statement::ForLoop(stmt) => v.visit_for_loop_stmt_mut(stmt),
+        Statement::FnCall(stmt) => v.visit_fn_call_stmt_mut(stmt),
+        Statement::While(stmt) => v.visit_while_stmt_mut(stmt),
+        Statement::ConstAssert(stmt) => v.visit_const_assert_stmt_mut(stmt),
+    }
+}
+
+pub fn walk_let_decl_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut LetDeclStatement) {
+    v.visit_expr_mut(&mut stmt.initializer);
+}
+
+pub fn walk_var_decl_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut VarDeclStatement) {
+    if let Some(initializer) = &mut stmt.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_assignment_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut AssignmentStatement) {
+    v.visit_assignment_lhs_mut(&mut stmt.lhs);
+    v.visit_expr_mut(&mut stmt.rhs);
+}
+
+pub fn walk_assignment_lhs<V: VisitMut + ?Sized>(v: &mut V, lhs: &mut AssignmentLhs) {
+    match lhs {
+        AssignmentLhs::Phony => {}
+        AssignmentLhs::Expr(node) => v.visit_lhs_expr_mut(node),
+    }
+}
+
+pub fn walk_lhs_expr<V: VisitMut + ?Sized>(v: &mut V, node: &mut LhsExprNode) {
+    match &mut node.expr {
+        LhsExpr::Ident(_) => {}
+        LhsExpr::Postfix(inner, postfix) => {
+            v.visit_lhs_expr_mut(inner);
+            v.visit_postfix_mut(postfix);
+        }
+        LhsExpr::Deref(inner) => v.visit_lhs_expr_mut(inner),
+        LhsExpr::AddressOf(inner) => v.visit_lhs_expr_mut(inner),
+    }
+}
+
+pub fn walk_if_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut IfStatement) {
+    v.visit_expr_mut(&mut stmt.condition);
+    v.visit_block_mut(&mut stmt.body);
+
+    if let Some(els) = &mut stmt.else_ {
+        v.visit_else_mut(els);
+    }
+}
+
+pub fn walk_else<V: VisitMut + ?Sized>(v: &mut V, els: &mut Else) {
+    match els {
+        Else::If(stmt) => v.visit_if_stmt_mut(stmt),
+        Else::Else(stmts) => v.visit_block_mut(stmts),
+    }
+}
+
+pub fn walk_return_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut ReturnStatement) {
+    if let Some(value) = &mut stmt.value {
+        v.visit_expr_mut(value);
+    }
+}
+
+pub fn walk_loop_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut LoopStatement) {
+    v.visit_block_mut(&mut stmt.body);
+
+    if let Some(continuing) = &mut stmt.continuing {
+        v.visit_continuing_stmt_mut(continuing);
+    }
+}
+
+pub fn walk_continuing_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut ContinuingStatement) {
+    v.visit_block_mut(&mut stmt.body);
+
+    if let Some(break_if) = &mut stmt.break_if {
+        v.visit_expr_mut(break_if);
+    }
+}
+
+pub fn walk_while_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut WhileStatement) {
+    v.visit_expr_mut(&mut stmt.condition);
+    v.visit_block_mut(&mut stmt.body);
+}
+
+pub fn walk_switch_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut SwitchStatement) {
+    v.visit_expr_mut(&mut stmt.selector);
+
+    for case in &mut stmt.cases {
+        v.visit_switch_case_mut(case);
+    }
+
+    for selector in &mut stmt.default_selectors {
+        v.visit_expr_mut(selector);
+    }
+
+    v.visit_block_mut(&mut stmt.default);
+}
+
+pub fn walk_switch_case<V: VisitMut + ?Sized>(v: &mut V, case: &mut SwitchCase) {
+    for selector in &mut case.selectors {
+        v.visit_expr_mut(selector);
+    }
+
+    v.visit_block_mut(&mut case.body);
+}
+
+pub fn walk_for_loop_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut ForLoopStatement) {
+    v.visit_for_loop_header_mut(&mut stmt.header);
+    v.visit_block_mut(&mut stmt.body);
+}
+
+pub fn walk_for_loop_header<V: VisitMut + ?Sized>(v: &mut V, header: &mut ForLoopHeader) {
+    if let Some(init) = &mut header.init {
+        match init {
+            ForLoopInit::VarDecl(stmt) => v.visit_var_decl_stmt_mut(stmt),
+        }
+    }
+
+    if let Some(condition) = &mut header.condition {
+        v.visit_expr_mut(condition);
+    }
+
+    if let Some(update) = &mut header.update {
+        match update {
+            ForLoopUpdate::Assignment(stmt) => v.visit_assignment_stmt_mut(stmt),
+        }
+    }
+}
+
+pub fn walk_fn_call_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut FnCallStatement) {
+    for arg in &mut stmt.args {
+        v.visit_expr_mut(arg);
+    }
+}
+
+pub fn walk_const_assert_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut ConstAssertStatement) {
+    v.visit_expr_mut(&mut stmt.expr);
+}
+
+pub fn walk_expr<V: VisitMut + ?Sized>(v: &mut V, node: &mut ExprNode) {
+    match &mut node.expr {
+        Expr::Lit(_) => {}
+        Expr::TypeCons(expr) => {
+            for arg in &mut expr.args {
+                v.visit_expr_mut(arg);
+            }
+        }
+        Expr::Var(_) => {}
+        Expr::Postfix(expr) => {
+            v.visit_expr_mut(&mut expr.inner);
+            v.visit_postfix_mut(&mut expr.postfix);
+        }
+        Expr::UnOp(expr) => v.visit_expr_mut(&mut expr.inner),
+        Expr::BinOp(expr) => {
+            v.visit_expr_mut(&mut expr.left);
+            v.visit_expr_mut(&mut expr.right);
+        }
+        Expr::FnCall(expr) => {
+            for arg in &mut expr.args {
+                v.visit_expr_mut(arg);
+            }
+        }
+    }
+}
+
+pub fn walk_postfix<V: VisitMut + ?Sized>(v: &mut V, postfix: &mut Postfix) {
+    match postfix {
+        Postfix::Index(index) => v.visit_expr_mut(index),
+        Postfix::Member(_) => {}
+    }
+}
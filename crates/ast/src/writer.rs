@@ -3,7 +3,10 @@ use std::io;
 
 use indenter::indented;
 
-use crate::{FnAttr, FnDecl, GlobalConstDecl, GlobalVarDecl, Module, StructDecl};
+use crate::{
+    FnAttr, FnDecl, GlobalConstDecl, GlobalOverrideDecl, GlobalVarDecl, Module, StructDecl,
+    TypeAliasDecl,
+};
 
 #[derive(Default)]
 pub struct Writer {
@@ -36,6 +39,16 @@ impl Writer {
     }
 
     pub fn write_module(&self, f: &mut dyn Write, module: &Module) -> Result {
+        if !module.enables.is_empty() {
+            writeln!(f, "enable {};", module.enables.join(", "))?;
+            writeln!(f)?;
+        }
+
+        for decl in &module.aliases {
+            self.write_type_alias(f, decl)?;
+            writeln!(f)?;
+        }
+
         for decl in &module.structs {
             self.write_struct(f, decl)?;
             writeln!(f)?;
@@ -46,11 +59,21 @@ impl Writer {
             writeln!(f)?;
         }
 
+        for decl in &module.overrides {
+            self.write_global_override(f, decl)?;
+            writeln!(f)?;
+        }
+
         for decl in &module.vars {
             self.write_global_var(f, decl)?;
             writeln!(f)?;
         }
 
+        for stmt in &module.const_asserts {
+            writeln!(f, "{stmt};")?;
+            writeln!(f)?;
+        }
+
         for decl in &module.functions {
             self.write_func(f, decl)?;
             writeln!(f)?;
@@ -59,6 +82,10 @@ impl Writer {
         Ok(())
     }
 
+    pub fn write_type_alias(&self, f: &mut dyn Write, decl: &TypeAliasDecl) -> Result {
+        writeln!(f, "alias {} = {};", decl.name, decl.data_type)
+    }
+
     pub fn write_struct(&self, f: &mut dyn Write, decl: &StructDecl) -> Result {
         writeln!(f, "struct {} {{", decl.name)?;
 
@@ -86,6 +113,18 @@ impl Writer {
         )
     }
 
+    pub fn write_global_override(&self, f: &mut dyn Write, decl: &GlobalOverrideDecl) -> Result {
+        self.write_attrs(f, decl.attrs.iter())?;
+
+        write!(f, "override {}: {}", decl.name, decl.data_type)?;
+
+        if let Some(initializer) = &decl.initializer {
+            write!(f, " = {initializer}")?;
+        }
+
+        writeln!(f, ";")
+    }
+
     pub fn write_global_var(&self, f: &mut dyn Write, decl: &GlobalVarDecl) -> Result {
         self.write_attrs(f, decl.attrs.iter())?;
 
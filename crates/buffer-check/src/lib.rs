@@ -1,31 +1,115 @@
-use common::Type;
+mod policy;
+
+use common::{ScalarType, Type};
 use reflection_types::{PipelineDescription, ResourceKind};
+use serde::Serialize;
+use serde_json::Value;
+
+pub use policy::FloatPolicy;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// A value in an output buffer which differs between executions.
+#[derive(Debug, Serialize)]
+pub struct Mismatch {
+    /// Name of the resource variable.
+    pub resource: String,
+    /// Path to the value within the resource, e.g. `s_output.c[3].y`.
+    pub path: String,
+    /// Decoded value from each execution.
+    pub values: Vec<Value>,
+    /// Indices of executions which produced identical values, grouped together.
+    pub groups: Vec<Vec<usize>>,
+}
 
 pub fn compare<'a>(
-    mut buffers: impl Iterator<Item = &'a Vec<Vec<u8>>>,
+    buffers: impl Iterator<Item = &'a Vec<Vec<u8>>>,
     pipeline_desc: &PipelineDescription,
     type_descs: &[Type],
-) -> bool {
-    if let Some(mut prev) = buffers.next() {
-        for execution in buffers {
-            for (i, (j, _)) in pipeline_desc
-                .resources
-                .iter()
-                .enumerate()
-                .filter(|(_, it)| it.kind == ResourceKind::StorageBuffer)
-                .enumerate()
-            {
-                for (offset, size) in type_descs[j].ranges() {
-                    let range = offset..(offset + size);
-                    if execution[i][range.clone()] != prev[i][range] {
-                        return false;
-                    }
+    float_policy: FloatPolicy,
+) -> Report {
+    let executions = buffers.collect::<Vec<_>>();
+    let mut report = Report::default();
+
+    if executions.len() < 2 {
+        return report;
+    }
+
+    for (i, (j, resource)) in pipeline_desc
+        .resources
+        .iter()
+        .enumerate()
+        .filter(|(_, it)| it.kind == ResourceKind::StorageBuffer)
+        .enumerate()
+    {
+        for field in type_descs[j].fields(&resource.name) {
+            for component in 0..field.components {
+                let offset = field.offset + component as usize * 4;
+                let bits = executions
+                    .iter()
+                    .map(|execution| {
+                        let bytes = &execution[i][offset..offset + 4];
+                        u32::from_le_bytes(bytes.try_into().unwrap())
+                    })
+                    .collect::<Vec<_>>();
+
+                let groups = match field.scalar_type {
+                    ScalarType::F32 => group_equal(&bits, |a, b| float_policy.eq(a, b)),
+                    _ => group_equal(&bits, |a, b| a == b),
+                };
+
+                if groups.len() == 1 {
+                    continue;
                 }
+
+                let path = if field.components > 1 {
+                    format!(
+                        "{}.{}",
+                        field.path,
+                        ["x", "y", "z", "w"][component as usize]
+                    )
+                } else {
+                    field.path.clone()
+                };
+
+                report.mismatches.push(Mismatch {
+                    resource: resource.name.clone(),
+                    path,
+                    values: bits
+                        .iter()
+                        .map(|it| field.scalar_type.decode(*it))
+                        .collect(),
+                    groups,
+                });
             }
+        }
+    }
+
+    report
+}
+
+/// Groups together values which are equal according to `eq`.
+///
+/// Each value is compared against the first member of each group, so with a non-transitive `eq`
+/// the grouping depends on the order of executions.
+fn group_equal(bits: &[u32], eq: impl Fn(u32, u32) -> bool) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
 
-            prev = execution;
+    for (index, value) in bits.iter().enumerate() {
+        match groups.iter_mut().find(|group| eq(bits[group[0]], *value)) {
+            Some(group) => group.push(index),
+            None => groups.push(vec![index]),
         }
     }
 
-    true
+    groups
 }
@@ -0,0 +1,249 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// Policy for deciding whether two `f32` values produced by different executions are equal.
+///
+/// This can be parsed from one of the following forms:
+///
+/// - `exact` - values must be bitwise identical
+/// - `ulp:<n>` - values may be up to `n` units in the last place apart
+/// - `rel:<epsilon>` - values may differ by up to `epsilon` relative to the larger magnitude
+/// - `nan` - values must be identical, except that any NaN equals any other NaN
+/// - `nan-zero` - like `nan`, but `0.0` and `-0.0` are also considered equal
+///
+/// The `ulp` and `rel` policies also treat all NaNs as equal, and `0.0` as equal to `-0.0`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum FloatPolicy {
+    #[default]
+    Exact,
+    Ulp(u32),
+    Relative(f32),
+    CanonicalNan {
+        ignore_zero_sign: bool,
+    },
+}
+
+impl FloatPolicy {
+    /// Compares the bit patterns of two `f32` values.
+    pub fn eq(&self, a: u32, b: u32) -> bool {
+        if a == b {
+            return true;
+        }
+
+        let (x, y) = (f32::from_bits(a), f32::from_bits(b));
+
+        match *self {
+            FloatPolicy::Exact => false,
+            FloatPolicy::CanonicalNan { ignore_zero_sign } => {
+                (x.is_nan() && y.is_nan()) || (ignore_zero_sign && x == 0.0 && y == 0.0)
+            }
+            FloatPolicy::Ulp(n) => {
+                if x.is_nan() || y.is_nan() {
+                    return x.is_nan() && y.is_nan();
+                }
+
+                ulp_distance(a, b) <= n as u64
+            }
+            FloatPolicy::Relative(epsilon) => {
+                if x.is_nan() || y.is_nan() {
+                    return x.is_nan() && y.is_nan();
+                }
+
+                // Infinities are only equal to themselves, which is handled above
+                if x.is_infinite() || y.is_infinite() {
+                    return false;
+                }
+
+                (x - y).abs() <= epsilon * x.abs().max(y.abs())
+            }
+        }
+    }
+}
+
+/// Number of representable `f32` values between two (non-NaN) bit patterns.
+fn ulp_distance(a: u32, b: u32) -> u64 {
+    // Map the bit patterns onto a monotonic integer line, with -0.0 and 0.0 both at zero
+    fn ordered(bits: u32) -> i64 {
+        if bits & 0x8000_0000 != 0 {
+            -((bits & 0x7fff_ffff) as i64)
+        } else {
+            bits as i64
+        }
+    }
+
+    ordered(a).abs_diff(ordered(b))
+}
+
+impl FromStr for FloatPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match value.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (value, None),
+        };
+
+        match (name, arg) {
+            ("exact", None) => Ok(FloatPolicy::Exact),
+            ("nan", None) => Ok(FloatPolicy::CanonicalNan {
+                ignore_zero_sign: false,
+            }),
+            ("nan-zero", None) => Ok(FloatPolicy::CanonicalNan {
+                ignore_zero_sign: true,
+            }),
+            ("ulp", Some(n)) => n
+                .parse()
+                .map(FloatPolicy::Ulp)
+                .map_err(|e| format!("invalid ulp distance `{n}`: {e}")),
+            ("rel", Some(epsilon)) => match epsilon.parse::<f32>() {
+                Ok(epsilon) if epsilon >= 0.0 => Ok(FloatPolicy::Relative(epsilon)),
+                _ => Err(format!("invalid relative epsilon `{epsilon}`")),
+            },
+            _ => Err(format!(
+                "invalid float comparison policy `{value}` \
+                (expected one of `exact`, `ulp:<n>`, `rel:<epsilon>`, `nan`, `nan-zero`)"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for FloatPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Display for FloatPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FloatPolicy::Exact => write!(f, "exact"),
+            FloatPolicy::Ulp(n) => write!(f, "ulp:{n}"),
+            FloatPolicy::Relative(epsilon) => write!(f, "rel:{epsilon}"),
+            FloatPolicy::CanonicalNan {
+                ignore_zero_sign: false,
+            } => write!(f, "nan"),
+            FloatPolicy::CanonicalNan {
+                ignore_zero_sign: true,
+            } => write!(f, "nan-zero"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eq(policy: &str, a: f32, b: f32) -> bool {
+        policy
+            .parse::<FloatPolicy>()
+            .unwrap()
+            .eq(a.to_bits(), b.to_bits())
+    }
+
+    /// Returns the value `n` representable steps above `x`.
+    fn step(x: f32, n: i32) -> f32 {
+        f32::from_bits((x.to_bits() as i32 + n) as u32)
+    }
+
+    const OTHER_NAN: u32 = 0x7fc0_0001;
+
+    #[test]
+    fn exact_compares_bits() {
+        assert!(eq("exact", 1.0, 1.0));
+        assert!(!eq("exact", 1.0, step(1.0, 1)));
+        assert!(!eq("exact", 0.0, -0.0));
+        assert!(FloatPolicy::Exact.eq(OTHER_NAN, OTHER_NAN));
+        assert!(!FloatPolicy::Exact.eq(f32::NAN.to_bits(), OTHER_NAN));
+    }
+
+    #[test]
+    fn ulp_distance_boundary() {
+        assert!(eq("ulp:2", 1.0, step(1.0, 2)));
+        assert!(!eq("ulp:2", 1.0, step(1.0, 3)));
+        assert!(eq("ulp:2", step(1.0, 2), 1.0));
+
+        // The distance is measured in representable values, so it spans an exponent change
+        assert!(eq("ulp:1", step(2.0, -1), 2.0));
+        assert!(eq("ulp:2", step(2.0, -1), step(2.0, 1)));
+        assert!(!eq("ulp:1", step(2.0, -1), step(2.0, 1)));
+    }
+
+    #[test]
+    fn ulp_distance_across_zero() {
+        let min = f32::from_bits(1);
+
+        assert!(eq("ulp:0", 0.0, -0.0));
+        assert!(eq("ulp:1", min, -0.0));
+        assert!(eq("ulp:2", -min, min));
+        assert!(!eq("ulp:1", -min, min));
+    }
+
+    #[test]
+    fn ulp_special_values() {
+        assert!(eq("ulp:0", f32::NAN, f32::from_bits(OTHER_NAN)));
+        assert!(!eq("ulp:4", f32::NAN, 1.0));
+        assert!(!eq("ulp:4", 1.0, f32::NAN));
+
+        // Infinity is the next value after the largest finite float
+        assert!(eq("ulp:1", f32::MAX, f32::INFINITY));
+        assert!(!eq("ulp:0", f32::MAX, f32::INFINITY));
+        assert!(!eq("ulp:100", f32::INFINITY, f32::NEG_INFINITY));
+        assert!(eq("ulp:0", f32::INFINITY, f32::INFINITY));
+    }
+
+    #[test]
+    fn relative_epsilon_boundary() {
+        assert!(eq("rel:0.5", 1.0, 2.0));
+        assert!(eq("rel:0.5", 2.0, 1.0));
+        assert!(!eq("rel:0.5", 1.0, step(2.0, 1)));
+        assert!(eq("rel:0", 0.0, -0.0));
+        assert!(!eq("rel:0", 1.0, step(1.0, 1)));
+    }
+
+    #[test]
+    fn relative_special_values() {
+        assert!(eq("rel:0.1", f32::NAN, f32::from_bits(OTHER_NAN)));
+        assert!(!eq("rel:0.1", f32::NAN, 0.0));
+        assert!(eq("rel:0.1", f32::INFINITY, f32::INFINITY));
+        assert!(!eq("rel:1", f32::MAX, f32::INFINITY));
+        assert!(!eq("rel:1", f32::INFINITY, f32::NEG_INFINITY));
+    }
+
+    #[test]
+    fn canonical_nan() {
+        assert!(eq("nan", f32::NAN, f32::from_bits(OTHER_NAN)));
+        assert!(eq("nan", -f32::NAN, f32::NAN));
+        assert!(!eq("nan", f32::NAN, f32::INFINITY));
+        assert!(!eq("nan", 0.0, -0.0));
+        assert!(!eq("nan", 1.0, step(1.0, 1)));
+
+        assert!(eq("nan-zero", 0.0, -0.0));
+        assert!(eq("nan-zero", f32::NAN, f32::from_bits(OTHER_NAN)));
+        assert!(!eq("nan-zero", 0.0, f32::from_bits(1)));
+    }
+
+    #[test]
+    fn parse_round_trips() {
+        for policy in ["exact", "ulp:0", "ulp:4", "rel:0.001", "nan", "nan-zero"] {
+            assert_eq!(policy.parse::<FloatPolicy>().unwrap().to_string(), policy);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_policies() {
+        for policy in [
+            "", "ulp", "ulp:", "ulp:-1", "ulp:1.5", "rel", "rel:-0.1", "rel:nan", "rel:x",
+            "exact:1", "nan:1", "fuzzy",
+        ] {
+            assert!(
+                policy.parse::<FloatPolicy>().is_err(),
+                "`{policy}` should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+
+use ast::{DataType, GlobalVarDecl, Module, StorageClass, VarQualifier};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+use crate::{aligned, AddressSpace, ScalarType, Type};
+
+/// Input data for the buffers used by a shader.
+///
+/// Entries may either be keyed by `group:binding` with a raw byte array as the value (the legacy
+/// format), or keyed by the name of the resource variable with a typed value:
+///
+/// ```json
+/// { "u_input": { "a": -5, "b": [1.5, 2.0, 0.0] } }
+/// ```
+///
+/// Scalars are written as json numbers. Vectors and arrays are json arrays, matrices are arrays of
+/// columns, and structs are objects keyed by member name. Any scalar may also be given as a hex
+/// string containing its bit pattern (e.g. `"0x7fc00000"`), and floats may additionally be
+/// `"inf"`, `"-inf"` or `"nan"`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct InputData(pub HashMap<String, Value>);
+
+impl InputData {
+    /// Converts the input data to the legacy byte format, keyed by `group:binding`.
+    pub fn to_bytes(&self, module: &Module) -> Result<HashMap<String, Vec<u8>>, String> {
+        let mut data = HashMap::new();
+
+        for (key, value) in &self.0 {
+            let (key, bytes) = if is_binding_key(key) {
+                let bytes = serde_json::from_value(value.clone())
+                    .map_err(|e| format!("invalid byte array for `{key}`: {e}"))?;
+                (key.clone(), bytes)
+            } else {
+                let var = resource_var(module, key)
+                    .ok_or_else(|| format!("no uniform or storage variable named `{key}`"))?;
+                let type_desc = var_type(var, Some(value))?;
+                (binding_key(var), type_desc.encode(value, key)?)
+            };
+
+            if data.insert(key.clone(), bytes).is_some() {
+                return Err(format!("input data for `{key}` specified more than once"));
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Converts input data in the legacy byte format to typed values keyed by variable name.
+    ///
+    /// Entries which don't correspond to any resource variable in the module are dropped.
+    pub fn from_bytes(
+        module: &Module,
+        data: &HashMap<String, Vec<u8>>,
+    ) -> Result<InputData, String> {
+        let mut values = HashMap::new();
+
+        for var in resource_vars(module) {
+            if let Some(bytes) = data.get(&binding_key(var)) {
+                // Only the layout is needed for decoding, which is the same in both address spaces
+                let type_desc =
+                    Type::from_buffer_len(&var.data_type, AddressSpace::Storage, bytes.len())?;
+
+                values.insert(var.name.clone(), type_desc.decode(bytes)?);
+            }
+        }
+
+        Ok(InputData(values))
+    }
+}
+
+fn is_binding_key(key: &str) -> bool {
+    match key.split_once(':') {
+        Some((group, binding)) => group.parse::<u32>().is_ok() && binding.parse::<u32>().is_ok(),
+        None => false,
+    }
+}
+
+fn binding_key(var: &GlobalVarDecl) -> String {
+    let group = var.group_index().unwrap();
+    let binding = var.binding_index().unwrap();
+    format!("{group}:{binding}")
+}
+
+fn resource_vars(module: &Module) -> impl Iterator<Item = &GlobalVarDecl> {
+    module.vars.iter().filter(|var| {
+        matches!(
+            &var.qualifier,
+            Some(VarQualifier {
+                storage_class: StorageClass::Uniform | StorageClass::Storage,
+                ..
+            })
+        )
+    })
+}
+
+fn resource_var<'a>(module: &'a Module, name: &str) -> Option<&'a GlobalVarDecl> {
+    resource_vars(module).find(|var| var.name == name)
+}
+
+fn var_type(var: &GlobalVarDecl, value: Option<&Value>) -> Result<Type, String> {
+    // The size of a runtime-sized array is determined by the number of elements provided
+    let runtime_array = match (&var.data_type, value) {
+        (DataType::Array(_, None), Some(value)) => Some(value),
+        (DataType::Struct(decl), Some(Value::Object(members))) => decl
+            .members
+            .last()
+            .filter(|member| matches!(member.data_type, DataType::Array(_, None)))
+            .and_then(|member| members.get(&member.name)),
+        _ => None,
+    };
+
+    let data_type = match runtime_array {
+        Some(Value::Array(elements)) => var
+            .data_type
+            .with_runtime_array_len(elements.len() as u32)
+            .unwrap(),
+        _ => var.data_type.clone(),
+    };
+
+    Type::try_from(&data_type).map_err(|e| e.to_owned())
+}
+
+impl Type {
+    /// Encodes a typed json value into a buffer, using the same layout as [`Type::ranges`].
+    ///
+    /// `path` is used to identify the location of the value in error messages.
+    pub fn encode(&self, value: &Value, path: &str) -> Result<Vec<u8>, String> {
+        let mut buffer = vec![0; self.buffer_size() as usize];
+        self.encode_at(&mut buffer, 0, value, path)?;
+        Ok(buffer)
+    }
+
+    fn encode_at(
+        &self,
+        buffer: &mut [u8],
+        offset: u32,
+        value: &Value,
+        path: &str,
+    ) -> Result<(), String> {
+        match self {
+            Type::Scalar { scalar_type } => {
+                let bits = scalar_type.encode(value, path)?;
+                let offset = offset as usize;
+                buffer[offset..offset + 4].copy_from_slice(&bits.to_le_bytes());
+            }
+            Type::Vector { size, scalar_type } => {
+                let components = expect_array(value, size.count(), path)?;
+                for (i, component) in components.iter().enumerate() {
+                    let bits = scalar_type.encode(component, &format!("{path}[{i}]"))?;
+                    let offset = (offset + i as u32 * 4) as usize;
+                    buffer[offset..offset + 4].copy_from_slice(&bits.to_le_bytes());
+                }
+            }
+            Type::Matrix {
+                columns,
+                rows,
+                scalar_type,
+            } => {
+                let stride = aligned(rows.vector_size(), rows.vector_alignment());
+                let column_type = Type::Vector {
+                    size: rows.clone(),
+                    scalar_type: scalar_type.clone(),
+                };
+
+                let values = expect_array(value, columns.count(), path)?;
+                for (i, column) in values.iter().enumerate() {
+                    let offset = offset + i as u32 * stride;
+                    column_type.encode_at(buffer, offset, column, &format!("{path}[{i}]"))?;
+                }
+            }
+            Type::Array { size, element_type } => {
+                let stride = aligned(element_type.size(), element_type.alignment());
+                let elements = expect_array(value, *size, path)?;
+                for (i, element) in elements.iter().enumerate() {
+                    let offset = offset + i as u32 * stride;
+                    element_type.encode_at(buffer, offset, element, &format!("{path}[{i}]"))?;
+                }
+            }
+            Type::Struct { members } => {
+                let fields = value
+                    .as_object()
+                    .ok_or_else(|| format!("expected object for `{path}`"))?;
+
+                if let Some(name) = fields
+                    .keys()
+                    .find(|name| !members.iter().any(|it| &it.name == *name))
+                {
+                    return Err(format!("unknown member `{name}` in `{path}`"));
+                }
+
+                let mut offset = offset;
+                for member in members {
+                    let path = format!("{path}.{}", member.name);
+                    let field = fields
+                        .get(&member.name)
+                        .ok_or_else(|| format!("missing value for `{path}`"))?;
+
+                    offset = aligned(offset, member.alignment());
+                    member.type_desc.encode_at(buffer, offset, field, &path)?;
+                    offset += member.type_desc.size();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes a buffer into a typed json value.
+    pub fn decode(&self, buffer: &[u8]) -> Result<Value, String> {
+        if buffer.len() < self.size() as usize {
+            return Err(format!(
+                "expected at least {} bytes of input data, found {}",
+                self.size(),
+                buffer.len()
+            ));
+        }
+
+        Ok(self.decode_at(buffer, 0))
+    }
+
+    fn decode_at(&self, buffer: &[u8], offset: u32) -> Value {
+        let read = |offset: u32| {
+            let offset = offset as usize;
+            u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap())
+        };
+
+        match self {
+            Type::Scalar { scalar_type } => scalar_type.decode(read(offset)),
+            Type::Vector { size, scalar_type } => Value::Array(
+                (0..size.count())
+                    .map(|i| scalar_type.decode(read(offset + i * 4)))
+                    .collect(),
+            ),
+            Type::Matrix {
+                columns,
+                rows,
+                scalar_type,
+            } => {
+                let stride = aligned(rows.vector_size(), rows.vector_alignment());
+                let column_type = Type::Vector {
+                    size: rows.clone(),
+                    scalar_type: scalar_type.clone(),
+                };
+
+                Value::Array(
+                    (0..columns.count())
+                        .map(|i| column_type.decode_at(buffer, offset + i * stride))
+                        .collect(),
+                )
+            }
+            Type::Array { size, element_type } => {
+                let stride = aligned(element_type.size(), element_type.alignment());
+                Value::Array(
+                    (0..*size)
+                        .map(|i| element_type.decode_at(buffer, offset + i * stride))
+                        .collect(),
+                )
+            }
+            Type::Struct { members } => {
+                let mut fields = Map::new();
+                let mut offset = offset;
+
+                for member in members {
+                    offset = aligned(offset, member.alignment());
+                    fields.insert(
+                        member.name.clone(),
+                        member.type_desc.decode_at(buffer, offset),
+                    );
+                    offset += member.type_desc.size();
+                }
+
+                Value::Object(fields)
+            }
+        }
+    }
+}
+
+fn expect_array<'a>(value: &'a Value, len: u32, path: &str) -> Result<&'a Vec<Value>, String> {
+    match value {
+        Value::Array(values) if values.len() == len as usize => Ok(values),
+        Value::Array(values) => Err(format!(
+            "expected {len} elements for `{path}`, found {}",
+            values.len()
+        )),
+        _ => Err(format!("expected array for `{path}`")),
+    }
+}
+
+impl ScalarType {
+    fn encode(&self, value: &Value, path: &str) -> Result<u32, String> {
+        let invalid = || format!("invalid value for `{path}` of type {self:?}: {value}");
+
+        if let Some(hex) = value.as_str().and_then(|it| it.strip_prefix("0x")) {
+            return u32::from_str_radix(hex, 16).map_err(|_| invalid());
+        }
+
+        match self {
+            ScalarType::I32 | ScalarType::AI32 => value
+                .as_i64()
+                .and_then(|it| i32::try_from(it).ok())
+                .map(|it| it as u32)
+                .ok_or_else(invalid),
+            ScalarType::U32 | ScalarType::AU32 => value
+                .as_u64()
+                .and_then(|it| u32::try_from(it).ok())
+                .ok_or_else(invalid),
+            ScalarType::F32 => match value {
+                Value::Number(n) => Ok((n.as_f64().ok_or_else(invalid)? as f32).to_bits()),
+                Value::String(s) => match s.as_str() {
+                    "inf" => Ok(f32::INFINITY.to_bits()),
+                    "-inf" => Ok(f32::NEG_INFINITY.to_bits()),
+                    "nan" => Ok(f32::NAN.to_bits()),
+                    _ => Err(invalid()),
+                },
+                _ => Err(invalid()),
+            },
+        }
+    }
+
+    /// Decodes the bit pattern of a scalar into a json value, using the same representation as
+    /// [`InputData`].
+    pub fn decode(&self, bits: u32) -> Value {
+        match self {
+            ScalarType::I32 | ScalarType::AI32 => Value::from(bits as i32),
+            ScalarType::U32 | ScalarType::AU32 => Value::from(bits),
+            ScalarType::F32 => {
+                let value = f32::from_bits(bits);
+                if value.is_nan() {
+                    // Keep the exact bit pattern so that round-tripping doesn't change the payload
+                    Value::from(format!("0x{bits:08x}"))
+                } else if value.is_infinite() {
+                    Value::from(if value > 0.0 { "inf" } else { "-inf" })
+                } else {
+                    // Go via the shortest decimal representation of the f32, so that we get `0.1`
+                    // rather than `0.10000000149011612`
+                    let value = value.to_string().parse::<f64>().unwrap();
+                    Value::Number(Number::from_f64(value).unwrap())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SRC: &str = r#"
+struct Input {
+    a: i32,
+    b: vec3<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> u_input: Input;
+
+@group(0) @binding(1)
+var<storage, read_write> s_output: array<u32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    s_output[0] = u32(u_input.a);
+}
+"#;
+
+    fn to_bytes(value: Value) -> Result<HashMap<String, Vec<u8>>, String> {
+        let data = serde_json::from_value::<InputData>(value).unwrap();
+        data.to_bytes(&parser::parse(SRC))
+    }
+
+    fn words(bytes: &[u8]) -> Vec<u32> {
+        bytes
+            .chunks_exact(4)
+            .map(|it| u32::from_le_bytes(it.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn typed_values_are_encoded_with_layout() {
+        let data = to_bytes(json!({ "u_input": { "a": -5, "b": [1.5, 2.0, 0.0] } })).unwrap();
+
+        // The vec3 member is aligned to 16 bytes, and the struct is padded to its alignment
+        assert_eq!(
+            words(&data["0:0"]),
+            [
+                -5i32 as u32,
+                0,
+                0,
+                0,
+                1.5f32.to_bits(),
+                2.0f32.to_bits(),
+                0,
+                0
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_array_size_comes_from_value() {
+        let data = to_bytes(json!({ "s_output": [1, 2, 3] })).unwrap();
+        assert_eq!(words(&data["0:1"]), [1, 2, 3]);
+    }
+
+    #[test]
+    fn scalars_can_be_given_as_bit_patterns() {
+        let data = to_bytes(json!({ "s_output": ["0xffffffff", "0x7fc00000"] })).unwrap();
+        assert_eq!(words(&data["0:1"]), [0xffffffff, 0x7fc00000]);
+
+        let data = to_bytes(json!({ "u_input": { "a": 0, "b": ["inf", "-inf", "nan"] } })).unwrap();
+        assert_eq!(
+            words(&data["0:0"])[4..7],
+            [
+                f32::INFINITY.to_bits(),
+                f32::NEG_INFINITY.to_bits(),
+                f32::NAN.to_bits()
+            ]
+        );
+    }
+
+    #[test]
+    fn legacy_entries_are_passed_through() {
+        let data = to_bytes(json!({ "0:1": [1, 2, 3, 4] })).unwrap();
+        assert_eq!(data["0:1"], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn malformed_inputs_are_rejected() {
+        let cases = [
+            (json!({ "missing": [1] }), "no uniform or storage variable"),
+            (json!({ "0:1": [256] }), "invalid byte array"),
+            (
+                json!({ "u_input": [1, 2] }),
+                "expected object for `u_input`",
+            ),
+            (
+                json!({ "u_input": { "a": 1 } }),
+                "missing value for `u_input.b`",
+            ),
+            (
+                json!({ "u_input": { "a": 1, "b": [0, 0, 0], "c": 2 } }),
+                "unknown member `c`",
+            ),
+            (
+                json!({ "u_input": { "a": 1, "b": [0, 0] } }),
+                "expected 3 elements for `u_input.b`",
+            ),
+            (
+                json!({ "u_input": { "a": 2147483648u32, "b": [0, 0, 0] } }),
+                "invalid value for `u_input.a`",
+            ),
+            (
+                json!({ "s_output": [-1] }),
+                "invalid value for `s_output[0]`",
+            ),
+            (json!({ "s_output": ["0xzz"] }), "invalid value"),
+            (
+                json!({ "u_input": { "a": 1, "b": ["infinity", 0, 0] } }),
+                "invalid value for `u_input.b[0]`",
+            ),
+            (
+                json!({ "0:1": [0, 0, 0, 0], "s_output": [0] }),
+                "specified more than once",
+            ),
+        ];
+
+        for (value, expected) in cases {
+            let err = to_bytes(value.clone()).unwrap_err();
+            assert!(
+                err.contains(expected),
+                "error for {value} was `{err}`, expected `{expected}`"
+            );
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip_through_typed_values() {
+        let module = parser::parse(SRC);
+        let value = json!({
+            "u_input": { "a": -5, "b": [0.1, "inf", "0x7fc00001"] },
+            "s_output": [1, 2, 3],
+        });
+
+        let data = serde_json::from_value::<InputData>(value.clone()).unwrap();
+        let bytes = data.to_bytes(&module).unwrap();
+        let decoded = InputData::from_bytes(&module, &bytes).unwrap();
+
+        assert_eq!(serde_json::to_value(decoded).unwrap(), value);
+    }
+}
@@ -0,0 +1,326 @@
+use ast::DataType;
+
+use crate::{aligned, AddressSpace, ScalarType, Type};
+
+impl Type {
+    /// Converts the type of a buffer in the given address space.
+    ///
+    /// Returns an error if the type is not host-shareable in that address space. For the uniform
+    /// address space this includes checking the additional layout constraints from the WGSL spec:
+    /// arrays must have a 16-byte aligned element stride, and struct and array members must be
+    /// placed at 16-byte aligned offsets (which requires an explicit `@align` attribute if the
+    /// natural alignment of the member is smaller).
+    pub fn from_data_type(data_type: &DataType, space: AddressSpace) -> Result<Type, String> {
+        if space == AddressSpace::Uniform && has_runtime_array(data_type) {
+            return Err(format!(
+                "type `{data_type}` contains a runtime-sized array, which is not allowed in the \
+                uniform address space"
+            ));
+        }
+
+        let type_desc = Type::try_from(data_type)
+            .map_err(|e| format!("type `{data_type}` is not host-shareable: {e}"))?;
+
+        if space == AddressSpace::Uniform {
+            type_desc.check_uniform_layout(&data_type.to_string())?;
+        }
+
+        Ok(type_desc)
+    }
+
+    /// Converts the type of a buffer whose contents are `len` bytes long.
+    ///
+    /// A runtime-sized array in the type is given as many elements as fit in the rest of the
+    /// buffer (but at least one), which matches the value of `arrayLength` for a buffer binding
+    /// of that size.
+    pub fn from_buffer_len(
+        data_type: &DataType,
+        space: AddressSpace,
+        len: usize,
+    ) -> Result<Type, String> {
+        let Some(empty) = data_type.with_runtime_array_len(0) else {
+            return Type::from_data_type(data_type, space);
+        };
+
+        if space == AddressSpace::Uniform {
+            // Reports that runtime-sized arrays are not allowed
+            return Type::from_data_type(data_type, space);
+        }
+
+        let (offset, stride) = Type::from_data_type(&empty, space)?.runtime_array_layout();
+        let count = (len as u32).saturating_sub(offset) / stride;
+        let sized = data_type.with_runtime_array_len(count.max(1)).unwrap();
+
+        Type::from_data_type(&sized, space)
+    }
+
+    /// Returns the offset and element stride of the array at the end of this type.
+    fn runtime_array_layout(&self) -> (u32, u32) {
+        match self {
+            Type::Array { element_type, .. } => {
+                (0, aligned(element_type.size(), element_type.alignment()))
+            }
+            Type::Struct { members } => {
+                let (last, init) = members
+                    .split_last()
+                    .expect("struct must have at least one member");
+
+                let mut offset = 0;
+                for member in init {
+                    offset = aligned(offset, member.alignment()) + member.type_desc.size();
+                }
+
+                let (_, stride) = last.type_desc.runtime_array_layout();
+                (aligned(offset, last.alignment()), stride)
+            }
+            _ => unreachable!("type does not end with an array"),
+        }
+    }
+
+    fn check_uniform_layout(&self, path: &str) -> Result<(), String> {
+        match self {
+            Type::Scalar { scalar_type } | Type::Vector { scalar_type, .. } => {
+                if matches!(scalar_type, ScalarType::AI32 | ScalarType::AU32) {
+                    return Err(format!(
+                        "`{path}` has an atomic type, which is not allowed in the uniform address \
+                        space"
+                    ));
+                }
+            }
+            Type::Matrix { .. } => {}
+            Type::Array { element_type, .. } => {
+                let stride = aligned(element_type.size(), element_type.alignment());
+                if !stride.is_multiple_of(16) {
+                    return Err(format!(
+                        "array `{path}` has an element stride of {stride} bytes, but arrays in the \
+                        uniform address space must have a stride that is a multiple of 16"
+                    ));
+                }
+
+                element_type.check_uniform_layout(&format!("{path}[]"))?;
+            }
+            Type::Struct { members } => {
+                let mut offset = 0;
+                let mut min_offset = 0;
+
+                for member in members {
+                    let path = format!("{path}.{}", member.name);
+
+                    offset = aligned(offset, member.alignment());
+
+                    let required_alignment = match &member.type_desc {
+                        Type::Array { .. } | Type::Struct { .. } => {
+                            aligned(member.type_desc.alignment(), 16)
+                        }
+                        type_desc => type_desc.alignment(),
+                    };
+
+                    if !offset.is_multiple_of(required_alignment) {
+                        return Err(format!(
+                            "member `{path}` is at offset {offset}, but must be aligned to \
+                            {required_alignment} bytes in the uniform address space (consider \
+                            adding `@align({required_alignment})`)"
+                        ));
+                    }
+
+                    if offset < min_offset {
+                        return Err(format!(
+                            "member `{path}` is at offset {offset}, but must be at offset \
+                            {min_offset} or later since it follows a struct member in the \
+                            uniform address space"
+                        ));
+                    }
+
+                    member.type_desc.check_uniform_layout(&path)?;
+
+                    let size = member.type_desc.size();
+
+                    // A member following a struct must start at least at the struct's size
+                    // rounded up to 16 bytes
+                    min_offset = match member.type_desc {
+                        Type::Struct { .. } => offset + aligned(size, 16),
+                        _ => 0,
+                    };
+
+                    offset += size;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn has_runtime_array(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Array(_, None) => true,
+        DataType::Array(inner, Some(_)) => has_runtime_array(inner),
+        DataType::Struct(decl) => decl
+            .members
+            .iter()
+            .any(|member| has_runtime_array(&member.data_type)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the type of a buffer variable, with `decls` providing any structs it refers to.
+    fn data_type(decls: &str, ty: &str) -> DataType {
+        let module = parser::parse(&format!(
+            "{decls}\n\
+            @group(0) @binding(0)\n\
+            var<storage, read_write> v: {ty};\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {{}}\n"
+        ));
+
+        module.vars[0].data_type.clone()
+    }
+
+    fn storage(decls: &str, ty: &str) -> Type {
+        Type::from_data_type(&data_type(decls, ty), AddressSpace::Storage).unwrap()
+    }
+
+    fn uniform(decls: &str, ty: &str) -> Result<Type, String> {
+        Type::from_data_type(&data_type(decls, ty), AddressSpace::Uniform)
+    }
+
+    fn offsets(type_desc: &Type) -> Vec<(String, usize)> {
+        type_desc
+            .fields("v")
+            .into_iter()
+            .map(|it| (it.path, it.offset))
+            .collect()
+    }
+
+    #[test]
+    fn scalar_vector_and_matrix_layout() {
+        let cases = [
+            ("f32", 4, 4),
+            ("vec2<u32>", 8, 8),
+            ("vec3<f32>", 12, 16),
+            ("vec4<i32>", 16, 16),
+            ("mat2x2<f32>", 16, 8),
+            ("mat3x3<f32>", 48, 16),
+            ("mat4x2<f32>", 32, 8),
+        ];
+
+        for (ty, size, alignment) in cases {
+            let type_desc = storage("", ty);
+            assert_eq!(type_desc.size(), size, "size of {ty}");
+            assert_eq!(type_desc.alignment(), alignment, "alignment of {ty}");
+        }
+    }
+
+    #[test]
+    fn array_stride_is_rounded_to_alignment() {
+        let type_desc = storage("", "array<vec3<f32>, 3>");
+        assert_eq!(type_desc.size(), 48);
+        assert_eq!(type_desc.alignment(), 16);
+    }
+
+    #[test]
+    fn struct_members_are_aligned() {
+        let decls = "struct S { a: f32, b: vec3<f32>, c: f32, d: vec2<f32>, }";
+        let type_desc = storage(decls, "S");
+
+        // `c` fits in the padding at the end of the vec3, but `d` must be 8-byte aligned
+        assert_eq!(
+            offsets(&type_desc),
+            [
+                ("v.a".to_owned(), 0),
+                ("v.b".to_owned(), 16),
+                ("v.c".to_owned(), 28),
+                ("v.d".to_owned(), 32),
+            ]
+        );
+        assert_eq!(type_desc.size(), 48);
+        assert_eq!(type_desc.buffer_size(), 48);
+    }
+
+    #[test]
+    fn explicit_alignment_is_honoured() {
+        let decls = "struct S { a: f32, @align(16) b: f32, }";
+        let type_desc = storage(decls, "S");
+
+        assert_eq!(
+            offsets(&type_desc),
+            [("v.a".to_owned(), 0), ("v.b".to_owned(), 16)]
+        );
+        assert_eq!(type_desc.alignment(), 16);
+        assert_eq!(type_desc.size(), 32);
+    }
+
+    #[test]
+    fn uniform_arrays_require_16_byte_stride() {
+        assert!(uniform("", "array<vec4<f32>, 2>").is_ok());
+        assert!(uniform("", "array<f32, 4>")
+            .unwrap_err()
+            .contains("stride of 4 bytes"));
+        assert!(uniform("", "array<vec2<f32>, 4>")
+            .unwrap_err()
+            .contains("stride of 8 bytes"));
+
+        // The same types are fine in the storage address space
+        storage("", "array<f32, 4>");
+    }
+
+    #[test]
+    fn uniform_struct_members_require_16_byte_alignment() {
+        let decls = "struct Inner { x: f32, }\n\
+            struct S { a: f32, b: Inner, }\n\
+            struct T { a: f32, @align(16) b: Inner, }";
+
+        let err = uniform(decls, "S").unwrap_err();
+        assert!(err.contains("member `S.b` is at offset 4"), "{err}");
+        assert!(err.contains("@align(16)"), "{err}");
+
+        assert!(uniform(decls, "T").is_ok());
+    }
+
+    #[test]
+    fn uniform_member_after_struct_is_padded() {
+        let decls = "struct Inner { x: f32, }\n\
+            struct S { @align(16) a: Inner, b: f32, }\n\
+            struct T { @align(16) a: Inner, @align(16) b: f32, }";
+
+        let err = uniform(decls, "S").unwrap_err();
+        assert!(err.contains("must be at offset 16 or later"), "{err}");
+
+        assert!(uniform(decls, "T").is_ok());
+    }
+
+    #[test]
+    fn uniform_rejects_runtime_arrays_and_atomics() {
+        let decls = "struct S { n: u32, data: array<u32>, }";
+
+        assert!(uniform(decls, "S")
+            .unwrap_err()
+            .contains("runtime-sized array"));
+        assert!(uniform("", "atomic<u32>")
+            .unwrap_err()
+            .contains("atomic type"));
+    }
+
+    #[test]
+    fn runtime_array_length_from_buffer_len() {
+        let decls = "struct S { n: u32, data: array<vec2<f32>>, }";
+        let data_type = data_type(decls, "S");
+
+        let len = |bytes| {
+            Type::from_buffer_len(&data_type, AddressSpace::Storage, bytes)
+                .unwrap()
+                .size()
+        };
+
+        // The array starts at offset 8 with a stride of 8, and always has at least one element
+        assert_eq!(len(28), 24);
+        assert_eq!(len(32), 32);
+        assert_eq!(len(0), 16);
+
+        assert!(Type::from_buffer_len(&data_type, AddressSpace::Uniform, 32).is_err());
+    }
+}
@@ -1,4 +1,9 @@
-#[derive(Debug)]
+mod input;
+mod layout;
+
+pub use input::InputData;
+
+#[derive(Clone, Debug)]
 pub enum ScalarType {
     I32,
     U32,
@@ -7,7 +12,7 @@ pub enum ScalarType {
     AI32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum VectorSize {
     N2,
     N3,
@@ -18,6 +23,31 @@ pub enum VectorSize {
 pub struct StructMember {
     pub name: String,
     pub type_desc: Type,
+    /// Explicit alignment from an `@align` attribute.
+    pub align: Option<u32>,
+}
+
+impl StructMember {
+    pub fn alignment(&self) -> u32 {
+        self.align.unwrap_or_else(|| self.type_desc.alignment())
+    }
+}
+
+/// Address space of a buffer, which determines the layout constraints that its type must satisfy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressSpace {
+    Uniform,
+    Storage,
+}
+
+impl AddressSpace {
+    pub fn from_storage_class(storage_class: ast::StorageClass) -> Option<AddressSpace> {
+        match storage_class {
+            ast::StorageClass::Uniform => Some(AddressSpace::Uniform),
+            ast::StorageClass::Storage => Some(AddressSpace::Storage),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -29,6 +59,11 @@ pub enum Type {
         size: VectorSize,
         scalar_type: ScalarType,
     },
+    Matrix {
+        columns: VectorSize,
+        rows: VectorSize,
+        scalar_type: ScalarType,
+    },
     Array {
         size: u32,
         element_type: Box<Type>,
@@ -42,6 +77,39 @@ fn aligned(size: u32, alignment: u32) -> u32 {
     ((size + (alignment - 1)) / alignment) * alignment
 }
 
+impl VectorSize {
+    fn from_count(n: u8) -> Result<VectorSize, &'static str> {
+        match n {
+            2 => Ok(VectorSize::N2),
+            3 => Ok(VectorSize::N3),
+            4 => Ok(VectorSize::N4),
+            _ => Err("invalid vector size"),
+        }
+    }
+
+    fn count(&self) -> u32 {
+        match self {
+            VectorSize::N2 => 2,
+            VectorSize::N3 => 3,
+            VectorSize::N4 => 4,
+        }
+    }
+
+    /// Size in bytes of a vector of 4-byte scalars with this number of components.
+    fn vector_size(&self) -> u32 {
+        self.count() * 4
+    }
+
+    /// Alignment of a vector of 4-byte scalars with this number of components.
+    fn vector_alignment(&self) -> u32 {
+        match self {
+            VectorSize::N2 => 8,
+            VectorSize::N3 => 16,
+            VectorSize::N4 => 16,
+        }
+    }
+}
+
 impl Type {
     pub fn buffer_size(&self) -> u32 {
         aligned(self.size(), self.alignment())
@@ -50,11 +118,11 @@ impl Type {
     pub fn size(&self) -> u32 {
         match self {
             Type::Scalar { .. } => 4,
-            Type::Vector { size, .. } => match size {
-                VectorSize::N2 => 8,
-                VectorSize::N3 => 12,
-                VectorSize::N4 => 16,
-            },
+            Type::Vector { size, .. } => size.vector_size(),
+            // A matrix is laid out as an array of column vectors
+            Type::Matrix { columns, rows, .. } => {
+                columns.count() * aligned(rows.vector_size(), rows.vector_alignment())
+            }
             Type::Array { size, element_type } => {
                 size * aligned(element_type.size(), element_type.alignment())
             }
@@ -63,9 +131,9 @@ impl Type {
                 let mut alignment = 0;
 
                 for member in members {
-                    let member_alignment = member.type_desc.alignment();
+                    let member_alignment = member.alignment();
                     let member_size = member.type_desc.size();
-                    alignment = u32::max(alignment, member.type_desc.alignment());
+                    alignment = u32::max(alignment, member_alignment);
                     size = aligned(size, member_alignment) + member_size;
                 }
 
@@ -77,40 +145,76 @@ impl Type {
     pub fn alignment(&self) -> u32 {
         match self {
             Type::Scalar { .. } => 4,
-            Type::Vector { size, .. } => match size {
-                VectorSize::N2 => 8,
-                VectorSize::N3 => 16,
-                VectorSize::N4 => 16,
-            },
+            Type::Vector { size, .. } => size.vector_alignment(),
+            Type::Matrix { rows, .. } => rows.vector_alignment(),
             Type::Array { element_type, .. } => element_type.alignment(),
             Type::Struct { members } => members
                 .iter()
-                .map(|it| it.type_desc.alignment())
+                .map(|it| it.alignment())
                 .max()
                 .expect("struct must have at least one member"),
         }
     }
 
     pub fn ranges(&self) -> Vec<(usize, usize)> {
-        let mut ranges = vec![];
+        self.fields("")
+            .into_iter()
+            .map(|field| (field.offset, field.size()))
+            .collect()
+    }
+
+    /// Returns the scalar, vector and matrix column fields that make up this type, along with
+    /// their offsets in the buffer layout.
+    ///
+    /// Field paths are built by appending member accesses and indices to `root`, e.g.
+    /// `root.member[3]`.
+    pub fn fields(&self, root: &str) -> Vec<Field> {
+        let mut fields = vec![];
 
-        fn collect_ranges(acc: &mut Vec<(usize, usize)>, mut offset: u32, type_desc: &Type) {
+        fn collect_fields(acc: &mut Vec<Field>, path: String, mut offset: u32, type_desc: &Type) {
             match type_desc {
-                Type::Scalar { .. } => acc.push((offset as _, type_desc.size() as _)),
-                Type::Vector { .. } => acc.push((offset as _, type_desc.size() as _)),
+                Type::Scalar { scalar_type } => acc.push(Field {
+                    path,
+                    offset: offset as _,
+                    scalar_type: scalar_type.clone(),
+                    components: 1,
+                }),
+                Type::Vector { size, scalar_type } => acc.push(Field {
+                    path,
+                    offset: offset as _,
+                    scalar_type: scalar_type.clone(),
+                    components: size.count(),
+                }),
+                Type::Matrix {
+                    columns,
+                    rows,
+                    scalar_type,
+                } => {
+                    let stride = aligned(rows.vector_size(), rows.vector_alignment());
+                    for i in 0..columns.count() {
+                        acc.push(Field {
+                            path: format!("{path}[{i}]"),
+                            offset: offset as _,
+                            scalar_type: scalar_type.clone(),
+                            components: rows.count(),
+                        });
+                        offset += stride;
+                    }
+                }
                 Type::Array { size, element_type } => {
                     let element_size = element_type.size();
                     let alignment = element_type.alignment();
-                    for _ in 0..*size {
-                        collect_ranges(acc, offset, element_type);
+                    for i in 0..*size {
+                        collect_fields(acc, format!("{path}[{i}]"), offset, element_type);
                         offset = aligned(offset + element_size, alignment);
                     }
                 }
                 Type::Struct { members } => {
                     for member in members {
-                        let alignment = member.type_desc.alignment();
+                        let alignment = member.alignment();
                         offset = aligned(offset, alignment);
-                        collect_ranges(acc, offset, &member.type_desc);
+                        let path = format!("{path}.{}", member.name);
+                        collect_fields(acc, path, offset, &member.type_desc);
                         let size = member.type_desc.size();
                         offset += size;
                     }
@@ -118,9 +222,25 @@ impl Type {
             }
         }
 
-        collect_ranges(&mut ranges, 0, self);
+        collect_fields(&mut fields, root.to_owned(), 0, self);
+
+        fields
+    }
+}
+
+/// A scalar or vector value within a buffer.
+#[derive(Debug)]
+pub struct Field {
+    pub path: String,
+    pub offset: usize,
+    pub scalar_type: ScalarType,
+    /// Number of scalar components, which is greater than one for vectors.
+    pub components: u32,
+}
 
-        ranges
+impl Field {
+    pub fn size(&self) -> usize {
+        self.components as usize * 4
     }
 }
 
@@ -148,12 +268,12 @@ impl TryFrom<&ast::DataType> for Type {
                 scalar_type: scalar.try_into()?,
             }),
             ast::DataType::Vector(n, scalar) => Ok(Type::Vector {
-                size: match n {
-                    2 => VectorSize::N2,
-                    3 => VectorSize::N3,
-                    4 => VectorSize::N4,
-                    _ => return Err("invalid vector size"),
-                },
+                size: VectorSize::from_count(*n)?,
+                scalar_type: scalar.try_into()?,
+            }),
+            ast::DataType::Matrix(c, r, scalar) => Ok(Type::Matrix {
+                columns: VectorSize::from_count(*c)?,
+                rows: VectorSize::from_count(*r)?,
                 scalar_type: scalar.try_into()?,
             }),
             ast::DataType::Array(inner, size) => Ok(Type::Array {
@@ -165,10 +285,18 @@ impl TryFrom<&ast::DataType> for Type {
 
                 for member in &decl.members {
                     let type_desc = Type::try_from(&member.data_type)?;
+                    let align = member
+                        .attrs
+                        .iter()
+                        .map(|attr| match attr {
+                            ast::StructMemberAttr::Align(align) => *align as u32,
+                        })
+                        .next();
 
                     members.push(StructMember {
                         name: member.name.clone(),
                         type_desc,
+                        align,
                     });
                 }
 
@@ -333,15 +333,23 @@ impl<'a> Generator<'a> {
 
         Shaders {
             safe: Module {
+                enables: vec![],
+                aliases: vec![],
                 structs: vec![],
                 consts: vec![],
+                overrides: vec![],
                 vars: global_vars.clone(),
+                const_asserts: vec![],
                 functions: safe_functions,
             },
             race: Module {
+                enables: vec![],
+                aliases: vec![],
                 structs: vec![],
                 consts: vec![],
+                overrides: vec![],
                 vars: global_vars.clone(),
+                const_asserts: vec![],
                 functions,
             },
             info: DataRaceInfo {
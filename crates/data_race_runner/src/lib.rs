@@ -142,7 +142,8 @@ fn reflect_shader(shader: &str, input_data: &HashMap<String, Vec<u8>>) -> Pipeli
         input_data
             .get(&format!("{}:{}", resource.group, resource.binding))
             .cloned()
-    });
+    })
+    .expect("invalid pipeline resource");
     pipeline_desc
 }
 
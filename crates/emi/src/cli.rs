@@ -0,0 +1,213 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use buffer_check::FloatPolicy;
+use clap::Parser;
+use common::InputData;
+use eyre::eyre;
+use harness_frontend::{ExecutionError, ExecutionEvent, Executor};
+use harness_types::ConfigId;
+use rand::prelude::StdRng;
+use rand::rngs::OsRng;
+use rand::{Rng, SeedableRng};
+use reflection_types::PipelineDescription;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to the wgsl shader program to generate variants of.
+    #[clap(action, default_value = "-")]
+    pub input: String,
+
+    /// Input data for the shader, either as a json string or a path to a json file.
+    ///
+    /// If not provided, this will be looked up in the same way as when running the shader.
+    #[clap(action)]
+    pub input_data: Option<String>,
+
+    /// Directory to write the variants to.
+    #[clap(short, long, action, default_value = "emi")]
+    pub output: PathBuf,
+
+    /// Number of variants to generate.
+    #[clap(short = 'n', long, action, default_value = "10")]
+    pub count: u32,
+
+    /// Optional u64 to seed the random generator.
+    #[clap(long, action)]
+    pub seed: Option<u64>,
+
+    /// Run the original shader and each variant on this configuration and compare their outputs.
+    ///
+    /// The variants are equivalent to the original for its inputs, so any difference in the output
+    /// buffers is a bug in the configuration.
+    #[clap(short, long, action)]
+    pub config: Option<ConfigId>,
+
+    /// Timeout in seconds for each execution when running with `--config`.
+    ///
+    /// Use 0 to disable the timeout.
+    #[clap(long, action, default_value = "30")]
+    pub timeout: u64,
+
+    #[clap(flatten)]
+    pub emi: crate::Options,
+}
+
+/// Generates variants of a shader and writes them to the output directory.
+///
+/// If a config is given in the options, the shaders are also executed using `executor`, which
+/// must then be provided.
+pub fn run(options: Options, executor: Option<&dyn Executor>) -> eyre::Result<()> {
+    let shader = harness_frontend::read_shader_from_path(&options.input)?;
+    let module = parser::try_parse(&shader)?;
+
+    // Round trip through the byte format so that the values are typed, whichever format they
+    // were given in
+    let input_data =
+        harness_frontend::read_input_data(&options.input, options.input_data.as_deref())?;
+    let bytes = input_data.to_bytes(&module).map_err(|e| eyre!(e))?;
+    let input_data = InputData::from_bytes(&module, &bytes).map_err(|e| eyre!(e))?;
+
+    let seed = match options.seed {
+        Some(seed) => seed,
+        None => OsRng.gen(),
+    };
+
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    fs::create_dir_all(&options.output)?;
+    fs::write(
+        options.output.join("inputs.json"),
+        serde_json::to_string_pretty(&input_data)?,
+    )?;
+
+    let oracle = match &options.config {
+        Some(config) => {
+            let executor = executor.ok_or_else(|| eyre!("no harness available to run variants"))?;
+            let timeout = match options.timeout {
+                0 => None,
+                timeout => Some(Duration::from_secs(timeout)),
+            };
+
+            let (pipeline_desc, type_descs) =
+                harness_frontend::reflect_shader(&shader, input_data.clone())?;
+
+            Some(Oracle {
+                executor,
+                config,
+                timeout,
+                pipeline_desc,
+                type_descs,
+            })
+        }
+        None => None,
+    };
+
+    let init_data = serde_json::to_string(&input_data)?;
+    let write_shader =
+        |name: String, header: String, module: &ast::Module| -> eyre::Result<String> {
+            let mut output = format!("// {init_data}\n{header}\n");
+            ast::writer::Writer::default().write_module(&mut output, module)?;
+
+            let path = options.output.join(name);
+            fs::write(&path, &output)?;
+            eprintln!("wrote {}", path.display());
+
+            Ok(output)
+        };
+
+    let original = write_shader("original.wgsl".to_owned(), String::new(), &module)?;
+    let expected = match &oracle {
+        Some(oracle) => Some(oracle.execute(&original)?),
+        None => None,
+    };
+
+    let mut failures = 0;
+
+    for i in 0..options.count {
+        let variant = crate::variant(&module, &input_data, &mut rng, &options.emi);
+        let header = format!("// Seed: {seed}\n// Variant: {i}\n");
+        let shader = write_shader(format!("variant-{i}.wgsl"), header, &variant)?;
+
+        if let (Some(oracle), Some(expected)) = (&oracle, &expected) {
+            match oracle.execute(&shader) {
+                Ok(buffers) if oracle.is_equal(expected, &buffers) => {
+                    eprintln!("variant {i}: ok");
+                }
+                Ok(_) => {
+                    eprintln!("variant {i}: mismatch");
+                    failures += 1;
+                }
+                Err(e) => {
+                    eprintln!("variant {i}: {e:#}");
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(eyre!(
+            "{failures} of {} variants didn't produce the same output as the original",
+            options.count
+        ));
+    }
+
+    Ok(())
+}
+
+/// Executes shaders on a single configuration and compares their outputs.
+struct Oracle<'a> {
+    executor: &'a dyn Executor,
+    config: &'a ConfigId,
+    timeout: Option<Duration>,
+    pipeline_desc: PipelineDescription,
+    type_descs: Vec<common::Type>,
+}
+
+impl<'a> Oracle<'a> {
+    fn execute(&self, shader: &str) -> eyre::Result<Vec<Vec<u8>>> {
+        let mut result = None;
+        let mut on_event = |event: ExecutionEvent| -> Result<(), ExecutionError> {
+            match event {
+                ExecutionEvent::Success(buffers, _) => result = Some(Ok(buffers)),
+                ExecutionEvent::Failure(stderr) => {
+                    let stderr = String::from_utf8_lossy(&stderr);
+                    result = Some(Err(eyre!("execution failed: {stderr}")));
+                }
+                ExecutionEvent::Timeout => result = Some(Err(eyre!("execution timed out"))),
+                _ => {}
+            }
+            Ok(())
+        };
+
+        self.executor
+            .execute(
+                shader,
+                1,
+                false,
+                &self.pipeline_desc,
+                std::slice::from_ref(self.config),
+                self.timeout,
+                &mut on_event,
+            )
+            .map_err(|e| eyre!(e))?;
+
+        result.unwrap_or_else(|| Err(eyre!("no result from executing on {}", self.config)))
+    }
+
+    /// Returns whether two executions produced the same buffers.
+    ///
+    /// Floats are compared exactly too, since variants never change float expressions.
+    fn is_equal(&self, expected: &[Vec<u8>], actual: &[Vec<u8>]) -> bool {
+        let executions = [expected.to_vec(), actual.to_vec()];
+        buffer_check::compare(
+            executions.iter(),
+            &self.pipeline_desc,
+            &self.type_descs,
+            FloatPolicy::Exact,
+        )
+        .is_ok()
+    }
+}
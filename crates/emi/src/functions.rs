@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::fold::{self, Fold};
+use ast::types::DataType;
+use ast::visit::{self, Visit};
+use ast::{
+    Expr, ExprNode, FnCallExpr, FnDecl, FnInput, FnOutput, LetDeclStatement, Module,
+    ReturnStatement, Statement, VarDeclStatement, VarExpr,
+};
+
+use crate::ExprInfo;
+
+/// A function whose body is a single return statement with a pure expression, so that calls to it
+/// can be inlined by substituting the arguments into the returned expression.
+///
+/// This covers most of the safe wrappers inserted by the reconditioner.
+pub struct SimpleFn {
+    params: Vec<String>,
+    body: ExprNode,
+    /// Names in the body which refer to module-scope declarations rather than parameters.
+    free_names: HashSet<String>,
+}
+
+impl SimpleFn {
+    /// Returns whether any of the module-scope declarations referenced by the body would be
+    /// shadowed by one of `locals` if the body was inlined.
+    pub fn is_shadowed_by(&self, locals: &HashSet<String>) -> bool {
+        !self.free_names.is_disjoint(locals)
+    }
+
+    /// Returns the body of the function with the parameters replaced by `args`.
+    ///
+    /// Arguments may be evaluated any number of times (including not at all), so they must be
+    /// pure. The body must not be shadowed by the caller's locals (see
+    /// [`SimpleFn::is_shadowed_by`]).
+    pub fn inline(&self, args: Vec<ExprNode>) -> ExprNode {
+        let mut substitute = Substitute(self.params.iter().cloned().zip(args).collect());
+        substitute.fold_expr(self.body.clone())
+    }
+}
+
+/// Finds the functions in a module whose calls can be inlined.
+pub fn simple_fns(module: &Module) -> HashMap<String, SimpleFn> {
+    let names = module
+        .functions
+        .iter()
+        .map(|it| it.name.clone())
+        .collect::<HashSet<_>>();
+
+    let mut fns = HashMap::new();
+
+    // Functions can only call functions declared before them, so one pass is enough to find
+    // functions that only call other simple functions
+    for decl in &module.functions {
+        if let [Statement::Return(ReturnStatement { value: Some(body) })] = decl.body.as_slice() {
+            let info = ExprInfo::of(body);
+            let is_pure = !info.has_pointers
+                && info
+                    .calls
+                    .iter()
+                    .all(|it| !names.contains(it) || fns.contains_key(it));
+
+            let has_ptr_params = decl
+                .inputs
+                .iter()
+                .any(|it| matches!(it.data_type, DataType::Ptr(_)));
+
+            if is_pure && !has_ptr_params {
+                let params = decl
+                    .inputs
+                    .iter()
+                    .map(|it| it.name.clone())
+                    .collect::<Vec<_>>();
+
+                let free_names = info
+                    .vars
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .filter(|it| !params.contains(it))
+                    .chain(info.calls)
+                    .collect();
+
+                let body = body.clone();
+                fns.insert(
+                    decl.name.clone(),
+                    SimpleFn {
+                        params,
+                        body,
+                        free_names,
+                    },
+                );
+            }
+        }
+    }
+
+    fns
+}
+
+/// Returns the names of the parameters and local variables declared anywhere in a function.
+///
+/// This doesn't take scopes into account, so it may include names which aren't in scope at a
+/// particular point in the function.
+pub fn local_names(decl: &FnDecl) -> HashSet<String> {
+    struct LocalNames(HashSet<String>);
+
+    impl Visit for LocalNames {
+        fn visit_let_decl_stmt(&mut self, stmt: &LetDeclStatement) {
+            self.0.insert(stmt.ident.clone());
+            visit::walk_let_decl_stmt(self, stmt)
+        }
+
+        fn visit_var_decl_stmt(&mut self, stmt: &VarDeclStatement) {
+            self.0.insert(stmt.ident.clone());
+            visit::walk_var_decl_stmt(self, stmt)
+        }
+    }
+
+    let mut names = LocalNames(decl.inputs.iter().map(|it| it.name.clone()).collect());
+    names.visit_block(&decl.body);
+    names.0
+}
+
+/// Moves an expression into a new function named `name`, returning the function along with a
+/// call to it which replaces the expression.
+///
+/// `params` are the local variables referenced by the expression, which are passed to the new
+/// function by value.
+pub fn outline(
+    name: String,
+    node: ExprNode,
+    params: Vec<(String, DataType)>,
+) -> (FnDecl, ExprNode) {
+    let data_type = node.data_type.clone();
+
+    let args = params
+        .iter()
+        .map(|(name, data_type)| VarExpr::new(name).into_node(data_type.clone()))
+        .collect();
+
+    // Inside the new function the variables are parameters, so they are no longer references
+    let mut substitute = Substitute(
+        params
+            .iter()
+            .map(|(name, data_type)| {
+                let param = VarExpr::new(name).into_node(data_type.dereference().clone());
+                (name.clone(), param)
+            })
+            .collect(),
+    );
+
+    let decl = FnDecl {
+        attrs: vec![],
+        name: name.clone(),
+        inputs: params
+            .iter()
+            .map(|(name, data_type)| FnInput::new(name, data_type.dereference().clone()))
+            .collect(),
+        output: Some(FnOutput::new(data_type.clone())),
+        body: vec![ReturnStatement::new(substitute.fold_expr(node)).into()],
+    };
+
+    (decl, FnCallExpr::new(name, args).into_node(data_type))
+}
+
+/// Replaces references to variables with the given expressions.
+struct Substitute(HashMap<String, ExprNode>);
+
+impl Fold for Substitute {
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        if let Expr::Var(expr) = &node.expr {
+            if let Some(replacement) = self.0.get(&expr.ident) {
+                return replacement.clone();
+            }
+        }
+
+        fold::walk_expr(self, node)
+    }
+}
@@ -0,0 +1,114 @@
+use ast::types::{DataType, MemoryViewType};
+use ast::{
+    BinOp, BinOpExpr, ExprNode, Lit, Module, Postfix, PostfixExpr, ScalarType, StorageClass,
+    VarExpr, VarQualifier,
+};
+use common::InputData;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
+use serde_json::Value;
+
+/// An integer in a uniform buffer, along with its value in the input data.
+struct KnownValue {
+    expr: ExprNode,
+    value: Lit,
+}
+
+/// Integers in uniform buffers whose values are known when generating a variant.
+///
+/// The compiler can't see these values, so expressions built from them are opaque to it even
+/// though their results are fixed for the given input data.
+pub struct KnownValues(Vec<KnownValue>);
+
+impl KnownValues {
+    pub fn new(module: &Module, inputs: &InputData) -> KnownValues {
+        let mut values = vec![];
+
+        for var in &module.vars {
+            if let Some(VarQualifier {
+                storage_class: StorageClass::Uniform,
+                ..
+            }) = var.qualifier
+            {
+                if let Some(value) = inputs.0.get(&var.name) {
+                    let data_type = DataType::Ref(MemoryViewType::new(
+                        var.data_type.clone(),
+                        StorageClass::Uniform,
+                    ));
+
+                    let expr = VarExpr::new(&var.name).into_node(data_type);
+                    collect(&mut values, expr, &var.data_type, value);
+                }
+            }
+        }
+
+        KnownValues(values)
+    }
+
+    /// Returns a comparison against a known value which evaluates to `result`.
+    pub fn predicate(&self, rng: &mut impl Rng, result: bool) -> Option<ExprNode> {
+        let known = self.0.choose(rng)?;
+
+        let ops = if result {
+            [BinOp::Equal, BinOp::LessEqual, BinOp::GreaterEqual]
+        } else {
+            [BinOp::NotEqual, BinOp::Less, BinOp::Greater]
+        };
+
+        let op = *ops.choose(rng).unwrap();
+        Some(BinOpExpr::new(op, known.expr.clone(), known.value).into())
+    }
+
+    /// Returns an expression of the given integer type which evaluates to zero.
+    pub fn zero(&self, rng: &mut impl Rng, scalar_type: ScalarType) -> Option<ExprNode> {
+        let data_type = DataType::Scalar(scalar_type);
+        let known = self
+            .0
+            .iter()
+            .filter(|it| it.value.data_type() == data_type)
+            .choose(rng)?;
+
+        Some(BinOpExpr::new(BinOp::Minus, known.expr.clone(), known.expr.clone()).into())
+    }
+}
+
+fn collect(values: &mut Vec<KnownValue>, expr: ExprNode, data_type: &DataType, value: &Value) {
+    let postfix = |postfix: Postfix| -> ExprNode { PostfixExpr::new(expr.clone(), postfix).into() };
+
+    match (data_type, value) {
+        (DataType::Scalar(ScalarType::I32), Value::Number(n)) => {
+            if let Some(n) = n.as_i64() {
+                let value = Lit::I32(n as i32);
+                values.push(KnownValue { expr, value });
+            }
+        }
+        (DataType::Scalar(ScalarType::U32), Value::Number(n)) => {
+            if let Some(n) = n.as_u64() {
+                let value = Lit::U32(n as u32);
+                values.push(KnownValue { expr, value });
+            }
+        }
+        (DataType::Vector(_, ty), Value::Array(components)) => {
+            for (component, value) in ["x", "y", "z", "w"].iter().zip(components) {
+                let expr = postfix(Postfix::member(*component));
+                collect(values, expr, &DataType::Scalar(*ty), value);
+            }
+        }
+        (DataType::Array(ty, Some(_)), Value::Array(elements)) => {
+            for (i, value) in elements.iter().enumerate() {
+                let expr = postfix(Postfix::index(Lit::U32(i as u32)));
+                collect(values, expr, ty, value);
+            }
+        }
+        (DataType::Struct(decl), Value::Object(members)) => {
+            for member in &decl.members {
+                if let Some(value) = members.get(&member.name) {
+                    let expr = postfix(Postfix::member(&member.name));
+                    collect(values, expr, &member.data_type, value);
+                }
+            }
+        }
+        // Floats are left out since their values may not survive the round trip through a literal
+        _ => {}
+    }
+}
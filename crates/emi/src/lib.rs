@@ -0,0 +1,484 @@
+//! Generation of program variants that are equivalent modulo inputs (EMI).
+//!
+//! A variant is rewritten using the values of its uniform inputs, which are known when the variant
+//! is generated but hidden from the compiler. For those inputs the variant computes exactly the
+//! same results as the original program, so running the two on the same configuration and
+//! comparing their output buffers gives an oracle which doesn't need a second implementation.
+
+pub mod cli;
+mod functions;
+mod inputs;
+
+use std::collections::{HashMap, HashSet};
+
+use ast::fold::{self, Fold};
+use ast::types::DataType;
+use ast::visit::{self, Visit};
+use ast::{
+    BinOp, BinOpExpr, Expr, ExprNode, FnCallExpr, FnDecl, IfStatement, Module, ScalarType,
+    Statement, TypeConsExpr, UnOp,
+};
+use clap::Parser;
+use common::InputData;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::functions::SimpleFn;
+use crate::inputs::KnownValues;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Probability of inserting a dead block before each statement.
+    #[clap(long, action, default_value = "0.1")]
+    pub dead_blocks: f64,
+
+    /// Probability of wrapping each integer or boolean expression in an opaque identity.
+    #[clap(long, action, default_value = "0.05")]
+    pub identities: f64,
+
+    /// Probability of moving each integer or boolean expression into a new function.
+    #[clap(long, action, default_value = "0.02")]
+    pub outline: f64,
+
+    /// Probability of inlining each call to a function which just returns an expression.
+    #[clap(long, action, default_value = "0.5")]
+    pub inline: f64,
+}
+
+/// Generates a variant of `module` which is equivalent to it for the given input data.
+pub fn variant(
+    module: &Module,
+    inputs: &InputData,
+    rng: &mut impl Rng,
+    options: &Options,
+) -> Module {
+    let mut emi = Emi {
+        rng,
+        options,
+        known: KnownValues::new(module, inputs),
+        globals: module
+            .vars
+            .iter()
+            .map(|it| it.name.clone())
+            .chain(module.overrides.iter().map(|it| it.name.clone()))
+            .chain(module.consts.iter().map(|it| it.name.clone()))
+            .collect(),
+        consts: module.consts.iter().map(|it| it.name.clone()).collect(),
+        fn_names: module.functions.iter().map(|it| it.name.clone()).collect(),
+        locals: HashSet::new(),
+        simple_fns: functions::simple_fns(module),
+        outlined: vec![],
+    };
+
+    let mut functions = vec![];
+
+    for decl in module.functions.iter().cloned() {
+        let decl = emi.fold_fn_decl(decl);
+
+        // Outlined functions go just before the function they came from, so that everything they
+        // call has already been declared
+        functions.append(&mut emi.outlined);
+        functions.push(decl);
+    }
+
+    Module {
+        functions,
+        ..module.clone()
+    }
+}
+
+struct Emi<'a, R> {
+    rng: &'a mut R,
+    options: &'a Options,
+    known: KnownValues,
+    /// Names of module-scope declarations, which outlined functions can refer to directly.
+    globals: HashSet<String>,
+    /// Names of module-scope constants.
+    consts: HashSet<String>,
+    fn_names: HashSet<String>,
+    /// Names of the parameters and local variables of the function currently being transformed,
+    /// any of which may shadow a module-scope declaration.
+    locals: HashSet<String>,
+    simple_fns: HashMap<String, SimpleFn>,
+    /// Functions created by outlining expressions in the function currently being transformed.
+    outlined: Vec<FnDecl>,
+}
+
+impl<'a, R: Rng> Fold for Emi<'a, R> {
+    fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+        self.locals = functions::local_names(&decl);
+        fold::walk_fn_decl(self, decl)
+    }
+
+    fn fold_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        let block = fold::walk_block(self, block);
+        let mut stmts = Vec::with_capacity(block.len());
+
+        for stmt in block {
+            if self.rng.gen_bool(self.options.dead_blocks) {
+                if let Some(dead_block) = self.gen_dead_block(&stmts) {
+                    stmts.push(dead_block);
+                }
+            }
+
+            stmts.push(stmt);
+        }
+
+        stmts
+    }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        let node = fold::walk_expr(self, node);
+        let info = ExprInfo::of(&node);
+
+        // An expression which doesn't refer to any variables may be a const-expression, which is
+        // evaluated using abstract types and must stay that way (e.g. switch case selectors)
+        if !self.is_runtime(&info) {
+            return node;
+        }
+
+        // Floats are left alone, since moving a float computation may change how the compiler
+        // optimises it (e.g. by contracting it into a fused multiply-add)
+        if !matches!(
+            node.data_type,
+            DataType::Scalar(ScalarType::I32 | ScalarType::U32 | ScalarType::Bool)
+                | DataType::Vector(_, ScalarType::I32 | ScalarType::U32 | ScalarType::Bool)
+        ) {
+            return node;
+        }
+
+        if let Expr::FnCall(expr) = &node.expr {
+            if self.can_inline(expr) && self.rng.gen_bool(self.options.inline) {
+                return self.simple_fns[&expr.ident].inline(expr.args.clone());
+            }
+        }
+
+        if !matches!(node.expr, Expr::Var(_)) && self.rng.gen_bool(self.options.outline) {
+            if let Some(params) = self.outline_params(&info) {
+                return self.outline(node, params);
+            }
+        }
+
+        if self.rng.gen_bool(self.options.identities) {
+            return self.gen_identity(node);
+        }
+
+        node
+    }
+}
+
+impl<'a, R: Rng> Emi<'a, R> {
+    fn is_runtime(&self, info: &ExprInfo) -> bool {
+        info.vars
+            .iter()
+            .any(|(name, _)| !self.consts.contains(name))
+    }
+
+    fn can_inline(&self, expr: &FnCallExpr) -> bool {
+        let Some(simple_fn) = self.simple_fns.get(&expr.ident) else {
+            return false;
+        };
+
+        // The body would refer to a local instead of the declaration it refers to in the callee
+        if simple_fn.is_shadowed_by(&self.locals) {
+            return false;
+        }
+
+        // Arguments are substituted into the function body, so they must be free of side effects
+        // and must not be const-expressions
+        expr.args.iter().all(|arg| {
+            let info = ExprInfo::of(arg);
+            self.is_runtime(&info)
+                && !info.has_pointers
+                && info
+                    .calls
+                    .iter()
+                    .all(|it| !self.fn_names.contains(it) || self.simple_fns.contains_key(it))
+        })
+    }
+
+    /// Returns the local variables to pass to a function outlined from an expression, or `None`
+    /// if the expression can't be outlined.
+    fn outline_params(&self, info: &ExprInfo) -> Option<Vec<(String, DataType)>> {
+        if info.has_pointers {
+            return None;
+        }
+
+        let mut params: Vec<(String, DataType)> = vec![];
+
+        for (name, data_type) in &info.vars {
+            let is_global = self.globals.contains(name) && !self.locals.contains(name);
+            if is_global || params.iter().any(|(it, _)| it == name) {
+                continue;
+            }
+
+            // Arrays and structs are left out, since indexing an array parameter with a dynamic
+            // index isn't supported by all implementations
+            if !matches!(
+                data_type.dereference(),
+                DataType::Scalar(_) | DataType::Vector(_, _)
+            ) {
+                return None;
+            }
+
+            params.push((name.clone(), data_type.clone()));
+        }
+
+        Some(params)
+    }
+
+    fn outline(&mut self, node: ExprNode, params: Vec<(String, DataType)>) -> ExprNode {
+        let name = (0..)
+            .map(|i| format!("_wgslsmith_emi_{i}"))
+            .find(|it| !self.fn_names.contains(it))
+            .unwrap();
+
+        self.fn_names.insert(name.clone());
+
+        let (decl, call) = functions::outline(name, node, params);
+        self.outlined.push(decl);
+        call
+    }
+
+    /// Generates an `if` statement which is never executed, containing a copy of one of the
+    /// statements preceding it in the block (which is guaranteed to be valid at that point).
+    fn gen_dead_block(&mut self, preceding: &[Statement]) -> Option<Statement> {
+        let stmt = preceding.choose(self.rng)?.clone();
+        let condition = self.known.predicate(self.rng, false)?;
+        Some(IfStatement::new(condition, vec![stmt]).into())
+    }
+
+    /// Wraps an integer or boolean expression in an operation which doesn't change its value.
+    fn gen_identity(&mut self, node: ExprNode) -> ExprNode {
+        let data_type = node.data_type.clone();
+        let scalar_type = match data_type {
+            DataType::Scalar(ty) | DataType::Vector(_, ty) => ty,
+            _ => unreachable!(),
+        };
+
+        // x + (u - u)
+        if scalar_type != ScalarType::Bool && self.rng.gen_bool(0.5) {
+            if let Some(zero) = self.known.zero(self.rng, scalar_type) {
+                return BinOpExpr::new(BinOp::Plus, node, zero).into();
+            }
+        }
+
+        // select(T(), x, true) or select(x, T(), false)
+        let result = self.rng.gen_bool(0.5);
+        let condition = match self.known.predicate(self.rng, result) {
+            Some(condition) => condition,
+            None => return node,
+        };
+
+        let other = TypeConsExpr::new(data_type.clone(), vec![]).into();
+        let (f, t) = if result { (other, node) } else { (node, other) };
+
+        FnCallExpr::new("select", vec![f, t, condition]).into_node(data_type)
+    }
+}
+
+/// Properties of an expression which determine how it can be transformed.
+#[derive(Default)]
+struct ExprInfo {
+    /// Variables referenced by the expression, along with their types.
+    vars: Vec<(String, DataType)>,
+    /// Functions called by the expression.
+    calls: Vec<String>,
+    /// Whether the expression takes the address of or dereferences anything.
+    has_pointers: bool,
+}
+
+impl ExprInfo {
+    fn of(node: &ExprNode) -> ExprInfo {
+        let mut info = ExprInfo::default();
+        info.visit_expr(node);
+        info
+    }
+}
+
+impl Visit for ExprInfo {
+    fn visit_expr(&mut self, node: &ExprNode) {
+        match &node.expr {
+            Expr::Var(expr) => self.vars.push((expr.ident.clone(), node.data_type.clone())),
+            Expr::UnOp(expr) if matches!(expr.op, UnOp::AddressOf | UnOp::Deref) => {
+                self.has_pointers = true
+            }
+            Expr::FnCall(expr) => self.calls.push(expr.ident.clone()),
+            _ => {}
+        }
+
+        visit::walk_expr(self, node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use reflection_types::{PipelineDescription, PipelineResource, ResourceKind};
+
+    use super::*;
+
+    const SHADER: &str = r#"
+struct Input {
+    a: i32,
+    b: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> u_input: Input;
+
+@group(0) @binding(1)
+var<storage, read_write> s_output: array<u32, 4>;
+
+fn add(x: u32, y: u32) -> u32 {
+    return x + y;
+}
+
+fn abs_i32(x: i32) -> i32 {
+    return select(x, -x, x < 0);
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var total = 0u;
+    for (var i = 0u; i < u_input.b; i = i + 1u) {
+        total = add(total, i);
+        if (total > 4u) {
+            s_output[1] = s_output[1] + 1u;
+        }
+    }
+    s_output[0] = total;
+    s_output[2] = u32(abs_i32(u_input.a));
+    let flag = u_input.a < 0 && u_input.b != 0u;
+    s_output[3] = select(1u, 2u, flag);
+}
+"#;
+
+    const INPUTS: &str = r#"{"u_input": {"a": -3, "b": 5}}"#;
+
+    fn inputs() -> InputData {
+        serde_json::from_str(INPUTS).unwrap()
+    }
+
+    fn write(module: &Module) -> String {
+        let mut output = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut output, module)
+            .unwrap();
+        output
+    }
+
+    /// Runs the shader in the interpreter, returning the contents of `s_output`.
+    fn run(module: &Module, inputs: &InputData) -> Vec<u8> {
+        let input = inputs.to_bytes(module).unwrap().remove("0:0").unwrap();
+        let input_size = input.len() as u32;
+        let resource = |kind, binding, size, init| PipelineResource {
+            name: format!("buffer_{binding}"),
+            kind,
+            group: 0,
+            binding,
+            size,
+            init,
+        };
+
+        let meta = PipelineDescription {
+            resources: vec![
+                resource(ResourceKind::UniformBuffer, 0, input_size, Some(input)),
+                resource(ResourceKind::StorageBuffer, 1, 16, None),
+            ],
+        };
+
+        let mut buffers = interp::run(&write(module), 1, &meta).unwrap();
+        buffers.pop().unwrap()
+    }
+
+    fn options(inline: f64) -> Options {
+        Options {
+            dead_blocks: 0.5,
+            identities: 0.5,
+            outline: 0.2,
+            inline,
+        }
+    }
+
+    fn inline_only() -> Options {
+        Options {
+            dead_blocks: 0.0,
+            identities: 0.0,
+            outline: 0.0,
+            inline: 1.0,
+        }
+    }
+
+    #[test]
+    fn variants_produce_the_same_output() {
+        let module = parser::parse(SHADER);
+        let inputs = inputs();
+        let expected = run(&module, &inputs);
+
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let variant = variant(&module, &inputs, &mut rng, &options(0.5));
+
+            assert_ne!(variant, module, "seed {seed}");
+            assert_eq!(run(&variant, &inputs), expected, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn calls_are_inlined() {
+        let module = parser::parse(SHADER);
+        let mut rng = StdRng::seed_from_u64(0);
+        let variant = write(&variant(&module, &inputs(), &mut rng, &inline_only()));
+
+        assert!(!variant.contains("add(total, i)"), "{variant}");
+        assert!(!variant.contains("abs_i32(u_input.a)"), "{variant}");
+    }
+
+    #[test]
+    fn calls_are_not_inlined_into_shadowing_scopes() {
+        const SHADER: &str = r#"
+struct Input {
+    a: i32,
+    b: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> u_input: Input;
+
+@group(0) @binding(1)
+var<storage, read_write> s_output: array<u32, 4>;
+
+var<private> offset: u32 = 7u;
+
+fn add_offset(x: u32) -> u32 {
+    return x + offset;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    let offset = 1u;
+    s_output[0] = add_offset(u_input.b) + offset;
+}
+"#;
+
+        let module = parser::parse(SHADER);
+        let inputs = inputs();
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let variant = variant(&module, &inputs, &mut rng, &inline_only());
+
+            assert_eq!(variant, module, "seed {seed}");
+        }
+
+        // Outlined expressions pass the shadowing local as a parameter
+        let expected = run(&module, &inputs);
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let variant = variant(&module, &inputs, &mut rng, &options(1.0));
+
+            assert_eq!(run(&variant, &inputs), expected, "seed {seed}");
+        }
+    }
+}
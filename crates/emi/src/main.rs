@@ -0,0 +1,6 @@
+use clap::Parser;
+use emi::cli::{self, Options};
+
+fn main() -> eyre::Result<()> {
+    cli::run(Options::parse(), None)
+}
@@ -32,7 +32,7 @@ pub enum Feature {
 
 pub fn run(options: Options) -> eyre::Result<()> {
     let input = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&input);
+    let ast = parser::try_parse(&input)?;
 
     // TODO: Potenially we want options for flow add here
     let result = crate::flow(ast);
@@ -0,0 +1,128 @@
+use ast::fold::{self, Fold};
+use ast::*;
+
+/// Inserts a statement at the start of every block in the program.
+///
+/// Blocks are numbered in the order that they are instrumented. Flow analysis and UB insertion
+/// both use this so that the block ids reported by a flow-instrumented shader can be used to
+/// select blocks for UB insertion in the original shader.
+///
+/// Statements returned by the callback are never themselves traversed, so they can't affect the
+/// numbering of the remaining blocks.
+pub struct BlockInstrumenter<F> {
+    block_count: u32,
+    build: F,
+}
+
+impl<F: FnMut(u32) -> Option<Statement>> BlockInstrumenter<F> {
+    pub fn new(build: F) -> BlockInstrumenter<F> {
+        BlockInstrumenter {
+            block_count: 0,
+            build,
+        }
+    }
+
+    /// Returns the number of blocks that have been instrumented so far.
+    pub fn block_count(&self) -> u32 {
+        self.block_count
+    }
+
+    fn next(&mut self) -> Option<Statement> {
+        let block = self.block_count;
+        self.block_count += 1;
+        (self.build)(block)
+    }
+
+    /// Inserts the statement at the start of the block.
+    fn prepend(&mut self, body: Vec<Statement>) -> Vec<Statement> {
+        let mut new_body: Vec<Statement> = self.next().into_iter().collect();
+        new_body.extend(self.fold_block(body));
+        new_body
+    }
+
+    /// Inserts the statement before the block, after wrapping the block in a compound statement.
+    fn wrap(&mut self, body: Vec<Statement>) -> Vec<Statement> {
+        let body = self.fold_block(body);
+        match self.next() {
+            Some(stmt) => vec![Statement::Compound(vec![stmt, body.into()])],
+            None => body,
+        }
+    }
+}
+
+impl<F: FnMut(u32) -> Option<Statement>> Fold for BlockInstrumenter<F> {
+    fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+        // Helper functions inserted by wgslsmith (e.g. reconditioning wrappers) aren't instrumented
+        if decl.name.starts_with("_wgslsmith_") {
+            return fold::walk_fn_decl(self, decl);
+        }
+
+        FnDecl {
+            body: self.prepend(decl.body),
+            ..decl
+        }
+    }
+
+    fn fold_if_stmt(&mut self, stmt: IfStatement) -> IfStatement {
+        IfStatement {
+            condition: stmt.condition,
+            body: self.wrap(stmt.body),
+            else_: stmt.else_.map(|els| Box::new(self.fold_else(*els))),
+        }
+    }
+
+    fn fold_else(&mut self, els: Else) -> Else {
+        match els {
+            Else::If(stmt) => Else::If(self.fold_if_stmt(stmt)),
+            Else::Else(stmts) => Else::Else(self.prepend(stmts)),
+        }
+    }
+
+    fn fold_loop_stmt(&mut self, stmt: LoopStatement) -> LoopStatement {
+        // The body can't be wrapped in a compound statement since declarations in the
+        // loop body must remain visible to the continuing block
+        LoopStatement {
+            body: self.prepend(stmt.body),
+            continuing: stmt.continuing.map(|it| self.fold_continuing_stmt(it)),
+        }
+    }
+
+    fn fold_while_stmt(&mut self, stmt: WhileStatement) -> WhileStatement {
+        WhileStatement {
+            condition: stmt.condition,
+            body: self.wrap(stmt.body),
+        }
+    }
+
+    fn fold_switch_stmt(&mut self, stmt: SwitchStatement) -> SwitchStatement {
+        SwitchStatement {
+            selector: stmt.selector,
+            cases: stmt
+                .cases
+                .into_iter()
+                .map(|it| self.fold_switch_case(it))
+                .collect(),
+            default_selectors: stmt.default_selectors,
+            default: self.wrap(stmt.default),
+        }
+    }
+
+    fn fold_switch_case(&mut self, case: SwitchCase) -> SwitchCase {
+        SwitchCase {
+            selectors: case.selectors,
+            body: self.wrap(case.body),
+        }
+    }
+
+    fn fold_for_loop_stmt(&mut self, stmt: ForLoopStatement) -> ForLoopStatement {
+        ForLoopStatement {
+            header: stmt.header,
+            body: self.wrap(stmt.body),
+        }
+    }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        // Expressions can't contain blocks
+        node
+    }
+}
@@ -0,0 +1,459 @@
+//! Csmith-style checksums of the values computed by a program.
+//!
+//! Without a checksum, only the value assigned to the output buffer at the end of the entry point
+//! is observable, so most of the generated computation is dead. Instead, the value of every local
+//! variable at the end of its block, every value returned from a function, and every private
+//! global at the end of the entry point is folded into a running hash which is written to the
+//! output buffer. Wrong code anywhere in the program then changes the observable output.
+
+use ast::fold::{self, Fold};
+use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::{
+    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, ExprNode, FnAttr,
+    FnCallExpr, FnDecl, FnInput, FnOutput, GlobalVarDecl, LetDeclStatement, Lit, Module, Postfix,
+    PostfixExpr, ReturnStatement, Statement, StorageClass, TypeConsExpr, VarDeclStatement, VarExpr,
+    VarQualifier,
+};
+
+/// Name of the private global variable holding the running hash.
+const CHECKSUM_VAR: &str = "CHECKSUM";
+
+/// Name of the member of the output buffer struct that the checksum is written to.
+pub const OUTPUT_MEMBER: &str = "checksum";
+
+/// Instruments a module to compute a checksum, which is assigned to `output` at the end of the
+/// entry point.
+pub fn insert(mut module: Module, output: AssignmentLhs) -> Module {
+    let mut checksum = Checksum {
+        output,
+        is_entry_point: false,
+        return_count: 0,
+        globals: module
+            .vars
+            .iter()
+            .filter(|decl| {
+                matches!(
+                    decl.qualifier,
+                    Some(VarQualifier {
+                        storage_class: StorageClass::Private,
+                        ..
+                    })
+                )
+            })
+            .map(|decl| (decl.name.clone(), decl.data_type.clone()))
+            .collect(),
+        hashed_types: vec![],
+    };
+
+    module.functions = module
+        .functions
+        .into_iter()
+        .map(|decl| checksum.fold_fn_decl(decl))
+        .collect();
+
+    let mut helpers = checksum
+        .hashed_types
+        .iter()
+        .map(gen_hash_fn)
+        .collect::<Vec<_>>();
+
+    helpers.append(&mut module.functions);
+    module.functions = helpers;
+
+    module.vars.push(GlobalVarDecl {
+        attrs: vec![],
+        qualifier: Some(VarQualifier {
+            storage_class: StorageClass::Private,
+            access_mode: None,
+        }),
+        name: CHECKSUM_VAR.to_owned(),
+        data_type: ScalarType::U32.into(),
+        initializer: None,
+    });
+
+    module
+}
+
+struct Checksum {
+    output: AssignmentLhs,
+    is_entry_point: bool,
+    return_count: u32,
+    globals: Vec<(String, DataType)>,
+    /// Types which need a hash function, in an order where each type follows its components.
+    hashed_types: Vec<DataType>,
+}
+
+impl Fold for Checksum {
+    fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+        self.is_entry_point = decl.attrs.iter().any(|it| matches!(it, FnAttr::Stage(_)));
+
+        let mut decl = fold::walk_fn_decl(self, decl);
+
+        if self.is_entry_point {
+            for (name, data_type) in self.globals.clone() {
+                decl.body.extend(self.hash_stmt(name, &data_type));
+            }
+
+            decl.body.push(
+                AssignmentStatement::new(self.output.clone(), AssignmentOp::Simple, checksum_var())
+                    .into(),
+            );
+        }
+
+        decl
+    }
+
+    fn fold_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        let block = fold::walk_block(self, block);
+
+        let mut locals = vec![];
+        let mut stmts = vec![];
+
+        for stmt in block {
+            match stmt {
+                Statement::LetDecl(ref decl) => {
+                    let data_type = decl.initializer.data_type.dereference().clone();
+                    locals.push((decl.ident.clone(), data_type));
+                }
+                Statement::VarDecl(ref decl) => {
+                    locals.push((decl.ident.clone(), decl.inferred_type().clone()));
+                }
+                Statement::Return(ReturnStatement { value: Some(value) })
+                    if !self.is_entry_point =>
+                {
+                    // Bind the return value to a variable so that it can be hashed before returning
+                    let data_type = value.data_type.dereference().clone();
+                    let ident = format!("checksum_ret_{}", self.return_count);
+                    self.return_count += 1;
+
+                    stmts.push(LetDeclStatement::new(ident.clone(), value).into());
+                    stmts.extend(self.hash_stmt(ident.clone(), &data_type));
+                    stmts.push(
+                        ReturnStatement::new(VarExpr::new(ident).into_node(data_type)).into(),
+                    );
+                    continue;
+                }
+                _ => {}
+            }
+
+            stmts.push(stmt);
+        }
+
+        let hashes = locals
+            .into_iter()
+            .filter_map(|(name, data_type)| self.hash_stmt(name, &data_type))
+            .collect::<Vec<_>>();
+
+        // Locals must be hashed before control leaves the block
+        let end = if matches!(
+            stmts.last(),
+            Some(Statement::Return(_) | Statement::Break | Statement::Continue)
+        ) {
+            stmts.len() - 1
+        } else {
+            stmts.len()
+        };
+
+        stmts.splice(end..end, hashes);
+        stmts
+    }
+}
+
+impl Checksum {
+    /// Generates a statement which folds the value of the variable `name` into the checksum.
+    ///
+    /// Returns `None` if values of the type can't be hashed (e.g. pointers).
+    fn hash_stmt(&mut self, name: String, data_type: &DataType) -> Option<Statement> {
+        if !self.require_hash_fn(data_type) {
+            return None;
+        }
+
+        let value = VarExpr::new(name).into_node(data_type.clone());
+        let lhs = AssignmentLhs::name(
+            CHECKSUM_VAR,
+            DataType::Ref(MemoryViewType::new(ScalarType::U32, StorageClass::Private)),
+        );
+
+        Some(
+            AssignmentStatement::new(
+                lhs,
+                AssignmentOp::Simple,
+                hash(data_type, checksum_var(), value),
+            )
+            .into(),
+        )
+    }
+
+    /// Records that a hash function is needed for the type, along with the hash functions for
+    /// its components. Returns false if the type can't be hashed.
+    fn require_hash_fn(&mut self, data_type: &DataType) -> bool {
+        if !is_hashable(data_type) {
+            return false;
+        }
+
+        match data_type {
+            DataType::Scalar(ScalarType::I32 | ScalarType::Bool) => {
+                self.require_hash_fn(&ScalarType::U32.into());
+            }
+            DataType::Scalar(_) => {}
+            _ => {
+                for component in
+                    components(data_type, VarExpr::new("v").into_node(data_type.clone()))
+                {
+                    self.require_hash_fn(&component.data_type);
+                }
+            }
+        }
+
+        if !self.hashed_types.contains(data_type) {
+            self.hashed_types.push(data_type.clone());
+        }
+
+        true
+    }
+}
+
+/// Returns true if values of the type contribute to the checksum.
+///
+/// Floats are excluded: WGSL only bounds the accuracy of most float operations, so conforming
+/// implementations can compute slightly different values, and there is no tolerance that can be
+/// applied when hashing (any rounding scheme has boundaries where nearby values hash
+/// differently). Composite types are hashable if any of their components are, and only those
+/// components are hashed.
+fn is_hashable(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Scalar(ty) | DataType::Vector(_, ty) | DataType::Matrix(_, _, ty) => {
+            matches!(ty, ScalarType::I32 | ScalarType::U32 | ScalarType::Bool)
+        }
+        DataType::Array(inner, Some(_)) => is_hashable(inner),
+        DataType::Struct(decl) => decl.members.iter().any(|it| is_hashable(&it.data_type)),
+        DataType::Array(_, None) | DataType::Ptr(_) | DataType::Ref(_) => false,
+    }
+}
+
+fn checksum_var() -> ExprNode {
+    VarExpr::new(CHECKSUM_VAR).into_node(ScalarType::U32.into())
+}
+
+fn hash(data_type: &DataType, hash: ExprNode, value: ExprNode) -> ExprNode {
+    FnCallExpr::new(hash_fn_name(data_type), vec![hash, value]).into_node(ScalarType::U32)
+}
+
+fn hash_fn_name(data_type: &DataType) -> String {
+    fn type_name(data_type: &DataType) -> String {
+        match data_type {
+            DataType::Scalar(ty) => ty.to_string(),
+            DataType::Vector(n, ty) => format!("vec{n}_{ty}"),
+            DataType::Matrix(c, r, ty) => format!("mat{c}x{r}_{ty}"),
+            DataType::Array(ty, Some(n)) => format!("array_{}_{n}", type_name(ty)),
+            DataType::Struct(decl) => decl.name.clone(),
+            _ => unreachable!("no hash function for type `{data_type}`"),
+        }
+    }
+
+    format!("_wgslsmith_checksum_{}", type_name(data_type))
+}
+
+/// Returns expressions for the components of a value of a vector, matrix, array or struct type.
+fn components(data_type: &DataType, value: ExprNode) -> Vec<ExprNode> {
+    let index = |i: u32| PostfixExpr::new(value.clone(), Postfix::index(Lit::U32(i))).into();
+
+    match data_type {
+        DataType::Vector(n, _) => ["x", "y", "z", "w"][..*n as usize]
+            .iter()
+            .map(|it| PostfixExpr::new(value.clone(), Postfix::member(*it)).into())
+            .collect(),
+        DataType::Matrix(n, _, _) => (0..*n as u32).map(index).collect(),
+        DataType::Array(_, Some(n)) => (0..*n).map(index).collect(),
+        DataType::Struct(decl) => decl
+            .members
+            .iter()
+            .map(|it| PostfixExpr::new(value.clone(), Postfix::member(&it.name)).into())
+            .collect(),
+        _ => unreachable!("type `{data_type}` has no components"),
+    }
+}
+
+/// Generates the function which folds a value of the given type into a hash.
+///
+/// Integers are combined using a step of the FNV-1a hash. Components which aren't hashable (see
+/// [`is_hashable`]) are skipped.
+fn gen_hash_fn(data_type: &DataType) -> FnDecl {
+    let h = VarExpr::new("h").into_node(ScalarType::U32.into());
+    let v = VarExpr::new("v").into_node(data_type.clone());
+
+    let convert = |ty: ScalarType, value: ExprNode| -> ExprNode {
+        TypeConsExpr::new(ty.into(), vec![value]).into()
+    };
+
+    let body = match data_type {
+        DataType::Scalar(ScalarType::U32) => vec![ReturnStatement::new(BinOpExpr::new(
+            BinOp::Times,
+            BinOpExpr::new(BinOp::BitXOr, h, v),
+            Lit::U32(16777619),
+        ))
+        .into()],
+        DataType::Scalar(ScalarType::I32) => {
+            let value = convert(ScalarType::U32, v);
+            vec![ReturnStatement::new(hash(&ScalarType::U32.into(), h, value)).into()]
+        }
+        DataType::Scalar(ScalarType::Bool) => {
+            let value = FnCallExpr::new("select", vec![Lit::U32(0).into(), Lit::U32(1).into(), v])
+                .into_node(ScalarType::U32);
+            vec![ReturnStatement::new(hash(&ScalarType::U32.into(), h, value)).into()]
+        }
+        _ => {
+            let result = VarExpr::new("r").into_node(ScalarType::U32.into());
+            let lhs = AssignmentLhs::name(
+                "r",
+                DataType::Ref(MemoryViewType::new(ScalarType::U32, StorageClass::Function)),
+            );
+
+            let mut body = vec![VarDeclStatement::new("r", None, Some(h)).into()];
+
+            for component in components(data_type, v) {
+                let data_type = component.data_type.dereference().clone();
+                if !is_hashable(&data_type) {
+                    continue;
+                }
+
+                let rhs = hash(&data_type, result.clone(), component);
+                body.push(AssignmentStatement::new(lhs.clone(), AssignmentOp::Simple, rhs).into());
+            }
+
+            body.push(ReturnStatement::new(result).into());
+            body
+        }
+    };
+
+    FnDecl {
+        attrs: vec![],
+        name: hash_fn_name(data_type),
+        inputs: vec![
+            FnInput::new("h", ScalarType::U32),
+            FnInput::new("v", data_type.clone()),
+        ],
+        output: Some(FnOutput::new(ScalarType::U32)),
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instrument(source: &str) -> String {
+        let module = parser::parse(source);
+        let output_type = module
+            .structs
+            .iter()
+            .find(|it| it.name == "Out")
+            .cloned()
+            .map(DataType::Struct)
+            .unwrap();
+
+        let output =
+            AssignmentLhs::member("s_output".to_owned(), output_type, OUTPUT_MEMBER.to_owned());
+
+        let mut out = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut out, &insert(module, output))
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn hashes_integer_values_before_leaving_blocks() {
+        let output = instrument(
+            "struct S { a: i32, f: f32, b: vec2<bool>, }\n\
+            struct Out { x: u32, checksum: u32, }\n\
+            @group(0) @binding(0)\n\
+            var<storage, read_write> s_output: Out;\n\
+            var<private> g: array<u32, 2>;\n\
+            fn f(p: i32) -> i32 {\n\
+                let a = p + 1;\n\
+                return a;\n\
+            }\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {\n\
+                var s: S;\n\
+                loop {\n\
+                    let b = f(1);\n\
+                    if (b > 0) { let c = 1u; continue; }\n\
+                    break;\n\
+                }\n\
+                let x = 1.0;\n\
+            }\n",
+        );
+
+        // Hash functions follow the functions for their components, and skip float members. The
+        // float local `x` is not hashed at all.
+        let expected = r#"
+var<private> CHECKSUM: u32;
+
+fn _wgslsmith_checksum_u32(h: u32, v: u32) -> u32 {
+    return (h ^ v) * 16777619u;
+}
+
+fn _wgslsmith_checksum_i32(h: u32, v: i32) -> u32 {
+    return _wgslsmith_checksum_u32(h, u32(v));
+}
+
+fn _wgslsmith_checksum_bool(h: u32, v: bool) -> u32 {
+    return _wgslsmith_checksum_u32(h, select(0u, 1u, v));
+}
+
+fn _wgslsmith_checksum_vec2_bool(h: u32, v: vec2<bool>) -> u32 {
+    var r = h;
+    r = _wgslsmith_checksum_bool(r, v.x);
+    r = _wgslsmith_checksum_bool(r, v.y);
+    return r;
+}
+
+fn _wgslsmith_checksum_S(h: u32, v: S) -> u32 {
+    var r = h;
+    r = _wgslsmith_checksum_i32(r, v.a);
+    r = _wgslsmith_checksum_vec2_bool(r, v.b);
+    return r;
+}
+
+fn _wgslsmith_checksum_array_u32_2(h: u32, v: array<u32, 2>) -> u32 {
+    var r = h;
+    r = _wgslsmith_checksum_u32(r, v[0u]);
+    r = _wgslsmith_checksum_u32(r, v[1u]);
+    return r;
+}
+
+fn f(p: i32) -> i32 {
+    let a = p + 1;
+    let checksum_ret_0 = a;
+    CHECKSUM = _wgslsmith_checksum_i32(CHECKSUM, checksum_ret_0);
+    CHECKSUM = _wgslsmith_checksum_i32(CHECKSUM, a);
+    return checksum_ret_0;
+}
+
+@compute
+@workgroup_size(1)
+fn main() {
+    var s: S;
+    loop {
+        let b = f(1);
+        if (b > 0) {
+            let c = 1u;
+            CHECKSUM = _wgslsmith_checksum_u32(CHECKSUM, c);
+            continue;
+        }
+        CHECKSUM = _wgslsmith_checksum_i32(CHECKSUM, b);
+        break;
+    }
+    let x = 1.0;
+    CHECKSUM = _wgslsmith_checksum_S(CHECKSUM, s);
+    CHECKSUM = _wgslsmith_checksum_array_u32_2(CHECKSUM, g);
+    (s_output).checksum = CHECKSUM;
+}
+"#;
+
+        let start = output.find("\nvar<private> CHECKSUM").unwrap();
+        assert_eq!(output[start..].trim_end(), expected.trim_end());
+    }
+}
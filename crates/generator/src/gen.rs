@@ -12,18 +12,22 @@ use std::rc::Rc;
 
 use ast::types::{DataType, MemoryViewType};
 use ast::{
-    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, FnAttr, FnDecl, GlobalVarAttr,
-    GlobalVarDecl, LetDeclStatement, Module, Postfix, PostfixExpr, ShaderStage, Statement,
-    StorageClass, VarExpr, VarQualifier,
+    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, FnAttr, FnCallStatement, FnDecl,
+    FnInput, FnInputAttr, GlobalVarAttr, GlobalVarDecl, LetDeclStatement, LhsExpr, LhsExprNode,
+    Module, Postfix, PostfixExpr, ScalarType, ShaderStage, Statement, StorageClass, StructDecl,
+    StructMember, VarExpr, VarQualifier,
 };
 use rand::prelude::{SliceRandom, StdRng};
 use rand::Rng;
 use rand_distr::{Binomial, Distribution, StandardNormal};
 
+use crate::checksum;
 use crate::gen::scope::Scope;
+use crate::profile::Profile;
+use crate::swarm::{Feature, FeatureSet};
 use crate::Options;
 
-use self::cx::Context;
+use self::cx::{Context, SelectionFilter};
 use self::structs::StructKind;
 
 #[derive(Default)]
@@ -31,11 +35,21 @@ struct FnState {
     is_loop: bool,
     block_depth: u32,
     expression_depth: u32,
+    /// Whether return statements are disallowed, which keeps the top level of the entry point in
+    /// uniform control flow so that barriers can be inserted there.
+    no_return: bool,
+    /// Arrays of atomics which can be accessed by atomic statements, as reference types.
+    atomics: Vec<(String, DataType)>,
+    /// Whether each invocation may only access its own element of the atomic arrays, which keeps
+    /// the values returned by atomic operations independent of how invocations are scheduled.
+    atomics_per_invocation: bool,
 }
 
 pub struct Generator<'a> {
     rng: &'a mut StdRng,
     options: Rc<Options>,
+    features: FeatureSet,
+    profile: Profile,
     cx: Context,
     return_type: Option<DataType>,
     fn_state: FnState,
@@ -49,10 +63,14 @@ pub struct Generator<'a> {
 
 impl<'a> Generator<'a> {
     pub fn new(rng: &'a mut StdRng, options: Rc<Options>) -> Self {
+        let features = FeatureSet::new(&options, rng);
+        let profile = options.profile.clone().unwrap_or_default();
         Generator {
             rng,
             options: options.clone(),
-            cx: Context::new(options),
+            cx: Context::new(options, &features, &profile),
+            features,
+            profile,
             return_type: None,
             fn_state: FnState::default(),
             global_scope: Scope::empty(),
@@ -66,11 +84,30 @@ impl<'a> Generator<'a> {
         }
     }
 
+    /// Returns the set of features which are disabled for the generated program.
+    pub fn features(&self) -> &FeatureSet {
+        &self.features
+    }
+
+    fn max_block_depth(&self) -> u32 {
+        self.profile
+            .limits
+            .max_block_depth
+            .unwrap_or(self.options.max_block_depth)
+    }
+
+    fn pointers_enabled(&self) -> bool {
+        self.options.enable_pointers && self.features.is_enabled(Feature::Pointers)
+    }
+
     #[tracing::instrument(skip(self))]
     pub fn gen_module(&mut self) -> Module {
-        let struct_count = self
-            .rng
-            .gen_range(self.options.min_structs..=self.options.max_structs);
+        let struct_count = if self.features.is_enabled(Feature::Structs) {
+            self.rng
+                .gen_range(self.options.min_structs..=self.options.max_structs)
+        } else {
+            0
+        };
 
         for i in 1..=struct_count {
             let name = format!("Struct_{}", i);
@@ -78,14 +115,37 @@ impl<'a> Generator<'a> {
             self.cx.types.insert(decl);
         }
 
+        let workgroup_size = if self.options.enable_workgroup_memory {
+            self.rng.gen_range(2..=8)
+        } else {
+            1
+        };
+
         let ub_type_decl =
             self.gen_struct_with("UniformBuffer".to_owned(), StructKind::UniformBuffer);
-        let sb_type_decl =
+        let mut sb_type_decl =
             self.gen_struct_with("StorageBuffer".to_owned(), StructKind::HostShareable);
 
+        if self.options.checksum {
+            let mut members = sb_type_decl.members.clone();
+            members.push(StructMember::new(
+                vec![],
+                checksum::OUTPUT_MEMBER,
+                ScalarType::U32.into(),
+            ));
+            sb_type_decl = StructDecl::new(sb_type_decl.name.clone(), members);
+        }
+
         self.global_scope
             .insert_readonly("u_input".to_owned(), DataType::Struct(ub_type_decl.clone()));
 
+        // With multiple invocations, each one writes to its own element of the output
+        let out_buf_type = if workgroup_size > 1 {
+            DataType::array(DataType::Struct(sb_type_decl.clone()), workgroup_size)
+        } else {
+            DataType::Struct(sb_type_decl.clone())
+        };
+
         let mut global_vars = vec![
             GlobalVarDecl {
                 attrs: vec![GlobalVarAttr::Group(0), GlobalVarAttr::Binding(0)],
@@ -104,45 +164,154 @@ impl<'a> Generator<'a> {
                     access_mode: Some(AccessMode::ReadWrite),
                 }),
                 name: "s_output".to_owned(),
-                data_type: DataType::Struct(sb_type_decl.clone()),
+                data_type: out_buf_type.clone(),
                 initializer: None,
             },
         ];
 
-        for i in 0..self.rng.gen_range(0..=5) {
+        let global_count = if self.features.is_enabled(Feature::Globals) {
+            self.rng.gen_range(0..=self.profile.limits.max_globals)
+        } else {
+            0
+        };
+
+        for i in 0..global_count {
             let name = format!("global{i}");
             global_vars.push(self.gen_global_var(name));
         }
 
+        let mut workgroup_vars = vec![];
+
+        if workgroup_size > 1 {
+            for i in 0..self.rng.gen_range(1..=3) {
+                let decl = self.gen_workgroup_var(format!("wg{i}"), workgroup_size);
+                workgroup_vars.push((decl.name.clone(), decl.data_type.clone()));
+                global_vars.push(decl);
+            }
+        }
+
+        let mut next_binding = 2;
+        let mut atomic_vars = vec![];
+
+        if self.options.enable_atomics && self.features.is_enabled(Feature::Atomics) {
+            let len = if workgroup_size > 1 {
+                workgroup_size
+            } else {
+                self.rng.gen_range(1..=4)
+            };
+
+            let storage_buffer = GlobalVarDecl {
+                attrs: vec![
+                    GlobalVarAttr::Group(0),
+                    GlobalVarAttr::Binding(next_binding),
+                ],
+                qualifier: Some(VarQualifier {
+                    storage_class: StorageClass::Storage,
+                    access_mode: Some(AccessMode::ReadWrite),
+                }),
+                name: "s_atomics".to_owned(),
+                data_type: self.gen_atomic_array_type(len),
+                initializer: None,
+            };
+
+            let workgroup_var = GlobalVarDecl {
+                attrs: vec![],
+                qualifier: Some(VarQualifier {
+                    storage_class: StorageClass::WorkGroup,
+                    access_mode: None,
+                }),
+                name: "wg_atomics".to_owned(),
+                data_type: self.gen_atomic_array_type(len),
+                initializer: None,
+            };
+
+            for decl in [storage_buffer, workgroup_var] {
+                let qualifier = decl.qualifier.as_ref().unwrap();
+                let mem_view = MemoryViewType {
+                    access_mode: AccessMode::ReadWrite,
+                    ..MemoryViewType::new(decl.data_type.clone(), qualifier.storage_class)
+                };
+                atomic_vars.push((decl.name.clone(), DataType::Ref(mem_view)));
+                global_vars.push(decl);
+            }
+
+            next_binding += 1;
+        }
+
+        let mut runtime_buffer_decl = None;
+
+        if self.options.enable_runtime_arrays && self.features.is_enabled(Feature::RuntimeArrays) {
+            let decl = self.gen_struct_with("RuntimeBuffer".to_owned(), StructKind::RuntimeBuffer);
+            let element_type = self
+                .cx
+                .types
+                .select_with_filter(self.rng, SelectionFilter::Uniform);
+
+            let buffers = [
+                ("s_array", DataType::array(element_type, None)),
+                ("s_runtime", DataType::Struct(decl.clone())),
+            ];
+
+            for (name, data_type) in buffers {
+                global_vars.push(self.gen_runtime_array_var(name, data_type, next_binding));
+                next_binding += 1;
+            }
+
+            runtime_buffer_decl = Some(decl);
+        }
+
+        let checksum_output = self
+            .options
+            .checksum
+            .then(|| checksum_output(&out_buf_type, workgroup_size));
+
         let entrypoint = self.gen_entrypoint_function(
             DataType::Struct(ub_type_decl.clone()),
-            DataType::Struct(sb_type_decl.clone()),
+            out_buf_type,
+            workgroup_size,
+            &workgroup_vars,
+            atomic_vars,
         );
 
-        let Context { types, fns } =
-            std::mem::replace(&mut self.cx, Context::new(self.options.clone()));
+        let Context { types, fns } = std::mem::replace(
+            &mut self.cx,
+            Context::new(self.options.clone(), &self.features, &self.profile),
+        );
 
         let mut functions = fns.into_fns();
 
         functions.push(entrypoint);
 
-        Module {
+        let module = Module {
+            enables: vec![],
+            aliases: vec![],
             structs: {
                 let mut structs = types.into_structs();
                 structs.push(ub_type_decl);
                 structs.push(sb_type_decl);
+                structs.extend(runtime_buffer_decl);
                 structs
             },
             consts: vec![],
+            overrides: vec![],
             vars: global_vars,
+            const_asserts: vec![],
             functions,
+        };
+
+        match checksum_output {
+            Some(output) => checksum::insert(module, output.into()),
+            None => module,
         }
     }
 
     fn gen_global_var(&mut self, name: String) -> GlobalVarDecl {
         let mut data_type = self.cx.types.select(self.rng);
 
-        if self.rng.gen_bool(0.5) {
+        if self
+            .rng
+            .gen_bool(self.profile.limits.global_array_probability)
+        {
             data_type = DataType::Array(Rc::new(data_type), Some(self.rng.gen_range(1..=32)));
         }
 
@@ -169,10 +338,95 @@ impl<'a> Generator<'a> {
         }
     }
 
+    /// Generates a workgroup variable with one element per invocation.
+    ///
+    /// Workgroup variables are not added to the global scope, since they may only be accessed in
+    /// the entry point where the barriers are.
+    fn gen_workgroup_var(&mut self, name: String, workgroup_size: u32) -> GlobalVarDecl {
+        let data_type = DataType::array(self.cx.types.select(self.rng), workgroup_size);
+
+        GlobalVarDecl {
+            attrs: vec![],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::WorkGroup,
+                access_mode: None,
+            }),
+            name,
+            data_type,
+            initializer: None,
+        }
+    }
+
+    /// Generates a read-only storage buffer which contains a runtime-sized array.
+    ///
+    /// The buffer is added to the global scope so that it can be read anywhere, with accesses to
+    /// the array being kept in bounds by the reconditioner using `arrayLength`.
+    fn gen_runtime_array_var(
+        &mut self,
+        name: &str,
+        data_type: DataType,
+        binding: i32,
+    ) -> GlobalVarDecl {
+        let mem_view = MemoryViewType::new(data_type.clone(), StorageClass::Storage);
+
+        self.global_scope
+            .insert_readonly(name.to_owned(), DataType::Ref(mem_view));
+
+        GlobalVarDecl {
+            attrs: vec![GlobalVarAttr::Group(0), GlobalVarAttr::Binding(binding)],
+            qualifier: Some(VarQualifier {
+                storage_class: StorageClass::Storage,
+                access_mode: Some(AccessMode::Read),
+            }),
+            name: name.to_owned(),
+            data_type,
+            initializer: None,
+        }
+    }
+
+    fn gen_atomic_array_type(&mut self, len: u32) -> DataType {
+        let ty = *[ScalarType::AU32, ScalarType::AI32]
+            .choose(self.rng)
+            .unwrap();
+        DataType::array(ty, len)
+    }
+
     #[tracing::instrument(skip(self))]
-    fn gen_entrypoint_function(&mut self, in_buf_type: DataType, out_buf_type: DataType) -> FnDecl {
-        let stmt_count = self.rng.gen_range(5..10);
-        let (_, block) = self.with_scope(self.global_scope.clone(), |this| {
+    fn gen_entrypoint_function(
+        &mut self,
+        in_buf_type: DataType,
+        out_buf_type: DataType,
+        workgroup_size: u32,
+        workgroup_vars: &[(String, DataType)],
+        atomic_vars: Vec<(String, DataType)>,
+    ) -> FnDecl {
+        let limits = &self.profile.limits;
+        let stmt_count = self
+            .rng
+            .gen_range(limits.entrypoint_min_stmts..=limits.entrypoint_max_stmts);
+
+        let mut inputs = vec![];
+        let mut entry_scope = self.global_scope.clone();
+
+        if workgroup_size > 1 {
+            inputs.push(FnInput {
+                attrs: vec![FnInputAttr::Builtin("local_invocation_index".to_owned())],
+                name: "local_index".to_owned(),
+                data_type: DataType::Scalar(ScalarType::U32),
+            });
+
+            entry_scope
+                .insert_readonly("local_index".to_owned(), DataType::Scalar(ScalarType::U32));
+        }
+
+        self.fn_state = FnState {
+            no_return: workgroup_size > 1,
+            atomics: atomic_vars,
+            atomics_per_invocation: workgroup_size > 1,
+            ..FnState::default()
+        };
+
+        let (_, block) = self.with_scope(entry_scope, |this| {
             let (scope, mut block) = this.gen_stmt_block(stmt_count);
 
             if let Some(Statement::Return(_)) = block.last() {
@@ -182,6 +436,10 @@ impl<'a> Generator<'a> {
             let prev_block = std::mem::replace(&mut this.current_block, block);
 
             this.with_scope(scope, |this| {
+                if !workgroup_vars.is_empty() {
+                    this.gen_workgroup_rounds(workgroup_vars);
+                }
+
                 this.current_block.push(
                     LetDeclStatement::new(
                         "x",
@@ -193,8 +451,30 @@ impl<'a> Generator<'a> {
                     .into(),
                 );
 
-                let out_lhs = AssignmentLhs::name("s_output", out_buf_type.clone());
-                let out_rhs = this.gen_expr(&out_buf_type);
+                if this.options.checksum {
+                    this.gen_output_members(&out_buf_type, workgroup_size);
+                    return;
+                }
+
+                let (out_lhs, out_rhs) = match &out_buf_type {
+                    DataType::Array(element_type, _) => {
+                        let ref_type = DataType::Ref(MemoryViewType::new(
+                            out_buf_type.clone(),
+                            StorageClass::Storage,
+                        ));
+                        let index = VarExpr::new("local_index")
+                            .into_node(DataType::Scalar(ScalarType::U32));
+                        (
+                            AssignmentLhs::array_index("s_output", ref_type, index),
+                            this.gen_expr(element_type),
+                        )
+                    }
+                    _ => (
+                        AssignmentLhs::name("s_output", out_buf_type.clone()),
+                        this.gen_expr(&out_buf_type),
+                    ),
+                };
+
                 this.current_block
                     .push(AssignmentStatement::new(out_lhs, AssignmentOp::Simple, out_rhs).into());
             });
@@ -202,18 +482,109 @@ impl<'a> Generator<'a> {
             std::mem::replace(&mut this.current_block, prev_block)
         });
 
+        self.fn_state = FnState::default();
+
         FnDecl {
             attrs: vec![
                 FnAttr::Stage(ShaderStage::Compute),
-                FnAttr::LitWorkgroupSize(1),
+                FnAttr::LitWorkgroupSize(workgroup_size),
             ],
             name: "main".to_owned(),
-            inputs: vec![],
+            inputs,
             output: None,
             body: block,
         }
     }
 
+    /// Assigns each member of this invocation's output except for the checksum, which is written
+    /// once it has been computed (see [`checksum::insert`]).
+    fn gen_output_members(&mut self, out_buf_type: &DataType, workgroup_size: u32) {
+        let decl = match out_buf_type {
+            DataType::Array(element_type, _) => element_type.as_ref(),
+            ty => ty,
+        };
+
+        let DataType::Struct(decl) = decl else {
+            unreachable!("output buffer must contain a struct");
+        };
+
+        for member in &decl.members {
+            if member.name == checksum::OUTPUT_MEMBER {
+                continue;
+            }
+
+            let lhs = output_member(out_buf_type, workgroup_size, &member.name);
+            let rhs = self.gen_expr(&member.data_type);
+
+            self.current_block
+                .push(AssignmentStatement::new(lhs.into(), AssignmentOp::Simple, rhs).into());
+        }
+    }
+
+    /// Generates rounds in which each invocation writes its own element of every workgroup
+    /// variable, followed by statements which may read any element.
+    ///
+    /// Writes and reads are always separated by a barrier, and the values to write are computed
+    /// before the barrier that precedes the writes, so there are no data races between invocations.
+    fn gen_workgroup_rounds(&mut self, workgroup_vars: &[(String, DataType)]) {
+        let rounds = self.rng.gen_range(1..=3);
+
+        for round in 0..rounds {
+            let mut values = vec![];
+
+            for (_, data_type) in workgroup_vars {
+                let DataType::Array(element_type, _) = data_type else {
+                    unreachable!("workgroup variable must be an array");
+                };
+
+                let ident = self.scope.next_name();
+                let value = self.gen_expr(element_type);
+                self.current_block
+                    .push(LetDeclStatement::new(ident.clone(), value).into());
+                self.scope
+                    .insert_readonly(ident.clone(), element_type.as_ref().clone());
+                values.push(VarExpr::new(ident).into_node(element_type.as_ref().clone()));
+            }
+
+            if round > 0 {
+                self.current_block.push(barrier("workgroupBarrier"));
+            }
+
+            for ((name, data_type), value) in workgroup_vars.iter().zip(values) {
+                let ref_type = DataType::Ref(MemoryViewType::new(
+                    data_type.clone(),
+                    StorageClass::WorkGroup,
+                ));
+                let index =
+                    VarExpr::new("local_index").into_node(DataType::Scalar(ScalarType::U32));
+                let lhs = AssignmentLhs::array_index(name.clone(), ref_type, index);
+                self.current_block
+                    .push(AssignmentStatement::new(lhs, AssignmentOp::Simple, value).into());
+            }
+
+            self.current_block.push(barrier("workgroupBarrier"));
+
+            if self.rng.gen_bool(0.5) {
+                self.current_block.push(barrier("storageBarrier"));
+            }
+
+            if round == 0 {
+                for (name, data_type) in workgroup_vars {
+                    let mem_view = MemoryViewType::new(data_type.clone(), StorageClass::WorkGroup);
+                    self.scope
+                        .insert_readonly(name.clone(), DataType::Ref(mem_view));
+                }
+            }
+
+            let stmt_count = self
+                .rng
+                .gen_range(self.options.block_min_stmts..=self.options.block_max_stmts);
+            let (scope, block) = self.gen_stmt_block(stmt_count);
+            self.scope = scope;
+            self.current_block.extend(block);
+        }
+    }
+
     fn with_scope<T>(&mut self, scope: Scope, block: impl FnOnce(&mut Self) -> T) -> (Scope, T) {
         let old_scope = std::mem::replace(&mut self.scope, scope);
         let res = block(self);
@@ -221,7 +592,10 @@ impl<'a> Generator<'a> {
     }
 
     fn gen_i32(&mut self) -> i32 {
-        if self.rng.gen_bool(0.5) {
+        if self
+            .rng
+            .gen_bool(1.0 - self.profile.literals.special_int_probability)
+        {
             (self.i32_dist.sample(self.rng) as i64 - i32::MAX as i64) as i32
         } else {
             *[0, 1, -1, i32::MAX, i32::MIN].choose(self.rng).unwrap()
@@ -229,7 +603,10 @@ impl<'a> Generator<'a> {
     }
 
     fn gen_u32(&mut self) -> u32 {
-        if self.rng.gen_bool(0.5) {
+        if self
+            .rng
+            .gen_bool(1.0 - self.profile.literals.special_int_probability)
+        {
             (self.u32_dist.sample(self.rng) as i64 - u32::MAX as i64).unsigned_abs() as u32
         } else {
             *[0, 1, u32::MAX].choose(self.rng).unwrap()
@@ -247,7 +624,167 @@ impl<'a> Generator<'a> {
         } else {
             k
         };
-        let x = k * 1000.0;
+        let x = k * self.profile.literals.float_scale;
         f32::clamp(x.trunc() as f32, -16777216.0, 16777216.0)
     }
 }
+
+fn barrier(ident: &str) -> Statement {
+    FnCallStatement::new(ident.to_owned(), vec![]).into()
+}
+
+/// Returns the location in the output buffer that the checksum for this invocation is written to.
+fn checksum_output(out_buf_type: &DataType, workgroup_size: u32) -> LhsExprNode {
+    output_member(out_buf_type, workgroup_size, checksum::OUTPUT_MEMBER)
+}
+
+/// Returns a member of the output struct for this invocation.
+fn output_member(out_buf_type: &DataType, workgroup_size: u32, member: &str) -> LhsExprNode {
+    let member = member.to_owned();
+
+    if workgroup_size == 1 {
+        return LhsExprNode::member("s_output".to_owned(), out_buf_type.clone(), member);
+    }
+
+    let index = VarExpr::new("local_index").into_node(ScalarType::U32.into());
+    let element = LhsExprNode::array_index(
+        "s_output".to_owned(),
+        DataType::Ref(MemoryViewType::new(
+            out_buf_type.clone(),
+            StorageClass::Storage,
+        )),
+        index,
+    );
+
+    let DataType::Array(element_type, _) = out_buf_type else {
+        unreachable!("output buffer must be an array with multiple invocations");
+    };
+
+    let DataType::Struct(decl) = element_type.as_ref() else {
+        unreachable!("output buffer must contain a struct");
+    };
+
+    let member_type = decl.member_type(&member).cloned().unwrap();
+
+    LhsExprNode {
+        data_type: DataType::Ref(MemoryViewType::new(member_type, StorageClass::Storage)),
+        expr: LhsExpr::Postfix(Box::new(element), Postfix::member(member)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::visit::{self, Visit};
+    use clap::Parser;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// Options which keep generated programs small, since parsing is slow in debug builds.
+    const SMALL: &[&str] = &[
+        "--max-fns",
+        "1",
+        "--max-block-depth",
+        "1",
+        "--block-max-stmts",
+        "2",
+        "--max-structs",
+        "2",
+    ];
+
+    fn gen_module(args: &[&str], seed: u64) -> Module {
+        let options = Options::parse_from(["gen"].iter().chain(SMALL).chain(args));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let module = Generator::new(&mut rng, Rc::new(options)).gen_module();
+
+        // Check that the generated code is valid enough to round trip through the parser
+        let mut source = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut source, &module)
+            .unwrap();
+        parser::parse(&source)
+    }
+
+    #[derive(Default)]
+    struct BarrierCounter(usize);
+
+    impl Visit for BarrierCounter {
+        fn visit_fn_call_stmt(&mut self, stmt: &FnCallStatement) {
+            if stmt.ident.ends_with("Barrier") {
+                self.0 += 1;
+            }
+            visit::walk_fn_call_stmt(self, stmt);
+        }
+    }
+
+    #[test]
+    fn barriers_are_only_at_entry_point_top_level() {
+        let mut total = 0;
+
+        for seed in 0..8 {
+            let module = gen_module(&["--enable-workgroup-memory"], seed);
+
+            let mut counter = BarrierCounter::default();
+            counter.visit_module(&module);
+
+            let entry_point = module
+                .functions
+                .iter()
+                .find(|it| it.name == "main")
+                .unwrap();
+            let top_level = entry_point
+                .body
+                .iter()
+                .filter(|stmt| {
+                    matches!(stmt, Statement::FnCall(call) if call.ident.ends_with("Barrier"))
+                })
+                .count();
+
+            assert_eq!(counter.0, top_level, "seed {seed}");
+
+            let workgroup_size = entry_point.attrs.iter().find_map(|attr| match attr {
+                FnAttr::LitWorkgroupSize(size) => Some(*size),
+                _ => None,
+            });
+            assert!(matches!(workgroup_size, Some(2..=8)), "seed {seed}");
+
+            total += top_level;
+        }
+
+        assert!(total > 0);
+    }
+
+    #[test]
+    fn runtime_arrays_are_read_only() {
+        let module = gen_module(&["--enable-runtime-arrays"], 0);
+
+        for name in ["s_array", "s_runtime"] {
+            let decl = module.vars.iter().find(|it| it.name == name).unwrap();
+
+            assert_eq!(
+                decl.qualifier.as_ref().unwrap().access_mode,
+                Some(AccessMode::Read)
+            );
+            assert!(decl.data_type.with_runtime_array_len(1).is_some());
+        }
+    }
+
+    #[test]
+    fn checksum_member_is_only_written_once() {
+        for args in [
+            &["--checksum"][..],
+            &["--checksum", "--enable-workgroup-memory"],
+        ] {
+            let module = gen_module(args, 0);
+
+            let mut source = String::new();
+            ast::writer::Writer::default()
+                .write_module(&mut source, &module)
+                .unwrap();
+
+            // The other members are assigned individually rather than by a struct constructor
+            assert_eq!(source.matches(".checksum = ").count(), 1, "{args:?}");
+            assert!(source.contains(".checksum = CHECKSUM;"), "{args:?}");
+        }
+    }
+}
@@ -20,7 +20,10 @@ pub const TINT_EXTRAS: &[BuiltinFn] = {
     &[CountLeadingZeros, CountTrailingZeros, Refract]
 };
 
-pub fn gen_builtins(enabled: &[BuiltinFn]) -> HashMap<DataType, Vec<Rc<Func>>> {
+pub fn gen_builtins(
+    enabled: &[BuiltinFn],
+    enable_matrices: bool,
+) -> HashMap<DataType, Vec<Rc<Func>>> {
     use BuiltinFn::*;
     use DataType::*;
     use ScalarType::*;
@@ -105,9 +108,7 @@ pub fn gen_builtins(enabled: &[BuiltinFn]) -> HashMap<DataType, Vec<Rc<Func>>> {
 
     for ty in scalar_and_vectors_of(F32) {
         for builtin in [
-            // Acos - // TODO: recondition,
             // Acosh - not implemented in tint/naga,
-            // Asin - // TODO: recondition,
             // Asinh - not implemnted in tint/naga,
             // Atan - // TODO: recondition,
             // Atanh - not implemented in tint/naga,
@@ -118,15 +119,11 @@ pub fn gen_builtins(enabled: &[BuiltinFn]) -> HashMap<DataType, Vec<Rc<Func>>> {
             // Exp,
             Exp2, Floor,
             // Fract,
-            // InverseSqrt - // TODO: recondition,
-            // Log - // TODO: recondition,
-            // Log2 - // TODO: recondition,
             // QuantizeToF16 - not implemented in tint/naga,
             // Radians,
             Round, Sign,
             // Sin,
             // Sinh,
-            // Sqrt - // TODO: recondition,
             // Tan - // TODO: recondition,
             // Tanh - // TODO: recondition,
             Trunc,
@@ -134,19 +131,50 @@ pub fn gen_builtins(enabled: &[BuiltinFn]) -> HashMap<DataType, Vec<Rc<Func>>> {
             map.add(builtin, [ty.clone()], ty.clone());
         }
 
-        for builtin in [Max, Min /*, Pow */, Step] {
+        for builtin in [Max, Min, Step] {
             map.add(builtin, [ty.clone(), ty.clone()], ty.clone());
         }
 
-        // for builtin in [Fma, Mix, Smoothstep] {
+        // for builtin in [Fma, Mix] {
         //     map.add(builtin, [ty.clone(), ty.clone(), ty.clone()], ty.clone());
         // }
 
+        // These are only well defined for some of their inputs, so they rely on the reconditioner
+        // sanitising their arguments and are disabled by default
+
+        for builtin in [Acos, Asin, InverseSqrt, Log, Log2, Sqrt] {
+            if enabled.contains(&builtin) {
+                map.add(builtin, [ty.clone()], ty.clone());
+            }
+        }
+
+        if enabled.contains(&Pow) {
+            map.add(Pow, [ty.clone(), ty.clone()], ty.clone());
+        }
+
+        if enabled.contains(&Smoothstep) {
+            map.add(Smoothstep, [ty.clone(), ty.clone(), ty.clone()], ty.clone());
+        }
+
+        // https://github.com/gfx-rs/naga/issues/1908
+        if enabled.contains(&Ldexp) {
+            map.add(Ldexp, [ty.clone(), ty.map(I32)], ty.clone());
+        }
+
         // map.add(Distance, [ty.clone(), ty.clone()], F32);
-        // map.add(Ldexp, [ty.clone(), ty.map(I32)], ty.clone()); // https://github.com/gfx-rs/naga/issues/1908
         // map.add(Length, [ty.clone()], F32);
     }
 
+    if enable_matrices {
+        for c in 2..=4 {
+            for r in 2..=4 {
+                map.add(Transpose, [Matrix(c, r, F32)], Matrix(r, c, F32));
+            }
+
+            map.add(Determinant, [Matrix(c, c, F32)], F32);
+        }
+    }
+
     // map.add(Cross, [Vector(3, F32), Vector(3, F32)], Vector(3, F32));
 
     // for ty in vectors_of(F32) {
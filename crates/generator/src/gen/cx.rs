@@ -7,6 +7,8 @@ use ast::{BuiltinFn, FnDecl, StructDecl};
 use rand::prelude::SliceRandom;
 use rand::Rng;
 
+use crate::profile::{self, Profile, ScalarWeights, TypeWeights};
+use crate::swarm::{Feature, FeatureSet};
 use crate::Options;
 
 use super::{builtins, utils};
@@ -17,10 +19,10 @@ pub struct Context {
 }
 
 impl Context {
-    pub fn new(options: Rc<Options>) -> Context {
+    pub fn new(options: Rc<Options>, features: &FeatureSet, profile: &Profile) -> Context {
         Context {
-            types: TypeContext::new(),
-            fns: FnContext::new(options),
+            types: TypeContext::new(options.enable_matrices, profile),
+            fns: FnContext::new(options, features),
         }
     }
 }
@@ -34,6 +36,9 @@ pub struct FnSignature {
 
 pub struct TypeContext {
     types: Vec<Rc<StructDecl>>,
+    enable_matrices: bool,
+    type_weights: TypeWeights,
+    scalar_weights: ScalarWeights,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -44,8 +49,13 @@ pub enum SelectionFilter {
 }
 
 impl TypeContext {
-    pub fn new() -> Self {
-        TypeContext { types: Vec::new() }
+    pub fn new(enable_matrices: bool, profile: &Profile) -> Self {
+        TypeContext {
+            types: Vec::new(),
+            enable_matrices,
+            type_weights: profile.types.clone(),
+            scalar_weights: profile.scalars.clone(),
+        }
     }
 
     pub fn insert(&mut self, decl: Rc<StructDecl>) {
@@ -71,28 +81,39 @@ impl TypeContext {
         enum DataTypeKind {
             Scalar,
             Vector,
+            Matrix,
             User,
         }
 
-        let allowed: &[DataTypeKind] = if matches!(
-            filter,
-            SelectionFilter::HostShareable | SelectionFilter::Uniform
-        ) || self.types.is_empty()
-        {
-            &[DataTypeKind::Scalar, DataTypeKind::Vector]
-        } else {
-            &[
-                DataTypeKind::Scalar,
-                DataTypeKind::Vector,
-                DataTypeKind::User,
-            ]
+        let mut allowed = vec![DataTypeKind::Scalar, DataTypeKind::Vector];
+
+        // Matrices are only available for floats, which are not allowed in the uniform buffer.
+        if self.enable_matrices && filter != SelectionFilter::Uniform {
+            allowed.push(DataTypeKind::Matrix);
+        }
+
+        if filter == SelectionFilter::Any && !self.types.is_empty() {
+            allowed.push(DataTypeKind::User);
+        }
+
+        let type_weight = |kind: &DataTypeKind| match kind {
+            DataTypeKind::Scalar => self.type_weights.scalar,
+            DataTypeKind::Vector => self.type_weights.vector,
+            DataTypeKind::Matrix => self.type_weights.matrix,
+            DataTypeKind::User => self.type_weights.user,
         };
 
-        match allowed.choose(rng).unwrap() {
-            DataTypeKind::Scalar => DataType::Scalar(allowed_scalars.choose(rng).copied().unwrap()),
-            DataTypeKind::Vector => DataType::Vector(
+        let choose_scalar = |rng: &mut _| {
+            *profile::choose_weighted(rng, allowed_scalars, |ty| self.scalar_weights.weight(*ty))
+        };
+
+        match profile::choose_weighted(rng, &allowed, type_weight) {
+            DataTypeKind::Scalar => DataType::Scalar(choose_scalar(rng)),
+            DataTypeKind::Vector => DataType::Vector(rng.gen_range(2..=4), choose_scalar(rng)),
+            DataTypeKind::Matrix => DataType::Matrix(
+                rng.gen_range(2..=4),
                 rng.gen_range(2..=4),
-                allowed_scalars.choose(rng).copied().unwrap(),
+                ScalarType::F32,
             ),
             DataTypeKind::User => DataType::Struct(self.types.choose(rng).cloned().unwrap()),
         }
@@ -131,9 +152,21 @@ pub struct FnContext {
 }
 
 impl FnContext {
-    pub fn new(options: Rc<Options>) -> Self {
+    pub fn new(options: Rc<Options>, features: &FeatureSet) -> Self {
+        let mut map = builtins::gen_builtins(&options.enabled_fns, options.enable_matrices);
+
+        // Remove builtins belonging to families that have been disabled
+        for funcs in map.values_mut() {
+            funcs.retain(|func| match func.as_ref() {
+                Func::Builtin(builtin, _) => features.is_enabled(Feature::for_builtin(*builtin)),
+                Func::User(_) => true,
+            });
+        }
+
+        map.retain(|_, funcs| !funcs.is_empty());
+
         FnContext {
-            map: builtins::gen_builtins(&options.enabled_fns),
+            map,
             decls: vec![],
             count: 0,
         }
@@ -8,6 +8,8 @@ use ast::{
 };
 
 use super::cx::Func;
+use crate::profile;
+use crate::swarm::Feature;
 
 #[derive(Clone, Copy, Debug)]
 enum ExprType {
@@ -26,24 +28,30 @@ impl<'a> super::Generator<'a> {
         match ty {
             DataType::Scalar(_) => allowed.push(ExprType::Lit),
             DataType::Vector(_, _) => allowed.push(ExprType::TypeCons),
+            DataType::Matrix(_, _, _) => allowed.push(ExprType::TypeCons),
             DataType::Array(_, _) => allowed.push(ExprType::TypeCons),
             DataType::Struct(_) => allowed.push(ExprType::TypeCons),
             DataType::Ptr(view) => return self.gen_pointer_expr(view),
             DataType::Ref(_) => panic!("explicit request to generate ref expression: `{ty}`"),
         }
 
-        if self.fn_state.expression_depth < 5 {
+        if self.fn_state.expression_depth < self.profile.limits.max_expr_depth {
             // Unary operators are available for all scalars and vectors.
-            if matches!(ty, DataType::Scalar(_) | DataType::Vector(_, _)) {
+            if matches!(ty, DataType::Scalar(_) | DataType::Vector(_, _))
+                && !self.un_ops(ty).is_empty()
+            {
                 allowed.push(ExprType::UnOp);
             }
 
-            // Binary operators are available for all scalars, and for {i32,u32,f32} vectors.
+            // Binary operators are available for all scalars, for {i32,u32,f32} vectors, and for
+            // matrices.
             if matches!(
                 ty,
                 DataType::Scalar(_)
                     | DataType::Vector(_, ScalarType::I32 | ScalarType::U32 | ScalarType::F32)
-            ) {
+                    | DataType::Matrix(_, _, _)
+            ) && !self.bin_ops(ty).is_empty()
+            {
                 allowed.push(ExprType::BinOp);
             }
 
@@ -65,7 +73,17 @@ impl<'a> super::Generator<'a> {
 
         tracing::info!("allowed constructions: {:?}", allowed);
 
-        match *allowed.choose(&mut self.rng).unwrap() {
+        let weights = &self.profile.exprs;
+        let weight = |t: &ExprType| match t {
+            ExprType::Lit => weights.lit,
+            ExprType::TypeCons => weights.type_cons,
+            ExprType::Var => weights.var,
+            ExprType::UnOp => weights.un_op,
+            ExprType::BinOp => weights.bin_op,
+            ExprType::FnCall => weights.fn_call,
+        };
+
+        match *profile::choose_weighted(self.rng, &allowed, weight) {
             ExprType::Lit => self.gen_lit_expr(ty),
             ExprType::TypeCons => self.gen_type_cons_expr(ty),
             ExprType::UnOp => self.gen_un_op_expr(ty),
@@ -127,6 +145,9 @@ impl<'a> super::Generator<'a> {
             DataType::Vector(n, t) => (0..*n)
                 .map(|_| self.gen_expr(&DataType::Scalar(*t)))
                 .collect(),
+            DataType::Matrix(c, r, t) => (0..*c)
+                .map(|_| self.gen_expr(&DataType::Vector(*r, *t)))
+                .collect(),
             DataType::Array(_, _) => vec![],
             DataType::Struct(decl) => decl
                 .members
@@ -147,6 +168,9 @@ impl<'a> super::Generator<'a> {
             DataType::Vector(n, t) => (0..*n)
                 .map(|_| self.gen_const_expr(&DataType::Scalar(*t)))
                 .collect(),
+            DataType::Matrix(c, r, t) => (0..*c)
+                .map(|_| self.gen_const_expr(&DataType::Vector(*r, *t)))
+                .collect(),
             DataType::Array(ty, Some(n)) => (0..*n).map(|_| self.gen_const_expr(&*ty)).collect(),
             DataType::Array(_, None) => panic!("runtime sized array is not constructable"),
             DataType::Struct(decl) => decl
@@ -175,6 +199,24 @@ impl<'a> super::Generator<'a> {
         self.fn_state.expression_depth += 1;
 
         let op = self.gen_bin_op(ty);
+
+        // Matrix multiplication can produce either a matrix or a vector, and the operand types
+        // follow the rules of linear algebra rather than matching the result type.
+        if op == BinOp::Times
+            && (ty.is_matrix()
+                || (self.options.enable_matrices
+                    && matches!(ty, DataType::Vector(_, ScalarType::F32))
+                    && self.rng.gen_bool(0.5)))
+        {
+            let (l_ty, r_ty) = self.gen_matrix_product_operands(ty);
+            let l = self.gen_expr(&l_ty);
+            let r = self.gen_expr(&r_ty);
+
+            self.fn_state.expression_depth -= 1;
+
+            return BinOpExpr::new(op, l, r).into();
+        }
+
         let l_ty = match op {
             // These operators work on scalar/vector integers.
             // The result type depends on the operand type.
@@ -235,6 +277,26 @@ impl<'a> super::Generator<'a> {
         BinOpExpr::new(op, l, r).into()
     }
 
+    /// Chooses operand types for a multiplication involving a matrix that produces `ty`.
+    fn gen_matrix_product_operands(&mut self, ty: &DataType) -> (DataType, DataType) {
+        let k = self.rng.gen_range(2..=4);
+        match *ty {
+            DataType::Vector(n, t) => {
+                if self.rng.gen_bool(0.5) {
+                    (DataType::Matrix(k, n, t), DataType::Vector(k, t))
+                } else {
+                    (DataType::Vector(k, t), DataType::Matrix(n, k, t))
+                }
+            }
+            DataType::Matrix(c, r, t) => match self.rng.gen_range(0..3) {
+                0 => (DataType::Matrix(k, r, t), DataType::Matrix(c, k, t)),
+                1 => (ty.clone(), DataType::Scalar(t)),
+                _ => (DataType::Scalar(t), ty.clone()),
+            },
+            _ => unreachable!("no matrix product produces `{ty}`"),
+        }
+    }
+
     fn gen_var_expr(&mut self, ty: &DataType) -> ExprNode {
         tracing::info!("generating var with {:?}, scope={:?}", ty, self.scope);
 
@@ -295,20 +357,19 @@ impl<'a> super::Generator<'a> {
         let mut args = vec![];
 
         for i in 0..arg_count {
-            let expr = if self.options.enable_pointers
-                && self.scope.has_references()
-                && self.rng.gen_bool(0.2)
-            {
-                let (name, mem_view) = self.scope.choose_reference(self.rng);
-                let var_expr = VarExpr::new(name).into_node(DataType::Ref(mem_view.clone()));
-                UnOpExpr::new(UnOp::AddressOf, var_expr).into()
-            } else {
-                self.fn_state.expression_depth += 1;
-                let data_type = self.cx.types.select(self.rng);
-                let expr = self.gen_expr(&data_type);
-                self.fn_state.expression_depth -= 1;
-                expr
-            };
+            let expr =
+                if self.pointers_enabled() && self.scope.has_references() && self.rng.gen_bool(0.2)
+                {
+                    let (name, mem_view) = self.scope.choose_reference(self.rng);
+                    let var_expr = VarExpr::new(name).into_node(DataType::Ref(mem_view.clone()));
+                    UnOpExpr::new(UnOp::AddressOf, var_expr).into()
+                } else {
+                    self.fn_state.expression_depth += 1;
+                    let data_type = self.cx.types.select(self.rng);
+                    let expr = self.gen_expr(&data_type);
+                    self.fn_state.expression_depth -= 1;
+                    expr
+                };
 
             params.push(FnInput {
                 attrs: vec![],
@@ -331,7 +392,9 @@ impl<'a> super::Generator<'a> {
         match expr.data_type.dereference() {
             DataType::Scalar(_) => unreachable!(),
             DataType::Vector(n, _) => self.gen_vector_accessor(*n, target, expr),
-            DataType::Array(_, _) => self.gen_array_accessor(target, expr),
+            DataType::Matrix(_, _, _) | DataType::Array(_, _) => {
+                self.gen_array_accessor(target, expr)
+            }
             DataType::Struct(decl) => self.gen_struct_accessor(&decl.clone(), target, expr),
             DataType::Ptr(_) => self.gen_pointer_deref(target, expr),
             DataType::Ref(_) => todo!(),
@@ -401,31 +464,52 @@ impl<'a> super::Generator<'a> {
     fn gen_un_op(&mut self, ty: &DataType) -> UnOp {
         tracing::info!("generating un_op with {:?}", ty);
 
+        let weights = &self.profile.un_ops;
+        *profile::choose_weighted(self.rng, &self.un_ops(ty), |op| weights.weight(*op))
+    }
+
+    /// Returns the unary operators which can produce a value of type `ty`, excluding any which
+    /// have been disabled.
+    fn un_ops(&self, ty: &DataType) -> Vec<UnOp> {
         let scalar_ty = match ty {
             DataType::Scalar(ty) => ty,
             DataType::Vector(_, ty) => ty,
+            DataType::Matrix(_, _, _) => unreachable!(),
             DataType::Array(_, _) => unreachable!(),
             DataType::Struct(_) => unreachable!(),
             DataType::Ptr(_) => todo!(),
             DataType::Ref(_) => todo!(),
         };
 
-        match scalar_ty {
-            ScalarType::Bool => UnOp::Not,
-            ScalarType::U32 | ScalarType::AU32 => UnOp::BitNot,
-            ScalarType::I32 | ScalarType::AI32 => [UnOp::Neg, UnOp::BitNot]
-                .choose(&mut self.rng)
-                .copied()
-                .unwrap(),
-            ScalarType::F32 => UnOp::Neg,
+        let allowed: &[UnOp] = match scalar_ty {
+            ScalarType::Bool => &[UnOp::Not],
+            ScalarType::U32 | ScalarType::AU32 => &[UnOp::BitNot],
+            ScalarType::I32 | ScalarType::AI32 => &[UnOp::Neg, UnOp::BitNot],
+            ScalarType::F32 => &[UnOp::Neg],
+        };
+
+        if !self.features.is_enabled(Feature::UnaryOps) {
+            return vec![];
         }
+
+        let mut allowed = allowed.to_vec();
+        allowed.retain(|op| self.profile.un_ops.weight(*op) > 0);
+        allowed
     }
 
     #[tracing::instrument(skip(self))]
     fn gen_bin_op(&mut self, ty: &DataType) -> BinOp {
+        let weights = &self.profile.bin_ops;
+        *profile::choose_weighted(self.rng, &self.bin_ops(ty), |op| weights.weight(*op))
+    }
+
+    /// Returns the binary operators which can produce a value of type `ty`, excluding any which
+    /// have been disabled.
+    fn bin_ops(&self, ty: &DataType) -> Vec<BinOp> {
         let scalar_ty = match ty {
-            DataType::Scalar(ty) => ty,
-            DataType::Vector(_, ty) => ty,
+            DataType::Scalar(ty) => Some(ty),
+            DataType::Vector(_, ty) => Some(ty),
+            DataType::Matrix(_, _, _) => None,
             DataType::Array(_, _) => unreachable!(),
             DataType::Struct(_) => unreachable!(),
             DataType::Ptr(_) => todo!(),
@@ -433,7 +517,9 @@ impl<'a> super::Generator<'a> {
         };
 
         let allowed: &[BinOp] = match scalar_ty {
-            ScalarType::Bool => &[
+            // Matrices only support addition, subtraction and multiplication.
+            None => &[BinOp::Plus, BinOp::Minus, BinOp::Times],
+            Some(ScalarType::Bool) => &[
                 BinOp::Equal,
                 BinOp::NotEqual,
                 BinOp::Less,
@@ -443,7 +529,7 @@ impl<'a> super::Generator<'a> {
                 BinOp::BitAnd,
                 BinOp::BitOr,
             ],
-            ScalarType::I32 | ScalarType::U32 => &[
+            Some(ScalarType::I32 | ScalarType::U32) => &[
                 BinOp::Plus,
                 BinOp::Minus,
                 BinOp::Times,
@@ -455,8 +541,8 @@ impl<'a> super::Generator<'a> {
                 BinOp::LShift,
                 BinOp::RShift,
             ],
-            ScalarType::F32 => &[BinOp::Plus, BinOp::Minus, BinOp::Times, BinOp::Divide],
-            ScalarType::AU32 | ScalarType::AI32 => &[], // TODO: No allowed ops
+            Some(ScalarType::F32) => &[BinOp::Plus, BinOp::Minus, BinOp::Times, BinOp::Divide],
+            Some(ScalarType::AU32 | ScalarType::AI32) => &[], // TODO: No allowed ops
         };
 
         let mut allowed = allowed.to_vec();
@@ -465,6 +551,10 @@ impl<'a> super::Generator<'a> {
             allowed.extend_from_slice(&[BinOp::LogAnd, BinOp::LogOr]);
         }
 
-        *allowed.choose(&mut self.rng).unwrap()
+        allowed.retain(|op| {
+            self.features.is_enabled(Feature::for_bin_op(*op))
+                && self.profile.bin_ops.weight(*op) > 0
+        });
+        allowed
     }
 }
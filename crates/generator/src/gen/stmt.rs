@@ -3,18 +3,20 @@ use std::mem;
 
 use ast::types::{DataType, MemoryViewType, ScalarType};
 use ast::{
-    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, Expr, ExprNode,
-    ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate, IfStatement, LetDeclStatement,
-    LhsExprNode, Lit, LoopStatement, ReturnStatement, Statement, StorageClass, SwitchCase,
-    SwitchStatement, UnOp, UnOpExpr, VarDeclStatement, VarExpr,
+    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, BuiltinFn, Expr, ExprNode,
+    FnCallExpr, FnCallStatement, ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate,
+    IfStatement, LetDeclStatement, LhsExprNode, Lit, LoopStatement, Postfix, PostfixExpr,
+    ReturnStatement, Statement, StorageClass, SwitchCase, SwitchStatement, UnOp, UnOpExpr,
+    VarDeclStatement, VarExpr,
 };
 use rand::prelude::SliceRandom;
 use rand::Rng;
 
 use super::scope::Scope;
 use super::utils::is_terminal_stmt;
+use crate::swarm::Feature;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum StatementType {
     LetDecl,
     VarDecl,
@@ -27,15 +29,34 @@ enum StatementType {
     ForLoop,
     Break,
     Continue,
+    Atomic,
+}
+
+impl StatementType {
+    fn feature(&self) -> Feature {
+        match self {
+            StatementType::LetDecl => Feature::LetStmt,
+            StatementType::VarDecl => Feature::VarStmt,
+            StatementType::Assignment => Feature::AssignmentStmt,
+            StatementType::If => Feature::IfStmt,
+            StatementType::Return => Feature::ReturnStmt,
+            StatementType::Loop => Feature::LoopStmt,
+            StatementType::Switch => Feature::SwitchStmt,
+            StatementType::ForLoop => Feature::ForStmt,
+            StatementType::Break => Feature::BreakStmt,
+            StatementType::Continue => Feature::ContinueStmt,
+            StatementType::Atomic => Feature::Atomics,
+        }
+    }
 }
 
 impl<'a> super::Generator<'a> {
     pub fn gen_stmt(&mut self) -> Statement {
-        let mut allowed = vec![
-            StatementType::LetDecl,
-            StatementType::VarDecl,
-            StatementType::Return,
-        ];
+        let mut allowed = vec![StatementType::LetDecl, StatementType::VarDecl];
+
+        if !self.fn_state.no_return {
+            allowed.push(StatementType::Return);
+        }
 
         if self.fn_state.is_loop {
             allowed.push(StatementType::Break);
@@ -46,7 +67,11 @@ impl<'a> super::Generator<'a> {
             allowed.push(StatementType::Assignment);
         }
 
-        if self.fn_state.block_depth < self.options.max_block_depth {
+        if !self.fn_state.atomics.is_empty() {
+            allowed.push(StatementType::Atomic);
+        }
+
+        if self.fn_state.block_depth < self.max_block_depth() {
             allowed.extend_from_slice(&[
                 // StatementType::Compound,
                 StatementType::If,
@@ -56,21 +81,31 @@ impl<'a> super::Generator<'a> {
             ]);
         }
 
-        let weights = |t: &StatementType| match t {
-            StatementType::LetDecl => 10,
-            StatementType::VarDecl => 10,
-            StatementType::Assignment => 10,
+        let weights = &self.profile.stmts;
+        let weight = |t: &StatementType| match t {
+            StatementType::LetDecl => weights.let_decl,
+            StatementType::VarDecl => weights.var_decl,
+            StatementType::Assignment => weights.assignment,
             // StatementType::Compound => 1,
-            StatementType::If => 5,
-            StatementType::Return => 1,
-            StatementType::Loop => 5,
-            StatementType::Switch => 5,
-            StatementType::ForLoop => 5,
-            StatementType::Break => 5,
-            StatementType::Continue => 5,
+            StatementType::If => weights.if_stmt,
+            StatementType::Return => weights.return_stmt,
+            StatementType::Loop => weights.loop_stmt,
+            StatementType::Switch => weights.switch,
+            StatementType::ForLoop => weights.for_loop,
+            StatementType::Break => weights.break_stmt,
+            StatementType::Continue => weights.continue_stmt,
+            StatementType::Atomic => weights.atomic,
         };
 
-        match allowed.choose_weighted(self.rng, weights).unwrap() {
+        allowed.retain(|t| self.features.is_enabled(t.feature()) && weight(t) > 0);
+
+        // Let declarations are always possible, so fall back to them if everything else has been
+        // disabled
+        if allowed.is_empty() {
+            return self.gen_let_stmt();
+        }
+
+        match allowed.choose_weighted(self.rng, weight).unwrap() {
             StatementType::LetDecl => self.gen_let_stmt(),
             StatementType::VarDecl => self.gen_var_stmt(),
             StatementType::Assignment => self.gen_assignment_stmt().into(),
@@ -82,11 +117,12 @@ impl<'a> super::Generator<'a> {
             StatementType::ForLoop => self.gen_for_stmt(),
             StatementType::Break => Statement::Break,
             StatementType::Continue => Statement::Continue,
+            StatementType::Atomic => self.gen_atomic_stmt(),
         }
     }
 
     fn gen_let_stmt(&mut self) -> Statement {
-        if self.options.enable_pointers && self.scope.has_mutables() && self.rng.gen_bool(0.2) {
+        if self.pointers_enabled() && self.scope.has_mutables() && self.rng.gen_bool(0.2) {
             let (ident, ty) = self.scope.choose_mutable(self.rng);
             let initializer =
                 UnOpExpr::new(UnOp::AddressOf, VarExpr::new(ident).into_node(ty.clone()));
@@ -154,6 +190,68 @@ impl<'a> super::Generator<'a> {
         .into()
     }
 
+    /// Generates a call to an atomic builtin on an element of one of the atomic arrays, binding the
+    /// result to a new variable if there is one.
+    ///
+    /// `atomicCompareExchangeWeak` is never generated, since it is allowed to fail spuriously and so
+    /// its result can legitimately differ between implementations.
+    fn gen_atomic_stmt(&mut self) -> Statement {
+        let (name, data_type) = self.fn_state.atomics.choose(self.rng).cloned().unwrap();
+
+        let value_type = match data_type.dereference() {
+            DataType::Array(element, _) => match element.as_ref() {
+                DataType::Scalar(ScalarType::AU32) => DataType::Scalar(ScalarType::U32),
+                DataType::Scalar(ScalarType::AI32) => DataType::Scalar(ScalarType::I32),
+                ty => unreachable!("expected atomic type, found `{ty}`"),
+            },
+            ty => unreachable!("expected array of atomics, found `{ty}`"),
+        };
+
+        let index = if self.fn_state.atomics_per_invocation {
+            VarExpr::new("local_index").into_node(DataType::Scalar(ScalarType::U32))
+        } else {
+            self.gen_expr(&DataType::Scalar(ScalarType::U32))
+        };
+
+        let element = PostfixExpr::new(
+            VarExpr::new(name).into_node(data_type),
+            Postfix::index(index),
+        );
+
+        let builtin = *[
+            BuiltinFn::AtomicAdd,
+            BuiltinFn::AtomicAnd,
+            BuiltinFn::AtomicExchange,
+            BuiltinFn::AtomicLoad,
+            BuiltinFn::AtomicMax,
+            BuiltinFn::AtomicMin,
+            BuiltinFn::AtomicOr,
+            BuiltinFn::AtomicStore,
+            BuiltinFn::AtomicSub,
+            BuiltinFn::AtomicXor,
+        ]
+        .choose(self.rng)
+        .unwrap();
+
+        let mut args: Vec<ExprNode> = vec![UnOpExpr::new(UnOp::AddressOf, element).into()];
+
+        if builtin != BuiltinFn::AtomicLoad {
+            args.push(self.gen_expr(&value_type));
+        }
+
+        if builtin == BuiltinFn::AtomicStore {
+            return FnCallStatement::new(builtin.as_ref().to_owned(), args).into();
+        }
+
+        let return_type = builtin
+            .return_type(args.iter().map(|it| &it.data_type))
+            .unwrap();
+
+        let value = FnCallExpr::new(builtin.as_ref(), args).into_node(return_type);
+
+        LetDeclStatement::new(self.scope.next_name(), value).into()
+    }
+
     fn gen_loop_stmt(&mut self) -> Statement {
         let max_count = self
             .rng
@@ -191,13 +289,13 @@ impl<'a> super::Generator<'a> {
                 //     body.push(Statement::Fallthrough);
                 // }
 
-                SwitchCase {
-                    selector: ExprNode {
+                SwitchCase::new(
+                    ExprNode {
                         data_type: DataType::Scalar(ScalarType::I32),
                         expr: Expr::Lit(Lit::I32(value)),
                     },
                     body,
-                }
+                )
             })
             .collect();
 
@@ -13,6 +13,9 @@ pub enum StructKind {
     Default,
     HostShareable,
     UniformBuffer,
+    /// A storage buffer which ends with a runtime-sized array. Only integer types are used, so
+    /// that random initial data can't contain NaNs or infinities.
+    RuntimeBuffer,
 }
 
 impl<'a> super::Generator<'a> {
@@ -28,7 +31,7 @@ impl<'a> super::Generator<'a> {
         let filter = match kind {
             StructKind::Default => SelectionFilter::Any,
             StructKind::HostShareable => SelectionFilter::HostShareable,
-            StructKind::UniformBuffer => SelectionFilter::Uniform,
+            StructKind::UniformBuffer | StructKind::RuntimeBuffer => SelectionFilter::Uniform,
         };
 
         let mut members = (0..member_count)
@@ -41,6 +44,15 @@ impl<'a> super::Generator<'a> {
             })
             .collect::<Vec<_>>();
 
+        if kind == StructKind::RuntimeBuffer {
+            let element_type = self.cx.types.select_with_filter(self.rng, filter);
+            members.push(StructMember::new(
+                vec![],
+                "data",
+                DataType::array(element_type, None),
+            ));
+        }
+
         if matches!(kind, StructKind::HostShareable | StructKind::UniformBuffer) {
             for member in &mut members {
                 if let DataType::Struct(_) = member.data_type {
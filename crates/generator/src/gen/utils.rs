@@ -44,6 +44,13 @@ pub fn accessible_types_of(ty: &DataType) -> Vec<DataType> {
             }
             derived
         }
+        DataType::Matrix(_, r, ty) => {
+            // Columns are accessible by indexing, and then their components by swizzling
+            let column = DataType::Vector(*r, *ty);
+            let mut derived = accessible_types_of(&column);
+            derived.push(column);
+            derived
+        }
         DataType::Array(ty, _) => vec![(**ty).clone()],
         DataType::Struct(decl) => decl.accessible_types().cloned().collect(),
         DataType::Ptr(view) | DataType::Ref(view) => accessible_types_of(&view.inner),
@@ -1,4 +1,7 @@
+mod checksum;
 mod gen;
+mod profile;
+mod swarm;
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -8,15 +11,17 @@ use std::path::Path;
 use std::rc::Rc;
 use std::str::FromStr;
 
-use ast::{BuiltinFn, StorageClass, VarQualifier};
+use ast::{BuiltinFn, Module, StorageClass, VarQualifier};
 use clap::Parser;
 use eyre::{bail, eyre};
 use hashers::fx_hash::FxHasher;
 
 pub use gen::{builtins, Generator};
+pub use profile::Profile;
 use rand::prelude::StdRng;
 use rand::rngs::OsRng;
 use rand::{Rng, SeedableRng};
+pub use swarm::{Feature, FeatureSet};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Preset {
@@ -53,12 +58,49 @@ pub struct Options {
     #[clap(long, action)]
     pub enable_pointers: bool,
 
+    /// Whether to enable generating matrix types.
+    #[clap(long, action)]
+    pub enable_matrices: bool,
+
+    /// Whether to enable generating workgroup variables and barriers.
+    ///
+    /// The entry point is given a random workgroup size greater than one, and each invocation
+    /// writes to its own element of the output buffer.
+    #[clap(long, action)]
+    pub enable_workgroup_memory: bool,
+
+    /// Whether to enable generating atomic variables and atomic builtin calls.
+    ///
+    /// Atomics are declared in a storage buffer and in workgroup memory, and may only be accessed
+    /// from the entry point.
+    #[clap(long, action)]
+    pub enable_atomics: bool,
+
+    /// Whether to enable generating storage buffers containing runtime-sized arrays.
+    ///
+    /// The arrays are read-only, and are indexed using `arrayLength` to keep accesses in bounds.
+    #[clap(long, action)]
+    pub enable_runtime_arrays: bool,
+
+    /// Whether to fold the values of all variables and function return values into a checksum.
+    ///
+    /// The checksum is written to an extra `checksum` member of the output buffer, so that wrong
+    /// code in intermediate computations also changes the output.
+    #[clap(long, action)]
+    pub checksum: bool,
+
     /// Skips the static pointer aliasing checks.
     ///
     /// This is only useful if reconditioning and pointer support is enabled.
     #[clap(long, action)]
     pub skip_pointer_checks: bool,
 
+    /// Rewrites calls which may cause invalid pointer aliasing, instead of rejecting the shader.
+    ///
+    /// Each offending pointer argument is replaced with a pointer to a fresh copy of its value.
+    #[clap(long, action)]
+    pub repair_aliasing: bool,
+
     /// Logging configuration string (see https://docs.rs/tracing-subscriber/0.3.7/tracing_subscriber/struct.EnvFilter.html#directives)
     #[clap(long, action)]
     pub log: Option<String>,
@@ -103,6 +145,21 @@ pub struct Options {
     #[clap(long, action, default_value = "5")]
     pub max_struct_members: u32,
 
+    /// Randomly disable a subset of generator features for each program (swarm testing).
+    ///
+    /// The disabled features are recorded in the header of the generated program.
+    #[clap(long, action)]
+    pub swarm: bool,
+
+    /// Disable a generator feature (e.g. `loop`, `shift-ops`, `structs`).
+    #[clap(long = "disable", action)]
+    pub disabled_features: Vec<Feature>,
+
+    /// Profile with weights and limits for generation, either the name of a shipped profile
+    /// ({naga-frontend,tint-backend,deep-control-flow}) or a path to a TOML file.
+    #[clap(long, action)]
+    pub profile: Option<Profile>,
+
     /// Preset options configuration. Individual options may still be overridden.
     #[clap(long, action)]
     pub preset: Option<Preset>,
@@ -131,6 +188,46 @@ impl BuildHasher for BuildFxHasher {
     }
 }
 
+/// Generates random initial contents for each of the uniform buffers in a module, and for each
+/// storage buffer containing a runtime-sized array, keyed by `group:binding`.
+///
+/// The size of the initial data for a runtime-sized array determines its length.
+pub fn gen_init_data(
+    module: &Module,
+    rng: &mut impl Rng,
+) -> eyre::Result<HashMap<String, Vec<u8>>> {
+    let mut init_data = HashMap::new();
+
+    for var in &module.vars {
+        if let Some(VarQualifier { storage_class, .. }) = &var.qualifier {
+            let data_type = match storage_class {
+                StorageClass::Uniform => var.data_type.clone(),
+                StorageClass::Storage => {
+                    match var.data_type.with_runtime_array_len(rng.gen_range(1..=16)) {
+                        Some(data_type) => data_type,
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            let space = common::AddressSpace::from_storage_class(*storage_class).unwrap();
+            let type_desc =
+                common::Type::from_data_type(&data_type, space).map_err(|e| eyre!(e))?;
+
+            let group = var.group_index().unwrap();
+            let binding = var.binding_index().unwrap();
+
+            let size = type_desc.buffer_size();
+            let data: Vec<u8> = (0..size).map(|_| rng.gen()).collect();
+
+            init_data.insert(format!("{group}:{binding}"), data);
+        }
+    }
+
+    Ok(init_data)
+}
+
 pub fn run(mut options: Options) -> eyre::Result<()> {
     if let Some(preset) = &options.preset {
         match preset {
@@ -171,22 +268,34 @@ pub fn run(mut options: Options) -> eyre::Result<()> {
     tracing::info!("generating shader from seed: {}", seed);
 
     let mut rng = StdRng::seed_from_u64(seed);
-    let mut shader = Generator::new(&mut rng, options.clone()).gen_module();
+    let mut generator = Generator::new(&mut rng, options.clone());
+    let mut shader = generator.gen_module();
+    let features = generator.features().clone();
 
     if options.recondition {
-        if options.enable_pointers
-            && !options.skip_pointer_checks
-            && !reconditioner::analysis::analyse(&shader)
-        {
-            bail!("rejected shader due to possible invalid aliasing");
+        if options.enable_pointers && !options.skip_pointer_checks {
+            if options.repair_aliasing {
+                let result = reconditioner::analysis::repair(shader)
+                    .ok_or_else(|| eyre!("failed to repair possible invalid aliasing"))?;
+
+                for call in &result.rewritten {
+                    tracing::info!("rewrote {call} to avoid possible invalid aliasing");
+                }
+
+                shader = result.ast;
+            } else if !reconditioner::analysis::analyse(&shader) {
+                bail!("rejected shader due to possible invalid aliasing");
+            }
         }
 
         shader = reconditioner::recondition_with(
             shader,
             reconditioner::Options {
                 only_loops: options.preset == Some(Preset::Tint),
+                ..Default::default()
             },
-        );
+        )
+        .ast;
     }
 
     if options.flow {
@@ -203,30 +312,13 @@ pub fn run(mut options: Options) -> eyre::Result<()> {
     };
 
     if !options.debug {
-        let mut init_data = HashMap::new();
-
-        for var in &shader.vars {
-            if let Some(VarQualifier { storage_class, .. }) = &var.qualifier {
-                if *storage_class != StorageClass::Uniform {
-                    continue;
-                }
-
-                let type_desc = common::Type::try_from(&var.data_type).map_err(|e| eyre!(e))?;
-
-                let group = var.group_index().unwrap();
-                let binding = var.binding_index().unwrap();
-
-                let size = type_desc.buffer_size();
-                let data: Vec<u8> = (0..size).map(|_| rng.gen()).collect();
-
-                init_data.insert(format!("{group}:{binding}"), data);
-            }
-        }
-
-        let init_data = serde_json::to_string(&init_data)?;
+        let init_data = serde_json::to_string(&gen_init_data(&shader, &mut rng)?)?;
 
         writeln!(output, "// {init_data}")?;
         writeln!(output, "// Seed: {seed}")?;
+        if options.swarm || !options.disabled_features.is_empty() {
+            writeln!(output, "// Disabled: {features}")?;
+        }
         writeln!(output)?;
     }
 
@@ -0,0 +1,448 @@
+use std::str::FromStr;
+
+use ast::{BinOp, ScalarType, UnOp};
+use rand::distributions::WeightedIndex;
+use rand::prelude::{Distribution, SliceRandom};
+use rand::Rng;
+use serde::Deserialize;
+
+/// Profiles which are shipped with the generator and can be selected by name.
+const BUILTIN_PROFILES: &[(&str, &str)] = &[
+    (
+        "naga-frontend",
+        include_str!("../profiles/naga-frontend.toml"),
+    ),
+    (
+        "tint-backend",
+        include_str!("../profiles/tint-backend.toml"),
+    ),
+    (
+        "deep-control-flow",
+        include_str!("../profiles/deep-control-flow.toml"),
+    ),
+];
+
+/// Weights and limits which control the shape of generated programs.
+///
+/// Any values which are missing from a profile file take their default value, which matches the
+/// generator's behaviour without a profile. Setting a weight to zero prevents that construct from
+/// being generated (unless there is no other option).
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Profile {
+    pub stmts: StmtWeights,
+    pub exprs: ExprWeights,
+    pub bin_ops: BinOpWeights,
+    pub un_ops: UnOpWeights,
+    pub types: TypeWeights,
+    pub scalars: ScalarWeights,
+    pub literals: Literals,
+    pub limits: Limits,
+}
+
+impl Profile {
+    /// Loads a profile, either by the name of a shipped profile or from a path to a TOML file.
+    pub fn load(name_or_path: &str) -> Result<Profile, String> {
+        let source = match BUILTIN_PROFILES
+            .iter()
+            .find(|(name, _)| *name == name_or_path)
+        {
+            Some((_, source)) => source.to_string(),
+            None => std::fs::read_to_string(name_or_path).map_err(|e| {
+                let names = BUILTIN_PROFILES.iter().map(|(name, _)| *name);
+                format!(
+                    "failed to read profile `{name_or_path}` (shipped profiles are {{{}}}): {e}",
+                    names.collect::<Vec<_>>().join(",")
+                )
+            })?,
+        };
+
+        Profile::parse(&source).map_err(|e| format!("invalid profile `{name_or_path}`: {e}"))
+    }
+
+    fn parse(source: &str) -> Result<Profile, String> {
+        let profile: Profile = toml::from_str(source).map_err(|e| e.to_string())?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Checks the values which the generator would otherwise panic on.
+    fn validate(&self) -> Result<(), String> {
+        let probabilities = [
+            (
+                "literals.special-int-probability",
+                self.literals.special_int_probability,
+            ),
+            (
+                "limits.global-array-probability",
+                self.limits.global_array_probability,
+            ),
+        ];
+
+        for (name, value) in probabilities {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("`{name}` must be between 0 and 1, found {value}"));
+            }
+        }
+
+        if !self.literals.float_scale.is_finite() {
+            return Err(format!(
+                "`literals.float-scale` must be finite, found {}",
+                self.literals.float_scale
+            ));
+        }
+
+        let Limits {
+            entrypoint_min_stmts: min,
+            entrypoint_max_stmts: max,
+            ..
+        } = self.limits;
+
+        if min > max {
+            return Err(format!(
+                "`limits.entrypoint-min-stmts` ({min}) must not be greater than \
+                `limits.entrypoint-max-stmts` ({max})"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Profile::load(s)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StmtWeights {
+    #[serde(rename = "let")]
+    pub let_decl: u32,
+    #[serde(rename = "var")]
+    pub var_decl: u32,
+    pub assignment: u32,
+    #[serde(rename = "if")]
+    pub if_stmt: u32,
+    #[serde(rename = "return")]
+    pub return_stmt: u32,
+    #[serde(rename = "loop")]
+    pub loop_stmt: u32,
+    pub switch: u32,
+    #[serde(rename = "for")]
+    pub for_loop: u32,
+    #[serde(rename = "break")]
+    pub break_stmt: u32,
+    #[serde(rename = "continue")]
+    pub continue_stmt: u32,
+    pub atomic: u32,
+}
+
+impl Default for StmtWeights {
+    fn default() -> Self {
+        StmtWeights {
+            let_decl: 10,
+            var_decl: 10,
+            assignment: 10,
+            if_stmt: 5,
+            return_stmt: 1,
+            loop_stmt: 5,
+            switch: 5,
+            for_loop: 5,
+            break_stmt: 5,
+            continue_stmt: 5,
+            atomic: 5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ExprWeights {
+    pub lit: u32,
+    pub type_cons: u32,
+    pub var: u32,
+    pub un_op: u32,
+    pub bin_op: u32,
+    pub fn_call: u32,
+}
+
+impl Default for ExprWeights {
+    fn default() -> Self {
+        ExprWeights {
+            lit: 1,
+            type_cons: 1,
+            var: 1,
+            un_op: 1,
+            bin_op: 1,
+            fn_call: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BinOpWeights {
+    pub plus: u32,
+    pub minus: u32,
+    pub times: u32,
+    pub divide: u32,
+    pub modulo: u32,
+    pub bit_and: u32,
+    pub bit_or: u32,
+    pub bit_xor: u32,
+    pub shift_left: u32,
+    pub shift_right: u32,
+    pub equal: u32,
+    pub not_equal: u32,
+    pub less: u32,
+    pub less_equal: u32,
+    pub greater: u32,
+    pub greater_equal: u32,
+    pub log_and: u32,
+    pub log_or: u32,
+}
+
+impl BinOpWeights {
+    pub fn weight(&self, op: BinOp) -> u32 {
+        match op {
+            BinOp::Plus => self.plus,
+            BinOp::Minus => self.minus,
+            BinOp::Times => self.times,
+            BinOp::Divide => self.divide,
+            BinOp::Mod => self.modulo,
+            BinOp::BitAnd => self.bit_and,
+            BinOp::BitOr => self.bit_or,
+            BinOp::BitXOr => self.bit_xor,
+            BinOp::LShift => self.shift_left,
+            BinOp::RShift => self.shift_right,
+            BinOp::Equal => self.equal,
+            BinOp::NotEqual => self.not_equal,
+            BinOp::Less => self.less,
+            BinOp::LessEqual => self.less_equal,
+            BinOp::Greater => self.greater,
+            BinOp::GreaterEqual => self.greater_equal,
+            BinOp::LogAnd => self.log_and,
+            BinOp::LogOr => self.log_or,
+        }
+    }
+}
+
+impl Default for BinOpWeights {
+    fn default() -> Self {
+        BinOpWeights {
+            plus: 1,
+            minus: 1,
+            times: 1,
+            divide: 1,
+            modulo: 1,
+            bit_and: 1,
+            bit_or: 1,
+            bit_xor: 1,
+            shift_left: 1,
+            shift_right: 1,
+            equal: 1,
+            not_equal: 1,
+            less: 1,
+            less_equal: 1,
+            greater: 1,
+            greater_equal: 1,
+            log_and: 1,
+            log_or: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct UnOpWeights {
+    pub neg: u32,
+    pub not: u32,
+    pub bit_not: u32,
+}
+
+impl UnOpWeights {
+    pub fn weight(&self, op: UnOp) -> u32 {
+        match op {
+            UnOp::Neg => self.neg,
+            UnOp::Not => self.not,
+            UnOp::BitNot => self.bit_not,
+            UnOp::AddressOf | UnOp::Deref => 0,
+        }
+    }
+}
+
+impl Default for UnOpWeights {
+    fn default() -> Self {
+        UnOpWeights {
+            neg: 1,
+            not: 1,
+            bit_not: 1,
+        }
+    }
+}
+
+/// Weights for the kinds of type chosen by `TypeContext::select_with_filter`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypeWeights {
+    pub scalar: u32,
+    pub vector: u32,
+    pub matrix: u32,
+    #[serde(rename = "struct")]
+    pub user: u32,
+}
+
+impl Default for TypeWeights {
+    fn default() -> Self {
+        TypeWeights {
+            scalar: 1,
+            vector: 1,
+            matrix: 1,
+            user: 1,
+        }
+    }
+}
+
+/// Weights for scalar types, used for both scalars and vector components.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScalarWeights {
+    pub i32: u32,
+    pub u32: u32,
+    pub f32: u32,
+    pub bool: u32,
+}
+
+impl ScalarWeights {
+    pub fn weight(&self, ty: ScalarType) -> u32 {
+        match ty {
+            ScalarType::I32 | ScalarType::AI32 => self.i32,
+            ScalarType::U32 | ScalarType::AU32 => self.u32,
+            ScalarType::F32 => self.f32,
+            ScalarType::Bool => self.bool,
+        }
+    }
+}
+
+impl Default for ScalarWeights {
+    fn default() -> Self {
+        ScalarWeights {
+            i32: 1,
+            u32: 1,
+            f32: 1,
+            bool: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Literals {
+    /// Probability of generating a boundary value (e.g. `0` or `i32::MIN`) for an integer
+    /// literal, rather than sampling from the full range.
+    pub special_int_probability: f64,
+    /// Scale of the normal distribution used for float literals.
+    pub float_scale: f64,
+}
+
+impl Default for Literals {
+    fn default() -> Self {
+        Literals {
+            special_int_probability: 0.5,
+            float_scale: 1000.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Limits {
+    /// Maximum depth of nested expressions, after which only literals, constructors and
+    /// variables are generated.
+    pub max_expr_depth: u32,
+    /// Maximum nested block depth. Overrides `--max-block-depth` if set.
+    pub max_block_depth: Option<u32>,
+    /// Minimum number of statements to generate in the entry point.
+    pub entrypoint_min_stmts: u32,
+    /// Maximum number of statements to generate in the entry point.
+    pub entrypoint_max_stmts: u32,
+    /// Maximum number of private global variables.
+    pub max_globals: u32,
+    /// Probability that a private global variable is an array.
+    pub global_array_probability: f64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_expr_depth: 5,
+            max_block_depth: None,
+            entrypoint_min_stmts: 5,
+            entrypoint_max_stmts: 9,
+            max_globals: 5,
+            global_array_probability: 0.5,
+        }
+    }
+}
+
+/// Chooses an item according to `weight`, falling back to a uniform choice if all weights are
+/// zero.
+pub fn choose_weighted<'a, T>(
+    rng: &mut impl Rng,
+    items: &'a [T],
+    weight: impl Fn(&T) -> u32,
+) -> &'a T {
+    match WeightedIndex::new(items.iter().map(weight)) {
+        Ok(dist) => &items[dist.sample(rng)],
+        Err(_) => items.choose(rng).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_profiles_are_valid() {
+        for (name, _) in BUILTIN_PROFILES {
+            if let Err(e) = Profile::load(name) {
+                panic!("{e}");
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases = [
+            (
+                "[limits]\nglobal-array-probability = 1.5",
+                "between 0 and 1",
+            ),
+            (
+                "[literals]\nspecial-int-probability = -0.1",
+                "between 0 and 1",
+            ),
+            (
+                "[literals]\nspecial-int-probability = nan",
+                "between 0 and 1",
+            ),
+            ("[literals]\nfloat-scale = inf", "must be finite"),
+            (
+                "[limits]\nentrypoint-min-stmts = 10\nentrypoint-max-stmts = 2",
+                "must not be greater than",
+            ),
+            ("[limits]\nmax-globalz = 1", "unknown field"),
+        ];
+
+        for (source, message) in cases {
+            let err = Profile::parse(source).unwrap_err();
+            assert!(err.contains(message), "{source}: {err}");
+        }
+
+        assert!(Profile::parse("[limits]\nentrypoint-min-stmts = 9").is_ok());
+    }
+}
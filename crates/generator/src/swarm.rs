@@ -0,0 +1,292 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use ast::{BinOp, BuiltinFn};
+use rand::Rng;
+
+use crate::Options;
+
+/// A feature of the generator that can be disabled for swarm testing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    LetStmt,
+    VarStmt,
+    AssignmentStmt,
+    IfStmt,
+    ReturnStmt,
+    LoopStmt,
+    SwitchStmt,
+    ForStmt,
+    BreakStmt,
+    ContinueStmt,
+    /// `+`, `-` and `*`.
+    ArithmeticOps,
+    /// `/` and `%`.
+    DivisionOps,
+    /// `&`, `|` and `^`.
+    BitwiseOps,
+    /// `<<` and `>>`.
+    ShiftOps,
+    /// `==`, `!=`, `<`, `<=`, `>` and `>=`.
+    ComparisonOps,
+    /// `&&` and `||`.
+    LogicalOps,
+    /// `-`, `!` and `~`.
+    UnaryOps,
+    /// Integer bit manipulation builtins, e.g. `countOneBits`.
+    BitBuiltins,
+    /// Floating point math builtins, e.g. `floor`.
+    MathBuiltins,
+    /// `abs`, `clamp`, `min` and `max`.
+    MinMaxBuiltins,
+    /// `select`, `all` and `any`.
+    SelectBuiltins,
+    /// Vector builtins, e.g. `dot`.
+    VectorBuiltins,
+    /// Matrix builtins, e.g. `transpose`.
+    MatrixBuiltins,
+    Pointers,
+    /// User-defined structs (excluding the input and output buffer types).
+    Structs,
+    /// Private global variables.
+    Globals,
+    /// Atomic builtin calls, e.g. `atomicAdd`.
+    Atomics,
+    /// Storage buffers containing runtime-sized arrays.
+    RuntimeArrays,
+}
+
+const FEATURES: &[(Feature, &str)] = &[
+    (Feature::LetStmt, "let"),
+    (Feature::VarStmt, "var"),
+    (Feature::AssignmentStmt, "assignment"),
+    (Feature::IfStmt, "if"),
+    (Feature::ReturnStmt, "return"),
+    (Feature::LoopStmt, "loop"),
+    (Feature::SwitchStmt, "switch"),
+    (Feature::ForStmt, "for"),
+    (Feature::BreakStmt, "break"),
+    (Feature::ContinueStmt, "continue"),
+    (Feature::ArithmeticOps, "arithmetic-ops"),
+    (Feature::DivisionOps, "division-ops"),
+    (Feature::BitwiseOps, "bitwise-ops"),
+    (Feature::ShiftOps, "shift-ops"),
+    (Feature::ComparisonOps, "comparison-ops"),
+    (Feature::LogicalOps, "logical-ops"),
+    (Feature::UnaryOps, "unary-ops"),
+    (Feature::BitBuiltins, "bit-builtins"),
+    (Feature::MathBuiltins, "math-builtins"),
+    (Feature::MinMaxBuiltins, "minmax-builtins"),
+    (Feature::SelectBuiltins, "select-builtins"),
+    (Feature::VectorBuiltins, "vector-builtins"),
+    (Feature::MatrixBuiltins, "matrix-builtins"),
+    (Feature::Pointers, "pointers"),
+    (Feature::Structs, "structs"),
+    (Feature::Globals, "globals"),
+    (Feature::Atomics, "atomics"),
+    (Feature::RuntimeArrays, "runtime-arrays"),
+];
+
+impl Feature {
+    pub fn all() -> impl Iterator<Item = Feature> {
+        FEATURES.iter().map(|(feature, _)| *feature)
+    }
+
+    pub fn name(&self) -> &'static str {
+        FEATURES.iter().find(|(it, _)| it == self).unwrap().1
+    }
+
+    pub fn for_bin_op(op: BinOp) -> Feature {
+        match op {
+            BinOp::Plus | BinOp::Minus | BinOp::Times => Feature::ArithmeticOps,
+            BinOp::Divide | BinOp::Mod => Feature::DivisionOps,
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXOr => Feature::BitwiseOps,
+            BinOp::LShift | BinOp::RShift => Feature::ShiftOps,
+            BinOp::Equal
+            | BinOp::NotEqual
+            | BinOp::Less
+            | BinOp::LessEqual
+            | BinOp::Greater
+            | BinOp::GreaterEqual => Feature::ComparisonOps,
+            BinOp::LogAnd | BinOp::LogOr => Feature::LogicalOps,
+        }
+    }
+
+    pub fn for_builtin(builtin: BuiltinFn) -> Feature {
+        use BuiltinFn::*;
+        match builtin {
+            CountLeadingZeros | CountOneBits | CountTrailingZeros | ExtractBits
+            | FirstLeadingBit | FirstTrailingBit | InsertBits | ReverseBits => Feature::BitBuiltins,
+            Abs | Clamp | Max | Min => Feature::MinMaxBuiltins,
+            All | Any | Select => Feature::SelectBuiltins,
+            Cross | Distance | Dot | FaceForward | Length | Normalize | Reflect | Refract => {
+                Feature::VectorBuiltins
+            }
+            Determinant | Transpose => Feature::MatrixBuiltins,
+            _ => Feature::MathBuiltins,
+        }
+    }
+
+    /// Whether disabling this feature can have any effect with the given options.
+    fn is_applicable(&self, options: &Options) -> bool {
+        match self {
+            Feature::Pointers => options.enable_pointers,
+            Feature::MatrixBuiltins => options.enable_matrices,
+            Feature::Atomics => options.enable_atomics,
+            Feature::RuntimeArrays => options.enable_runtime_arrays,
+            _ => true,
+        }
+    }
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FEATURES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(feature, _)| *feature)
+            .ok_or_else(|| {
+                let names = FEATURES.iter().map(|(_, name)| *name).collect::<Vec<_>>();
+                format!("invalid feature - must be one of {{{}}}", names.join(","))
+            })
+    }
+}
+
+/// The set of features that have been disabled for a generated program.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FeatureSet {
+    disabled: BTreeSet<Feature>,
+}
+
+impl FeatureSet {
+    /// Creates the feature set for a new program.
+    ///
+    /// Features passed with `--disable` are always disabled. In swarm mode, each of the remaining
+    /// features is additionally disabled with a probability of 0.5.
+    pub fn new(options: &Options, rng: &mut impl Rng) -> FeatureSet {
+        let mut disabled = BTreeSet::from_iter(options.disabled_features.iter().copied());
+
+        if options.swarm {
+            for feature in Feature::all() {
+                if feature.is_applicable(options) && rng.gen_bool(0.5) {
+                    disabled.insert(feature);
+                }
+            }
+        }
+
+        FeatureSet { disabled }
+    }
+
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        !self.disabled.contains(&feature)
+    }
+
+    pub fn disabled(&self) -> impl Iterator<Item = Feature> + '_ {
+        self.disabled.iter().copied()
+    }
+}
+
+impl Display for FeatureSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.disabled.is_empty() {
+            return f.write_str("none");
+        }
+
+        for (i, feature) in self.disabled.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{feature}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for FeatureSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let disabled = match s.trim() {
+            "none" => BTreeSet::new(),
+            s => s
+                .split(',')
+                .map(|it| it.trim().parse())
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(FeatureSet { disabled })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn feature_names_round_trip() {
+        for feature in Feature::all() {
+            assert_eq!(feature.to_string().parse::<Feature>(), Ok(feature));
+        }
+
+        assert!("loops"
+            .parse::<Feature>()
+            .unwrap_err()
+            .contains("shift-ops"));
+    }
+
+    #[test]
+    fn feature_set_round_trips() {
+        let set = "structs, loop,shift-ops".parse::<FeatureSet>().unwrap();
+        assert_eq!(set.to_string(), "loop,shift-ops,structs");
+        assert_eq!(set.to_string().parse::<FeatureSet>(), Ok(set));
+
+        let empty = FeatureSet::default();
+        assert_eq!(empty.to_string(), "none");
+        assert_eq!("none".parse::<FeatureSet>(), Ok(empty));
+
+        assert!("loop,bogus".parse::<FeatureSet>().is_err());
+    }
+
+    #[test]
+    fn explicitly_disabled_features_are_always_disabled() {
+        let options = Options::parse_from(["gen", "--disable", "if", "--disable", "globals"]);
+        let set = FeatureSet::new(&options, &mut StdRng::seed_from_u64(0));
+
+        assert_eq!(
+            set.disabled().collect::<Vec<_>>(),
+            [Feature::IfStmt, Feature::Globals]
+        );
+    }
+
+    #[test]
+    fn swarm_only_disables_applicable_features() {
+        let options = Options::parse_from(["gen", "--swarm", "--disable", "var"]);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut seen = BTreeSet::new();
+        for _ in 0..64 {
+            let set = FeatureSet::new(&options, &mut rng);
+            assert!(!set.is_enabled(Feature::VarStmt));
+            seen.extend(set.disabled());
+        }
+
+        // Features which depend on options that weren't enabled are never sampled
+        assert!(!seen.contains(&Feature::Pointers));
+        assert!(!seen.contains(&Feature::Atomics));
+        assert!(seen.contains(&Feature::LoopStmt));
+    }
+}
@@ -1,7 +1,7 @@
 mod printer;
 mod utils;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fs::File;
 use std::path::Path;
 use std::time::Duration;
@@ -14,10 +14,9 @@ pub use printer::{ExecutionEvent, ExecutionResult, Printer};
 
 use types::ConfigId;
 
-pub fn read_input_data(
-    shader: &str,
-    input_data: Option<&str>,
-) -> eyre::Result<HashMap<String, Vec<u8>>> {
+pub use common::InputData;
+
+pub fn read_input_data(shader: &str, input_data: Option<&str>) -> eyre::Result<InputData> {
     match input_data {
         Some(input_data) => {
             // Try parsing value as json string
@@ -76,13 +75,19 @@ pub fn read_shader_from_path(path: &str) -> eyre::Result<String> {
 
 pub fn reflect_shader(
     shader: &str,
-    mut input_data: HashMap<String, Vec<u8>>,
-) -> (PipelineDescription, Vec<common::Type>) {
-    let module = parser::parse(shader);
+    input_data: InputData,
+) -> eyre::Result<(PipelineDescription, Vec<common::Type>)> {
+    let module = parser::try_parse(shader)?;
+
+    let mut input_data = input_data
+        .to_bytes(&module)
+        .map_err(|e| eyre!(e))
+        .wrap_err("failed to parse input data")?;
 
     let (mut pipeline_desc, type_descs) = reflection::reflect(&module, |resource| {
         input_data.remove(&format!("{}:{}", resource.group, resource.binding))
-    });
+    })
+    .map_err(|e| eyre!(e))?;
 
     let mut resource_vars = HashSet::new();
 
@@ -96,7 +101,7 @@ pub fn reflect_shader(
         .resources
         .retain(|resource| !resource_vars.contains(&resource.name));
 
-    (pipeline_desc, type_descs)
+    Ok((pipeline_desc, type_descs))
 }
 
 #[derive(Debug)]
@@ -162,9 +167,11 @@ pub trait Executor {
 pub mod cli {
     use std::time::Duration;
 
+    use buffer_check::{FloatPolicy, Report};
     use clap::Parser;
     use color_eyre::Help;
     use eyre::eyre;
+    use serde::Serialize;
     use types::ConfigId;
 
     use crate::{ExecutionEvent, ExecutionResult, Executor};
@@ -201,23 +208,43 @@ pub mod cli {
         /// Number of workgroups
         #[clap(long, action, default_value = "1")]
         pub workgroups: u32,
+
+        /// Policy for comparing floating point values between executions.
+        ///
+        /// One of `exact`, `ulp:<n>`, `rel:<epsilon>`, `nan` (all NaNs are equal) or `nan-zero`
+        /// (additionally, `0.0` equals `-0.0`).
+        #[clap(long, action, default_value = "exact")]
+        pub float_compare: FloatPolicy,
+
+        /// Path at which to write a json report of any mismatching values (use '-' for stdout).
+        #[clap(long, action)]
+        pub report: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct JsonReport<'a> {
+        configs: &'a [ConfigId],
+        #[serde(flatten)]
+        report: &'a Report,
     }
 
     pub fn run(options: RunOptions, executor: &dyn Executor) -> eyre::Result<()> {
         let shader = super::read_shader_from_path(&options.shader)?;
         let input_data = super::read_input_data(&options.shader, options.input_data.as_deref())?;
-        let (pipeline_desc, type_descs) = super::reflect_shader(&shader, input_data);
+        let (pipeline_desc, type_descs) = super::reflect_shader(&shader, input_data)?;
 
         let printer = super::Printer::new();
 
+        let mut configs = vec![];
         let mut executions = vec![];
         let mut is_fail = false;
         let mut on_event = |event: ExecutionEvent| {
             printer.print_execution_event(&event, &pipeline_desc)?;
-            if let ExecutionEvent::Success(buffers, _) = event {
-                executions.push(buffers);
-            } else if let ExecutionEvent::Failure(_) = event {
-                is_fail = true
+            match event {
+                ExecutionEvent::Start(config) => configs.push(config),
+                ExecutionEvent::Success(buffers, _) => executions.push(buffers),
+                ExecutionEvent::Failure(_) => is_fail = true,
+                _ => {}
             }
             Ok(())
         };
@@ -251,9 +278,29 @@ pub mod cli {
             panic!("one or more executions failed");
         }
 
-        if buffer_check::compare(executions.iter(), &pipeline_desc, &type_descs) {
+        let report = buffer_check::compare(
+            executions.iter(),
+            &pipeline_desc,
+            &type_descs,
+            options.float_compare,
+        );
+
+        if let Some(path) = &options.report {
+            let json = serde_json::to_string_pretty(&JsonReport {
+                configs: &configs,
+                report: &report,
+            })?;
+
+            match path.as_str() {
+                "-" => println!("{json}"),
+                path => std::fs::write(path, json)?,
+            }
+        }
+
+        if report.is_ok() {
             printer.print_execution_result(ExecutionResult::Ok)?;
         } else {
+            printer.print_mismatch_report(&configs, &report)?;
             printer.print_execution_result(ExecutionResult::Mismatch)?;
             std::process::exit(1);
         }
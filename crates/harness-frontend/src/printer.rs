@@ -1,6 +1,7 @@
 use std::io::{self, Write};
 
 use bincode::{Decode, Encode};
+use buffer_check::Report;
 use reflection::{PipelineDescription, ResourceKind};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use types::{Config, ConfigId};
@@ -213,6 +214,97 @@ impl Printer {
 
         Ok(())
     }
+
+    /// Prints a table of the values which differ between executions.
+    ///
+    /// Executions which produced the same value are labelled with the same letter.
+    pub fn print_mismatch_report(&self, configs: &[ConfigId], report: &Report) -> io::Result<()> {
+        const MAX_ROWS: usize = 50;
+
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        let rows = report
+            .mismatches
+            .iter()
+            .take(MAX_ROWS)
+            .map(|mismatch| {
+                let mut cells = vec![String::new(); mismatch.values.len()];
+                for (label, group) in mismatch.groups.iter().enumerate() {
+                    let label = (b'A' + (label % 26) as u8) as char;
+                    for &index in group {
+                        cells[index] = format!("{} ({label})", mismatch.values[index]);
+                    }
+                }
+                (mismatch.path.as_str(), cells)
+            })
+            .collect::<Vec<_>>();
+
+        let headers = configs.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+
+        let path_width = rows
+            .iter()
+            .map(|(path, _)| path.len())
+            .chain(std::iter::once("path".len()))
+            .max()
+            .unwrap_or(0);
+
+        let widths = headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| {
+                rows.iter()
+                    .map(|(_, cells)| cells[i].len())
+                    .chain(std::iter::once(header.len()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        stdout.set_color(&dimmed())?;
+
+        write!(&mut stdout, "{:<path_width$}", "path")?;
+        for (header, width) in headers.iter().zip(&widths) {
+            write!(&mut stdout, " | {header:<width$}")?;
+        }
+        writeln!(&mut stdout)?;
+
+        write!(&mut stdout, "{}", "-".repeat(path_width + 1))?;
+        for width in &widths {
+            write!(&mut stdout, "+{}", "-".repeat(width + 2))?;
+        }
+
+        stdout.reset()?;
+        writeln!(&mut stdout)?;
+
+        for (path, cells) in &rows {
+            stdout.set_color(&cyan())?;
+            write!(&mut stdout, "{path:<path_width$}")?;
+            stdout.reset()?;
+
+            for (cell, width) in cells.iter().zip(&widths) {
+                stdout.set_color(&dimmed())?;
+                write!(&mut stdout, " | ")?;
+                stdout.reset()?;
+                write!(&mut stdout, "{cell:<width$}")?;
+            }
+
+            writeln!(&mut stdout)?;
+        }
+
+        if report.mismatches.len() > MAX_ROWS {
+            stdout.set_color(&dimmed())?;
+            writeln!(
+                &mut stdout,
+                "... and {} more",
+                report.mismatches.len() - MAX_ROWS
+            )?;
+            stdout.reset()?;
+        }
+
+        writeln!(&mut stdout)?;
+
+        Ok(())
+    }
 }
 
 fn dimmed() -> ColorSpec {
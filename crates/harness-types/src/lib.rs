@@ -8,6 +8,7 @@ use serde::Serialize;
 pub enum Implementation {
     Dawn,
     Wgpu,
+    Interp,
 }
 
 #[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq, Serialize)]
@@ -15,6 +16,7 @@ pub enum BackendType {
     Dx12 = 3,
     Metal = 4,
     Vulkan = 5,
+    Cpu,
 }
 
 #[derive(Clone, Debug, Decode, Encode, Serialize)]
@@ -42,12 +44,14 @@ impl FromStr for ConfigId {
             implementation: match imp {
                 "dawn" => Implementation::Dawn,
                 "wgpu" => Implementation::Wgpu,
+                "interp" => Implementation::Interp,
                 _ => return Err("invalid implementation"),
             },
             backend: match backend {
                 "dx12" => BackendType::Dx12,
                 "mtl" => BackendType::Metal,
                 "vk" => BackendType::Vulkan,
+                "cpu" => BackendType::Cpu,
                 _ => return Err("invalid backend"),
             },
             device_id: device.parse().map_err(|_| "invalid device id")?,
@@ -60,12 +64,14 @@ impl Display for ConfigId {
         let impl_id = match self.implementation {
             Implementation::Dawn => "dawn",
             Implementation::Wgpu => "wgpu",
+            Implementation::Interp => "interp",
         };
 
         let backend_id = match self.backend {
             BackendType::Dx12 => "dx12",
             BackendType::Metal => "mtl",
             BackendType::Vulkan => "vk",
+            BackendType::Cpu => "cpu",
         };
 
         let device = self.device_id;
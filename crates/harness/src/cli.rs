@@ -68,29 +68,38 @@ fn internal_run(config: ConfigId) -> eyre::Result<()> {
     Ok(())
 }
 
-pub fn execute<Host: HarnessHost>(options: RunOptions) -> eyre::Result<()> {
-    struct Executor<Host>(PhantomData<Host>);
+/// Executes shaders locally, in a subprocess created by the harness host.
+pub struct Executor<Host>(PhantomData<Host>);
 
-    impl<Host> Executor<Host> {
-        fn new() -> Executor<Host> {
-            Executor(PhantomData)
-        }
+impl<Host> Default for Executor<Host> {
+    fn default() -> Executor<Host> {
+        Executor(PhantomData)
     }
+}
 
-    impl<Host: HarnessHost> frontend::Executor for Executor<Host> {
-        fn execute(
-            &self,
-            shader: &str,
-            workgroups: u32,
-            flow: bool,
-            pipeline_desc: &PipelineDescription,
-            configs: &[ConfigId],
-            timeout: Option<Duration>,
-            on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
-        ) -> Result<(), ExecutionError> {
-            crate::execute::<Host, _>(shader, workgroups, flow, pipeline_desc, configs, timeout, on_event)
-        }
+impl<Host: HarnessHost> frontend::Executor for Executor<Host> {
+    fn execute(
+        &self,
+        shader: &str,
+        workgroups: u32,
+        flow: bool,
+        pipeline_desc: &PipelineDescription,
+        configs: &[ConfigId],
+        timeout: Option<Duration>,
+        on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
+    ) -> Result<(), ExecutionError> {
+        crate::execute::<Host, _>(
+            shader,
+            workgroups,
+            flow,
+            pipeline_desc,
+            configs,
+            timeout,
+            on_event,
+        )
     }
+}
 
-    frontend::cli::run(options, &Executor::<Host>::new())
+pub fn execute<Host: HarnessHost>(options: RunOptions) -> eyre::Result<()> {
+    frontend::cli::run(options, &Executor::<Host>::default())
 }
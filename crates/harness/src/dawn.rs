@@ -52,6 +52,7 @@ pub async fn run(
         crate::BackendType::Dx12 => WGPUBackendType_WGPUBackendType_D3D12,
         crate::BackendType::Metal => WGPUBackendType_WGPUBackendType_Metal,
         crate::BackendType::Vulkan => WGPUBackendType_WGPUBackendType_Vulkan,
+        crate::BackendType::Cpu => return Err(eyre!("dawn does not support backend: {config}")),
     };
 
     let device = Instance::new()
@@ -0,0 +1,17 @@
+use reflection::PipelineDescription;
+
+pub fn get_adapters() -> Vec<types::Adapter> {
+    vec![types::Adapter {
+        name: "wgslsmith interpreter".to_owned(),
+        device_id: 0,
+        backend: crate::BackendType::Cpu,
+    }]
+}
+
+pub fn run(
+    shader: &str,
+    workgroups: u32,
+    meta: &PipelineDescription,
+) -> color_eyre::Result<Vec<Vec<u8>>> {
+    interp::run(shader, workgroups, meta)
+}
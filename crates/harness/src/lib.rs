@@ -1,4 +1,5 @@
 mod dawn;
+mod interp;
 mod server;
 mod wgpu;
 
@@ -32,6 +33,12 @@ pub fn query_configs() -> Vec<Config> {
             .map(|adapter| Config::new(Implementation::Dawn, adapter)),
     );
 
+    configurations.extend(
+        interp::get_adapters()
+            .into_iter()
+            .map(|adapter| Config::new(Implementation::Interp, adapter)),
+    );
+
     configurations
 }
 
@@ -82,7 +89,9 @@ struct ExecutionOutput {
     pub flow: Option<Vec<u32>>,
 }
 
-fn execute<Host: HarnessHost, E: FnMut(ExecutionEvent) -> Result<(), ExecutionError>>(
+/// Executes a shader on each of the given configs (or the default configs if none are given),
+/// using a separate process for each execution.
+pub fn execute<Host: HarnessHost, E: FnMut(ExecutionEvent) -> Result<(), ExecutionError>>(
     shader: &str,
     workgroups: u32,
     flow: bool,
@@ -158,5 +167,6 @@ pub fn execute_config(
     match config.implementation {
         Implementation::Dawn => block_on(dawn::run(shader, workgroups, pipeline_desc, config)),
         Implementation::Wgpu => block_on(wgpu::run(shader, workgroups, pipeline_desc, config)),
+        Implementation::Interp => interp::run(shader, workgroups, pipeline_desc),
     }
 }
@@ -43,6 +43,7 @@ pub async fn run(
         crate::BackendType::Dx12 => wgpu::Backend::Dx12,
         crate::BackendType::Metal => wgpu::Backend::Metal,
         crate::BackendType::Vulkan => wgpu::Backend::Vulkan,
+        crate::BackendType::Cpu => return Err(eyre!("wgpu does not support backend: {config}")),
     };
 
     let instance = Instance::new(Backends::all());
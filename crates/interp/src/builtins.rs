@@ -0,0 +1,410 @@
+use ast::{BinOp, BuiltinFn, UnOp};
+use eyre::{bail, eyre};
+
+use crate::ops::{self, bin_op, map, zip, zip3};
+use crate::value::Value;
+
+/// Evaluates a call to a builtin function which doesn't operate on pointers.
+pub fn call(builtin: BuiltinFn, args: Vec<Value>) -> eyre::Result<Value> {
+    use BuiltinFn::*;
+
+    let mut args = args.into_iter();
+    let mut arg = || args.next().ok_or_else(|| eyre!("missing argument"));
+
+    Ok(match builtin {
+        Abs => map(arg()?, |v| {
+            Ok(match v {
+                Value::I32(v) => Value::I32(v.wrapping_abs()),
+                Value::F32(v) => Value::F32(v.abs()),
+                v => v,
+            })
+        })?,
+        Acos => map_f32(arg()?, f32::acos)?,
+        Acosh => map_f32(arg()?, f32::acosh)?,
+        Asin => map_f32(arg()?, f32::asin)?,
+        Asinh => map_f32(arg()?, f32::asinh)?,
+        Atan => map_f32(arg()?, f32::atan)?,
+        Atanh => map_f32(arg()?, f32::atanh)?,
+        Atan2 => zip(arg()?, arg()?, |y, x| {
+            Ok(Value::F32(y.as_f32()?.atan2(x.as_f32()?)))
+        })?,
+        All => Value::Bool(
+            arg()?
+                .into_components()
+                .iter()
+                .map(Value::as_bool)
+                .collect::<eyre::Result<Vec<_>>>()?
+                .into_iter()
+                .all(|it| it),
+        ),
+        Any => Value::Bool(
+            arg()?
+                .into_components()
+                .iter()
+                .map(Value::as_bool)
+                .collect::<eyre::Result<Vec<_>>>()?
+                .into_iter()
+                .any(|it| it),
+        ),
+        Ceil => map_f32(arg()?, f32::ceil)?,
+        Clamp => {
+            let (e, low, high) = (arg()?, arg()?, arg()?);
+            min(max(e, low)?, high)?
+        }
+        Cos => map_f32(arg()?, f32::cos)?,
+        Cosh => map_f32(arg()?, f32::cosh)?,
+        CountLeadingZeros => map_bits(arg()?, u32::leading_zeros)?,
+        CountOneBits => map_bits(arg()?, u32::count_ones)?,
+        CountTrailingZeros => map_bits(arg()?, u32::trailing_zeros)?,
+        Cross => {
+            let a = f32_components(arg()?)?;
+            let b = f32_components(arg()?)?;
+            if a.len() != 3 || b.len() != 3 {
+                bail!("cross product requires 3-component vectors");
+            }
+
+            Value::Vector(vec![
+                Value::F32(a[1] * b[2] - b[1] * a[2]),
+                Value::F32(a[2] * b[0] - b[2] * a[0]),
+                Value::F32(a[0] * b[1] - b[0] * a[1]),
+            ])
+        }
+        Degrees => map_f32(arg()?, f32::to_degrees)?,
+        Determinant => Value::F32(determinant(&matrix_columns(arg()?)?)),
+        Distance => {
+            let difference = bin_op(BinOp::Minus, arg()?, arg()?)?;
+            length(difference)?
+        }
+        Dot => ops::dot(arg()?, arg()?)?,
+        Exp => map_f32(arg()?, f32::exp)?,
+        Exp2 => map_f32(arg()?, f32::exp2)?,
+        ExtractBits => {
+            let (e, offset, count) = (arg()?, arg()?.as_u32()?, arg()?.as_u32()?);
+            map(e, |e| extract_bits(e, offset, count))?
+        }
+        FaceForward => {
+            let (e1, e2, e3) = (arg()?, arg()?, arg()?);
+            if ops::dot(e2, e3)?.as_f32()? < 0.0 {
+                e1
+            } else {
+                ops::un_op(UnOp::Neg, e1)?
+            }
+        }
+        FirstLeadingBit => map(arg()?, |v| {
+            Ok(match v {
+                Value::I32(v) => {
+                    let v = if v < 0 { !v } else { v };
+                    Value::I32(match v {
+                        0 => -1,
+                        v => 31 - v.leading_zeros() as i32,
+                    })
+                }
+                Value::U32(v) => Value::U32(match v {
+                    0 => u32::MAX,
+                    v => 31 - v.leading_zeros(),
+                }),
+                v => bail!("invalid argument `{v:?}` for firstLeadingBit"),
+            })
+        })?,
+        FirstTrailingBit => map(arg()?, |v| {
+            Ok(match v {
+                Value::I32(0) => Value::I32(-1),
+                Value::I32(v) => Value::I32(v.trailing_zeros() as i32),
+                Value::U32(0) => Value::U32(u32::MAX),
+                Value::U32(v) => Value::U32(v.trailing_zeros()),
+                v => bail!("invalid argument `{v:?}` for firstTrailingBit"),
+            })
+        })?,
+        Floor => map_f32(arg()?, f32::floor)?,
+        Fma => {
+            let (a, b, c) = (arg()?, arg()?, arg()?);
+            zip3(a, b, c, |a, b, c| {
+                Ok(Value::F32(a.as_f32()?.mul_add(b.as_f32()?, c.as_f32()?)))
+            })?
+        }
+        Fract => map_f32(arg()?, |v| v - v.floor())?,
+        InsertBits => {
+            let (e, new_bits) = (arg()?, arg()?);
+            let (offset, count) = (arg()?.as_u32()?, arg()?.as_u32()?);
+            zip(e, new_bits, |e, new_bits| {
+                insert_bits(e, new_bits, offset, count)
+            })?
+        }
+        InverseSqrt => map_f32(arg()?, |v| 1.0 / v.sqrt())?,
+        Ldexp => zip(arg()?, arg()?, |e1, e2| {
+            Ok(Value::F32(e1.as_f32()? * 2f32.powi(e2.as_i32()?)))
+        })?,
+        Length => length(arg()?)?,
+        Log => map_f32(arg()?, f32::ln)?,
+        Log2 => map_f32(arg()?, f32::log2)?,
+        Max => max(arg()?, arg()?)?,
+        Min => min(arg()?, arg()?)?,
+        Mix => {
+            let (a, b, t) = (arg()?, arg()?, arg()?);
+            let one_minus_t = bin_op(BinOp::Minus, Value::F32(1.0), t.clone())?;
+            bin_op(
+                BinOp::Plus,
+                bin_op(BinOp::Times, a, one_minus_t)?,
+                bin_op(BinOp::Times, b, t)?,
+            )?
+        }
+        Normalize => {
+            let v = arg()?;
+            let length = length(v.clone())?;
+            bin_op(BinOp::Divide, v, length)?
+        }
+        Pow => zip(arg()?, arg()?, |a, b| {
+            Ok(Value::F32(a.as_f32()?.powf(b.as_f32()?)))
+        })?,
+        QuantizeToF16 => map_f32(arg()?, quantize_to_f16)?,
+        Radians => map_f32(arg()?, f32::to_radians)?,
+        Reflect => {
+            let (e1, e2) = (arg()?, arg()?);
+            let scale = 2.0 * ops::dot(e2.clone(), e1.clone())?.as_f32()?;
+            bin_op(
+                BinOp::Minus,
+                e1,
+                bin_op(BinOp::Times, e2, Value::F32(scale))?,
+            )?
+        }
+        Refract => {
+            let (e1, e2, e3) = (arg()?, arg()?, arg()?.as_f32()?);
+            let d = ops::dot(e2.clone(), e1.clone())?.as_f32()?;
+            let k = 1.0 - e3 * e3 * (1.0 - d * d);
+            if k < 0.0 {
+                map(e1, |_| Ok(Value::F32(0.0)))?
+            } else {
+                bin_op(
+                    BinOp::Minus,
+                    bin_op(BinOp::Times, e1, Value::F32(e3))?,
+                    bin_op(BinOp::Times, e2, Value::F32(e3 * d + k.sqrt()))?,
+                )?
+            }
+        }
+        ReverseBits => map_bits(arg()?, u32::reverse_bits)?,
+        Round => map_f32(arg()?, f32::round_ties_even)?,
+        Select => {
+            let (f, t, cond) = (arg()?, arg()?, arg()?);
+            match cond {
+                Value::Bool(cond) => {
+                    if cond {
+                        t
+                    } else {
+                        f
+                    }
+                }
+                cond => zip(
+                    zip(f, t, |f, t| Ok(Value::Vector(vec![f, t])))?,
+                    cond,
+                    |ft, c| {
+                        let mut ft = ft.into_components();
+                        Ok(ft.swap_remove(c.as_bool()? as usize))
+                    },
+                )?,
+            }
+        }
+        Sign => map(arg()?, |v| {
+            Ok(match v {
+                Value::I32(v) => Value::I32(v.signum()),
+                Value::F32(v) if v > 0.0 => Value::F32(1.0),
+                Value::F32(v) if v < 0.0 => Value::F32(-1.0),
+                Value::F32(_) => Value::F32(0.0),
+                v => bail!("invalid argument `{v:?}` for sign"),
+            })
+        })?,
+        Sin => map_f32(arg()?, f32::sin)?,
+        Sinh => map_f32(arg()?, f32::sinh)?,
+        Smoothstep => {
+            let (low, high, x) = (arg()?, arg()?, arg()?);
+            zip3(low, high, x, |low, high, x| {
+                let (low, high, x) = (low.as_f32()?, high.as_f32()?, x.as_f32()?);
+                let t = ((x - low) / (high - low)).clamp(0.0, 1.0);
+                Ok(Value::F32(t * t * (3.0 - 2.0 * t)))
+            })?
+        }
+        Sqrt => map_f32(arg()?, f32::sqrt)?,
+        Step => zip(arg()?, arg()?, |edge, x| {
+            Ok(Value::F32(if edge.as_f32()? <= x.as_f32()? {
+                1.0
+            } else {
+                0.0
+            }))
+        })?,
+        Tan => map_f32(arg()?, f32::tan)?,
+        Tanh => map_f32(arg()?, f32::tanh)?,
+        Transpose => {
+            let columns = matrix_columns(arg()?)?;
+            let rows = columns.first().map(Vec::len).unwrap_or(0);
+            Value::Matrix(
+                (0..rows)
+                    .map(|r| Value::Vector(columns.iter().map(|c| Value::F32(c[r])).collect()))
+                    .collect(),
+            )
+        }
+        Trunc => map_f32(arg()?, f32::trunc)?,
+        ArrayLength
+        | AtomicAdd
+        | AtomicAnd
+        | AtomicCompareExchangeWeak
+        | AtomicExchange
+        | AtomicLoad
+        | AtomicMax
+        | AtomicMin
+        | AtomicOr
+        | AtomicStore
+        | AtomicSub
+        | AtomicXor => {
+            bail!("builtin `{}` requires a pointer argument", builtin.as_ref())
+        }
+        ShiftLeft | ShiftRight => bail!("`{}` is not a WGSL function", builtin.as_ref()),
+    })
+}
+
+fn map_f32(v: Value, f: impl Fn(f32) -> f32) -> eyre::Result<Value> {
+    map(v, |v| Ok(Value::F32(f(v.as_f32()?))))
+}
+
+/// Applies `f` to the bits of each component of an integer scalar or vector.
+fn map_bits(v: Value, f: impl Fn(u32) -> u32) -> eyre::Result<Value> {
+    map(v, |v| {
+        Ok(match v {
+            Value::I32(v) => Value::I32(f(v as u32) as i32),
+            Value::U32(v) => Value::U32(f(v)),
+            v => bail!("expected integer, found `{v:?}`"),
+        })
+    })
+}
+
+fn min(a: Value, b: Value) -> eyre::Result<Value> {
+    zip(a, b, |a, b| {
+        Ok(match (a, b) {
+            (Value::I32(a), Value::I32(b)) => Value::I32(a.min(b)),
+            (Value::U32(a), Value::U32(b)) => Value::U32(a.min(b)),
+            (Value::F32(a), Value::F32(b)) => Value::F32(a.min(b)),
+            (a, b) => bail!("invalid arguments `{a:?}` and `{b:?}` for min"),
+        })
+    })
+}
+
+fn max(a: Value, b: Value) -> eyre::Result<Value> {
+    zip(a, b, |a, b| {
+        Ok(match (a, b) {
+            (Value::I32(a), Value::I32(b)) => Value::I32(a.max(b)),
+            (Value::U32(a), Value::U32(b)) => Value::U32(a.max(b)),
+            (Value::F32(a), Value::F32(b)) => Value::F32(a.max(b)),
+            (a, b) => bail!("invalid arguments `{a:?}` and `{b:?}` for max"),
+        })
+    })
+}
+
+fn f32_components(v: Value) -> eyre::Result<Vec<f32>> {
+    v.into_components().iter().map(Value::as_f32).collect()
+}
+
+fn length(v: Value) -> eyre::Result<Value> {
+    let sum: f32 = f32_components(v)?.iter().map(|it| it * it).sum();
+    Ok(Value::F32(sum.sqrt()))
+}
+
+fn matrix_columns(v: Value) -> eyre::Result<Vec<Vec<f32>>> {
+    match v {
+        Value::Matrix(columns) => columns.into_iter().map(f32_components).collect(),
+        v => Err(eyre!("expected matrix, found `{v:?}`")),
+    }
+}
+
+/// Computes the determinant of a square matrix by cofactor expansion along the first column.
+fn determinant(columns: &[Vec<f32>]) -> f32 {
+    if columns.len() == 1 {
+        return columns[0][0];
+    }
+
+    let mut result = 0.0;
+    for row in 0..columns.len() {
+        let minor = columns[1..]
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .enumerate()
+                    .filter(|(r, _)| *r != row)
+                    .map(|(_, v)| *v)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let sign = if row % 2 == 0 { 1.0 } else { -1.0 };
+        result += sign * columns[0][row] * determinant(&minor);
+    }
+
+    result
+}
+
+fn extract_bits(e: Value, offset: u32, count: u32) -> eyre::Result<Value> {
+    let offset = offset.min(32);
+    let count = count.min(32 - offset);
+
+    Ok(match e {
+        _ if count == 0 => match e {
+            Value::I32(_) => Value::I32(0),
+            _ => Value::U32(0),
+        },
+        Value::I32(e) => {
+            // Shift the field to the top of the word, then arithmetic shift it back down to
+            // sign-extend it
+            let shifted = ((e as u32) << (32 - offset - count)) as i32;
+            Value::I32(shifted >> (32 - count))
+        }
+        Value::U32(e) => Value::U32(((e as u64 >> offset) & ((1u64 << count) - 1)) as u32),
+        e => bail!("invalid argument `{e:?}` for extractBits"),
+    })
+}
+
+fn insert_bits(e: Value, new_bits: Value, offset: u32, count: u32) -> eyre::Result<Value> {
+    let offset = offset.min(32);
+    let count = count.min(32 - offset);
+    let mask = (((1u64 << count) - 1) << offset) as u32;
+    let insert =
+        |e: u32, new_bits: u32| (e & !mask) | (((new_bits as u64) << offset) as u32 & mask);
+
+    Ok(match (e, new_bits) {
+        (Value::I32(e), Value::I32(n)) => Value::I32(insert(e as u32, n as u32) as i32),
+        (Value::U32(e), Value::U32(n)) => Value::U32(insert(e, n)),
+        (e, n) => bail!("invalid arguments `{e:?}` and `{n:?}` for insertBits"),
+    })
+}
+
+/// Rounds an f32 to the nearest value representable as an f16, using round-to-nearest-even.
+fn quantize_to_f16(v: f32) -> f32 {
+    if !v.is_finite() {
+        return v;
+    }
+
+    let bits = v.to_bits();
+    let sign = bits & 0x8000_0000;
+    let abs = f32::from_bits(bits & 0x7fff_ffff);
+
+    // Values too large for f16 overflow to infinity
+    if abs >= 65520.0 {
+        return f32::from_bits(sign | 0x7f80_0000);
+    }
+
+    // Below the smallest normal f16 the spacing between representable values is fixed at 2^-24,
+    // otherwise the f16 mantissa keeps 10 of the 23 bits
+    let quantized = if abs < 2f32.powi(-14) {
+        let step = 2f32.powi(-24);
+        (abs / step).round_ties_even() * step
+    } else {
+        let bits = abs.to_bits();
+        let dropped = 13;
+        let half = 1 << (dropped - 1);
+        let remainder = bits & ((1 << dropped) - 1);
+        let mut truncated = bits >> dropped;
+        if remainder > half || (remainder == half && truncated & 1 == 1) {
+            truncated += 1;
+        }
+        f32::from_bits(truncated << dropped)
+    };
+
+    f32::from_bits(sign | quantized.to_bits())
+}
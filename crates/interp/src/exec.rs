@@ -0,0 +1,841 @@
+use std::collections::HashMap;
+use std::mem;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use ast::*;
+use eyre::{bail, eyre};
+
+use crate::builtins;
+use crate::ops::{bin_op, un_op};
+use crate::value::{Pointer, Root, Value};
+
+/// What a name refers to.
+#[derive(Clone, Debug)]
+enum Binding {
+    /// A variable, whose value can be modified through a reference.
+    Var(Root),
+    /// An immutable value, e.g. from a `let` declaration or a function parameter.
+    Value(Value),
+}
+
+/// The result of evaluating an expression which may be a reference.
+enum Ref {
+    Place(Pointer),
+    Value(Value),
+}
+
+/// How control leaves a statement.
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Fallthrough,
+    Return(Option<Value>),
+}
+
+struct Scope {
+    bindings: HashMap<String, Binding>,
+    /// Number of local variables that were allocated when the scope was entered.
+    locals: usize,
+}
+
+/// The state of a function invocation which has been suspended between statements of its
+/// top-level block.
+pub struct Frame {
+    scopes: Vec<Scope>,
+    locals: Vec<Value>,
+    /// Whether the invocation has returned.
+    pub returned: bool,
+}
+
+pub struct Interpreter<'a> {
+    functions: HashMap<&'a str, &'a FnDecl>,
+    structs: HashMap<&'a str, &'a Rc<StructDecl>>,
+    global_bindings: HashMap<String, Binding>,
+    globals: Vec<Value>,
+    locals: Vec<Value>,
+    scopes: Vec<Scope>,
+    workgroup_size: u32,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(module: &'a Module) -> Interpreter<'a> {
+        Interpreter {
+            functions: module
+                .functions
+                .iter()
+                .map(|it| (it.name.as_str(), it))
+                .collect(),
+            structs: module
+                .structs
+                .iter()
+                .map(|it| (it.name.as_str(), it))
+                .collect(),
+            global_bindings: HashMap::new(),
+            globals: vec![],
+            locals: vec![],
+            scopes: vec![],
+            workgroup_size: 1,
+        }
+    }
+
+    pub fn set_workgroup_size(&mut self, workgroup_size: u32) {
+        self.workgroup_size = workgroup_size;
+    }
+
+    /// Binds a name to a module-scope constant value.
+    pub fn bind_const(&mut self, name: &str, value: Value) {
+        self.global_bindings
+            .insert(name.to_owned(), Binding::Value(value));
+    }
+
+    /// Allocates a module-scope variable and returns its index.
+    pub fn alloc_global(&mut self, name: &str, value: Value) -> usize {
+        let index = self.globals.len();
+        self.globals.push(value);
+        self.global_bindings
+            .insert(name.to_owned(), Binding::Var(Root::Global(index)));
+        index
+    }
+
+    pub fn global(&self, index: usize) -> &Value {
+        &self.globals[index]
+    }
+
+    pub fn set_global(&mut self, index: usize, value: Value) {
+        self.globals[index] = value;
+    }
+
+    pub fn lookup_const(&self, name: &str) -> Option<&Value> {
+        match self.global_bindings.get(name) {
+            Some(Binding::Value(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Evaluates an expression outside of any function, e.g. a global initializer.
+    pub fn eval_const(&mut self, expr: &ExprNode) -> eyre::Result<Value> {
+        self.eval(expr)
+    }
+
+    /// Calls a user-defined function with the given arguments.
+    pub fn call_fn(&mut self, name: &str, args: Vec<Value>) -> eyre::Result<Option<Value>> {
+        let (decl, bindings) = self.bind_args(name, args)?;
+
+        let locals = self.locals.len();
+        let caller_scopes = mem::replace(&mut self.scopes, vec![Scope { bindings, locals }]);
+
+        let result = self.exec_stmts(&decl.body);
+
+        self.scopes = caller_scopes;
+        self.locals.truncate(locals);
+
+        match result? {
+            Flow::Next | Flow::Return(None) => Ok(None),
+            Flow::Return(Some(value)) => Ok(Some(value)),
+            Flow::Break | Flow::Continue | Flow::Fallthrough => {
+                bail!("unexpected control flow out of function `{name}`")
+            }
+        }
+    }
+
+    /// Creates a frame for an invocation of the function `name`, without executing any of its body.
+    pub fn enter_fn(&self, name: &str, args: Vec<Value>) -> eyre::Result<Frame> {
+        let (_, bindings) = self.bind_args(name, args)?;
+
+        Ok(Frame {
+            scopes: vec![Scope {
+                bindings,
+                locals: 0,
+            }],
+            locals: vec![],
+            returned: false,
+        })
+    }
+
+    /// Executes `stmts` as part of the top-level block of the function invocation in `frame`.
+    ///
+    /// This is used to run an entry point up to a barrier, and then resume it once the other
+    /// invocations in the workgroup have reached the barrier.
+    pub fn resume(&mut self, frame: &mut Frame, stmts: &[Statement]) -> eyre::Result<()> {
+        mem::swap(&mut self.scopes, &mut frame.scopes);
+        mem::swap(&mut self.locals, &mut frame.locals);
+
+        let result = self.exec_stmts(stmts);
+
+        mem::swap(&mut self.scopes, &mut frame.scopes);
+        mem::swap(&mut self.locals, &mut frame.locals);
+
+        match result? {
+            Flow::Next => {}
+            Flow::Return(_) => frame.returned = true,
+            Flow::Break | Flow::Continue | Flow::Fallthrough => {
+                bail!("unexpected control flow out of entry point")
+            }
+        }
+
+        Ok(())
+    }
+
+    fn bind_args(
+        &self,
+        name: &str,
+        args: Vec<Value>,
+    ) -> eyre::Result<(&'a FnDecl, HashMap<String, Binding>)> {
+        let decl = *self
+            .functions
+            .get(name)
+            .ok_or_else(|| eyre!("function `{name}` not found"))?;
+
+        if decl.inputs.len() != args.len() {
+            bail!(
+                "function `{name}` expects {} arguments, found {}",
+                decl.inputs.len(),
+                args.len()
+            );
+        }
+
+        let bindings = decl
+            .inputs
+            .iter()
+            .zip(args)
+            .map(|(input, arg)| (input.name.clone(), Binding::Value(arg)))
+            .collect();
+
+        Ok((decl, bindings))
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope {
+            bindings: HashMap::new(),
+            locals: self.locals.len(),
+        });
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scope stack must not be empty");
+        self.locals.truncate(scope.locals);
+    }
+
+    fn bind(&mut self, name: &str, binding: Binding) {
+        self.scopes
+            .last_mut()
+            .expect("scope stack must not be empty")
+            .bindings
+            .insert(name.to_owned(), binding);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.bindings.get(name))
+            .or_else(|| self.global_bindings.get(name))
+    }
+
+    fn root(&self, root: Root) -> &Value {
+        match root {
+            Root::Global(index) => &self.globals[index],
+            Root::Local(index) => &self.locals[index],
+        }
+    }
+
+    fn root_mut(&mut self, root: Root) -> &mut Value {
+        match root {
+            Root::Global(index) => &mut self.globals[index],
+            Root::Local(index) => &mut self.locals[index],
+        }
+    }
+
+    fn peek(&self, ptr: &Pointer) -> eyre::Result<&Value> {
+        let mut value = self.root(ptr.root);
+        for &index in &ptr.path {
+            value = value
+                .children()
+                .and_then(|it| it.get(index))
+                .ok_or_else(|| eyre!("out of bounds access at index {index}"))?;
+        }
+        Ok(value)
+    }
+
+    fn load(&self, ptr: &Pointer) -> eyre::Result<Value> {
+        self.peek(ptr).cloned()
+    }
+
+    fn store(&mut self, ptr: &Pointer, new_value: Value) -> eyre::Result<()> {
+        let mut value = self.root_mut(ptr.root);
+        for &index in &ptr.path {
+            value = value
+                .children_mut()
+                .and_then(|it| it.get_mut(index))
+                .ok_or_else(|| eyre!("out of bounds access at index {index}"))?;
+        }
+        *value = new_value;
+        Ok(())
+    }
+
+    fn exec_block(&mut self, stmts: &[Statement]) -> eyre::Result<Flow> {
+        self.push_scope();
+        let flow = self.exec_stmts(stmts);
+        self.pop_scope();
+        flow
+    }
+
+    fn exec_stmts(&mut self, stmts: &[Statement]) -> eyre::Result<Flow> {
+        for stmt in stmts {
+            match self.exec_stmt(stmt)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn exec_stmt(&mut self, stmt: &Statement) -> eyre::Result<Flow> {
+        match stmt {
+            Statement::LetDecl(stmt) => {
+                let value = self.eval(&stmt.initializer)?;
+                self.bind(&stmt.ident, Binding::Value(value));
+            }
+            Statement::VarDecl(stmt) => self.exec_var_decl(stmt)?,
+            Statement::Assignment(stmt) => self.exec_assignment(stmt)?,
+            Statement::Compound(stmts) => return self.exec_block(stmts),
+            Statement::If(stmt) => return self.exec_if(stmt),
+            Statement::Return(stmt) => {
+                let value = stmt.value.as_ref().map(|it| self.eval(it)).transpose()?;
+                return Ok(Flow::Return(value));
+            }
+            Statement::Loop(stmt) => return self.exec_loop(stmt),
+            Statement::Break => return Ok(Flow::Break),
+            Statement::Continue => return Ok(Flow::Continue),
+            Statement::Switch(stmt) => return self.exec_switch(stmt),
+            Statement::Fallthrough => return Ok(Flow::Fallthrough),
+            Statement::ForLoop(stmt) => {
+                self.push_scope();
+                let flow = self.exec_for_loop(stmt);
+                self.pop_scope();
+                return flow;
+            }
+            Statement::FnCall(stmt) => {
+                self.call(&stmt.ident, &stmt.args)?;
+            }
+            Statement::While(stmt) => return self.exec_while(stmt),
+            // Constant assertions are checked at shader creation time and have no runtime effect
+            Statement::ConstAssert(_) => {}
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn exec_var_decl(&mut self, stmt: &VarDeclStatement) -> eyre::Result<()> {
+        let value = match &stmt.initializer {
+            Some(initializer) => self.eval(initializer)?,
+            None => Value::zero(stmt.inferred_type())?,
+        };
+
+        let index = self.locals.len();
+        self.locals.push(value);
+        self.bind(&stmt.ident, Binding::Var(Root::Local(index)));
+
+        Ok(())
+    }
+
+    fn exec_assignment(&mut self, stmt: &AssignmentStatement) -> eyre::Result<()> {
+        let lhs = match &stmt.lhs {
+            AssignmentLhs::Phony => {
+                self.eval(&stmt.rhs)?;
+                return Ok(());
+            }
+            AssignmentLhs::Expr(lhs) => lhs,
+        };
+
+        let place = match self.eval_lhs(lhs)? {
+            Ref::Place(place) => place,
+            Ref::Value(_) => bail!("left hand side of assignment `{lhs}` is not a reference"),
+        };
+
+        let op = stmt.op.bin_op();
+
+        let rhs = self.eval(&stmt.rhs)?;
+        let value = match op {
+            Some(op) => bin_op(op, self.load(&place)?, rhs)?,
+            None => rhs,
+        };
+
+        self.store(&place, value)
+    }
+
+    fn exec_if(&mut self, stmt: &IfStatement) -> eyre::Result<Flow> {
+        if self.eval(&stmt.condition)?.as_bool()? {
+            return self.exec_block(&stmt.body);
+        }
+
+        match stmt.else_.as_deref() {
+            Some(Else::If(stmt)) => self.exec_if(stmt),
+            Some(Else::Else(stmts)) => self.exec_block(stmts),
+            None => Ok(Flow::Next),
+        }
+    }
+
+    fn exec_loop(&mut self, stmt: &LoopStatement) -> eyre::Result<Flow> {
+        loop {
+            // Declarations in the loop body are visible in the continuing block, so they share a
+            // scope
+            self.push_scope();
+            let flow = self.exec_loop_iteration(stmt);
+            self.pop_scope();
+
+            match flow? {
+                Flow::Next | Flow::Continue => {}
+                Flow::Break => return Ok(Flow::Next),
+                flow => return Ok(flow),
+            }
+        }
+    }
+
+    fn exec_loop_iteration(&mut self, stmt: &LoopStatement) -> eyre::Result<Flow> {
+        match self.exec_stmts(&stmt.body)? {
+            Flow::Next | Flow::Continue => {}
+            flow => return Ok(flow),
+        }
+
+        if let Some(continuing) = &stmt.continuing {
+            self.push_scope();
+            let flow = self.exec_continuing(continuing);
+            self.pop_scope();
+            return flow;
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn exec_continuing(&mut self, stmt: &ContinuingStatement) -> eyre::Result<Flow> {
+        match self.exec_stmts(&stmt.body)? {
+            Flow::Next => {}
+            flow => return Ok(flow),
+        }
+
+        if let Some(break_if) = &stmt.break_if {
+            if self.eval(break_if)?.as_bool()? {
+                return Ok(Flow::Break);
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn exec_while(&mut self, stmt: &WhileStatement) -> eyre::Result<Flow> {
+        while self.eval(&stmt.condition)?.as_bool()? {
+            match self.exec_block(&stmt.body)? {
+                Flow::Next | Flow::Continue => {}
+                Flow::Break => break,
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn exec_for_loop(&mut self, stmt: &ForLoopStatement) -> eyre::Result<Flow> {
+        if let Some(ForLoopInit::VarDecl(init)) = &stmt.header.init {
+            self.exec_var_decl(init)?;
+        }
+
+        loop {
+            if let Some(condition) = &stmt.header.condition {
+                if !self.eval(condition)?.as_bool()? {
+                    break;
+                }
+            }
+
+            match self.exec_block(&stmt.body)? {
+                Flow::Next | Flow::Continue => {}
+                Flow::Break => break,
+                flow => return Ok(flow),
+            }
+
+            if let Some(ForLoopUpdate::Assignment(update)) = &stmt.header.update {
+                self.exec_assignment(update)?;
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn exec_switch(&mut self, stmt: &SwitchStatement) -> eyre::Result<Flow> {
+        let selector = self.eval(&stmt.selector)?;
+
+        let mut start = stmt.cases.len();
+        'cases: for (i, case) in stmt.cases.iter().enumerate() {
+            for case_selector in &case.selectors {
+                if self.eval(case_selector)? == selector {
+                    start = i;
+                    break 'cases;
+                }
+            }
+        }
+
+        // The default case is treated as coming after all other cases for the purpose of
+        // fallthrough
+        let bodies = stmt
+            .cases
+            .iter()
+            .map(|it| it.body.as_slice())
+            .chain([stmt.default.as_slice()]);
+
+        for body in bodies.skip(start) {
+            match self.exec_block(body)? {
+                Flow::Fallthrough => {}
+                Flow::Next | Flow::Break => break,
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn eval(&mut self, node: &ExprNode) -> eyre::Result<Value> {
+        match &node.expr {
+            Expr::Lit(lit) => Ok(match lit {
+                Lit::Bool(v) => Value::Bool(*v),
+                Lit::I32(v) => Value::I32(*v),
+                Lit::U32(v) => Value::U32(*v),
+                Lit::F32(v) => Value::F32(*v),
+            }),
+            Expr::TypeCons(expr) => {
+                let args = expr
+                    .args
+                    .iter()
+                    .map(|it| self.eval(it))
+                    .collect::<eyre::Result<Vec<_>>>()?;
+                construct(&expr.data_type, args)
+            }
+            Expr::UnOp(expr) if !matches!(expr.op, UnOp::AddressOf | UnOp::Deref) => {
+                un_op(expr.op, self.eval(&expr.inner)?)
+            }
+            Expr::BinOp(expr) => match expr.op {
+                BinOp::LogAnd => Ok(Value::Bool(
+                    self.eval(&expr.left)?.as_bool()? && self.eval(&expr.right)?.as_bool()?,
+                )),
+                BinOp::LogOr => Ok(Value::Bool(
+                    self.eval(&expr.left)?.as_bool()? || self.eval(&expr.right)?.as_bool()?,
+                )),
+                op => {
+                    let left = self.eval(&expr.left)?;
+                    let right = self.eval(&expr.right)?;
+                    bin_op(op, left, right)
+                }
+            },
+            Expr::FnCall(expr) => self
+                .call(&expr.ident, &expr.args)?
+                .ok_or_else(|| eyre!("function `{}` does not return a value", expr.ident)),
+            _ => match self.eval_ref(node)? {
+                Ref::Place(place) => self.load(&place),
+                Ref::Value(value) => Ok(value),
+            },
+        }
+    }
+
+    fn eval_ref(&mut self, node: &ExprNode) -> eyre::Result<Ref> {
+        match &node.expr {
+            Expr::Var(expr) => self.resolve(&expr.ident),
+            Expr::Postfix(expr) => {
+                let base = self.eval_ref(&expr.inner)?;
+                self.postfix(base, &expr.postfix)
+            }
+            Expr::UnOp(UnOpExpr {
+                op: UnOp::Deref,
+                inner,
+            }) => Ok(Ref::Place(self.eval(inner)?.into_ptr()?)),
+            Expr::UnOp(UnOpExpr {
+                op: UnOp::AddressOf,
+                inner,
+            }) => match self.eval_ref(inner)? {
+                Ref::Place(place) => Ok(Ref::Value(Value::Ptr(place))),
+                Ref::Value(_) => bail!("cannot take the address of `{inner}`"),
+            },
+            _ => Ok(Ref::Value(self.eval(node)?)),
+        }
+    }
+
+    fn eval_lhs(&mut self, node: &LhsExprNode) -> eyre::Result<Ref> {
+        match &node.expr {
+            LhsExpr::Ident(ident) => self.resolve(ident),
+            LhsExpr::Postfix(inner, postfix) => {
+                let base = self.eval_lhs(inner)?;
+                self.postfix(base, postfix)
+            }
+            LhsExpr::Deref(inner) => {
+                let ptr = match self.eval_lhs(inner)? {
+                    Ref::Place(place) => self.load(&place)?,
+                    Ref::Value(value) => value,
+                };
+                Ok(Ref::Place(ptr.into_ptr()?))
+            }
+            LhsExpr::AddressOf(inner) => match self.eval_lhs(inner)? {
+                Ref::Place(place) => Ok(Ref::Value(Value::Ptr(place))),
+                Ref::Value(_) => bail!("cannot take the address of `{inner}`"),
+            },
+        }
+    }
+
+    fn resolve(&mut self, ident: &str) -> eyre::Result<Ref> {
+        if let Some(binding) = self.lookup(ident) {
+            return Ok(match binding {
+                Binding::Var(root) => Ref::Place(Pointer::new(*root)),
+                Binding::Value(value) => Ref::Value(value.clone()),
+            });
+        }
+
+        // Instrumentation passes can refer to struct members using a dotted name
+        if let Some((base, members)) = ident.split_once('.') {
+            let mut value = self.resolve(base)?;
+            for member in members.split('.') {
+                value = self.postfix(value, &Postfix::Member(member.to_owned()))?;
+            }
+            return Ok(value);
+        }
+
+        Err(eyre!("unresolved identifier `{ident}`"))
+    }
+
+    fn postfix(&mut self, base: Ref, postfix: &Postfix) -> eyre::Result<Ref> {
+        // Pointers are implicitly dereferenced when accessing their components
+        let base = match base {
+            Ref::Value(Value::Ptr(ptr)) => Ref::Place(ptr),
+            base => base,
+        };
+
+        match postfix {
+            Postfix::Index(index) => {
+                let index = self.eval(index)?.as_index()?;
+                match base {
+                    Ref::Place(place) => Ok(Ref::Place(place.child(index))),
+                    Ref::Value(value) => Ok(Ref::Value(
+                        value
+                            .children()
+                            .and_then(|it| it.get(index))
+                            .cloned()
+                            .ok_or_else(|| eyre!("out of bounds access at index {index}"))?,
+                    )),
+                }
+            }
+            Postfix::Member(member) => {
+                let indices = match &base {
+                    Ref::Place(place) => member_indices(self.peek(place)?, member)?,
+                    Ref::Value(value) => member_indices(value, member)?,
+                };
+
+                match (base, indices.as_slice()) {
+                    (Ref::Place(place), [index]) => Ok(Ref::Place(place.child(*index))),
+                    (Ref::Place(place), indices) => {
+                        let value = self.load(&place)?;
+                        Ok(Ref::Value(swizzle(value, indices)))
+                    }
+                    (Ref::Value(value), [index]) => Ok(Ref::Value(
+                        value.children().map(|it| it[*index].clone()).unwrap(),
+                    )),
+                    (Ref::Value(value), indices) => Ok(Ref::Value(swizzle(value, indices))),
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, ident: &str, args: &[ExprNode]) -> eyre::Result<Option<Value>> {
+        let args = args
+            .iter()
+            .map(|it| self.eval(it))
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        if self.functions.contains_key(ident) {
+            return self.call_fn(ident, args);
+        }
+
+        // Struct constructors are parsed as function calls
+        if let Some(decl) = self.structs.get(ident) {
+            let ty = DataType::Struct(Rc::clone(decl));
+            return construct(&ty, args).map(Some);
+        }
+
+        match ident {
+            "workgroupBarrier" | "storageBarrier" => {
+                // Barriers at the top level of the entry point are handled by `run`, which splits
+                // the entry point into phases. Anywhere else, invocations are executed one at a
+                // time, so barriers are only meaningful with a single invocation per workgroup.
+                if self.workgroup_size != 1 {
+                    bail!(
+                        "`{ident}` is only supported at the top level of the entry point with a \
+                         workgroup size greater than 1"
+                    );
+                }
+                return Ok(None);
+            }
+            "arrayLength" => {
+                let ptr = single(args)?.into_ptr()?;
+                return match self.peek(&ptr)? {
+                    Value::Array(elements) => Ok(Some(Value::U32(elements.len() as u32))),
+                    v => bail!("expected array, found `{v:?}`"),
+                };
+            }
+            "atomicLoad" => {
+                let ptr = single(args)?.into_ptr()?;
+                return Ok(Some(self.load(&ptr)?));
+            }
+            "atomicStore" => {
+                let (ptr, value) = pair(args)?;
+                self.store(&ptr.into_ptr()?, value)?;
+                return Ok(None);
+            }
+            "atomicCompareExchangeWeak" => {
+                // This is allowed to fail spuriously, but we model the strong version since that
+                // is what the backends implement in practice
+                let [ptr, cmp, value]: [Value; 3] =
+                    args.try_into().map_err(|_| eyre!("expected 3 arguments"))?;
+                let ptr = ptr.into_ptr()?;
+                let old = self.load(&ptr)?;
+
+                let ty = match old {
+                    Value::U32(_) => ScalarType::U32,
+                    Value::I32(_) => ScalarType::I32,
+                    _ => bail!("expected atomic integer, found `{old:?}`"),
+                };
+
+                let exchanged = old == cmp;
+                if exchanged {
+                    self.store(&ptr, value)?;
+                }
+
+                return Ok(Some(Value::Struct(
+                    atomic_compare_exchange_result(ty),
+                    vec![old, Value::Bool(exchanged)],
+                )));
+            }
+            _ => {}
+        }
+
+        // Atomic read-modify-write operations return the original value. Since invocations are
+        // executed sequentially, these don't need any synchronization.
+        let atomic_op: Option<fn(Value, Value) -> eyre::Result<Value>> = match ident {
+            "atomicAdd" => Some(|a, b| bin_op(BinOp::Plus, a, b)),
+            "atomicSub" => Some(|a, b| bin_op(BinOp::Minus, a, b)),
+            "atomicMax" => Some(|a, b| builtins::call(BuiltinFn::Max, vec![a, b])),
+            "atomicMin" => Some(|a, b| builtins::call(BuiltinFn::Min, vec![a, b])),
+            "atomicAnd" => Some(|a, b| bin_op(BinOp::BitAnd, a, b)),
+            "atomicOr" => Some(|a, b| bin_op(BinOp::BitOr, a, b)),
+            "atomicXor" => Some(|a, b| bin_op(BinOp::BitXOr, a, b)),
+            "atomicExchange" => Some(|_, b| Ok(b)),
+            _ => None,
+        };
+
+        if let Some(op) = atomic_op {
+            let (ptr, value) = pair(args)?;
+            let ptr = ptr.into_ptr()?;
+            let old = self.load(&ptr)?;
+            self.store(&ptr, op(old.clone(), value)?)?;
+            return Ok(Some(old));
+        }
+
+        let builtin =
+            BuiltinFn::from_str(ident).map_err(|_| eyre!("unknown function `{ident}`"))?;
+
+        builtins::call(builtin, args).map(Some)
+    }
+}
+
+fn single(args: Vec<Value>) -> eyre::Result<Value> {
+    let [arg]: [Value; 1] = args.try_into().map_err(|_| eyre!("expected 1 argument"))?;
+    Ok(arg)
+}
+
+fn pair(args: Vec<Value>) -> eyre::Result<(Value, Value)> {
+    let [a, b]: [Value; 2] = args.try_into().map_err(|_| eyre!("expected 2 arguments"))?;
+    Ok((a, b))
+}
+
+/// Returns the component indices referred to by a member access, which is either a struct member
+/// or a vector swizzle.
+fn member_indices(value: &Value, member: &str) -> eyre::Result<Vec<usize>> {
+    match value {
+        Value::Struct(decl, _) => decl
+            .members
+            .iter()
+            .position(|it| it.name == member)
+            .map(|it| vec![it])
+            .ok_or_else(|| eyre!("struct `{}` has no member `{member}`", decl.name)),
+        Value::Vector(components) => member
+            .chars()
+            .map(|c| {
+                let index = match c {
+                    'x' | 'r' => 0,
+                    'y' | 'g' => 1,
+                    'z' | 'b' => 2,
+                    'w' | 'a' => 3,
+                    _ => bail!("invalid swizzle `{member}`"),
+                };
+
+                if index >= components.len() {
+                    bail!("invalid swizzle `{member}`");
+                }
+
+                Ok(index)
+            })
+            .collect(),
+        v => bail!("cannot access member `{member}` of `{v:?}`"),
+    }
+}
+
+fn swizzle(value: Value, indices: &[usize]) -> Value {
+    let components = value.into_components();
+    Value::Vector(indices.iter().map(|i| components[*i].clone()).collect())
+}
+
+/// Evaluates a type constructor expression.
+fn construct(ty: &DataType, args: Vec<Value>) -> eyre::Result<Value> {
+    if args.is_empty() {
+        return Value::zero(ty);
+    }
+
+    Ok(match ty {
+        DataType::Scalar(t) => single(args)?.convert(*t)?,
+        DataType::Vector(n, t) => {
+            let components = args
+                .into_iter()
+                .flat_map(Value::into_components)
+                .map(|it| it.convert(*t))
+                .collect::<eyre::Result<Vec<_>>>()?;
+
+            match components.as_slice() {
+                [component] => Value::Vector(vec![component.clone(); *n as usize]),
+                _ if components.len() == *n as usize => Value::Vector(components),
+                _ => bail!("invalid number of components for `{ty}`"),
+            }
+        }
+        DataType::Matrix(c, r, t) => match args.as_slice() {
+            [Value::Matrix(_)] => single(args)?,
+            _ if args.len() == *c as usize => Value::Matrix(
+                args.into_iter()
+                    .map(|column| construct(&DataType::Vector(*r, *t), vec![column]))
+                    .collect::<eyre::Result<_>>()?,
+            ),
+            _ if args.len() == (*c * *r) as usize => {
+                let scalars = args
+                    .iter()
+                    .map(|it| it.convert(*t))
+                    .collect::<eyre::Result<Vec<_>>>()?;
+
+                Value::Matrix(
+                    scalars
+                        .chunks(*r as usize)
+                        .map(|column| Value::Vector(column.to_vec()))
+                        .collect(),
+                )
+            }
+            _ => bail!("invalid number of arguments for `{ty}`"),
+        },
+        DataType::Array(_, _) => Value::Array(args),
+        DataType::Struct(decl) => Value::Struct(decl.clone(), args),
+        DataType::Ptr(_) | DataType::Ref(_) => bail!("cannot construct a value of type `{ty}`"),
+    })
+}
@@ -0,0 +1,231 @@
+use ast::DataType;
+use common::{AddressSpace, Field, ScalarType, Type};
+use eyre::{bail, eyre};
+
+use crate::value::Value;
+
+/// Returns the layout of a buffer of the given type whose contents are `len` bytes long.
+///
+/// Layout rules are the same in every address space, so the type is always laid out as a storage
+/// buffer.
+fn layout(ty: &DataType, len: usize) -> eyre::Result<Type> {
+    Type::from_buffer_len(ty, AddressSpace::Storage, len)
+        .map_err(|e| eyre!("invalid type `{ty}` for buffer: {e}"))
+}
+
+fn next_field(fields: &mut impl Iterator<Item = Field>) -> eyre::Result<Field> {
+    fields
+        .next()
+        .ok_or_else(|| eyre!("buffer layout does not match its type"))
+}
+
+fn read_scalar(scalar_type: &ScalarType, bytes: &[u8], offset: usize) -> eyre::Result<Value> {
+    let word = bytes
+        .get(offset..offset + 4)
+        .ok_or_else(|| eyre!("buffer is too small"))?
+        .try_into()
+        .unwrap();
+
+    Ok(match scalar_type {
+        ScalarType::I32 | ScalarType::AI32 => Value::I32(i32::from_le_bytes(word)),
+        ScalarType::U32 | ScalarType::AU32 => Value::U32(u32::from_le_bytes(word)),
+        ScalarType::F32 => Value::F32(f32::from_le_bytes(word)),
+    })
+}
+
+fn write_scalar(value: &Value, bytes: &mut [u8], offset: usize) -> eyre::Result<()> {
+    let word = match value {
+        Value::I32(v) => v.to_le_bytes(),
+        Value::U32(v) => v.to_le_bytes(),
+        Value::F32(v) => v.to_le_bytes(),
+        v => bail!("cannot store `{v:?}` in a buffer"),
+    };
+
+    bytes
+        .get_mut(offset..offset + 4)
+        .ok_or_else(|| eyre!("buffer is too small"))?
+        .copy_from_slice(&word);
+
+    Ok(())
+}
+
+/// Decodes a value of the given type from the start of `bytes`.
+///
+/// If `ty` is (or ends with) a runtime-sized array, the array extends to the end of `bytes`.
+pub fn decode(ty: &DataType, bytes: &[u8]) -> eyre::Result<Value> {
+    let type_desc = layout(ty, bytes.len())?;
+    let mut fields = type_desc.fields("").into_iter();
+    decode_fields(ty, &type_desc, &mut fields, bytes)
+}
+
+/// Decodes a value by reading the fields that make it up, in the order given by
+/// [`Type::fields`].
+fn decode_fields(
+    ty: &DataType,
+    type_desc: &Type,
+    fields: &mut impl Iterator<Item = Field>,
+    bytes: &[u8],
+) -> eyre::Result<Value> {
+    Ok(match (ty, type_desc) {
+        (DataType::Scalar(_), _) => {
+            let field = next_field(fields)?;
+            read_scalar(&field.scalar_type, bytes, field.offset)?
+        }
+        (DataType::Vector(..), _) => {
+            let field = next_field(fields)?;
+            Value::Vector(
+                (0..field.components as usize)
+                    .map(|i| read_scalar(&field.scalar_type, bytes, field.offset + i * 4))
+                    .collect::<eyre::Result<_>>()?,
+            )
+        }
+        (DataType::Matrix(c, r, t), _) => {
+            let column = DataType::Vector(*r, *t);
+            Value::Matrix(
+                (0..*c)
+                    .map(|_| decode_fields(&column, type_desc, fields, bytes))
+                    .collect::<eyre::Result<_>>()?,
+            )
+        }
+        (DataType::Array(inner, _), Type::Array { size, element_type }) => Value::Array(
+            (0..*size)
+                .map(|_| decode_fields(inner, element_type, fields, bytes))
+                .collect::<eyre::Result<_>>()?,
+        ),
+        (DataType::Struct(decl), Type::Struct { members }) => Value::Struct(
+            decl.clone(),
+            decl.members
+                .iter()
+                .zip(members)
+                .map(|(member, desc)| {
+                    decode_fields(&member.data_type, &desc.type_desc, fields, bytes)
+                })
+                .collect::<eyre::Result<_>>()?,
+        ),
+        _ => bail!("invalid type `{ty}` for buffer"),
+    })
+}
+
+/// Encodes a value of the given type into the start of `bytes`, leaving any padding untouched.
+pub fn encode(ty: &DataType, value: &Value, bytes: &mut [u8]) -> eyre::Result<()> {
+    let type_desc = layout(ty, bytes.len())?;
+    let mut fields = type_desc.fields("").into_iter();
+    encode_fields(ty, value, &mut fields, bytes)
+}
+
+/// Encodes a value by writing the fields that make it up, in the order given by
+/// [`Type::fields`].
+fn encode_fields(
+    ty: &DataType,
+    value: &Value,
+    fields: &mut impl Iterator<Item = Field>,
+    bytes: &mut [u8],
+) -> eyre::Result<()> {
+    match (ty, value) {
+        (DataType::Scalar(_), value) => write_scalar(value, bytes, next_field(fields)?.offset)?,
+        (DataType::Vector(..), Value::Vector(components)) => {
+            let offset = next_field(fields)?.offset;
+            for (i, component) in components.iter().enumerate() {
+                write_scalar(component, bytes, offset + i * 4)?;
+            }
+        }
+        (DataType::Matrix(_, r, t), Value::Matrix(columns)) => {
+            let column_type = DataType::Vector(*r, *t);
+            for column in columns {
+                encode_fields(&column_type, column, fields, bytes)?;
+            }
+        }
+        (DataType::Array(inner, _), Value::Array(elements)) => {
+            for element in elements {
+                encode_fields(inner, element, fields, bytes)?;
+            }
+        }
+        (DataType::Struct(decl), Value::Struct(_, members)) => {
+            for (member, value) in decl.members.iter().zip(members) {
+                encode_fields(&member.data_type, value, fields, bytes)?;
+            }
+        }
+        (ty, value) => bail!("cannot store `{value:?}` as `{ty}`"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn struct_type(decl: &str) -> DataType {
+        let module = parser::parse(&format!(
+            "{decl}\n\
+            @group(0) @binding(0)\n\
+            var<storage, read_write> v: S;\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {{}}\n"
+        ));
+
+        module.vars[0].data_type.clone()
+    }
+
+    #[test]
+    fn members_are_encoded_at_aligned_offsets() {
+        let ty = struct_type("struct S { a: f32, b: vec3<f32>, c: f32, }");
+        let value = Value::Struct(
+            match &ty {
+                DataType::Struct(decl) => decl.clone(),
+                _ => unreachable!(),
+            },
+            vec![
+                Value::F32(1.0),
+                Value::Vector(vec![Value::F32(2.0), Value::F32(3.0), Value::F32(4.0)]),
+                Value::F32(5.0),
+            ],
+        );
+
+        let mut bytes = vec![0; 32];
+        encode(&ty, &value, &mut bytes).unwrap();
+
+        // `b` is 16-byte aligned, and `c` fits in the padding at the end of it
+        let words = bytes
+            .chunks(4)
+            .map(|it| f32::from_le_bytes(it.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(words, [1.0, 0.0, 0.0, 0.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn members_are_decoded_at_aligned_offsets() {
+        let ty = struct_type("struct S { a: u32, @align(16) b: u32, c: array<u32>, }");
+
+        let bytes = (0..28u32).flat_map(u32::to_le_bytes).collect::<Vec<_>>();
+        let Value::Struct(_, members) = decode(&ty, &bytes).unwrap() else {
+            panic!("expected struct");
+        };
+
+        // The runtime-sized array extends to the end of the buffer
+        assert_eq!(
+            members,
+            [
+                Value::U32(0),
+                Value::U32(4),
+                Value::Array((5..28).map(Value::U32).collect()),
+            ]
+        );
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        // The vec3 is followed by a scalar in its padding, so the struct has no padding at all
+        let ty = struct_type("struct S { a: vec3<f32>, b: f32, m: mat2x2<f32>, }");
+
+        let bytes = (1..=8)
+            .flat_map(|it| (it as f32).to_le_bytes())
+            .collect::<Vec<_>>();
+
+        let value = decode(&ty, &bytes).unwrap();
+        let mut encoded = vec![0; bytes.len()];
+        encode(&ty, &value, &mut encoded).unwrap();
+
+        assert_eq!(encoded, bytes);
+    }
+}
@@ -0,0 +1,503 @@
+//! A reference interpreter for the subset of WGSL generated by wgslsmith.
+//!
+//! Compute shaders are executed directly on the CPU by walking the AST. Invocations are run one
+//! at a time, in order of workgroup and then local invocation index. Barriers at the top level of
+//! the entry point split it into phases, and every invocation in a workgroup completes a phase
+//! before any of them starts the next one. This is one valid schedule for the programs we
+//! generate, which means the interpreter can be used as an oracle for differential testing on
+//! machines without a GPU.
+
+mod builtins;
+mod exec;
+mod layout;
+mod ops;
+mod value;
+
+use std::collections::HashMap;
+
+use ast::{
+    DataType, FnAttr, FnDecl, FnInputAttr, GlobalVarDecl, Module, Statement, StorageClass,
+    WorkgroupSizeArg,
+};
+use eyre::{bail, eyre};
+use reflection_types::{PipelineDescription, ResourceKind};
+
+use crate::exec::Interpreter;
+use crate::value::Value;
+
+/// Executes the `main` entry point of a compute shader, dispatching `workgroups` workgroups.
+///
+/// Returns the final contents of the storage buffers in `meta`, in the same format as the GPU
+/// backends in the harness.
+pub fn run(
+    shader: &str,
+    workgroups: u32,
+    meta: &PipelineDescription,
+) -> eyre::Result<Vec<Vec<u8>>> {
+    let module = parser::try_parse(shader)?;
+    let entry_point = module
+        .functions
+        .iter()
+        .find(|it| it.name == "main")
+        .ok_or_else(|| eyre!("entry point `main` not found"))?;
+
+    let mut interp = Interpreter::new(&module);
+
+    for decl in &module.consts {
+        let value = interp.eval_const(&decl.initializer)?;
+        interp.bind_const(&decl.name, value);
+    }
+
+    for decl in &module.overrides {
+        let Some(initializer) = &decl.initializer else {
+            bail!("override `{}` has no initializer", decl.name);
+        };
+
+        let value = interp.eval_const(initializer)?;
+        interp.bind_const(&decl.name, value);
+    }
+
+    let workgroup_size = workgroup_size(&interp, entry_point)?;
+    interp.set_workgroup_size(workgroup_size.iter().product());
+
+    let memory = Memory::new(&mut interp, &module, meta)?;
+    let phases = entry_point.body.split(is_barrier).collect::<Vec<_>>();
+
+    for workgroup_id in 0..workgroups {
+        memory.reset(&mut interp, &module, StorageClass::WorkGroup)?;
+
+        // Frame and private variables of each invocation in the workgroup
+        let mut invocations = vec![];
+
+        for z in 0..workgroup_size[2] {
+            for y in 0..workgroup_size[1] {
+                for x in 0..workgroup_size[0] {
+                    memory.reset(&mut interp, &module, StorageClass::Private)?;
+
+                    let local_id = [x, y, z];
+                    let args = entry_point
+                        .inputs
+                        .iter()
+                        .map(|input| {
+                            let builtin = input
+                                .attrs
+                                .iter()
+                                .map(|FnInputAttr::Builtin(name)| name.as_str())
+                                .next()
+                                .ok_or_else(|| {
+                                    eyre!("entry point input `{}` is not a builtin", input.name)
+                                })?;
+
+                            let vec3 = |v: [u32; 3]| Value::Vector(v.map(Value::U32).to_vec());
+
+                            Ok(match builtin {
+                                "global_invocation_id" => {
+                                    vec3([workgroup_id * workgroup_size[0] + x, y, z])
+                                }
+                                "local_invocation_id" => vec3(local_id),
+                                "local_invocation_index" => {
+                                    Value::U32((z * workgroup_size[1] + y) * workgroup_size[0] + x)
+                                }
+                                "workgroup_id" => vec3([workgroup_id, 0, 0]),
+                                "num_workgroups" => vec3([workgroups, 1, 1]),
+                                _ => bail!("unsupported builtin input `{builtin}`"),
+                            })
+                        })
+                        .collect::<eyre::Result<Vec<_>>>()?;
+
+                    let frame = interp.enter_fn(&entry_point.name, args)?;
+                    let private = memory.save(&interp, &module, StorageClass::Private);
+                    invocations.push((frame, private));
+                }
+            }
+        }
+
+        for (i, phase) in phases.iter().enumerate() {
+            if i > 0 {
+                let returned = invocations.iter().filter(|(it, _)| it.returned).count();
+                if returned != 0 && returned != invocations.len() {
+                    bail!("barrier is not reached by all invocations in the workgroup");
+                }
+            }
+
+            for (frame, private) in &mut invocations {
+                if frame.returned {
+                    continue;
+                }
+
+                memory.restore(&mut interp, &module, StorageClass::Private, private);
+                interp.resume(frame, phase)?;
+                *private = memory.save(&interp, &module, StorageClass::Private);
+            }
+        }
+    }
+
+    memory.read_storage_buffers(&interp, meta)
+}
+
+/// Whether a statement is a barrier which separates phases of the entry point.
+fn is_barrier(stmt: &Statement) -> bool {
+    matches!(stmt, Statement::FnCall(call) if call.ident == "workgroupBarrier" || call.ident == "storageBarrier")
+}
+
+fn workgroup_size(interp: &Interpreter, entry_point: &FnDecl) -> eyre::Result<[u32; 3]> {
+    let resolve = |name: &str| -> eyre::Result<u32> {
+        let value = interp
+            .lookup_const(name)
+            .ok_or_else(|| eyre!("unresolved workgroup size `{name}`"))?;
+        Ok(value.as_index()? as u32)
+    };
+
+    for attr in &entry_point.attrs {
+        match attr {
+            FnAttr::LitWorkgroupSize(x) => return Ok([*x, 1, 1]),
+            FnAttr::VarWorkgroupSize(x) => return Ok([resolve(x)?, 1, 1]),
+            FnAttr::MultiWorkgroupSize(args) => {
+                let mut size = [1; 3];
+                for (dim, arg) in size.iter_mut().zip(args) {
+                    *dim = match arg {
+                        WorkgroupSizeArg::Lit(v) => *v,
+                        WorkgroupSizeArg::Var(name) => resolve(name)?,
+                    };
+                }
+                return Ok(size);
+            }
+            FnAttr::Stage(_) => {}
+        }
+    }
+
+    bail!("entry point has no workgroup size")
+}
+
+/// Tracks where module-scope variables live in the interpreter.
+struct Memory {
+    /// Global index of each module-scope variable, in declaration order.
+    globals: Vec<usize>,
+    /// Global index and type of the variable bound to each buffer, by group and binding.
+    buffers: HashMap<(u32, u32), (usize, DataType)>,
+}
+
+impl Memory {
+    fn new(
+        interp: &mut Interpreter,
+        module: &Module,
+        meta: &PipelineDescription,
+    ) -> eyre::Result<Memory> {
+        let mut globals = vec![];
+        let mut buffers = HashMap::new();
+
+        for decl in &module.vars {
+            let storage_class = decl
+                .qualifier
+                .as_ref()
+                .map(|it| it.storage_class)
+                .unwrap_or(StorageClass::Private);
+
+            let index = match storage_class {
+                StorageClass::Uniform | StorageClass::Storage => {
+                    let (group, binding) = decl
+                        .group_index()
+                        .zip(decl.binding_index())
+                        .ok_or_else(|| eyre!("buffer `{}` has no binding", decl.name))?;
+
+                    let resource = meta
+                        .resources
+                        .iter()
+                        .find(|it| it.group == group && it.binding == binding)
+                        .ok_or_else(|| eyre!("no resource found for buffer `{}`", decl.name))?;
+
+                    let zeroed = vec![0; resource.size as usize];
+                    let bytes = resource.init.as_deref().unwrap_or(&zeroed);
+                    let value = layout::decode(&decl.data_type, bytes)?;
+
+                    let index = interp.alloc_global(&decl.name, value);
+                    buffers.insert((group, binding), (index, decl.data_type.clone()));
+                    index
+                }
+                // Other variables are initialized when their invocation or workgroup starts
+                _ => interp.alloc_global(&decl.name, Value::Bool(false)),
+            };
+
+            globals.push(index);
+        }
+
+        Ok(Memory { globals, buffers })
+    }
+
+    /// Reinitializes all module-scope variables in the given storage class.
+    fn reset(
+        &self,
+        interp: &mut Interpreter,
+        module: &Module,
+        storage_class: StorageClass,
+    ) -> eyre::Result<()> {
+        for (decl, index) in self.vars(module, storage_class) {
+            let value = match &decl.initializer {
+                Some(initializer) => interp.eval_const(initializer)?,
+                None => Value::zero(&decl.data_type)?,
+            };
+
+            interp.set_global(index, value);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the current values of all module-scope variables in the given storage class.
+    fn save(
+        &self,
+        interp: &Interpreter,
+        module: &Module,
+        storage_class: StorageClass,
+    ) -> Vec<Value> {
+        self.vars(module, storage_class)
+            .map(|(_, index)| interp.global(index).clone())
+            .collect()
+    }
+
+    /// Restores values previously returned by [`Memory::save`].
+    fn restore(
+        &self,
+        interp: &mut Interpreter,
+        module: &Module,
+        storage_class: StorageClass,
+        values: &[Value],
+    ) {
+        for ((_, index), value) in self.vars(module, storage_class).zip(values) {
+            interp.set_global(index, value.clone());
+        }
+    }
+
+    /// Returns the declaration and global index of each module-scope variable in the given storage
+    /// class.
+    fn vars<'m>(
+        &'m self,
+        module: &'m Module,
+        storage_class: StorageClass,
+    ) -> impl Iterator<Item = (&'m GlobalVarDecl, usize)> + 'm {
+        module
+            .vars
+            .iter()
+            .zip(&self.globals)
+            .filter(move |(decl, _)| {
+                let decl_storage_class = decl
+                    .qualifier
+                    .as_ref()
+                    .map(|it| it.storage_class)
+                    .unwrap_or(StorageClass::Private);
+                decl_storage_class == storage_class
+            })
+            .map(|(decl, index)| (decl, *index))
+    }
+
+    fn read_storage_buffers(
+        &self,
+        interp: &Interpreter,
+        meta: &PipelineDescription,
+    ) -> eyre::Result<Vec<Vec<u8>>> {
+        let mut results = vec![];
+
+        for resource in &meta.resources {
+            if resource.kind != ResourceKind::StorageBuffer {
+                continue;
+            }
+
+            let mut bytes = resource
+                .init
+                .clone()
+                .unwrap_or_else(|| vec![0; resource.size as usize]);
+
+            if let Some((index, ty)) = self.buffers.get(&(resource.group, resource.binding)) {
+                layout::encode(ty, interp.global(*index), &mut bytes)?;
+            }
+
+            results.push(bytes);
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reflection_types::{PipelineDescription, PipelineResource, ResourceKind};
+
+    fn storage_buffer(binding: u32, size: u32, init: Option<Vec<u8>>) -> PipelineResource {
+        PipelineResource {
+            name: format!("buffer_{binding}"),
+            kind: ResourceKind::StorageBuffer,
+            group: 0,
+            binding,
+            init,
+            size,
+        }
+    }
+
+    fn run(shader: &str, workgroups: u32, resources: Vec<PipelineResource>) -> Vec<Vec<u8>> {
+        super::run(shader, workgroups, &PipelineDescription { resources }).unwrap()
+    }
+
+    fn words(bytes: &[u8]) -> Vec<u32> {
+        bytes
+            .chunks_exact(4)
+            .map(|it| u32::from_le_bytes(it.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn invocations_see_their_own_builtins() {
+        const SRC: &str = r#"
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 8>;
+
+@compute @workgroup_size(4)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(local_invocation_index) index: u32) {
+    output[gid.x] = index * 10u + gid.x;
+}
+"#;
+        let output = run(SRC, 2, vec![storage_buffer(0, 32, None)]);
+        assert_eq!(words(&output[0]), [0, 11, 22, 33, 4, 15, 26, 37]);
+    }
+
+    #[test]
+    fn private_variables_are_per_invocation() {
+        const SRC: &str = r#"
+var<private> counter: u32;
+
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 4>;
+
+@compute @workgroup_size(4)
+fn main(@builtin(local_invocation_index) index: u32) {
+    counter = counter + 1u;
+    workgroupBarrier();
+    counter = counter + 1u;
+    output[index] = counter;
+}
+"#;
+        let output = run(SRC, 1, vec![storage_buffer(0, 16, None)]);
+        assert_eq!(words(&output[0]), [2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn barriers_split_entry_point_into_phases() {
+        // Each invocation reads a value written by its neighbour before the barrier, which is only
+        // deterministic if every invocation finishes the first phase before the second starts
+        const SRC: &str = r#"
+var<workgroup> shared: array<u32, 4>;
+
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 4>;
+
+@compute @workgroup_size(4)
+fn main(@builtin(local_invocation_index) index: u32) {
+    shared[index] = index + 1u;
+    workgroupBarrier();
+    output[index] = shared[(index + 1u) % 4u];
+}
+"#;
+        let output = run(SRC, 1, vec![storage_buffer(0, 16, None)]);
+        assert_eq!(words(&output[0]), [2, 3, 4, 1]);
+    }
+
+    #[test]
+    fn workgroup_memory_is_reset_between_workgroups() {
+        const SRC: &str = r#"
+var<workgroup> total: u32;
+
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 4>;
+
+@compute @workgroup_size(2)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    total = total + 1u;
+    workgroupBarrier();
+    output[gid.x] = total;
+}
+"#;
+        let output = run(SRC, 2, vec![storage_buffer(0, 16, None)]);
+        assert_eq!(words(&output[0]), [2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn barrier_skipped_by_some_invocations_is_an_error() {
+        const SRC: &str = r#"
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 2>;
+
+@compute @workgroup_size(2)
+fn main(@builtin(local_invocation_index) index: u32) {
+    if (index == 0u) {
+        return;
+    }
+    workgroupBarrier();
+    output[index] = 1u;
+}
+"#;
+        let meta = PipelineDescription {
+            resources: vec![storage_buffer(0, 8, None)],
+        };
+        assert!(super::run(SRC, 1, &meta).is_err());
+    }
+
+    #[test]
+    fn storage_buffers_are_encoded_with_padding_untouched() {
+        // `b` is 16-byte aligned, leaving 12 bytes of padding after `a`
+        const SRC: &str = r#"
+struct Output {
+    a: u32,
+    b: vec3<u32>,
+    c: u32,
+}
+
+@group(0) @binding(0)
+var<storage, read_write> output: Output;
+
+@compute @workgroup_size(1)
+fn main() {
+    output.a = 1u;
+    output.b = vec3<u32>(2u, 3u, 4u);
+    output.c = 5u;
+}
+"#;
+        let init = vec![0xff; 32];
+        let output = run(SRC, 1, vec![storage_buffer(0, 32, Some(init))]);
+        assert_eq!(
+            words(&output[0]),
+            [1, u32::MAX, u32::MAX, u32::MAX, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn storage_buffers_are_decoded_from_initial_data() {
+        const SRC: &str = r#"
+@group(0) @binding(0)
+var<storage, read> input: array<i32, 3>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<i32, 3>;
+
+@compute @workgroup_size(1)
+fn main() {
+    output[0] = input[2];
+    output[1] = input[1];
+    output[2] = input[0];
+}
+"#;
+        let init = [-1i32, 7, 42]
+            .iter()
+            .flat_map(|it| it.to_le_bytes())
+            .collect();
+        let output = run(
+            SRC,
+            1,
+            vec![
+                storage_buffer(0, 12, Some(init)),
+                storage_buffer(1, 12, None),
+            ],
+        );
+
+        // Every storage buffer is returned, in the order of the pipeline resources
+        assert_eq!(words(&output[1]), [42, 7, (-1i32) as u32]);
+    }
+}
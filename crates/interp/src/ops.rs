@@ -0,0 +1,231 @@
+use ast::{BinOp, UnOp};
+use eyre::{bail, eyre};
+
+use crate::value::Value;
+
+/// Applies `f` to each component of a scalar or vector value.
+pub fn map(v: Value, f: impl Fn(Value) -> eyre::Result<Value>) -> eyre::Result<Value> {
+    match v {
+        Value::Vector(vs) => Ok(Value::Vector(
+            vs.into_iter().map(f).collect::<eyre::Result<_>>()?,
+        )),
+        v => f(v),
+    }
+}
+
+/// Applies `f` to each pair of corresponding components of two scalar or vector values. If one
+/// of the values is a scalar and the other is a vector, the scalar is splatted.
+pub fn zip(
+    a: Value,
+    b: Value,
+    f: impl Fn(Value, Value) -> eyre::Result<Value>,
+) -> eyre::Result<Value> {
+    match (a, b) {
+        (Value::Vector(a), Value::Vector(b)) => {
+            if a.len() != b.len() {
+                bail!("mismatched vector sizes");
+            }
+
+            Ok(Value::Vector(
+                a.into_iter()
+                    .zip(b)
+                    .map(|(a, b)| f(a, b))
+                    .collect::<eyre::Result<_>>()?,
+            ))
+        }
+        (Value::Vector(a), b) => Ok(Value::Vector(
+            a.into_iter()
+                .map(|a| f(a, b.clone()))
+                .collect::<eyre::Result<_>>()?,
+        )),
+        (a, Value::Vector(b)) => Ok(Value::Vector(
+            b.into_iter()
+                .map(|b| f(a.clone(), b))
+                .collect::<eyre::Result<_>>()?,
+        )),
+        (a, b) => f(a, b),
+    }
+}
+
+/// Applies `f` to each triple of corresponding components of three scalar or vector values, with
+/// any scalars splatted to the size of the vectors.
+pub fn zip3(
+    a: Value,
+    b: Value,
+    c: Value,
+    f: impl Fn(Value, Value, Value) -> eyre::Result<Value>,
+) -> eyre::Result<Value> {
+    let len = [&a, &b, &c].into_iter().find_map(|v| match v {
+        Value::Vector(vs) => Some(vs.len()),
+        _ => None,
+    });
+
+    let len = match len {
+        Some(len) => len,
+        None => return f(a, b, c),
+    };
+
+    let components = |v: Value| match v {
+        Value::Vector(vs) if vs.len() != len => bail!("mismatched vector sizes"),
+        Value::Vector(vs) => Ok(vs),
+        v => Ok(vec![v; len]),
+    };
+
+    Ok(Value::Vector(
+        components(a)?
+            .into_iter()
+            .zip(components(b)?)
+            .zip(components(c)?)
+            .map(|((a, b), c)| f(a, b, c))
+            .collect::<eyre::Result<_>>()?,
+    ))
+}
+
+pub fn un_op(op: UnOp, v: Value) -> eyre::Result<Value> {
+    if let Value::Matrix(columns) = v {
+        return Ok(Value::Matrix(
+            columns
+                .into_iter()
+                .map(|it| un_op(op, it))
+                .collect::<eyre::Result<_>>()?,
+        ));
+    }
+
+    map(v, |v| {
+        Ok(match (op, v) {
+            (UnOp::Neg, Value::I32(v)) => Value::I32(v.wrapping_neg()),
+            (UnOp::Neg, Value::F32(v)) => Value::F32(-v),
+            (UnOp::Not, Value::Bool(v)) => Value::Bool(!v),
+            (UnOp::BitNot, Value::I32(v)) => Value::I32(!v),
+            (UnOp::BitNot, Value::U32(v)) => Value::U32(!v),
+            (op, v) => bail!("invalid operand `{v:?}` for unary operator `{op}`"),
+        })
+    })
+}
+
+pub fn bin_op(op: BinOp, l: Value, r: Value) -> eyre::Result<Value> {
+    match (op, l, r) {
+        (BinOp::Times, l @ Value::Matrix(_), r) | (BinOp::Times, l, r @ Value::Matrix(_)) => {
+            matrix_times(l, r)
+        }
+        (BinOp::Plus | BinOp::Minus, Value::Matrix(a), Value::Matrix(b)) => Ok(Value::Matrix(
+            a.into_iter()
+                .zip(b)
+                .map(|(a, b)| bin_op(op, a, b))
+                .collect::<eyre::Result<_>>()?,
+        )),
+        (op, l, r) => zip(l, r, |l, r| scalar_bin_op(op, l, r)),
+    }
+}
+
+fn scalar_bin_op(op: BinOp, l: Value, r: Value) -> eyre::Result<Value> {
+    use BinOp::*;
+
+    Ok(match (l, r) {
+        (Value::Bool(a), Value::Bool(b)) => Value::Bool(match op {
+            LogAnd | BitAnd => a & b,
+            LogOr | BitOr => a | b,
+            BitXOr => a ^ b,
+            Equal => a == b,
+            NotEqual => a != b,
+            _ => bail!("invalid operands for binary operator `{op}`"),
+        }),
+        (Value::I32(a), Value::I32(b)) => match op {
+            Plus => Value::I32(a.wrapping_add(b)),
+            Minus => Value::I32(a.wrapping_sub(b)),
+            Times => Value::I32(a.wrapping_mul(b)),
+            // Division by zero and overflow yield the dividend, and the corresponding remainder
+            // is zero
+            Divide => Value::I32(a.checked_div(b).unwrap_or(a)),
+            Mod => Value::I32(a.checked_rem(b).unwrap_or(0)),
+            BitAnd => Value::I32(a & b),
+            BitOr => Value::I32(a | b),
+            BitXOr => Value::I32(a ^ b),
+            _ => compare(op, a, b)?,
+        },
+        (Value::U32(a), Value::U32(b)) => match op {
+            Plus => Value::U32(a.wrapping_add(b)),
+            Minus => Value::U32(a.wrapping_sub(b)),
+            Times => Value::U32(a.wrapping_mul(b)),
+            Divide => Value::U32(a.checked_div(b).unwrap_or(a)),
+            Mod => Value::U32(a.checked_rem(b).unwrap_or(0)),
+            BitAnd => Value::U32(a & b),
+            BitOr => Value::U32(a | b),
+            BitXOr => Value::U32(a ^ b),
+            LShift => Value::U32(a.wrapping_shl(b)),
+            RShift => Value::U32(a.wrapping_shr(b)),
+            _ => compare(op, a, b)?,
+        },
+        (Value::I32(a), Value::U32(b)) => match op {
+            LShift => Value::I32(a.wrapping_shl(b)),
+            RShift => Value::I32(a.wrapping_shr(b)),
+            _ => bail!("invalid operands for binary operator `{op}`"),
+        },
+        (Value::F32(a), Value::F32(b)) => match op {
+            Plus => Value::F32(a + b),
+            Minus => Value::F32(a - b),
+            Times => Value::F32(a * b),
+            Divide => Value::F32(a / b),
+            Mod => Value::F32(a % b),
+            _ => compare(op, a, b)?,
+        },
+        (l, r) => bail!("invalid operands `{l:?}` and `{r:?}` for binary operator `{op}`"),
+    })
+}
+
+fn compare<T: PartialOrd>(op: BinOp, a: T, b: T) -> eyre::Result<Value> {
+    Ok(Value::Bool(match op {
+        BinOp::Equal => a == b,
+        BinOp::NotEqual => a != b,
+        BinOp::Less => a < b,
+        BinOp::LessEqual => a <= b,
+        BinOp::Greater => a > b,
+        BinOp::GreaterEqual => a >= b,
+        _ => bail!("invalid operands for binary operator `{op}`"),
+    }))
+}
+
+pub fn dot(a: Value, b: Value) -> eyre::Result<Value> {
+    let products = zip(a, b, |a, b| scalar_bin_op(BinOp::Times, a, b))?;
+    let mut components = products.into_components().into_iter();
+    let first = components
+        .next()
+        .ok_or_else(|| eyre!("cannot take the dot product of empty vectors"))?;
+    components.try_fold(first, |acc, it| scalar_bin_op(BinOp::Plus, acc, it))
+}
+
+fn matrix_times(l: Value, r: Value) -> eyre::Result<Value> {
+    match (l, r) {
+        // Each column of the result is a linear combination of the columns of the matrix
+        (Value::Matrix(columns), Value::Vector(v)) => {
+            let mut result: Option<Value> = None;
+            for (column, scale) in columns.into_iter().zip(v) {
+                let term = bin_op(BinOp::Times, column, scale)?;
+                result = Some(match result {
+                    Some(acc) => bin_op(BinOp::Plus, acc, term)?,
+                    None => term,
+                });
+            }
+            result.ok_or_else(|| eyre!("cannot multiply empty matrix"))
+        }
+        (v @ Value::Vector(_), Value::Matrix(columns)) => Ok(Value::Vector(
+            columns
+                .into_iter()
+                .map(|column| dot(v.clone(), column))
+                .collect::<eyre::Result<_>>()?,
+        )),
+        (l @ Value::Matrix(_), Value::Matrix(columns)) => Ok(Value::Matrix(
+            columns
+                .into_iter()
+                .map(|column| matrix_times(l.clone(), column))
+                .collect::<eyre::Result<_>>()?,
+        )),
+        (Value::Matrix(columns), s) | (s, Value::Matrix(columns)) => Ok(Value::Matrix(
+            columns
+                .into_iter()
+                .map(|column| bin_op(BinOp::Times, column, s.clone()))
+                .collect::<eyre::Result<_>>()?,
+        )),
+        (l, r) => bail!("invalid operands `{l:?}` and `{r:?}` for matrix multiplication"),
+    }
+}
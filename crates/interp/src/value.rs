@@ -0,0 +1,178 @@
+use std::rc::Rc;
+
+use ast::{DataType, ScalarType, StructDecl};
+use eyre::{bail, eyre};
+
+/// A runtime value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+    Vector(Vec<Value>),
+    /// A matrix, stored as a list of column vectors.
+    Matrix(Vec<Value>),
+    Array(Vec<Value>),
+    Struct(Rc<StructDecl>, Vec<Value>),
+    Ptr(Pointer),
+}
+
+/// The variable that a pointer points into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Root {
+    Global(usize),
+    Local(usize),
+}
+
+/// A pointer to a memory location, given by a root variable and a path of component indices
+/// (struct members, array elements, matrix columns or vector components) leading into it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pointer {
+    pub root: Root,
+    pub path: Vec<usize>,
+}
+
+impl Pointer {
+    pub fn new(root: Root) -> Pointer {
+        Pointer { root, path: vec![] }
+    }
+
+    pub fn child(mut self, index: usize) -> Pointer {
+        self.path.push(index);
+        self
+    }
+}
+
+impl Value {
+    /// Returns the zero value for a type.
+    pub fn zero(ty: &DataType) -> eyre::Result<Value> {
+        Ok(match ty {
+            DataType::Scalar(t) => Value::zero_scalar(*t),
+            DataType::Vector(n, t) => Value::Vector(vec![Value::zero_scalar(*t); *n as usize]),
+            DataType::Matrix(c, r, t) => {
+                Value::Matrix(vec![
+                    Value::Vector(vec![Value::zero_scalar(*t); *r as usize]);
+                    *c as usize
+                ])
+            }
+            DataType::Array(inner, n) => {
+                let element = Value::zero(inner)?;
+                Value::Array(vec![element; n.unwrap_or(0) as usize])
+            }
+            DataType::Struct(decl) => Value::Struct(
+                decl.clone(),
+                decl.members
+                    .iter()
+                    .map(|it| Value::zero(&it.data_type))
+                    .collect::<eyre::Result<_>>()?,
+            ),
+            DataType::Ptr(_) | DataType::Ref(_) => bail!("type `{ty}` has no zero value"),
+        })
+    }
+
+    fn zero_scalar(ty: ScalarType) -> Value {
+        match ty {
+            ScalarType::Bool => Value::Bool(false),
+            ScalarType::I32 | ScalarType::AI32 => Value::I32(0),
+            ScalarType::U32 | ScalarType::AU32 => Value::U32(0),
+            ScalarType::F32 => Value::F32(0.0),
+        }
+    }
+
+    /// Converts a scalar value to the given scalar type, using the semantics of WGSL value
+    /// constructors.
+    pub fn convert(&self, ty: ScalarType) -> eyre::Result<Value> {
+        Ok(match (ty, self) {
+            (ScalarType::Bool, Value::Bool(v)) => Value::Bool(*v),
+            (ScalarType::Bool, Value::I32(v)) => Value::Bool(*v != 0),
+            (ScalarType::Bool, Value::U32(v)) => Value::Bool(*v != 0),
+            (ScalarType::Bool, Value::F32(v)) => Value::Bool(*v != 0.0),
+            (ScalarType::I32 | ScalarType::AI32, Value::Bool(v)) => Value::I32(*v as i32),
+            (ScalarType::I32 | ScalarType::AI32, Value::I32(v)) => Value::I32(*v),
+            (ScalarType::I32 | ScalarType::AI32, Value::U32(v)) => Value::I32(*v as i32),
+            (ScalarType::I32 | ScalarType::AI32, Value::F32(v)) => Value::I32(*v as i32),
+            (ScalarType::U32 | ScalarType::AU32, Value::Bool(v)) => Value::U32(*v as u32),
+            (ScalarType::U32 | ScalarType::AU32, Value::I32(v)) => Value::U32(*v as u32),
+            (ScalarType::U32 | ScalarType::AU32, Value::U32(v)) => Value::U32(*v),
+            (ScalarType::U32 | ScalarType::AU32, Value::F32(v)) => Value::U32(*v as u32),
+            (ScalarType::F32, Value::Bool(v)) => Value::F32(*v as u32 as f32),
+            (ScalarType::F32, Value::I32(v)) => Value::F32(*v as f32),
+            (ScalarType::F32, Value::U32(v)) => Value::F32(*v as f32),
+            (ScalarType::F32, Value::F32(v)) => Value::F32(*v),
+            (ty, v) => bail!("cannot convert `{v:?}` to `{ty}`"),
+        })
+    }
+
+    pub fn as_bool(&self) -> eyre::Result<bool> {
+        match self {
+            Value::Bool(v) => Ok(*v),
+            v => Err(eyre!("expected bool, found `{v:?}`")),
+        }
+    }
+
+    pub fn as_i32(&self) -> eyre::Result<i32> {
+        match self {
+            Value::I32(v) => Ok(*v),
+            v => Err(eyre!("expected i32, found `{v:?}`")),
+        }
+    }
+
+    pub fn as_u32(&self) -> eyre::Result<u32> {
+        match self {
+            Value::U32(v) => Ok(*v),
+            v => Err(eyre!("expected u32, found `{v:?}`")),
+        }
+    }
+
+    pub fn as_f32(&self) -> eyre::Result<f32> {
+        match self {
+            Value::F32(v) => Ok(*v),
+            v => Err(eyre!("expected f32, found `{v:?}`")),
+        }
+    }
+
+    /// Interprets an integer value as an index.
+    pub fn as_index(&self) -> eyre::Result<usize> {
+        match self {
+            Value::I32(v) if *v >= 0 => Ok(*v as usize),
+            Value::U32(v) => Ok(*v as usize),
+            v => Err(eyre!("invalid index `{v:?}`")),
+        }
+    }
+
+    pub fn into_ptr(self) -> eyre::Result<Pointer> {
+        match self {
+            Value::Ptr(ptr) => Ok(ptr),
+            v => Err(eyre!("expected pointer, found `{v:?}`")),
+        }
+    }
+
+    /// Returns the components of a vector, or the value itself as a single component if it is
+    /// a scalar.
+    pub fn into_components(self) -> Vec<Value> {
+        match self {
+            Value::Vector(components) => components,
+            v => vec![v],
+        }
+    }
+
+    /// Returns the components of a composite value.
+    pub fn children(&self) -> Option<&[Value]> {
+        match self {
+            Value::Vector(vs) | Value::Matrix(vs) | Value::Array(vs) | Value::Struct(_, vs) => {
+                Some(vs)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Option<&mut [Value]> {
+        match self {
+            Value::Vector(vs) | Value::Matrix(vs) | Value::Array(vs) | Value::Struct(_, vs) => {
+                Some(vs)
+            }
+            _ => None,
+        }
+    }
+}
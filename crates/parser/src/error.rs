@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+use pest::error::{Error, ErrorVariant, LineColLocation};
+use pest::iterators::Pair;
+
+use crate::Rule;
+
+/// An error encountered while parsing a shader, either because the source does not match the
+/// grammar or because it is semantically invalid (e.g. it references an undefined variable).
+#[derive(Clone, Debug)]
+pub struct ParseError {
+    /// Line number (1-based) at which the error occurred.
+    pub line: usize,
+    /// Column number (1-based) at which the error occurred.
+    pub col: usize,
+    /// The grammar rule that was expected, or the rule that was being parsed when a semantic
+    /// error was detected.
+    pub rule: Option<Rule>,
+    /// Description of what went wrong.
+    pub message: String,
+    /// Rendered source snippet pointing at the offending location.
+    pub snippet: String,
+}
+
+impl ParseError {
+    pub(crate) fn at(pair: &Pair<Rule>, message: impl Display) -> ParseError {
+        let error = Error::new_from_span(
+            ErrorVariant::<Rule>::CustomError {
+                message: message.to_string(),
+            },
+            pair.as_span(),
+        );
+
+        ParseError {
+            rule: Some(pair.as_rule()),
+            ..ParseError::from(error)
+        }
+    }
+}
+
+impl From<Error<Rule>> for ParseError {
+    fn from(error: Error<Rule>) -> Self {
+        let (line, col) = match error.line_col {
+            LineColLocation::Pos(pos) => pos,
+            LineColLocation::Span(start, _) => start,
+        };
+
+        let rule = match &error.variant {
+            ErrorVariant::ParsingError { positives, .. } => positives.first().copied(),
+            ErrorVariant::CustomError { .. } => None,
+        };
+
+        ParseError {
+            line,
+            col,
+            rule,
+            message: error.variant.message().into_owned(),
+            snippet: error.to_string(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "failed to parse shader at {}:{}", self.line, self.col)?;
+        write!(f, "{}", self.snippet)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -10,6 +10,10 @@ use pest::Parser;
 use rpds::HashTrieMap;
 use strum::IntoEnumIterator;
 
+mod error;
+
+pub use error::ParseError;
+
 #[derive(pest_derive::Parser)]
 #[grammar = "grammar.pest"]
 struct WGSLParser;
@@ -33,6 +37,7 @@ pub struct Environment {
     vars: HashTrieMap<String, DataType>,
     fns: HashTrieMap<String, Func>,
     types: HashTrieMap<String, Rc<StructDecl>>,
+    aliases: HashTrieMap<String, DataType>,
 }
 
 fn builtins() -> HashTrieMap<String, Func> {
@@ -47,6 +52,7 @@ impl Environment {
             vars: HashTrieMap::new(),
             fns: builtins(),
             types: HashTrieMap::new(),
+            aliases: HashTrieMap::new(),
         }
     }
 
@@ -62,6 +68,14 @@ impl Environment {
         self.types.get(name)
     }
 
+    pub fn alias(&self, name: &str) -> Option<&DataType> {
+        self.aliases.get(name)
+    }
+
+    pub fn insert_alias(&mut self, name: String, ty: DataType) {
+        self.aliases.insert_mut(name, ty);
+    }
+
     pub fn insert_struct(&mut self, name: String, decl: Rc<StructDecl>) {
         self.types.insert_mut(name, decl);
     }
@@ -79,66 +93,110 @@ impl Environment {
     }
 }
 
+/// Parses a WGSL module, panicking if the input is not a valid shader.
+///
+/// See [`try_parse`] for a version which reports errors to the caller.
 pub fn parse(input: &str) -> Module {
-    let pairs = WGSLParser::parse(Rule::translation_unit, input).unwrap();
+    try_parse(input).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Parses a WGSL module, returning a [`ParseError`] pointing at the offending source location if
+/// the input is not a valid shader.
+pub fn try_parse(input: &str) -> Result<Module, ParseError> {
+    let pairs = WGSLParser::parse(Rule::translation_unit, input)?;
     let pair = pairs.into_iter().next().unwrap();
     parse_translation_unit(pair, &mut Environment::new())
 }
 
+/// Parses a WGSL function declaration, panicking if the input is not a valid function.
+///
+/// See [`try_parse_fn`] for a version which reports errors to the caller.
 pub fn parse_fn(input: &str, env: &mut Environment) -> FnDecl {
-    let pairs = WGSLParser::parse(Rule::function_decl, input).unwrap();
+    try_parse_fn(input, env).unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub fn try_parse_fn(input: &str, env: &mut Environment) -> Result<FnDecl, ParseError> {
+    let pairs = WGSLParser::parse(Rule::function_decl, input)?;
     let pair = pairs.into_iter().next().unwrap();
     parse_function_decl(pair, env)
 }
 
-fn parse_translation_unit(pair: Pair<Rule>, env: &mut Environment) -> Module {
-    let decls = pair
-        .into_inner()
+fn parse_translation_unit(pair: Pair<Rule>, env: &mut Environment) -> Result<Module, ParseError> {
+    let mut pairs = pair.into_inner().peekable();
+
+    let enables = pairs
+        .by_ref()
+        .peeking_take_while(|pair| pair.as_rule() == Rule::enable_directive)
+        .flat_map(|pair| pair.into_inner().map(|pair| pair.as_str().to_owned()))
+        .collect();
+
+    let decls = pairs
         .take_while(|pair| pair.as_rule() != Rule::EOI)
         .map(|pair| parse_global_decl(pair, env))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
     let mut functions = vec![];
+    let mut aliases = vec![];
     let mut structs = vec![];
     let mut consts = vec![];
+    let mut overrides = vec![];
     let mut vars = vec![];
+    let mut const_asserts = vec![];
 
     for decl in decls {
         match decl {
             GlobalDecl::Const(decl) => consts.push(decl),
+            GlobalDecl::Override(decl) => overrides.push(decl),
             GlobalDecl::Var(decl) => vars.push(decl),
+            GlobalDecl::Alias(decl) => aliases.push(decl),
+            GlobalDecl::ConstAssert(stmt) => const_asserts.push(stmt),
             GlobalDecl::Struct(decl) => structs.push(decl),
             GlobalDecl::Fn(decl) => functions.push(decl),
         }
     }
 
-    Module {
+    Ok(Module {
+        enables,
+        aliases,
         functions,
         structs,
         consts,
+        overrides,
         vars,
-    }
+        const_asserts,
+    })
 }
 
 enum GlobalDecl {
     Const(GlobalConstDecl),
+    Override(GlobalOverrideDecl),
     Var(GlobalVarDecl),
+    Alias(TypeAliasDecl),
+    ConstAssert(ConstAssertStatement),
     Struct(Rc<StructDecl>),
     Fn(FnDecl),
 }
 
-fn parse_global_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalDecl {
+fn parse_global_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<GlobalDecl, ParseError> {
     let pair = pair.into_inner().next().unwrap();
-    match pair.as_rule() {
-        Rule::global_constant_decl => GlobalDecl::Const(parse_global_const_decl(pair, env)),
-        Rule::global_variable_decl => GlobalDecl::Var(parse_global_variable_decl(pair, env)),
-        Rule::struct_decl => GlobalDecl::Struct(parse_struct_decl(pair, env)),
-        Rule::function_decl => GlobalDecl::Fn(parse_function_decl(pair, env)),
+    Ok(match pair.as_rule() {
+        Rule::global_constant_decl => GlobalDecl::Const(parse_global_const_decl(pair, env)?),
+        Rule::global_override_decl => GlobalDecl::Override(parse_global_override_decl(pair, env)?),
+        Rule::global_variable_decl => GlobalDecl::Var(parse_global_variable_decl(pair, env)?),
+        Rule::type_alias_decl => GlobalDecl::Alias(parse_type_alias_decl(pair, env)?),
+        Rule::const_assert_statement => {
+            GlobalDecl::ConstAssert(parse_const_assert_statement(pair, env)?)
+        }
+        Rule::struct_decl => GlobalDecl::Struct(parse_struct_decl(pair, env)?),
+        Rule::function_decl => GlobalDecl::Fn(parse_function_decl(pair, env)?),
         _ => unreachable!(),
-    }
+    })
 }
 
-fn parse_global_const_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalConstDecl {
+fn parse_global_const_decl(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+) -> Result<GlobalConstDecl, ParseError> {
     let mut pairs = pair.into_inner().peekable();
 
     let name = pairs.next().unwrap().as_str().to_owned();
@@ -147,23 +205,118 @@ fn parse_global_const_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalCon
     if let Some(pair) = pairs.peek() {
         if pair.as_rule() == Rule::type_decl {
             let pair = pairs.next().unwrap();
-            data_type = Some(parse_type_decl(pair, env));
+            data_type = Some(parse_type_decl(pair, env)?);
         }
     }
 
-    let expr = parse_expression(pairs.next().unwrap(), env);
+    let expr = parse_expression(pairs.next().unwrap(), env)?;
     let data_type = data_type.unwrap_or_else(|| expr.data_type.clone());
 
     env.insert_var(name.clone(), data_type.clone());
 
-    GlobalConstDecl {
+    Ok(GlobalConstDecl {
         name,
         data_type,
         initializer: expr,
+    })
+}
+
+fn parse_global_override_decl(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+) -> Result<GlobalOverrideDecl, ParseError> {
+    let decl_pair = pair.clone();
+    let mut pairs = pair.into_inner().peekable();
+
+    let attrs = pairs
+        .by_ref()
+        .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
+        .flat_map(|pair| {
+            pair.into_inner().map(|pair| {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap();
+                let arg = pairs.next().unwrap();
+                match name.as_str() {
+                    "id" => Ok(OverrideAttr::Id(parse_attr_arg(&arg)?)),
+                    _ => Err(ParseError::at(
+                        &name,
+                        format!("invalid override attribute: {}", name.as_str()),
+                    )),
+                }
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let name = pairs.next().unwrap().as_str().to_owned();
+    let mut data_type = None;
+
+    if let Some(pair) = pairs.peek() {
+        if pair.as_rule() == Rule::type_decl {
+            let pair = pairs.next().unwrap();
+            data_type = Some(parse_type_decl(pair, env)?);
+        }
     }
+
+    let initializer = pairs
+        .next()
+        .map(|pair| parse_expression(pair, env))
+        .transpose()?;
+
+    let data_type = match (data_type, &initializer) {
+        (Some(data_type), _) => data_type,
+        (None, Some(initializer)) => initializer.data_type.clone(),
+        (None, None) => {
+            return Err(ParseError::at(
+                &decl_pair,
+                "override declaration must have type or initializer",
+            ))
+        }
+    };
+
+    env.insert_var(name.clone(), data_type.clone());
+
+    Ok(GlobalOverrideDecl {
+        attrs,
+        name,
+        data_type,
+        initializer,
+    })
+}
+
+fn parse_type_alias_decl(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+) -> Result<TypeAliasDecl, ParseError> {
+    let mut pairs = pair.into_inner();
+    let name = pairs.next().unwrap().as_str().to_owned();
+    let data_type = parse_type_decl(pairs.next().unwrap(), env)?;
+
+    env.insert_alias(name.clone(), data_type.clone());
+    env.insert_func(name.clone(), data_type.clone());
+
+    Ok(TypeAliasDecl { name, data_type })
+}
+
+fn parse_const_assert_statement(
+    pair: Pair<Rule>,
+    env: &Environment,
+) -> Result<ConstAssertStatement, ParseError> {
+    let pair = pair.into_inner().next().unwrap();
+    Ok(ConstAssertStatement::new(parse_expression(pair, env)?))
 }
 
-fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalVarDecl {
+/// Parses an integer attribute argument such as the `0` in `@binding(0)`.
+fn parse_attr_arg<T: std::str::FromStr>(pair: &Pair<Rule>) -> Result<T, ParseError> {
+    pair.as_str()
+        .parse()
+        .map_err(|_| ParseError::at(pair, format!("invalid attribute argument: {}", pair.as_str())))
+}
+
+fn parse_global_variable_decl(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+) -> Result<GlobalVarDecl, ParseError> {
+    let decl_pair = pair.clone();
     let mut pairs = pair.into_inner().peekable();
 
     let attrs = pairs
@@ -172,16 +325,19 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
         .flat_map(|pair| {
             pair.into_inner().map(|pair| {
                 let mut pairs = pair.into_inner();
-                let name = pairs.next().unwrap().as_str();
-                let arg = pairs.next().unwrap().as_str();
-                match name {
-                    "binding" => GlobalVarAttr::Binding(arg.parse().unwrap()),
-                    "group" => GlobalVarAttr::Group(arg.parse().unwrap()),
-                    _ => panic!("invalid global variable attribute: {}", name),
+                let name = pairs.next().unwrap();
+                let arg = pairs.next().unwrap();
+                match name.as_str() {
+                    "binding" => Ok(GlobalVarAttr::Binding(parse_attr_arg(&arg)?)),
+                    "group" => Ok(GlobalVarAttr::Group(parse_attr_arg(&arg)?)),
+                    _ => Err(ParseError::at(
+                        &name,
+                        format!("invalid global variable attribute: {}", name.as_str()),
+                    )),
                 }
             })
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     let mut qualifier = None;
 
@@ -209,7 +365,13 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
         }
     }
 
-    let qualifier = qualifier.expect("module scope var declaration must specify storage class");
+    let qualifier = qualifier.ok_or_else(|| {
+        ParseError::at(
+            &decl_pair,
+            "module scope var declaration must specify storage class",
+        )
+    })?;
+
     let name = pairs.next().unwrap().as_str().to_owned();
     let mut data_type = None;
     let mut expr = None;
@@ -217,21 +379,25 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
     if let Some(pair) = pairs.peek() {
         if pair.as_rule() == Rule::type_decl {
             let pair = pairs.next().unwrap();
-            data_type = Some(parse_type_decl(pair, env));
+            data_type = Some(parse_type_decl(pair, env)?);
         }
     }
 
     if pairs.peek().is_some() {
         let pair = pairs.next().unwrap();
-        expr = Some(parse_expression(pair, env))
+        expr = Some(parse_expression(pair, env)?)
     }
 
-    let data_type = data_type.unwrap_or_else(|| {
-        expr.as_ref()
-            .expect("var declaration must have type or initializer")
-            .data_type
-            .clone()
-    });
+    let data_type = match (data_type, &expr) {
+        (Some(data_type), _) => data_type,
+        (None, Some(expr)) => expr.data_type.clone(),
+        (None, None) => {
+            return Err(ParseError::at(
+                &decl_pair,
+                "var declaration must have type or initializer",
+            ))
+        }
+    };
 
     let mut ref_view = MemoryViewType::new(data_type.clone(), qualifier.storage_class);
     if let Some(access_mode) = qualifier.access_mode {
@@ -240,16 +406,16 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
 
     env.insert_var(name.clone(), DataType::Ref(ref_view));
 
-    GlobalVarDecl {
+    Ok(GlobalVarDecl {
         attrs,
         qualifier: Some(qualifier),
         name,
         data_type,
         initializer: expr,
-    }
+    })
 }
 
-fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Rc<StructDecl> {
+fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<Rc<StructDecl>, ParseError> {
     let mut pairs = pair.into_inner();
     let name = pairs.next().unwrap().as_str().to_owned();
     let members = pairs
@@ -262,122 +428,167 @@ fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Rc<StructDecl>
                 .flat_map(|pair| {
                     pair.into_inner().map(|pair| {
                         let mut pairs = pair.into_inner();
-                        let name = pairs.next().unwrap().as_str();
-                        let arg = pairs.next().unwrap().as_str();
-                        match name {
-                            "align" => StructMemberAttr::Align(arg.parse().unwrap()),
-                            _ => panic!("invalid struct member attribute: {}", name),
+                        let name = pairs.next().unwrap();
+                        let arg = pairs.next().unwrap();
+                        match name.as_str() {
+                            "align" => Ok(StructMemberAttr::Align(parse_attr_arg(&arg)?)),
+                            _ => Err(ParseError::at(
+                                &name,
+                                format!("invalid struct member attribute: {}", name.as_str()),
+                            )),
                         }
                     })
                 })
-                .collect();
+                .collect::<Result<_, _>>()?;
 
             let name = pairs.next().unwrap().as_str().to_owned();
-            let data_type = parse_type_decl(pairs.next().unwrap(), env);
-            StructMember::new(attrs, name, data_type)
+            let data_type = parse_type_decl(pairs.next().unwrap(), env)?;
+            Ok(StructMember::new(attrs, name, data_type))
         })
-        .collect();
+        .collect::<Result<_, ParseError>>()?;
     let decl = StructDecl::new(name.clone(), members);
     env.insert_struct(name, decl.clone());
     env.insert_func(decl.name.clone(), DataType::Struct(decl.clone()));
 
-    decl
+    Ok(decl)
+}
+
+fn parse_fn_attr(pair: Pair<Rule>, env: &Environment) -> Result<FnAttr, ParseError> {
+    let mut pairs = pair.into_inner();
+    let name = pairs.next().unwrap();
+    Ok(match name.as_str() {
+        "compute" => FnAttr::Stage(ShaderStage::Compute),
+        "vertex" => FnAttr::Stage(ShaderStage::Vertex),
+        "fragment" => FnAttr::Stage(ShaderStage::Fragment),
+        "stage" => {
+            let arg = pairs.next().unwrap();
+            FnAttr::Stage(match arg.as_str() {
+                "compute" => ShaderStage::Compute,
+                "vertex" => ShaderStage::Vertex,
+                "fragment" => ShaderStage::Fragment,
+                _ => return Err(ParseError::at(&arg, "invalid argument for stage attr")),
+            })
+        }
+        "workgroup_size" => {
+            let mut args = pairs
+                .map(|arg| parse_workgroup_size_arg(arg, env))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if args.len() > 3 {
+                return Err(ParseError::at(
+                    &name,
+                    "workgroup_size attr takes at most 3 arguments",
+                ));
+            }
+
+            if args.len() == 1 {
+                match args.remove(0) {
+                    WorkgroupSizeArg::Lit(v) => FnAttr::LitWorkgroupSize(v),
+                    WorkgroupSizeArg::Var(v) => FnAttr::VarWorkgroupSize(v),
+                }
+            } else {
+                FnAttr::MultiWorkgroupSize(args)
+            }
+        }
+        _ => {
+            return Err(ParseError::at(
+                &name,
+                format!("invalid function attribute: {}", name.as_str()),
+            ))
+        }
+    })
+}
+
+fn parse_workgroup_size_arg(
+    pair: Pair<Rule>,
+    env: &Environment,
+) -> Result<WorkgroupSizeArg, ParseError> {
+    let expr = match pair.as_rule() {
+        Rule::literal_expression => parse_literal_expression(pair.clone())?,
+        Rule::ident => parse_var_expression(pair.clone(), env)?,
+        _ => return Err(ParseError::at(&pair, "invalid argument for workgroup_size attr")),
+    };
+
+    match expr.expr {
+        Expr::Lit(Lit::I32(v)) if v >= 0 => Ok(WorkgroupSizeArg::Lit(v as u32)),
+        Expr::Lit(Lit::U32(v)) => Ok(WorkgroupSizeArg::Lit(v)),
+        Expr::Var(VarExpr { ident: v }) => Ok(WorkgroupSizeArg::Var(v)),
+        _ => Err(ParseError::at(&pair, "invalid argument for workgroup_size attr")),
+    }
 }
 
-fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
+fn parse_param(pair: Pair<Rule>, env: &Environment) -> Result<FnInput, ParseError> {
+    let param_pair = pair.clone();
+    let pairs = pair.into_inner();
+    let mut attrs: Vec<FnInputAttr> = vec![]; // Make an empty vector and add Attrs as we read them
+    let mut data_type: Option<DataType> = None;
+    let mut name: Option<String> = None;
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::attribute => {
+                // append an attr to the list
+                let mut attr_pair = pair.into_inner();
+                let attr_type = attr_pair.next().unwrap();
+                let attr_arg = attr_pair.next(); // Dont unwrap till we match
+                match (attr_type.as_str(), attr_arg) {
+                    ("builtin", Some(arg)) => {
+                        attrs.push(FnInputAttr::Builtin(arg.as_str().to_owned()));
+                    }
+                    _ => {
+                        return Err(ParseError::at(
+                            &attr_type,
+                            format!("unimplemented param attribute: {}", attr_type.as_str()),
+                        ))
+                    }
+                }
+            }
+            Rule::ident => {
+                // Grab the ident and set to name
+                name = Some(pair.as_str().to_owned());
+            }
+            Rule::type_decl => {
+                // call parse_type_decl
+                data_type = Some(parse_type_decl(pair, env)?);
+            }
+            _ => return Err(ParseError::at(&pair, "invalid argument for param")),
+        }
+    }
+
+    Ok(FnInput {
+        attrs,
+        name: name.ok_or_else(|| ParseError::at(&param_pair, "param is missing an identifier"))?,
+        data_type: data_type
+            .ok_or_else(|| ParseError::at(&param_pair, "param is missing a data type"))?,
+    })
+}
+
+fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> Result<FnDecl, ParseError> {
     let mut pairs = pair.into_inner().peekable();
 
     let attrs = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
-        .flat_map(|pair| {
-            pair.into_inner().map(|pair| {
-                let mut pairs = pair.into_inner();
-                let name = pairs.next().unwrap().as_str();
-                match name {
-                    "compute" => FnAttr::Stage(ShaderStage::Compute),
-                    "vertex" => FnAttr::Stage(ShaderStage::Vertex),
-                    "fragment" => FnAttr::Stage(ShaderStage::Fragment),
-                    "stage" => FnAttr::Stage(match pairs.next().unwrap().as_str() {
-                        "compute" => ShaderStage::Compute,
-                        "vertex" => ShaderStage::Vertex,
-                        "fragment" => ShaderStage::Fragment,
-                        _ => panic!("invalid argument for stage attr"),
-                    }),
-                    "workgroup_size" => {
-                      let next_pair = pairs.next().unwrap();
-                      let expr = match next_pair.as_rule() {
-                        Rule::literal_expression => {
-                          parse_literal_expression(next_pair)
-                        },
-                        Rule::ident => {
-                          parse_var_expression(next_pair, env)
-                        }
-                        _ => panic!{"invalid argument for workgroup_size attr"}
-                      };
-                      match expr.expr {
-                        Expr::Lit(Lit::I32(v)) => FnAttr::LitWorkgroupSize(v.try_into().unwrap()),
-                        Expr::Lit(Lit::U32(v)) => FnAttr::LitWorkgroupSize(v),
-                        Expr::Var(VarExpr { ident: v }) => FnAttr::VarWorkgroupSize(v),
-                        _ => panic!("invalid argument for workgroup_size attr"),
-                      }
-                    },
-                    _ => panic!("invalid function attribute: {}", name),
-                }
-            })
-        })
-        .collect();
+        .flat_map(|pair| pair.into_inner().map(|pair| parse_fn_attr(pair, env)))
+        .collect::<Result<_, _>>()?;
 
     let name = pairs.next().unwrap().as_str().to_owned();
     let inputs = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::param)
-        .map(|pair| {
-            let pairs = pair.into_inner();
-            let mut attrs: Vec<FnInputAttr> = vec![]; // Make an empty vector and add Attrs as we read them
-            let mut data_type: Option<DataType> = None;
-            let mut name: Option<String> = None; 
-            for pair in pairs {
-                match pair.as_rule() {
-                    Rule::attribute => {
-                        // append an attr to the list
-                        let mut attr_pair = pair.into_inner();
-                        let attr_type = attr_pair.next().unwrap();
-                        let attr_arg = attr_pair.next(); // Dont unwrap till we match
-                        match attr_type.as_str() {
-                            "builtin" => {
-                                attrs.push(FnInputAttr::Builtin(attr_arg.unwrap().as_str().to_owned()));
-                            },
-                            _ => panic!("Unimplemented param attribute"),
-                        }
-                    },
-                    Rule::ident => {
-                        // Grab the ident and set to name
-                        name = Some(pair.as_str().to_owned());
-                    },
-                    Rule::type_decl => {
-                        // call parse_type_decl
-                        data_type = Some(parse_type_decl(pair, env));
-                    },
-                    _ => panic!("Invalid argument for param"),
-                }
-            }
-            FnInput {
-                attrs,
-                name: name.expect("Param is missing an identifier"),
-                data_type: data_type.expect("Param is missing a data type"),
-            }
-        })
-        .collect::<Vec<_>>();
+        .map(|pair| parse_param(pair, env))
+        .collect::<Result<Vec<_>, _>>()?;
 
     let output = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::type_decl)
-        .map(|pair| FnOutput {
-            attrs: vec![],
-            data_type: parse_type_decl(pair, env),
+        .map(|pair| -> Result<_, ParseError> {
+            Ok(FnOutput {
+                attrs: vec![],
+                data_type: parse_type_decl(pair, env)?,
+            })
         })
-        .next();
+        .next()
+        .transpose()?;
 
     if let Some(output) = &output {
         env.insert_func(name.clone(), output.data_type.clone());
@@ -388,18 +599,18 @@ fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
         env.insert_var(param.name.clone(), param.data_type.clone());
     }
 
-    let body = parse_compound_statement(pairs.next().unwrap(), &env).into_compount_statement();
+    let body = parse_compound_statement(pairs.next().unwrap(), &env)?.into_compount_statement();
 
-    FnDecl {
+    Ok(FnDecl {
         attrs,
         name,
         inputs,
         output,
         body,
-    }
+    })
 }
 
-fn parse_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
+fn parse_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement, ParseError> {
     let pair = if pair.as_rule() == Rule::statement {
         pair.into_inner().next().unwrap()
     } else {
@@ -414,33 +625,52 @@ fn parse_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
         Rule::if_statement => parse_if_statement(pair, env),
         Rule::return_statement => parse_return_statement(pair, env),
         Rule::loop_statement => parse_loop_statement(pair, env),
-        Rule::break_statement => Statement::Break,
-        Rule::continue_statement => Statement::Continue,
-        Rule::fallthrough_statement => Statement::Fallthrough,
+        Rule::while_statement => parse_while_statement(pair, env),
+        Rule::break_statement => Ok(Statement::Break),
+        Rule::continue_statement => Ok(Statement::Continue),
+        Rule::fallthrough_statement => Ok(Statement::Fallthrough),
         Rule::switch_statement => parse_switch_statement(pair, env),
         Rule::for_statement => parse_for_statement(pair, env),
         Rule::call_statement => parse_call_statement(pair, env),
+        Rule::const_assert_statement => Ok(parse_const_assert_statement(pair, env)?.into()),
         _ => unreachable!(),
     }
 }
 
-fn parse_let_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
-    let mut pairs = pair.into_inner();
+fn parse_let_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement, ParseError> {
+    let mut pairs = pair.into_inner().peekable();
+
+    let is_const = pairs
+        .next_if(|pair| pair.as_rule() == Rule::const_keyword)
+        .is_some();
+
     let ident = pairs.next().unwrap().as_str().to_owned();
-    let initializer = parse_expression(pairs.next().unwrap(), env);
-    let stmt = LetDeclStatement::new(ident.clone(), initializer);
+
+    let data_type = match pairs.next_if(|pair| pair.as_rule() == Rule::type_decl) {
+        Some(pair) => Some(parse_type_decl(pair, env)?),
+        None => None,
+    };
+
+    let initializer = parse_expression(pairs.next().unwrap(), env)?;
+    let stmt = LetDeclStatement {
+        is_const,
+        data_type,
+        ..LetDeclStatement::new(ident.clone(), initializer)
+    };
+
     env.insert_var(ident, stmt.inferred_type().clone());
-    stmt.into()
+    Ok(stmt.into())
 }
 
-fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
+fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement, ParseError> {
+    let stmt_pair = pair.clone();
     let mut pairs = pair.into_inner();
     let ident = pairs.next().unwrap().as_str().to_owned();
 
     let mut pair = pairs.next();
 
     let specified_type = if let Some(Rule::type_decl) = pair.as_ref().map(|it| it.as_rule()) {
-        let ty = parse_type_decl(pair.unwrap(), env);
+        let ty = parse_type_decl(pair.unwrap(), env)?;
         pair = pairs.next();
         Some(ty)
     } else {
@@ -448,25 +678,32 @@ fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
     };
 
     let initializer = if let Some(Rule::expression) = pair.as_ref().map(|it| it.as_rule()) {
-        Some(parse_expression(pair.unwrap(), env))
+        Some(parse_expression(pair.unwrap(), env)?)
     } else {
         None
     };
 
+    if specified_type.is_none() && initializer.is_none() {
+        return Err(ParseError::at(
+            &stmt_pair,
+            "var declaration must have type or initializer",
+        ));
+    }
+
     let stmt = VarDeclStatement::new(ident.clone(), specified_type, initializer);
 
     let ref_view = MemoryViewType::new(stmt.inferred_type().clone(), StorageClass::Function);
     env.insert_var(ident, DataType::Ref(ref_view));
 
-    stmt.into()
+    Ok(stmt.into())
 }
 
-fn parse_assignment_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_assignment_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
     let mut pairs = pair.into_inner();
 
-    let lhs = parse_assignment_lhs(pairs.next().unwrap(), env);
+    let lhs = parse_assignment_lhs(pairs.next().unwrap(), env)?;
     let op = pairs.next().unwrap();
-    let rhs = parse_expression(pairs.next().unwrap(), env);
+    let rhs = parse_expression(pairs.next().unwrap(), env)?;
 
     let op = op.into_inner().next().unwrap();
     let op = match op.as_rule() {
@@ -480,35 +717,37 @@ fn parse_assignment_statement(pair: Pair<Rule>, env: &Environment) -> Statement
             Rule::op_and_equal => AssignmentOp::And,
             Rule::op_or_equal => AssignmentOp::Or,
             Rule::op_xor_equal => AssignmentOp::Xor,
+            Rule::op_shl_equal => AssignmentOp::LShift,
+            Rule::op_shr_equal => AssignmentOp::RShift,
             _ => unreachable!(),
         },
         _ => unreachable!(),
     };
 
-    AssignmentStatement::new(lhs, op, rhs).into()
+    Ok(AssignmentStatement::new(lhs, op, rhs).into())
 }
 
-fn parse_assignment_lhs(pair: Pair<Rule>, env: &Environment) -> AssignmentLhs {
-    match pair.as_rule() {
+fn parse_assignment_lhs(pair: Pair<Rule>, env: &Environment) -> Result<AssignmentLhs, ParseError> {
+    Ok(match pair.as_rule() {
         Rule::lhs_phony => AssignmentLhs::Phony,
-        Rule::lhs_expression => AssignmentLhs::Expr(parse_lhs_expression(pair, env)),
+        Rule::lhs_expression => AssignmentLhs::Expr(parse_lhs_expression(pair, env)?),
         _ => unreachable!(),
-    }
+    })
 }
 
-fn parse_compound_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_compound_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
     let mut inner_env = env.clone();
-    Statement::Compound(
+    Ok(Statement::Compound(
         pair.into_inner()
             .map(|pair| parse_statement(pair, &mut inner_env))
-            .collect(),
-    )
+            .collect::<Result<_, _>>()?,
+    ))
 }
 
-fn parse_if_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_if_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
     let mut pairs = pair.into_inner();
-    let condition = parse_paren_expression(pairs.next().unwrap(), env);
-    let block = parse_compound_statement(pairs.next().unwrap(), env).into_compount_statement();
+    let condition = parse_paren_expression(pairs.next().unwrap(), env)?;
+    let block = parse_compound_statement(pairs.next().unwrap(), env)?.into_compount_statement();
 
     let els = pairs
         .next()
@@ -517,95 +756,171 @@ fn parse_if_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
             Rule::if_statement => parse_if_statement(pair, env),
             _ => unreachable!(),
         })
+        .transpose()?
         .map(|stmt| match stmt {
             Statement::Compound(stmts) => Else::Else(stmts),
             Statement::If(stmt) => Else::If(stmt),
             _ => unreachable!(),
         });
 
-    IfStatement::new(condition, block).with_else(els).into()
+    Ok(IfStatement::new(condition, block).with_else(els).into())
 }
 
-fn parse_return_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_return_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
     let expression = pair
         .into_inner()
         .next()
-        .map(|pair| parse_expression(pair, env));
+        .map(|pair| parse_expression(pair, env))
+        .transpose()?;
 
     if let Some(value) = expression {
-        ReturnStatement::new(value).into()
+        Ok(ReturnStatement::new(value).into())
     } else {
-        ReturnStatement::none().into()
+        Ok(ReturnStatement::none().into())
     }
 }
 
-fn parse_loop_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_loop_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
+    // The continuing block is able to access declarations from the loop body, so they must share
+    // the same scope
+    let mut inner_env = env.clone();
+    let mut pairs = pair.into_inner().peekable();
+
+    let body = pairs
+        .by_ref()
+        .peeking_take_while(|pair| pair.as_rule() == Rule::statement)
+        .map(|pair| parse_statement(pair, &mut inner_env))
+        .collect::<Result<_, _>>()?;
+
+    let continuing = pairs
+        .next()
+        .map(|pair| parse_continuing_statement(pair, &inner_env))
+        .transpose()?;
+
+    Ok(LoopStatement::new(body).with_continuing(continuing).into())
+}
+
+fn parse_continuing_statement(
+    pair: Pair<Rule>,
+    env: &Environment,
+) -> Result<ContinuingStatement, ParseError> {
+    let mut inner_env = env.clone();
+    let mut pairs = pair.into_inner().peekable();
+
+    let body = pairs
+        .by_ref()
+        .peeking_take_while(|pair| pair.as_rule() == Rule::statement)
+        .map(|pair| parse_statement(pair, &mut inner_env))
+        .collect::<Result<_, _>>()?;
+
+    let break_if = pairs
+        .next()
+        .map(|pair| parse_expression(pair.into_inner().next().unwrap(), &inner_env))
+        .transpose()?;
+
+    Ok(ContinuingStatement::new(body, break_if))
+}
+
+fn parse_while_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
     let mut pairs = pair.into_inner();
-    let block = parse_compound_statement(pairs.next().unwrap(), env).into_compount_statement();
-    LoopStatement::new(block).into()
+    let condition = parse_expression(pairs.next().unwrap(), env)?;
+    let body = parse_compound_statement(pairs.next().unwrap(), env)?.into_compount_statement();
+    Ok(WhileStatement::new(condition, body).into())
 }
 
-fn parse_switch_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_switch_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
+    let stmt_pair = pair.clone();
     let mut pairs = pair.into_inner();
 
-    let expr = parse_expression(pairs.next().unwrap(), env);
+    let expr = parse_expression(pairs.next().unwrap(), env)?;
 
     let mut cases = vec![];
+    let mut default_selectors = vec![];
     let mut default = None;
 
     for pair in pairs {
-        let mut pairs = pair.into_inner();
-        let pair = pairs.next().unwrap();
+        let mut pairs = pair.into_inner().peekable();
+
+        let mut selectors = vec![];
+        let mut is_default = false;
+
+        for pair in pairs
+            .by_ref()
+            .peeking_take_while(|pair| pair.as_rule() != Rule::compound_statement)
+        {
+            match pair.as_rule() {
+                Rule::expression => selectors.push(parse_expression(pair, env)?),
+                Rule::default_selector => is_default = true,
+                _ => unreachable!(),
+            }
+        }
 
-        if pair.as_rule() == Rule::expression {
-            let selector = parse_expression(pair, env);
-            let body =
-                parse_compound_statement(pairs.next().unwrap(), env).into_compount_statement();
-            cases.push(SwitchCase { selector, body });
+        let body = parse_compound_statement(pairs.next().unwrap(), env)?.into_compount_statement();
+
+        // Any other selectors in the `default` clause are kept so that the clause is written back
+        // out as it was, even though they would be routed to the default body anyway
+        if selectors.is_empty() || is_default {
+            default_selectors = selectors;
+            default = Some(body);
         } else {
-            default = Some(parse_compound_statement(pair, env).into_compount_statement());
+            cases.push(SwitchCase { selectors, body });
         }
     }
 
-    let default = default.expect("switch statement must have default case");
+    let default = default
+        .ok_or_else(|| ParseError::at(&stmt_pair, "switch statement must have default case"))?;
 
-    SwitchStatement::new(expr, cases, default).into()
+    Ok(SwitchStatement {
+        default_selectors,
+        ..SwitchStatement::new(expr, cases, default)
+    }
+    .into())
 }
 
-fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
+fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Result<Statement, ParseError> {
     let mut pairs = pair.into_inner();
 
     let mut pair = pairs.next().unwrap();
 
     let mut init = None;
     if pair.as_rule() == Rule::for_init {
-        match parse_statement(pair.into_inner().next().unwrap(), env) {
+        match parse_statement(pair.clone().into_inner().next().unwrap(), env)? {
             Statement::VarDecl(stmt) => {
                 init = Some(ForLoopInit::VarDecl(stmt));
             }
-            _ => panic!("only assignment statement is currently supported in for loop init"),
+            _ => {
+                return Err(ParseError::at(
+                    &pair,
+                    "only var declaration is currently supported in for loop init",
+                ))
+            }
         };
         pair = pairs.next().unwrap();
     }
 
     let mut condition = None;
     if pair.as_rule() == Rule::expression {
-        condition = Some(parse_expression(pair, env));
+        condition = Some(parse_expression(pair, env)?);
         pair = pairs.next().unwrap();
     }
 
     let mut update = None;
     if pair.as_rule() == Rule::for_update {
-        match parse_statement(pair.into_inner().next().unwrap(), env) {
+        match parse_statement(pair.clone().into_inner().next().unwrap(), env)? {
             Statement::Assignment(stmt) => {
                 update = Some(ForLoopUpdate::Assignment(stmt));
             }
-            _ => panic!("only assignment statement is currently supported in for loop init"),
+            _ => {
+                return Err(ParseError::at(
+                    &pair,
+                    "only assignment statement is currently supported in for loop update",
+                ))
+            }
         };
         pair = pairs.next().unwrap();
     }
 
-    let body = parse_compound_statement(pair, env);
+    let body = parse_compound_statement(pair, env)?;
 
     let header = ForLoopHeader {
         init,
@@ -613,20 +928,22 @@ fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
         update,
     };
 
-    ForLoopStatement::new(header, body.into_compount_statement()).into()
+    Ok(ForLoopStatement::new(header, body.into_compount_statement()).into())
 }
 
-fn parse_call_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_call_statement(pair: Pair<Rule>, env: &Environment) -> Result<Statement, ParseError> {
     let pair = pair.into_inner().next().unwrap();
     let mut pairs = pair.into_inner();
 
     let ident = pairs.next().unwrap().as_str().to_owned();
-    let args = pairs.map(|it| parse_expression(it, env)).collect();
+    let args = pairs
+        .map(|it| parse_expression(it, env))
+        .collect::<Result<_, _>>()?;
 
-    FnCallStatement::new(ident, args).into()
+    Ok(FnCallStatement::new(ident, args).into())
 }
 
-fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
+fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> Result<LhsExprNode, ParseError> {
     let mut pairs = pair.into_inner().peekable();
 
     let prefixes: Vec<_> = pairs
@@ -634,40 +951,42 @@ fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
         .peeking_take_while(|pair| pair.as_rule() != Rule::core_lhs_expression)
         .collect();
 
-    fn parse_core(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
+    fn parse_core(pair: Pair<Rule>, env: &Environment) -> Result<LhsExprNode, ParseError> {
         let pair = pair.into_inner().next().unwrap();
         match pair.as_rule() {
             Rule::lhs_expression => parse_lhs_expression(pair, env),
             Rule::ident => {
                 let ident = pair.as_str().to_owned();
-                LhsExprNode {
-                    data_type: env
-                        .var(&ident)
-                        .expect("variable must be defined before use")
-                        .clone(),
+                Ok(LhsExprNode {
+                    data_type: env.var(&ident).cloned().ok_or_else(|| {
+                        ParseError::at(
+                            &pair,
+                            format!("variable `{ident}` must be defined before use"),
+                        )
+                    })?,
                     expr: LhsExpr::Ident(ident),
-                }
+                })
             }
             _ => unreachable!(),
         }
     }
 
-    let node = parse_core(pairs.next().unwrap(), env);
-    let node = pairs.fold(node, |node, pair| {
+    let mut node = parse_core(pairs.next().unwrap(), env)?;
+    for pair in pairs {
         let pair = pair.into_inner().next().unwrap();
         let postfix = match pair.as_rule() {
-            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env))),
+            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env)?)),
             Rule::ident => Postfix::Member(pair.as_str().to_owned()),
             _ => unreachable!(),
         };
 
-        LhsExprNode {
+        node = LhsExprNode {
             data_type: postfix.type_eval(&node.data_type),
             expr: LhsExpr::Postfix(Box::new(node), postfix),
-        }
-    });
+        };
+    }
 
-    prefixes.iter().rev().fold(node, |node, pair| {
+    Ok(prefixes.iter().rev().fold(node, |node, pair| {
         let (data_type, expr) = match pair.as_rule() {
             Rule::op_address_of => (
                 UnOp::AddressOf.type_eval(&node.data_type),
@@ -681,7 +1000,7 @@ fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
         };
 
         LhsExprNode { data_type, expr }
-    })
+    }))
 }
 
 fn precedence_table() -> PrecClimber<Rule> {
@@ -712,7 +1031,7 @@ fn precedence_table() -> PrecClimber<Rule> {
     ])
 }
 
-fn parse_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
         Rule::infix_expression => parse_infix_expression(pair, env),
@@ -721,18 +1040,21 @@ fn parse_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     }
 }
 
-fn parse_infix_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_infix_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let pairs = pair.into_inner();
 
     let unary = |pair| parse_unary_expression(pair, env);
-    let infix = |l: ExprNode, op: Pair<Rule>, r: ExprNode| -> ExprNode {
-        BinOpExpr::new(op.as_rule().into(), l, r).into()
+    let infix = |l: Result<ExprNode, ParseError>,
+                 op: Pair<Rule>,
+                 r: Result<ExprNode, ParseError>|
+     -> Result<ExprNode, ParseError> {
+        Ok(BinOpExpr::new(op.as_rule().into(), l?, r?).into())
     };
 
     precedence_table().climb(pairs, unary, infix)
 }
 
-fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let mut pairs = pair.into_inner();
 
     let first_pair = pairs.next().unwrap();
@@ -750,19 +1072,19 @@ fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
         _ => unreachable!(),
     };
 
-    let expr = parse_unary_expression(pairs.next().unwrap(), env);
+    let expr = parse_unary_expression(pairs.next().unwrap(), env)?;
 
-    UnOpExpr::new(op, expr).into()
+    Ok(UnOpExpr::new(op, expr).into())
 }
 
-fn parse_singular_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_singular_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let mut pairs = pair.into_inner();
-    let mut expr = parse_primary_expression(pairs.next().unwrap(), env);
+    let mut expr = parse_primary_expression(pairs.next().unwrap(), env)?;
 
     for pf in pairs {
         let pair = pf.into_inner().next().unwrap();
         let pf = match pair.as_rule() {
-            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env))),
+            Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env)?)),
             Rule::ident => Postfix::Member(pair.as_str().to_owned()),
             _ => unreachable!(),
         };
@@ -770,10 +1092,10 @@ fn parse_singular_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
         expr = PostfixExpr::new(expr, pf).into();
     }
 
-    expr
+    Ok(expr)
 }
 
-fn parse_primary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_primary_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
         Rule::literal_expression => parse_literal_expression(pair),
@@ -786,42 +1108,50 @@ fn parse_primary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     }
 }
 
-fn parse_literal_expression(pair: Pair<Rule>) -> ExprNode {
+fn parse_literal_expression(pair: Pair<Rule>) -> Result<ExprNode, ParseError> {
     let pair = pair.into_inner().next().unwrap();
+
+    fn parse_lit<T: std::str::FromStr>(pair: &Pair<Rule>, s: &str) -> Result<T, ParseError> {
+        s.parse()
+            .map_err(|_| ParseError::at(pair, format!("invalid literal: {}", pair.as_str())))
+    }
+
     let (t, lit) = match pair.as_rule() {
-        Rule::bool_literal => (ScalarType::Bool, Lit::Bool(pair.as_str().parse().unwrap())),
+        Rule::bool_literal => (ScalarType::Bool, Lit::Bool(parse_lit(&pair, pair.as_str())?)),
         Rule::uint_literal => (
             ScalarType::U32,
-            Lit::U32(pair.as_str().trim_end_matches('u').parse().unwrap()),
+            Lit::U32(parse_lit(&pair, pair.as_str().trim_end_matches('u'))?),
         ),
-        Rule::int_literal => (ScalarType::I32, Lit::I32(pair.as_str().parse().unwrap())),
-        Rule::float_literal => (ScalarType::F32, Lit::F32(pair.as_str().parse().unwrap())),
+        Rule::int_literal => (ScalarType::I32, Lit::I32(parse_lit(&pair, pair.as_str())?)),
+        Rule::float_literal => (ScalarType::F32, Lit::F32(parse_lit(&pair, pair.as_str())?)),
         _ => unreachable!(),
     };
 
-    ExprNode {
+    Ok(ExprNode {
         data_type: DataType::Scalar(t),
         expr: Expr::Lit(lit),
-    }
+    })
 }
 
-fn parse_type_cons_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_type_cons_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let mut pairs = pair.into_inner();
     let t_decl = pairs.next().unwrap();
 
-    let t = parse_type_decl(t_decl, env);
-    let args = pairs.map(|pair| parse_expression(pair, env)).collect();
+    let t = parse_type_decl(t_decl, env)?;
+    let args = pairs
+        .map(|pair| parse_expression(pair, env))
+        .collect::<Result<_, _>>()?;
 
-    TypeConsExpr::new(t, args).into()
+    Ok(TypeConsExpr::new(t, args).into())
 }
 
-fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let mut pairs = pair.into_inner();
 
     let ident = pairs.next().unwrap();
     let args = pairs
         .map(|pair| parse_expression(pair, env))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
     struct FunSig<'a>(&'a str, &'a [ExprNode]);
 
@@ -842,23 +1172,32 @@ fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
 
     let return_type = env
         .func(ident.as_str(), args.iter().map(|arg| &arg.data_type))
-        .unwrap_or_else(|| panic!("`{}` not found", FunSig(ident.as_str(), &args)));
+        .ok_or_else(|| {
+            ParseError::at(
+                &ident,
+                format!("`{}` not found", FunSig(ident.as_str(), &args)),
+            )
+        })?;
 
-    FnCallExpr::new(ident.as_str().to_owned(), args).into_node(return_type)
+    Ok(FnCallExpr::new(ident.as_str().to_owned(), args).into_node(return_type))
 }
 
-fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
+fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> Result<DataType, ParseError> {
     let pair = pair.into_inner().next().unwrap();
 
     fn parse_t_scalar(pair: Pair<Rule>) -> ScalarType {
         pair.into_inner().next().unwrap().as_rule().into()
     }
 
-    match pair.as_rule() {
+    Ok(match pair.as_rule() {
         Rule::t_scalar => DataType::Scalar(parse_t_scalar(pair)),
         Rule::t_atomic => {
-          let t_atomic = pair.into_inner().next().unwrap();
-          DataType::Scalar(parse_t_scalar(t_atomic))
+            let t_atomic = pair.into_inner().next().unwrap();
+            DataType::Scalar(match parse_t_scalar(t_atomic) {
+                ScalarType::U32 => ScalarType::AU32,
+                ScalarType::I32 => ScalarType::AI32,
+                _ => unreachable!(),
+            })
         }
         Rule::t_vector => {
             let t_vector = pair.into_inner().next().unwrap();
@@ -871,38 +1210,66 @@ fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
 
             DataType::Vector(n, parse_t_scalar(t_vector.into_inner().next().unwrap()))
         }
+        Rule::t_matrix => {
+            let mut pairs = pair.into_inner();
+            let dims = pairs.next().unwrap().as_str().as_bytes();
+            DataType::Matrix(
+                dims[3] - b'0',
+                dims[5] - b'0',
+                parse_t_scalar(pairs.next().unwrap()),
+            )
+        }
         Rule::array_type_decl => {
             let mut pairs = pair.into_inner();
             let pair = pairs.next().unwrap();
             DataType::Array(
-                Rc::new(parse_type_decl(pair, env)),
-                pairs.next().map(|it| it.as_str().parse().unwrap()),
+                Rc::new(parse_type_decl(pair, env)?),
+                pairs
+                    .next()
+                    .map(|it| {
+                        it.as_str().parse().map_err(|_| {
+                            ParseError::at(&it, format!("invalid array size: {}", it.as_str()))
+                        })
+                    })
+                    .transpose()?,
             )
         }
         Rule::ptr_type_decl => {
             let mut pairs = pair.into_inner();
             let storage_class = parse_storage_class(pairs.next().unwrap());
-            let inner = parse_type_decl(pairs.next().unwrap(), env);
+            let inner = parse_type_decl(pairs.next().unwrap(), env)?;
             DataType::Ptr(MemoryViewType::new(inner, storage_class))
         }
-        Rule::ident => DataType::Struct(
-            env.ty(pair.as_str())
-                .unwrap_or_else(|| panic!("type not found: {}", pair.as_str()))
-                .clone(),
-        ),
-        _ => panic!("{}", pair),
-    }
+        Rule::ident => {
+            if let Some(decl) = env.ty(pair.as_str()) {
+                DataType::Struct(decl.clone())
+            } else if let Some(ty) = env.alias(pair.as_str()) {
+                ty.clone()
+            } else {
+                return Err(ParseError::at(
+                    &pair,
+                    format!("type not found: {}", pair.as_str()),
+                ));
+            }
+        }
+        _ => return Err(ParseError::at(&pair, format!("unsupported type: {}", pair.as_str()))),
+    })
 }
 
-fn parse_var_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
-    VarExpr::new(pair.as_str()).into_node(
+fn parse_var_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
+    Ok(VarExpr::new(pair.as_str()).into_node(
         env.var(pair.as_str())
-            .unwrap_or_else(|| panic!("variable `{}` must be defined before use", pair.as_str()))
+            .ok_or_else(|| {
+                ParseError::at(
+                    &pair,
+                    format!("variable `{}` must be defined before use", pair.as_str()),
+                )
+            })?
             .clone(),
-    )
+    ))
 }
 
-fn parse_paren_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+fn parse_paren_expression(pair: Pair<Rule>, env: &Environment) -> Result<ExprNode, ParseError> {
     let pair = pair.into_inner().next().unwrap();
     parse_expression(pair, env)
 }
@@ -970,15 +1337,19 @@ mod tests {
                 const SRC: &str = include_str!(concat!("tests/", stringify!($name), ".wgsl"));
                 let pairs = WGSLParser::parse(Rule::translation_unit, SRC).unwrap();
                 let pair = pairs.into_iter().next().unwrap();
-                let module = parse_translation_unit(pair, &mut Environment::new());
+                let module = parse_translation_unit(pair, &mut Environment::new()).unwrap();
                 insta::assert_debug_snapshot!(module);
             }
         };
     }
 
+    test_case!(assignments);
+    test_case!(atomics);
     test_case!(calls);
     test_case!(floats);
     test_case!(loops);
+    test_case!(matrices);
+    test_case!(modern);
     test_case!(ptrs);
     test_case!(structs);
 
@@ -987,4 +1358,40 @@ mod tests {
     test_case!(test_3);
     test_case!(test_4);
     test_case!(test_5);
+
+    #[test]
+    fn round_trip() {
+        const SRC: &str = r#"fn main() {
+    const a: u32 = 2u;
+    let b: i32 = 1;
+    switch (b) {
+        case 1, 2: {
+        }
+        case 3, default: {
+        }
+    }
+}
+
+"#;
+        let mut output = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut output, &parse(SRC))
+            .unwrap();
+        assert_eq!(output, SRC);
+    }
+
+    #[test]
+    fn undefined_variable_error() {
+        const SRC: &str = "fn main() {\n    let x = y;\n}\n";
+        let error = try_parse(SRC).unwrap_err();
+        assert_eq!((error.line, error.col), (2, 13));
+        assert_eq!(error.rule, Some(Rule::var_expression));
+    }
+
+    #[test]
+    fn syntax_error() {
+        const SRC: &str = "fn main() {\n    let x = ;\n}\n";
+        let error = try_parse(SRC).unwrap_err();
+        assert_eq!((error.line, error.col), (2, 13));
+    }
 }
@@ -0,0 +1,16 @@
+fn main() {
+    var x: i32 = 1;
+    var y: vec2<u32> = vec2<u32>(1u);
+    x += 2;
+    x -= 3;
+    x *= 4;
+    x /= 5;
+    x %= 6;
+    x &= 7;
+    x |= 8;
+    x ^= 9;
+    x <<= 10u;
+    x >>= 11u;
+    y <<= vec2<u32>(12u);
+    y >>= vec2<u32>(13u);
+}
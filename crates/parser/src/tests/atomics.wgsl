@@ -0,0 +1,24 @@
+@group(0) @binding(0)
+var<storage, read_write> counters: array<atomic<u32>, 4>;
+
+var<workgroup> shared: atomic<i32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    atomicStore(&counters[0], 1u);
+    atomicStore(&shared, -1);
+
+    let a = atomicLoad(&counters[1]);
+    let b = atomicAdd(&counters[2], 2u);
+    let c = atomicSub(&shared, 3);
+    let d = atomicMax(&shared, 4);
+    let e = atomicMin(&counters[3], a);
+    let f = atomicAnd(&counters[0], b);
+    let g = atomicOr(&shared, c);
+    let h = atomicXor(&shared, d);
+    let i = atomicExchange(&counters[1], e);
+
+    let result = atomicCompareExchangeWeak(&counters[2], f, 5u);
+    let old_value = result.old_value;
+    let exchanged = atomicCompareExchangeWeak(&shared, g, h).exchanged;
+}
@@ -0,0 +1,21 @@
+@group(0) @binding(0)
+var<storage, read_write> s_output: mat3x2<f32>;
+
+var<private> v: vec3<f32>;
+
+fn f(a: mat2x3<f32>) -> mat3x2<f32> {
+    return transpose(a);
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var m = mat2x2<f32>(vec2<f32>(1.0, 2.0), vec2<f32>(3.0, 4.0));
+    let d = determinant(m);
+    let c: vec2<f32> = m[1];
+    m[0] = c * m;
+    let p = mat2x3<f32>(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0));
+    let q = f(p) * p;
+    s_output = f(p) + f(p) * 2.0;
+    s_output[2] = c * q;
+    v = p * c + vec3<f32>(q[0].x, q[1].y, d);
+}
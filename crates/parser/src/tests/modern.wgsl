@@ -0,0 +1,37 @@
+enable f16;
+
+alias Vec = vec4<f32>;
+
+const N: u32 = 4u;
+@id(0) override scale: f32 = 2.0;
+const_assert N > 2u;
+
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 4>;
+
+@compute @workgroup_size(N, 1, 1,)
+fn main() {
+    var i = 0u;
+    while (i < N) {
+        output[i] = i;
+        i = i + 1u;
+    }
+
+    let v: Vec = Vec(scale, 0.0, 0.0, 1.0);
+    const c: u32 = N - 1u;
+
+    loop {
+        let x = i;
+        continuing {
+            i = x - 1u;
+            break if i == 0u;
+        }
+    }
+
+    switch (i32(i)) {
+        case 1, 2, {
+            const_assert N == 4u;
+        }
+        case 3, default: {}
+    }
+}
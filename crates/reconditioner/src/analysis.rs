@@ -1,6 +1,8 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
 use std::hash::Hash;
 
+use ast::fold::{self, Fold};
 use ast::*;
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
@@ -36,7 +38,7 @@ struct FnContext<'a> {
 }
 
 #[derive(Debug)]
-struct FnCall<'a>(u32, &'a str, Vec<Option<RootIdentifier>>);
+struct FnCall<'a>(u32, &'a str, Vec<Option<RootIdentifier>>, &'a [ExprNode]);
 
 impl<'a> Hash for FnCall<'a> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
@@ -75,9 +77,52 @@ impl<'a> Analysis<'a> {
     }
 }
 
+/// A call with a pointer argument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallSite {
+    /// Name of the function containing the call.
+    pub caller: String,
+    pub callee: String,
+    /// Index of the pointer argument.
+    pub arg: usize,
+    /// The pointer argument, as written in the original module.
+    pub original: String,
+}
+
+impl Display for CallSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "call to `{}` in `{}` (argument {}: `{}`)",
+            self.callee, self.caller, self.arg, self.original
+        )
+    }
+}
+
+/// A memory location which may be accessed through more than one pointer.
+struct Aliasing {
+    function: String,
+    loc: u32,
+    /// Calls which pass a pointer that may point to the location, in the order they were visited.
+    sites: Vec<CallSite>,
+}
+
 /// PRECONDITION: Functions must be defined in order, such that earlier functions do not contain
 /// calls to later functions.
 pub fn analyse(module: &Module) -> bool {
+    match check(module) {
+        Ok(()) => true,
+        Err(aliasing) => {
+            eprintln!(
+                "possible aliased access to mem loc `{}` in `{}`",
+                aliasing.loc, aliasing.function
+            );
+            false
+        }
+    }
+}
+
+fn check(module: &Module) -> Result<(), Aliasing> {
     let mut analysis = Analysis::default();
 
     let mut global_scope = Scope {
@@ -153,17 +198,56 @@ pub fn analyse(module: &Module) -> bool {
                 .find(|(access_type, _)| matches!(access_type, AccessType::Write))
             {
                 if accesses.iter().any(|it| it.1 != *root_id) {
-                    eprintln!(
-                        "possible aliased access to mem loc `{loc_id}` in `{}`",
-                        func.name
-                    );
-                    return false;
+                    return Err(Aliasing {
+                        function: func.name.clone(),
+                        loc: loc_id,
+                        sites: sites_pointing_to(&calls, &points_to, loc_id),
+                    });
                 }
             }
         }
     }
 
-    true
+    Ok(())
+}
+
+fn sites_pointing_to(
+    calls: &HashMap<&str, HashSet<FnCall>>,
+    points_to: &HashMap<&str, HashMap<u32, HashSet<u32>>>,
+    loc: u32,
+) -> Vec<CallSite> {
+    let mut calls = calls
+        .iter()
+        .flat_map(|(caller, calls)| calls.iter().map(move |call| (*caller, call)))
+        .collect::<Vec<_>>();
+
+    calls.sort_by_key(|(_, call)| call.0);
+
+    let mut sites = vec![];
+
+    for (caller, FnCall(_, callee, arg_ids, args)) in calls {
+        for (i, root_id) in arg_ids.iter().enumerate() {
+            let may_point_to_loc = match root_id {
+                Some(RootIdentifier::Mem(it)) => *it == loc,
+                Some(RootIdentifier::Param(p)) => points_to
+                    .get(caller)
+                    .and_then(|it| it.get(p))
+                    .is_some_and(|it| it.contains(&loc)),
+                None => false,
+            };
+
+            if may_point_to_loc {
+                sites.push(CallSite {
+                    caller: caller.to_owned(),
+                    callee: (*callee).to_owned(),
+                    arg: i,
+                    original: args[i].to_string(),
+                });
+            }
+        }
+    }
+
+    sites
 }
 
 // TODO: Use visitor pattern to avoid duplicating code with analysis in harness?
@@ -221,7 +305,22 @@ fn visit_stmt<'a>(
                 visit_expr(analysis, scope, cx, value);
             }
         }
-        Statement::Loop(stmt) => visit_stmt_block(analysis, scope, cx, &stmt.body),
+        Statement::Loop(stmt) => {
+            // The continuing block shares a scope with the loop body
+            let mut scope = scope.clone();
+
+            for stmt in &stmt.body {
+                visit_stmt(analysis, &mut scope, cx, stmt);
+            }
+
+            if let Some(continuing) = &stmt.continuing {
+                visit_stmt_block(analysis, &mut scope, cx, &continuing.body);
+
+                if let Some(break_if) = &continuing.break_if {
+                    visit_expr(analysis, &mut scope, cx, break_if);
+                }
+            }
+        }
         Statement::Break => {}
         Statement::Switch(stmt) => {
             visit_expr(analysis, scope, cx, &stmt.selector);
@@ -269,6 +368,11 @@ fn visit_stmt<'a>(
         }
         Statement::Continue => {}
         Statement::Fallthrough => {}
+        Statement::While(stmt) => {
+            visit_expr(analysis, scope, cx, &stmt.condition);
+            visit_stmt_block(analysis, scope, cx, &stmt.body);
+        }
+        Statement::ConstAssert(_) => {}
     }
 }
 
@@ -417,7 +521,7 @@ fn visit_function_call<'a>(
             .calls
             .entry(cx.name)
             .or_default()
-            .insert(FnCall(id, ident, arg_ids));
+            .insert(FnCall(id, ident, arg_ids, args));
     }
 }
 
@@ -429,3 +533,329 @@ fn find_pointer_expr_root(node: &ExprNode) -> &str {
         _ => unreachable!("invalid subexpression encountered in pointer expression"),
     }
 }
+
+pub struct RepairResult {
+    pub ast: Module,
+    /// Calls whose pointer argument was replaced with a pointer to a fresh copy of its value, with
+    /// one entry for each call that was rewritten.
+    pub rewritten: Vec<CallSite>,
+}
+
+/// Rewrites call sites which may cause invalid pointer aliasing, until the module is accepted by
+/// [`analyse`].
+///
+/// Each offending pointer argument is copied into a fresh variable, and the call is passed a
+/// pointer to the copy instead. Writes through the pointer are therefore not visible to the caller.
+///
+/// Calls are identified by the caller, the callee and the source of the pointer argument, so any
+/// other calls in the same function which pass an identical argument to the same callee are
+/// rewritten along with the offending one (they contribute to the same aliasing).
+///
+/// Returns `None` if the module couldn't be repaired, e.g. if the only offending calls are in a
+/// for loop header where there is nowhere to put the copy.
+pub fn repair(mut module: Module) -> Option<RepairResult> {
+    let mut rewritten = vec![];
+    let mut next_copy = 0;
+
+    'repair: loop {
+        let aliasing = match check(&module) {
+            Ok(()) => {
+                return Some(RepairResult {
+                    ast: module,
+                    rewritten,
+                })
+            }
+            Err(aliasing) => aliasing,
+        };
+
+        for site in aliasing.sites {
+            let mut rewriter = Rewriter::new(&site, &mut next_copy);
+
+            module = rewriter.fold_module(module);
+
+            if !rewriter.rewritten.is_empty() {
+                module.vars.append(&mut rewriter.globals);
+                rewritten.append(&mut rewriter.rewritten);
+                continue 'repair;
+            }
+        }
+
+        return None;
+    }
+}
+
+/// Replaces the pointer argument at a call site with a pointer to a fresh copy of its value.
+///
+/// Every call in the caller which passes the same argument to the same callee is rewritten, since
+/// they all contribute to the same aliasing, and each one is recorded separately.
+///
+/// Copies are inserted before the statement containing the call, so calls in positions which are
+/// not evaluated exactly once before that statement are left alone: for loop headers, `while` and
+/// `else if` conditions, and `break if` conditions.
+struct Rewriter<'a> {
+    site: &'a CallSite,
+    next_copy: &'a mut u32,
+    function: String,
+    /// Statements to insert before the statement currently being rewritten.
+    pending: Vec<Statement>,
+    /// Private variables to add to the module, for copies of private pointer arguments.
+    globals: Vec<GlobalVarDecl>,
+    /// Calls which have been rewritten so far.
+    rewritten: Vec<CallSite>,
+}
+
+impl<'a> Rewriter<'a> {
+    fn new(site: &'a CallSite, next_copy: &'a mut u32) -> Self {
+        Rewriter {
+            site,
+            next_copy,
+            function: String::new(),
+            pending: vec![],
+            globals: vec![],
+            rewritten: vec![],
+        }
+    }
+
+    fn rewrite_args(&mut self, ident: &str, mut args: Vec<ExprNode>) -> Vec<ExprNode> {
+        if self.function != self.site.caller || ident != self.site.callee {
+            return args;
+        }
+
+        if let Some(arg) = args.get_mut(self.site.arg) {
+            if arg.to_string() == self.site.original {
+                if let Some(copy) = self.copy(arg) {
+                    *arg = copy;
+                    self.rewritten.push(self.site.clone());
+                }
+            }
+        }
+
+        args
+    }
+
+    fn copy(&mut self, arg: &ExprNode) -> Option<ExprNode> {
+        let DataType::Ptr(view) = &arg.data_type else {
+            return None;
+        };
+
+        let value = match &arg.expr {
+            Expr::UnOp(UnOpExpr {
+                op: UnOp::AddressOf,
+                inner,
+            }) => inner.as_ref().clone(),
+            _ => UnOpExpr::new(UnOp::Deref, arg.clone()).into(),
+        };
+
+        let name = format!("_wgslsmith_alias_copy_{}", self.next_copy);
+        let ref_type = DataType::Ref(view.clone());
+
+        match view.storage_class {
+            StorageClass::Function => {
+                self.pending
+                    .push(VarDeclStatement::new(&name, None, Some(value)).into());
+            }
+            StorageClass::Private => {
+                self.globals.push(GlobalVarDecl {
+                    attrs: vec![],
+                    qualifier: Some(VarQualifier {
+                        storage_class: StorageClass::Private,
+                        access_mode: None,
+                    }),
+                    name: name.clone(),
+                    data_type: view.inner.as_ref().clone(),
+                    initializer: None,
+                });
+
+                self.pending.push(
+                    AssignmentStatement::new(
+                        AssignmentLhs::name(&name, ref_type.clone()),
+                        AssignmentOp::Simple,
+                        value,
+                    )
+                    .into(),
+                );
+            }
+            // Copying anything else would change which invocations can see the value
+            _ => return None,
+        }
+
+        *self.next_copy += 1;
+
+        Some(UnOpExpr::new(UnOp::AddressOf, VarExpr::new(name).into_node(ref_type)).into())
+    }
+}
+
+impl<'a> Fold for Rewriter<'a> {
+    fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+        self.function = decl.name.clone();
+        fold::walk_fn_decl(self, decl)
+    }
+
+    fn fold_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        // Copies made while rewriting the statement containing this block belong before it
+        let outer = std::mem::take(&mut self.pending);
+        let mut stmts = Vec::with_capacity(block.len());
+
+        for stmt in block {
+            let stmt = self.fold_stmt(stmt);
+            stmts.append(&mut self.pending);
+            stmts.push(stmt);
+        }
+
+        self.pending = outer;
+        stmts
+    }
+
+    fn fold_for_loop_header(&mut self, header: ForLoopHeader) -> ForLoopHeader {
+        header
+    }
+
+    fn fold_while_stmt(&mut self, stmt: WhileStatement) -> WhileStatement {
+        WhileStatement {
+            body: self.fold_block(stmt.body),
+            ..stmt
+        }
+    }
+
+    fn fold_else(&mut self, els: Else) -> Else {
+        match els {
+            // The condition is only evaluated once the preceding conditions have been
+            Else::If(stmt) => Else::If(IfStatement {
+                body: self.fold_block(stmt.body),
+                else_: stmt.else_.map(|els| Box::new(self.fold_else(*els))),
+                ..stmt
+            }),
+            Else::Else(stmts) => Else::Else(self.fold_block(stmts)),
+        }
+    }
+
+    fn fold_continuing_stmt(&mut self, stmt: ContinuingStatement) -> ContinuingStatement {
+        ContinuingStatement {
+            body: self.fold_block(stmt.body),
+            ..stmt
+        }
+    }
+
+    fn fold_fn_call_stmt(&mut self, stmt: FnCallStatement) -> FnCallStatement {
+        let stmt = fold::walk_fn_call_stmt(self, stmt);
+        FnCallStatement {
+            args: self.rewrite_args(&stmt.ident, stmt.args),
+            ident: stmt.ident,
+        }
+    }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        let node = fold::walk_expr(self, node);
+
+        let expr = match node.expr {
+            Expr::FnCall(call) => FnCallExpr {
+                args: self.rewrite_args(&call.ident, call.args),
+                ident: call.ident,
+            }
+            .into(),
+            expr => expr,
+        };
+
+        ExprNode { expr, ..node }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(module: &Module) -> String {
+        let mut output = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut output, module)
+            .unwrap();
+        output
+    }
+
+    fn site(arg: usize, original: &str) -> CallSite {
+        CallSite {
+            caller: "main".to_owned(),
+            callee: "f".to_owned(),
+            arg,
+            original: original.to_owned(),
+        }
+    }
+
+    #[test]
+    fn function_scope_aliasing_is_repaired() {
+        let module = parser::parse(
+            "fn f(a: ptr<function, i32>, b: ptr<function, i32>) {\n\
+                *a = *b + 1;\n\
+            }\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {\n\
+                var x = 1;\n\
+                f(&x, &x);\n\
+            }\n",
+        );
+
+        assert!(check(&module).is_err());
+
+        let result = repair(module).unwrap();
+        let output = write(&result.ast);
+
+        assert!(check(&result.ast).is_ok(), "{output}");
+        assert_eq!(result.rewritten, [site(0, "&x")]);
+        assert!(
+            output.contains("var _wgslsmith_alias_copy_0 = x;"),
+            "{output}"
+        );
+        assert!(
+            output.contains("f(&_wgslsmith_alias_copy_0, &x);"),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn private_aliasing_is_repaired() {
+        let module = parser::parse(
+            "var<private> g: i32;\n\
+            fn f(a: ptr<private, i32>) {\n\
+                *a = g + 1;\n\
+            }\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {\n\
+                f(&g);\n\
+            }\n",
+        );
+
+        assert!(check(&module).is_err());
+
+        let result = repair(module).unwrap();
+        let output = write(&result.ast);
+
+        assert!(check(&result.ast).is_ok(), "{output}");
+        assert_eq!(result.rewritten, [site(0, "&g")]);
+        assert!(
+            output.contains("var<private> _wgslsmith_alias_copy_0: i32;"),
+            "{output}"
+        );
+        assert!(output.contains("_wgslsmith_alias_copy_0 = g;"), "{output}");
+        assert!(output.contains("f(&_wgslsmith_alias_copy_0);"), "{output}");
+    }
+
+    #[test]
+    fn identical_calls_are_each_listed() {
+        let module = parser::parse(
+            "fn f(a: ptr<function, i32>, b: ptr<function, i32>) {\n\
+                *a = *b + 1;\n\
+            }\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {\n\
+                var x = 1;\n\
+                f(&x, &x);\n\
+                f(&x, &x);\n\
+            }\n",
+        );
+
+        let result = repair(module).unwrap();
+
+        assert!(check(&result.ast).is_ok());
+        assert_eq!(result.rewritten, [site(0, "&x"), site(0, "&x")]);
+    }
+}
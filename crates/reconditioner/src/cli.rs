@@ -3,7 +3,7 @@ use std::io::Read;
 
 use clap::{Parser, ValueEnum};
 
-use crate::analysis;
+use crate::{analysis, BuiltinFamily};
 
 #[derive(Parser)]
 pub struct Options {
@@ -23,6 +23,20 @@ pub struct Options {
         require_value_delimiter(true)
     )]
     pub enable: Vec<Feature>,
+
+    /// Builtin families whose arguments shouldn't be sanitised.
+    #[clap(
+        long,
+        value_enum,
+        action,
+        use_value_delimiter(true),
+        require_value_delimiter(true)
+    )]
+    pub skip_builtins: Vec<BuiltinFamily>,
+
+    /// Path at which to write a json report of where each safety check came from.
+    #[clap(long, action)]
+    pub provenance: Option<String>,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -32,7 +46,7 @@ pub enum Feature {
 
 pub fn run(options: Options) -> eyre::Result<()> {
     let input = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&input);
+    let ast = parser::try_parse(&input)?;
 
     let result = analysis::analyse(&ast);
     if !result {
@@ -47,8 +61,15 @@ pub fn run(options: Options) -> eyre::Result<()> {
         rec_opts.only_loops = true;
     }
 
+    rec_opts.skip_builtins = options.skip_builtins;
+    rec_opts.provenance = options.provenance.is_some();
+
     let result = crate::recondition_with(ast, rec_opts);
 
+    if let (Some(path), Some(provenance)) = (&options.provenance, &result.provenance) {
+        std::fs::write(path, serde_json::to_string_pretty(provenance)?)?;
+    }
+
     struct Output(Box<dyn std::io::Write>);
 
     impl std::fmt::Write for Output {
@@ -65,7 +86,7 @@ pub fn run(options: Options) -> eyre::Result<()> {
     };
 
     ast::writer::Writer::default()
-        .write_module(&mut Output(output), &result)
+        .write_module(&mut Output(output), &result.ast)
         .unwrap();
 
     Ok(())
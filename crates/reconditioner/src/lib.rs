@@ -2,16 +2,25 @@ mod safe_wrappers;
 
 pub mod analysis;
 pub mod cli;
+pub mod provenance;
+pub mod unrecondition;
 
 use std::collections::HashSet;
 use std::fmt::Display;
+use std::str::FromStr;
 
+use ast::fold::{self, Fold};
 use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::visit::{self, Visit};
 use ast::*;
+use clap::ValueEnum;
+use provenance::{LoopCounter, Provenance, WrapperCall};
 
 pub struct ReconditionResult {
     pub ast: Module,
     pub loop_count: u32,
+    /// Where each of the safety checks came from, if requested in the options.
+    pub provenance: Option<Provenance>,
 }
 
 #[derive(Hash, PartialEq, Eq)]
@@ -25,7 +34,10 @@ enum Wrapper {
     Times(DataType),
     Divide(DataType),
     Mod(DataType),
+    LShift(DataType),
+    RShift(DataType),
     Index(DataType),
+    Builtin(BuiltinFn, DataType),
 }
 
 impl Wrapper {
@@ -34,6 +46,10 @@ impl Wrapper {
         match self {
             Wrapper::Clamp(ty) => safe_wrappers::clamp(name, ty),
             Wrapper::Dot(ty) => safe_wrappers::dot(name, ty),
+            Wrapper::FloatOp(ty @ DataType::Matrix(_, r, t)) => {
+                let column_wrapper = Wrapper::FloatOp(DataType::Vector(*r, *t));
+                safe_wrappers::float_matrix(name, ty, column_wrapper.to_string())
+            }
             Wrapper::FloatOp(ty) => safe_wrappers::float(name, ty),
             Wrapper::FloatDivide(ty) => safe_wrappers::float_divide(name, ty),
             Wrapper::Plus(ty) => safe_wrappers::plus(name, ty),
@@ -41,7 +57,10 @@ impl Wrapper {
             Wrapper::Times(ty) => safe_wrappers::times(name, ty),
             Wrapper::Divide(ty) => safe_wrappers::divide(name, ty),
             Wrapper::Mod(ty) => safe_wrappers::modulo(name, ty),
+            Wrapper::LShift(ty) => safe_wrappers::shift(name, BinOp::LShift, ty),
+            Wrapper::RShift(ty) => safe_wrappers::shift(name, BinOp::RShift, ty),
             Wrapper::Index(ty) => safe_wrappers::index(name, ty),
+            Wrapper::Builtin(builtin, ty) => safe_wrappers::builtin(name, *builtin, ty),
         }
     }
 }
@@ -58,7 +77,10 @@ impl Display for Wrapper {
             Wrapper::Times(ty) => ("mult", ty),
             Wrapper::Divide(ty) => ("div", ty),
             Wrapper::Mod(ty) => ("mod", ty),
+            Wrapper::LShift(ty) => ("shl", ty),
+            Wrapper::RShift(ty) => ("shr", ty),
             Wrapper::Index(ty) => ("index", ty),
+            Wrapper::Builtin(builtin, ty) => (builtin.as_ref(), ty),
         };
 
         write!(f, "_wgslsmith_{name}_")?;
@@ -66,32 +88,95 @@ impl Display for Wrapper {
         match ty {
             DataType::Scalar(ty) => write!(f, "{ty}"),
             DataType::Vector(n, ty) => write!(f, "vec{n}_{ty}"),
+            DataType::Matrix(c, r, ty) => write!(f, "mat{c}x{r}_{ty}"),
             _ => unimplemented!("no wrappers available for expressions of type `{ty}`"),
         }
     }
 }
 
+/// Groups of builtins which only give well defined results for some of their inputs, and have
+/// their arguments sanitised by the reconditioner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BuiltinFamily {
+    /// `sqrt` and `inverseSqrt`.
+    Sqrt,
+    /// `log` and `log2`.
+    Log,
+    /// `acos` and `asin`.
+    InverseTrig,
+    Pow,
+    Ldexp,
+    Smoothstep,
+    /// `extractBits` and `insertBits`.
+    Bits,
+}
+
+impl BuiltinFamily {
+    /// Returns the family that `builtin` belongs to, or `None` if its arguments don't need to be
+    /// sanitised.
+    pub fn of(builtin: BuiltinFn) -> Option<BuiltinFamily> {
+        let family = match builtin {
+            BuiltinFn::Sqrt | BuiltinFn::InverseSqrt => BuiltinFamily::Sqrt,
+            BuiltinFn::Log | BuiltinFn::Log2 => BuiltinFamily::Log,
+            BuiltinFn::Acos | BuiltinFn::Asin => BuiltinFamily::InverseTrig,
+            BuiltinFn::Pow => BuiltinFamily::Pow,
+            BuiltinFn::Ldexp => BuiltinFamily::Ldexp,
+            BuiltinFn::Smoothstep => BuiltinFamily::Smoothstep,
+            BuiltinFn::ExtractBits | BuiltinFn::InsertBits => BuiltinFamily::Bits,
+            _ => return None,
+        };
+
+        Some(family)
+    }
+}
+
 #[derive(Default)]
 pub struct Options {
     pub only_loops: bool,
+    /// Builtin families whose arguments are passed through unchanged.
+    pub skip_builtins: Vec<BuiltinFamily>,
+    /// Whether to report where each of the safety checks came from.
+    pub provenance: bool,
 }
 
 pub fn recondition(ast: Module) -> Module {
-    recondition_with(ast, Options::default())
+    recondition_with(ast, Options::default()).ast
 }
 
-pub fn recondition_with(mut ast: Module, options: Options) -> Module {
+pub fn recondition_with(mut ast: Module, options: Options) -> ReconditionResult {
     let mut reconditioner = Reconditioner::new(options);
 
-    let functions = ast
+    let mut functions = ast
         .functions
         .into_iter()
-        .map(|f| reconditioner.recondition_fn(f))
+        .map(|f| reconditioner.fold_fn_decl(f))
         .collect::<Vec<_>>();
 
-    ast.functions = reconditioner
-        .wrappers
-        .iter()
+    // Wrapper calls may have been reordered or copied since they were inserted, so they are
+    // numbered by where they ended up
+    if let Some(provenance) = &mut reconditioner.provenance {
+        let (untagged, tags) = unrecondition::untag_wrapper_calls(functions);
+        functions = untagged;
+        provenance.wrapper_calls = tags
+            .into_iter()
+            .map(|(site, tag)| {
+                let inserted = &reconditioner.inserted_calls[tag];
+                WrapperCall {
+                    site,
+                    function: inserted.function.clone(),
+                    wrapper: inserted.wrapper.clone(),
+                    original: inserted.original.clone(),
+                }
+            })
+            .collect();
+    }
+
+    // Matrix wrappers call the wrappers for their columns, so they need to be declared last
+    let mut wrappers = reconditioner.wrappers.iter().collect::<Vec<_>>();
+    wrappers.sort_by_key(|it| matches!(it, Wrapper::FloatOp(DataType::Matrix(..))));
+
+    ast.functions = wrappers
+        .into_iter()
         .map(Wrapper::gen_fn_decl)
         .chain(functions)
         .collect();
@@ -109,285 +194,178 @@ pub fn recondition_with(mut ast: Module, options: Options) -> Module {
         });
     }
 
-    ast
+    ReconditionResult {
+        provenance: reconditioner.provenance,
+        loop_count: reconditioner.loop_var,
+        ast,
+    }
 }
 
 struct Reconditioner {
     loop_var: u32,
     wrappers: HashSet<Wrapper>,
     only_loops: bool,
+    skip_builtins: Vec<BuiltinFamily>,
+    provenance: Option<Provenance>,
+    /// Wrapper calls in the order that they were inserted, if provenance is enabled.
+    inserted_calls: Vec<InsertedCall>,
+    /// Name of the function being reconditioned.
+    function: String,
+    /// Source of the expression currently being reconditioned, which is recorded as the original
+    /// expression for any wrapper calls inserted around it. Only tracked if provenance is enabled.
+    original: String,
+    /// Source of a compound assignment whose target has been bound to a pointer, which is used in
+    /// place of the rewritten assignment.
+    assignment_original: Option<String>,
 }
 
-impl Reconditioner {
-    fn new(options: Options) -> Reconditioner {
-        Reconditioner {
-            loop_var: 0,
-            wrappers: HashSet::new(),
-            only_loops: options.only_loops,
-        }
+struct InsertedCall {
+    function: String,
+    wrapper: String,
+    original: String,
+}
+
+impl Fold for Reconditioner {
+    fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+        self.function = decl.name.clone();
+        fold::walk_fn_decl(self, decl)
     }
 
-    fn recondition_fn(&mut self, mut decl: FnDecl) -> FnDecl {
-        decl.body = decl
-            .body
-            .into_iter()
-            .map(|s| self.recondition_stmt(s))
-            .collect();
-        decl
-    }
-
-    fn recondition_else(&mut self, els: Else) -> Else {
-        match els {
-            Else::If(IfStatement {
-                condition,
-                body,
-                else_,
-            }) => Else::If(IfStatement {
-                condition: self.recondition_expr(condition),
-                body: body.into_iter().map(|s| self.recondition_stmt(s)).collect(),
-                else_: else_.map(|els| Box::new(self.recondition_else(*els))),
-            }),
-            Else::Else(stmts) => Else::Else(
-                stmts
-                    .into_iter()
-                    .map(|s| self.recondition_stmt(s))
-                    .collect(),
-            ),
+    fn fold_loop_stmt(&mut self, stmt: LoopStatement) -> LoopStatement {
+        LoopStatement {
+            body: self.recondition_loop_body(stmt.body),
+            continuing: stmt.continuing.map(|it| self.fold_continuing_stmt(it)),
         }
     }
 
-    fn recondition_stmt(&mut self, stmt: Statement) -> Statement {
-        match stmt {
-            Statement::LetDecl(LetDeclStatement { ident, initializer }) => {
-                LetDeclStatement::new(ident, self.recondition_expr(initializer)).into()
-            }
-            Statement::VarDecl(VarDeclStatement {
-                ident,
-                data_type,
-                initializer,
-            }) => VarDeclStatement::new(
-                ident,
-                data_type,
-                initializer.map(|e| self.recondition_expr(e)),
-            )
-            .into(),
-            Statement::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                AssignmentStatement::new(
-                    self.recondition_assignment_lhs(lhs),
-                    op,
-                    self.recondition_expr(rhs),
-                )
-                .into()
-            }
-            Statement::Compound(s) => {
-                Statement::Compound(s.into_iter().map(|s| self.recondition_stmt(s)).collect())
-            }
-            Statement::If(IfStatement {
-                condition,
-                body,
-                else_,
-            }) => IfStatement::new(
-                self.recondition_expr(condition),
-                body.into_iter().map(|s| self.recondition_stmt(s)).collect(),
-            )
-            .with_else(else_.map(|els| self.recondition_else(*els)))
-            .into(),
-            Statement::Return(ReturnStatement { value }) => ReturnStatement {
-                value: value.map(|e| self.recondition_expr(e)),
-            }
-            .into(),
-            Statement::Loop(LoopStatement { body }) => {
-                LoopStatement::new(self.recondition_loop_body(body)).into()
-            }
-            Statement::Break => Statement::Break,
-            Statement::Switch(SwitchStatement {
-                selector,
-                cases,
-                default,
-            }) => SwitchStatement::new(
-                self.recondition_expr(selector),
-                cases
-                    .into_iter()
-                    .map(|SwitchCase { selector, body }| SwitchCase {
-                        selector: self.recondition_expr(selector),
-                        body: body
-                            .into_iter()
-                            .map(|it| self.recondition_stmt(it))
-                            .collect(),
-                    })
-                    .collect(),
-                default
-                    .into_iter()
-                    .map(|it| self.recondition_stmt(it))
-                    .collect(),
-            )
-            .into(),
-            Statement::ForLoop(ForLoopStatement { header, body }) => ForLoopStatement::new(
-                ForLoopHeader {
-                    init: header.init.map(|init| self.recondition_for_init(init)),
-                    condition: header.condition.map(|e| self.recondition_expr(e)),
-                    update: header
-                        .update
-                        .map(|update| self.recondition_for_update(update)),
-                },
-                self.recondition_loop_body(body),
-            )
-            .into(),
-            Statement::FnCall(FnCallStatement { ident, args }) => {
-                Statement::FnCall(FnCallStatement::new(
-                    ident,
-                    args.into_iter()
-                        .map(|it| self.recondition_expr(it))
-                        .collect(),
-                ))
-            }
-            Statement::Continue => Statement::Continue,
-            Statement::Fallthrough => Statement::Fallthrough,
+    fn fold_while_stmt(&mut self, stmt: WhileStatement) -> WhileStatement {
+        WhileStatement {
+            condition: self.fold_expr(stmt.condition),
+            body: self.recondition_loop_body(stmt.body),
         }
     }
 
-    fn recondition_for_init(&mut self, init: ForLoopInit) -> ForLoopInit {
-        match init {
-            ForLoopInit::VarDecl(VarDeclStatement {
-                ident,
-                data_type,
-                initializer,
-            }) => ForLoopInit::VarDecl(VarDeclStatement::new(
-                ident,
-                data_type,
-                initializer.map(|e| self.recondition_expr(e)),
-            )),
+    fn fold_for_loop_stmt(&mut self, stmt: ForLoopStatement) -> ForLoopStatement {
+        ForLoopStatement {
+            header: Box::new(self.fold_for_loop_header(*stmt.header)),
+            body: self.recondition_loop_body(stmt.body),
         }
     }
 
-    fn recondition_for_update(&mut self, update: ForLoopUpdate) -> ForLoopUpdate {
-        match update {
-            ForLoopUpdate::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                ForLoopUpdate::Assignment(AssignmentStatement::new(
-                    self.recondition_assignment_lhs(lhs),
-                    op,
-                    self.recondition_expr(rhs),
-                ))
+    fn fold_const_assert_stmt(&mut self, stmt: ConstAssertStatement) -> ConstAssertStatement {
+        // Const assertions must remain const-expressions, so they can't call the wrappers
+        stmt
+    }
+
+    fn fold_stmt(&mut self, stmt: Statement) -> Statement {
+        match stmt {
+            Statement::Assignment(stmt) if !self.only_loops && is_compound_with_calls(&stmt) => {
+                self.assignment_original = Some(self.source_of(|| compound_source(&stmt)));
+                let block = bind_compound_assignment_target(stmt);
+                Statement::Compound(self.fold_block(block))
             }
+            stmt => fold::walk_stmt(self, stmt),
         }
     }
 
-    fn recondition_loop_body(&mut self, body: Vec<Statement>) -> Vec<Statement> {
-        let id = self.loop_var();
+    fn fold_assignment_stmt(&mut self, stmt: AssignmentStatement) -> AssignmentStatement {
+        let original = match self.assignment_original.take() {
+            Some(original) => original,
+            None => self.source_of(|| compound_source(&stmt)),
+        };
 
-        let counters_ty = DataType::Ref(MemoryViewType::new(
-            DataType::array(ScalarType::U32, None),
-            StorageClass::Private,
-        ));
+        let stmt = fold::walk_assignment_stmt(self, stmt);
+        if self.only_loops {
+            return stmt;
+        }
 
-        let break_check = IfStatement::new(
-            BinOpExpr::new(
-                BinOp::GreaterEqual,
-                PostfixExpr::new(
-                    VarExpr::new("LOOP_COUNTERS").into_node(counters_ty.clone()),
-                    Postfix::index(Lit::U32(id)),
-                ),
-                Lit::U32(1),
-            ),
-            vec![Statement::Break],
-        );
+        let (lhs, op) = match (&stmt.lhs, stmt.op.bin_op()) {
+            (AssignmentLhs::Expr(lhs), Some(op)) => (lhs, op),
+            _ => return stmt,
+        };
 
-        let counter_increment = AssignmentStatement::new(
-            AssignmentLhs::array_index("LOOP_COUNTERS", counters_ty.clone(), Lit::U32(id).into()),
-            AssignmentOp::Simple,
-            BinOpExpr::new(
-                BinOp::Plus,
-                PostfixExpr::new(
-                    VarExpr::new("LOOP_COUNTERS").into_node(counters_ty),
-                    Postfix::index(Lit::U32(id)),
-                ),
-                Lit::U32(1),
-            ),
-        );
+        // Compound assignments are desugared so that the operator goes through the same wrappers
+        // as a binary expression. This evaluates the target twice, so targets containing calls are
+        // bound to a pointer first (see `fold_stmt`). That isn't possible in a for-loop update,
+        // where any calls in the target are evaluated twice instead.
+        self.original = original;
+        let data_type = lhs.data_type.dereference().clone();
+        let rhs = self.recondition_bin_op_expr(data_type, op, lhs_to_expr(lhs), stmt.rhs);
 
-        std::iter::once(break_check.into())
-            .chain(std::iter::once(counter_increment.into()))
-            .chain(body.into_iter().map(|s| self.recondition_stmt(s)))
-            .collect()
+        AssignmentStatement::new(stmt.lhs, AssignmentOp::Simple, rhs)
     }
 
-    fn recondition_assignment_lhs(&mut self, lhs: AssignmentLhs) -> AssignmentLhs {
+    fn fold_assignment_lhs(&mut self, lhs: AssignmentLhs) -> AssignmentLhs {
         if self.only_loops {
             return lhs;
         }
 
-        match lhs {
-            AssignmentLhs::Phony => AssignmentLhs::Phony,
-            AssignmentLhs::Expr(expr) => AssignmentLhs::Expr(self.recondition_lhs_expr(expr)),
-        }
+        fold::walk_assignment_lhs(self, lhs)
     }
 
-    fn recondition_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
-        let expr = match node.expr {
-            LhsExpr::Ident(ident) => LhsExpr::Ident(ident),
-            LhsExpr::Postfix(expr, postfix) => {
-                let expr = Box::new(self.recondition_lhs_expr(*expr));
-                let postfix = match postfix {
-                    Postfix::Index(index) => {
-                        let index = self.recondition_expr(*index);
-                        Postfix::index(self.recondition_array_index(&expr.data_type, index))
-                    }
-                    Postfix::Member(ident) => Postfix::Member(ident),
-                };
+    fn fold_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
+        let original = self.source_of(|| match &node.expr {
+            LhsExpr::Postfix(_, Postfix::Index(index)) => index.to_string(),
+            _ => String::new(),
+        });
 
-                LhsExpr::Postfix(expr, postfix)
+        let node = fold::walk_lhs_expr(self, node);
+        let expr = match node.expr {
+            LhsExpr::Postfix(expr, Postfix::Index(index)) => {
+                self.original = original;
+                let index = self.recondition_array_index(&lhs_to_expr(&expr), *index);
+                LhsExpr::Postfix(expr, Postfix::index(index))
             }
-            LhsExpr::Deref(_) => todo!(),
-            LhsExpr::AddressOf(_) => todo!(),
+            expr => expr,
         };
 
         LhsExprNode { expr, ..node }
     }
 
-    fn recondition_expr(&mut self, node: ExprNode) -> ExprNode {
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
         if self.only_loops {
             return node;
         }
 
+        // Index wrappers guard the index rather than the whole expression
+        let original = self.source_of(|| match &node.expr {
+            Expr::Postfix(PostfixExpr {
+                postfix: Postfix::Index(index),
+                ..
+            }) => index.to_string(),
+            _ => node.to_string(),
+        });
+
+        let node = fold::walk_expr(self, node);
+        self.original = original;
+
         let reconditioned = match node.expr {
-            Expr::TypeCons(expr) => Expr::TypeCons(TypeConsExpr::new(
-                expr.data_type,
-                expr.args
-                    .into_iter()
-                    .map(|e| self.recondition_expr(e))
-                    .collect(),
-            )),
-            Expr::UnOp(expr) => {
-                let inner = self.recondition_expr(*expr.inner);
-                let op = expr.op;
-                match op {
-                    UnOp::Neg => {
-                        let data_type = inner.data_type.dereference().clone();
-                        let mut expr = self.recondition_negation(inner);
-                        if data_type.as_scalar().unwrap() == ScalarType::F32 {
-                            expr = FnCallExpr::new(
-                                self.safe_wrapper(Wrapper::FloatOp(data_type.clone())),
-                                vec![ExprNode { data_type, expr }],
-                            )
-                            .into();
-                        }
-                        expr
+            Expr::UnOp(expr) => match expr.op {
+                UnOp::Neg => {
+                    let inner = *expr.inner;
+                    let data_type = inner.data_type.dereference().clone();
+                    let mut expr = self.recondition_negation(inner);
+                    if data_type.as_scalar().unwrap() == ScalarType::F32 {
+                        expr = FnCallExpr::new(
+                            self.safe_wrapper(Wrapper::FloatOp(data_type.clone())),
+                            vec![ExprNode { data_type, expr }],
+                        )
+                        .into();
                     }
-                    _ => UnOpExpr::new(op, inner).into(),
+                    expr
                 }
-            }
+                _ => Expr::UnOp(expr),
+            },
             Expr::BinOp(expr) => {
-                let left = self.recondition_expr(*expr.left);
-                let right = self.recondition_expr(*expr.right);
-                return self.recondition_bin_op_expr(node.data_type, expr.op, left, right);
+                return self.recondition_bin_op_expr(
+                    node.data_type,
+                    expr.op,
+                    *expr.left,
+                    *expr.right,
+                );
             }
             Expr::FnCall(expr) => {
-                let args: Vec<ExprNode> = expr
-                    .args
-                    .into_iter()
-                    .map(|e| self.recondition_expr(e))
-                    .collect();
-
+                let args = expr.args;
                 let expr = match expr.ident.as_str() {
                     "clamp" => FnCallExpr::new(
                         self.safe_wrapper(Wrapper::Clamp(args[0].data_type.dereference().clone())),
@@ -397,7 +375,10 @@ impl Reconditioner {
                         self.safe_wrapper(Wrapper::Dot(args[0].data_type.dereference().clone())),
                         args,
                     ),
-                    _ => FnCallExpr::new(expr.ident, args),
+                    _ => match self.builtin_wrapper(&expr.ident, &args) {
+                        Some(wrapper) => FnCallExpr::new(wrapper, args),
+                        None => FnCallExpr::new(expr.ident, args),
+                    },
                 };
 
                 if matches!(node.data_type.as_scalar(), Some(ScalarType::F32)) {
@@ -410,17 +391,12 @@ impl Reconditioner {
                     expr.into()
                 }
             }
-            Expr::Postfix(expr) => {
-                let e = self.recondition_expr(*expr.inner);
-                let postfix = match expr.postfix {
-                    Postfix::Index(index) => {
-                        let index = self.recondition_expr(*index);
-                        Postfix::Index(Box::new(self.recondition_array_index(&e.data_type, index)))
-                    }
-                    Postfix::Member(n) => Postfix::Member(n),
-                };
-
-                PostfixExpr::new(e, postfix).into()
+            Expr::Postfix(PostfixExpr {
+                inner,
+                postfix: Postfix::Index(index),
+            }) => {
+                let index = self.recondition_array_index(&inner, *index);
+                PostfixExpr::new(*inner, Postfix::index(index)).into()
             }
             e => e,
         };
@@ -430,6 +406,78 @@ impl Reconditioner {
             expr: reconditioned,
         }
     }
+}
+
+impl Reconditioner {
+    fn new(options: Options) -> Reconditioner {
+        Reconditioner {
+            loop_var: 0,
+            wrappers: HashSet::new(),
+            only_loops: options.only_loops,
+            skip_builtins: options.skip_builtins,
+            provenance: options.provenance.then(Provenance::default),
+            inserted_calls: vec![],
+            function: String::new(),
+            original: String::new(),
+            assignment_original: None,
+        }
+    }
+
+    /// Returns the source produced by `f` if provenance is enabled, to avoid rendering every
+    /// expression otherwise.
+    fn source_of(&self, f: impl FnOnce() -> String) -> String {
+        if self.provenance.is_some() {
+            f()
+        } else {
+            String::new()
+        }
+    }
+
+    fn recondition_loop_body(&mut self, body: Vec<Statement>) -> Vec<Statement> {
+        let id = self.loop_var();
+
+        if let Some(provenance) = &mut self.provenance {
+            provenance.loop_counters.push(LoopCounter {
+                function: self.function.clone(),
+                counter: id,
+            });
+        }
+
+        let counters_ty = DataType::Ref(MemoryViewType::new(
+            DataType::array(ScalarType::U32, None),
+            StorageClass::Private,
+        ));
+
+        let break_check = IfStatement::new(
+            BinOpExpr::new(
+                BinOp::GreaterEqual,
+                PostfixExpr::new(
+                    VarExpr::new("LOOP_COUNTERS").into_node(counters_ty.clone()),
+                    Postfix::index(Lit::U32(id)),
+                ),
+                Lit::U32(1),
+            ),
+            vec![Statement::Break],
+        );
+
+        let counter_increment = AssignmentStatement::new(
+            AssignmentLhs::array_index("LOOP_COUNTERS", counters_ty.clone(), Lit::U32(id).into()),
+            AssignmentOp::Simple,
+            BinOpExpr::new(
+                BinOp::Plus,
+                PostfixExpr::new(
+                    VarExpr::new("LOOP_COUNTERS").into_node(counters_ty),
+                    Postfix::index(Lit::U32(id)),
+                ),
+                Lit::U32(1),
+            ),
+        );
+
+        std::iter::once(break_check.into())
+            .chain(std::iter::once(counter_increment.into()))
+            .chain(self.fold_block(body))
+            .collect()
+    }
 
     fn recondition_negation(&mut self, inner: ExprNode) -> Expr {
         // TODO: Workaround for bug in naga which generates incorrect code for double negation
@@ -461,25 +509,44 @@ impl Reconditioner {
         BinOpExpr::new(BinOp::Times, neg_multiplier, inner).into()
     }
 
-    fn recondition_array_index(&mut self, array_type: &DataType, index: ExprNode) -> ExprNode {
-        let size = match array_type.dereference() {
-            DataType::Array(_, Some(n)) => *n,
+    fn recondition_array_index(&mut self, array: &ExprNode, index: ExprNode) -> ExprNode {
+        let index_type = index.data_type.dereference().clone();
+        let index_scalar_type = index_type.as_scalar().unwrap();
+
+        let size_expr: ExprNode = match array.data_type.dereference() {
+            // The size of a runtime-sized array is only known from `arrayLength`, which returns a
+            // u32 so it must be converted for signed indices
             DataType::Array(_, None) => {
-                todo!("runtime-sized arrays are not currently supported")
+                let size = FnCallExpr::new(
+                    BuiltinFn::ArrayLength.as_ref(),
+                    vec![UnOpExpr::new(UnOp::AddressOf, array.clone()).into()],
+                )
+                .into_node(DataType::Scalar(ScalarType::U32));
+
+                match index_scalar_type {
+                    ScalarType::I32 => TypeConsExpr::new(index_type.clone(), vec![size]).into(),
+                    ScalarType::U32 => size,
+                    _ => unreachable!("index expression must be an integer"),
+                }
             }
-            _ => unreachable!("index operator cannot be applied to type `{array_type}`"),
-        };
+            array_type => {
+                let size = match array_type {
+                    DataType::Array(_, Some(n)) => *n,
+                    DataType::Vector(n, _) | DataType::Matrix(n, _, _) => *n as u32,
+                    _ => unreachable!("index operator cannot be applied to type `{array_type}`"),
+                };
 
-        let index_type = index.data_type.dereference().clone();
-        let size_expr = match index_type.as_scalar().unwrap() {
-            ScalarType::I32 => Lit::I32(size as i32),
-            ScalarType::U32 => Lit::U32(size),
-            _ => unreachable!("index expression must be an integer"),
+                match index_scalar_type {
+                    ScalarType::I32 => Lit::I32(size as i32).into(),
+                    ScalarType::U32 => Lit::U32(size).into(),
+                    _ => unreachable!("index expression must be an integer"),
+                }
+            }
         };
 
         FnCallExpr::new(
             self.safe_wrapper(Wrapper::Index(index_type.clone())),
-            vec![index, size_expr.into()],
+            vec![index, size_expr],
         )
         .into_node(index_type)
     }
@@ -491,20 +558,13 @@ impl Reconditioner {
         operand: ExprNode,
         shift_value: ExprNode,
     ) -> ExprNode {
-        let shift_type = shift_value.data_type.dereference();
-        let shift_bound: ExprNode = match ty {
-            DataType::Scalar(_) => Lit::U32(32).into(),
-            DataType::Vector(_, _) => {
-                TypeConsExpr::new(shift_type.clone(), vec![Lit::U32(32).into()]).into()
-            }
+        let wrapper = match shift_op {
+            BinOp::LShift => Wrapper::LShift(ty.clone()),
+            BinOp::RShift => Wrapper::RShift(ty.clone()),
             _ => unreachable!(),
         };
 
-        ExprNode::from(BinOpExpr::new(
-            shift_op,
-            operand,
-            BinOpExpr::new(BinOp::Mod, shift_value, shift_bound),
-        ))
+        FnCallExpr::new(self.safe_wrapper(wrapper), vec![operand, shift_value]).into_node(ty)
     }
 
     fn recondition_bin_op_expr(
@@ -519,7 +579,8 @@ impl Reconditioner {
         }
 
         match data_type.as_scalar().unwrap() {
-            ScalarType::I32 | ScalarType::U32 | ScalarType::AU32 | ScalarType::AI32 => { // TODO: Check!!
+            ScalarType::I32 | ScalarType::U32 | ScalarType::AU32 | ScalarType::AI32 => {
+                // TODO: Check!!
                 self.recondition_integer_bin_op_expr(data_type, op, l, r)
             }
             ScalarType::F32 if op == BinOp::Divide => {
@@ -577,15 +638,247 @@ impl Reconditioner {
         FnCallExpr::new(self.safe_wrapper(wrapper), vec![l, r]).into_node(data_type)
     }
 
+    /// Returns the wrapper to call instead of `ident` if it is a builtin whose arguments need to be
+    /// sanitised.
+    fn builtin_wrapper(&mut self, ident: &str, args: &[ExprNode]) -> Option<String> {
+        let builtin = BuiltinFn::from_str(ident).ok()?;
+        let family = BuiltinFamily::of(builtin)?;
+
+        if self.skip_builtins.contains(&family) {
+            return None;
+        }
+
+        let data_type = args[0].data_type.dereference().clone();
+        Some(self.safe_wrapper(Wrapper::Builtin(builtin, data_type)))
+    }
+
     fn loop_var(&mut self) -> u32 {
         let cur = self.loop_var;
         self.loop_var += 1;
         cur
     }
 
+    /// Returns the name of a wrapper for a call that is about to be inserted around the current
+    /// expression, declaring the wrapper if needed.
+    ///
+    /// If provenance is enabled, the name is tagged with the index of the call in
+    /// `inserted_calls` until the calls are numbered.
     fn safe_wrapper(&mut self, wrapper: Wrapper) -> String {
         let ident = wrapper.to_string();
+        self.declare_wrapper(wrapper);
+
+        if self.provenance.is_none() {
+            return ident;
+        }
+
+        let tag = self.inserted_calls.len();
+        self.inserted_calls.push(InsertedCall {
+            function: self.function.clone(),
+            wrapper: ident.clone(),
+            original: self.original.clone(),
+        });
+
+        format!("{ident}{}{tag}", unrecondition::TAG_SEPARATOR)
+    }
+
+    fn declare_wrapper(&mut self, wrapper: Wrapper) {
+        // Matrix wrappers are implemented in terms of the wrappers for their columns
+        if let Wrapper::FloatOp(DataType::Matrix(_, r, t)) = &wrapper {
+            self.declare_wrapper(Wrapper::FloatOp(DataType::Vector(*r, *t)));
+        }
+
         self.wrappers.insert(wrapper);
-        ident
+    }
+}
+
+/// Returns true if the statement is a compound assignment whose target contains a function call.
+fn is_compound_with_calls(stmt: &AssignmentStatement) -> bool {
+    struct HasCalls(bool);
+
+    impl Visit for HasCalls {
+        fn visit_expr(&mut self, node: &ExprNode) {
+            self.0 |= matches!(node.expr, Expr::FnCall(_));
+            visit::walk_expr(self, node);
+        }
+    }
+
+    let AssignmentLhs::Expr(lhs) = &stmt.lhs else {
+        return false;
+    };
+
+    let mut has_calls = HasCalls(false);
+    has_calls.visit_lhs_expr(lhs);
+
+    stmt.op.bin_op().is_some() && has_calls.0
+}
+
+/// Returns the source of the operation performed by a compound assignment, e.g. `a + b` for
+/// `a += b`.
+fn compound_source(stmt: &AssignmentStatement) -> String {
+    match (&stmt.lhs, stmt.op.bin_op()) {
+        (AssignmentLhs::Expr(lhs), Some(op)) => {
+            BinOpExpr::new(op, lhs_to_expr(lhs), stmt.rhs.clone()).to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Rewrites a compound assignment so that its target is only evaluated once, by binding it to a
+/// pointer: `a[f()] += b` becomes `{ let p = &a[f()]; *p += b; }`.
+///
+/// Vector components can't be pointed to, so for those the pointer refers to the vector and the
+/// component index (if any) is bound separately.
+fn bind_compound_assignment_target(stmt: AssignmentStatement) -> Vec<Statement> {
+    const PTR: &str = "_wgslsmith_target";
+    const INDEX: &str = "_wgslsmith_target_index";
+
+    let AssignmentLhs::Expr(lhs) = stmt.lhs else {
+        unreachable!("phony assignments can't be compound");
+    };
+
+    let (target, component) = match lhs.expr {
+        LhsExpr::Postfix(inner, postfix)
+            if matches!(inner.data_type.dereference(), DataType::Vector(..)) =>
+        {
+            (*inner, Some(postfix))
+        }
+        expr => (
+            LhsExprNode {
+                data_type: lhs.data_type.clone(),
+                expr,
+            },
+            None,
+        ),
+    };
+
+    let view = target
+        .data_type
+        .as_memory_view()
+        .expect("assignment target must be a reference")
+        .clone();
+
+    let mut block =
+        vec![
+            LetDeclStatement::new(PTR, UnOpExpr::new(UnOp::AddressOf, lhs_to_expr(&target))).into(),
+        ];
+
+    let mut new_lhs = LhsExprNode {
+        data_type: target.data_type,
+        expr: LhsExpr::Deref(Box::new(LhsExprNode::name(
+            PTR.to_owned(),
+            DataType::Ptr(view),
+        ))),
+    };
+
+    if let Some(postfix) = component {
+        let postfix = match postfix {
+            Postfix::Index(index) => {
+                let data_type = index.data_type.dereference().clone();
+                block.push(LetDeclStatement::new(INDEX, *index).into());
+                Postfix::index(VarExpr::new(INDEX).into_node(data_type))
+            }
+            postfix => postfix,
+        };
+
+        new_lhs = LhsExprNode {
+            data_type: lhs.data_type,
+            expr: LhsExpr::Postfix(Box::new(new_lhs), postfix),
+        };
+    }
+
+    block.push(AssignmentStatement::new(new_lhs.into(), stmt.op, stmt.rhs).into());
+    block
+}
+
+/// Converts the target of an assignment into the equivalent expression, so that it can be used
+/// as an operand (e.g. in a call to `arrayLength`).
+fn lhs_to_expr(node: &LhsExprNode) -> ExprNode {
+    let expr = match &node.expr {
+        LhsExpr::Ident(name) => VarExpr::new(name).into(),
+        LhsExpr::Postfix(inner, postfix) => Expr::Postfix(PostfixExpr {
+            inner: Box::new(lhs_to_expr(inner)),
+            postfix: postfix.clone(),
+        }),
+        LhsExpr::Deref(inner) => UnOpExpr::new(UnOp::Deref, lhs_to_expr(inner)).into(),
+        LhsExpr::AddressOf(inner) => UnOpExpr::new(UnOp::AddressOf, lhs_to_expr(inner)).into(),
+    };
+
+    ExprNode {
+        data_type: node.data_type.clone(),
+        expr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn recondition(source: &str) -> String {
+        let mut output = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut output, &super::recondition(parser::parse(source)))
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn runtime_array_index_is_bounded_by_array_length() {
+        let output = recondition(
+            "struct Data { n: u32, values: array<u32>, }\n\
+            @group(0) @binding(0)\n\
+            var<storage, read> data: Data;\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {\n\
+                let i = 3;\n\
+                let j = 1u;\n\
+                let x = data.values[i];\n\
+                let y = data.values[j];\n\
+            }\n",
+        );
+
+        // Signed indices need the length to be converted
+        assert!(
+            output.contains("data.values[_wgslsmith_index_i32(i, i32(arrayLength(&data.values)))]"),
+            "{output}"
+        );
+        assert!(
+            output.contains("data.values[_wgslsmith_index_u32(j, arrayLength(&data.values))]"),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn compound_assignments_go_through_wrappers() {
+        let output = recondition(
+            "var<private> a: array<u32, 4>;\n\
+            fn f() -> i32 {\n\
+                return 1;\n\
+            }\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {\n\
+                var x = 5u;\n\
+                x /= 0u;\n\
+                x <<= 40u;\n\
+                a[f()] += x;\n\
+                for (var i = 0u; i < 4u; a[f()] /= x) {\n\
+                }\n\
+                for (var i = 0u; i < 4u; x <<= 40u) {\n\
+                }\n\
+            }\n",
+        );
+
+        let expected = [
+            "x = _wgslsmith_div_u32(x, 0u);",
+            "x = _wgslsmith_shl_u32(x, 40u);",
+            // The target is only evaluated once, through a pointer
+            "let _wgslsmith_target = &a[_wgslsmith_index_i32(f(), 4)];",
+            "*(_wgslsmith_target) = _wgslsmith_add_u32(*_wgslsmith_target, x);",
+            // There's nowhere to bind the target in a for-loop update, so it's evaluated twice
+            "(a)[_wgslsmith_index_i32(f(), 4)] = \
+                _wgslsmith_div_u32(a[_wgslsmith_index_i32(f(), 4)], x))",
+            "x = _wgslsmith_shl_u32(x, 40u))",
+        ];
+
+        for expected in expected {
+            assert!(output.contains(expected), "{output}");
+        }
     }
 }
@@ -0,0 +1,32 @@
+//! Reporting of where the safety checks in a reconditioned module came from.
+
+use serde::Serialize;
+
+/// Describes the safety checks inserted by the reconditioner, recorded as each one is inserted.
+#[derive(Debug, Default, Serialize)]
+pub struct Provenance {
+    /// Calls to safe wrappers, in the order that they appear in the module.
+    pub wrapper_calls: Vec<WrapperCall>,
+    /// Loops which are limited by a counter.
+    pub loop_counters: Vec<LoopCounter>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WrapperCall {
+    /// Index of the call among the sites found by [`crate::unrecondition::sites`], i.e. the call
+    /// is at `Site::Wrapper(site)`.
+    pub site: usize,
+    /// Name of the function containing the call.
+    pub function: String,
+    pub wrapper: String,
+    /// The source expression that the call guards, as it was before reconditioning.
+    pub original: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoopCounter {
+    /// Name of the function containing the loop.
+    pub function: String,
+    /// Index of the loop's counter in `LOOP_COUNTERS`.
+    pub counter: u32,
+}
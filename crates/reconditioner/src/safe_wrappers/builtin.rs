@@ -0,0 +1,224 @@
+use ast::{
+    BinOp, BinOpExpr, BuiltinFn, DataType, ExprNode, FnCallExpr, FnDecl, FnInput, FnOutput, Lit,
+    ReturnStatement, ScalarType, TypeConsExpr, VarExpr,
+};
+
+/// Generates a wrapper for a builtin whose result is only well defined for some of its inputs,
+/// which replaces any arguments outside that domain before calling the builtin.
+///
+/// `data_type` is the type of the first argument, which is also the return type for all of the
+/// builtins handled here.
+pub fn builtin(name: String, builtin: BuiltinFn, data_type: &DataType) -> FnDecl {
+    let var = |name: &str, data_type: &DataType| VarExpr::new(name).into_node(data_type.clone());
+    let call = |ident: &str, args: Vec<ExprNode>, data_type: &DataType| {
+        FnCallExpr::new(ident, args).into_node(data_type.clone())
+    };
+
+    let u32_type = DataType::Scalar(ScalarType::U32);
+
+    let (inputs, args) = match builtin {
+        // sqrt(|x|)
+        BuiltinFn::Sqrt => {
+            let x = var("x", data_type);
+            let args = vec![call("abs", vec![x], data_type)];
+            (vec![FnInput::new("x", data_type.clone())], args)
+        }
+        // f(x == 0 ? 1 : |x|)
+        BuiltinFn::InverseSqrt | BuiltinFn::Log | BuiltinFn::Log2 => {
+            let x = var("x", data_type);
+            let args = vec![non_zero(call("abs", vec![x.clone()], data_type), x)];
+            (vec![FnInput::new("x", data_type.clone())], args)
+        }
+        // f(clamp(x, -1, 1))
+        BuiltinFn::Acos | BuiltinFn::Asin => {
+            let x = var("x", data_type);
+            let args = vec![call(
+                "clamp",
+                vec![
+                    x,
+                    splat(data_type, Lit::F32(-1.0)),
+                    splat(data_type, Lit::F32(1.0)),
+                ],
+                data_type,
+            )];
+            (vec![FnInput::new("x", data_type.clone())], args)
+        }
+        // pow(x == 0 ? 1 : |x|, y)
+        BuiltinFn::Pow => {
+            let x = var("x", data_type);
+            let args = vec![
+                non_zero(call("abs", vec![x.clone()], data_type), x),
+                var("y", data_type),
+            ];
+            let inputs = vec![
+                FnInput::new("x", data_type.clone()),
+                FnInput::new("y", data_type.clone()),
+            ];
+            (inputs, args)
+        }
+        // ldexp(x, clamp(e, -64, 64))
+        //
+        // Float operands are kept below 2^24 by the other wrappers, so this keeps the result well
+        // within the range of normal f32 values.
+        BuiltinFn::Ldexp => {
+            let exp_type = data_type.map(ScalarType::I32);
+            let args = vec![
+                var("x", data_type),
+                call(
+                    "clamp",
+                    vec![
+                        var("e", &exp_type),
+                        splat(&exp_type, Lit::I32(-64)),
+                        splat(&exp_type, Lit::I32(64)),
+                    ],
+                    &exp_type,
+                ),
+            ];
+            let inputs = vec![
+                FnInput::new("x", data_type.clone()),
+                FnInput::new("e", exp_type),
+            ];
+            (inputs, args)
+        }
+        // smoothstep(low, low >= high ? low + 1 : high, x)
+        BuiltinFn::Smoothstep => {
+            let low = var("low", data_type);
+            let high = var("high", data_type);
+            let args = vec![
+                low.clone(),
+                call(
+                    "select",
+                    vec![
+                        high.clone(),
+                        BinOpExpr::new(BinOp::Plus, low.clone(), splat(data_type, Lit::F32(1.0)))
+                            .into(),
+                        BinOpExpr::new(BinOp::GreaterEqual, low, high).into(),
+                    ],
+                    data_type,
+                ),
+                var("x", data_type),
+            ];
+            let inputs = vec![
+                FnInput::new("low", data_type.clone()),
+                FnInput::new("high", data_type.clone()),
+                FnInput::new("x", data_type.clone()),
+            ];
+            (inputs, args)
+        }
+        // extractBits(e, min(offset, 32), min(count, 32 - min(offset, 32)))
+        BuiltinFn::ExtractBits => {
+            let (offset, count) = bit_range();
+            let args = vec![var("e", data_type), offset, count];
+            let inputs = vec![
+                FnInput::new("e", data_type.clone()),
+                FnInput::new("offset", u32_type.clone()),
+                FnInput::new("count", u32_type),
+            ];
+            (inputs, args)
+        }
+        // insertBits(e, newbits, min(offset, 32), min(count, 32 - min(offset, 32)))
+        BuiltinFn::InsertBits => {
+            let (offset, count) = bit_range();
+            let args = vec![
+                var("e", data_type),
+                var("newbits", data_type),
+                offset,
+                count,
+            ];
+            let inputs = vec![
+                FnInput::new("e", data_type.clone()),
+                FnInput::new("newbits", data_type.clone()),
+                FnInput::new("offset", u32_type.clone()),
+                FnInput::new("count", u32_type),
+            ];
+            (inputs, args)
+        }
+        _ => unreachable!("no wrapper for builtin `{}`", builtin.as_ref()),
+    };
+
+    FnDecl {
+        attrs: vec![],
+        name,
+        inputs,
+        output: Some(FnOutput::new(data_type.clone())),
+        body: vec![ReturnStatement::new(call(builtin.as_ref(), args, data_type)).into()],
+    }
+}
+
+fn splat(data_type: &DataType, lit: Lit) -> ExprNode {
+    TypeConsExpr::new(data_type.clone(), vec![lit.into()]).into()
+}
+
+/// Returns `value`, with any components where `x` is zero replaced by one.
+fn non_zero(value: ExprNode, x: ExprNode) -> ExprNode {
+    let data_type = value.data_type.clone();
+    let condition = BinOpExpr::new(BinOp::Equal, x, splat(&data_type, Lit::F32(0.0)));
+    FnCallExpr::new(
+        "select",
+        vec![value, splat(&data_type, Lit::F32(1.0)), condition.into()],
+    )
+    .into_node(data_type)
+}
+
+/// Returns the `offset` and `count` arguments for `extractBits` or `insertBits`, clamped so that
+/// `offset + count` is at most 32.
+fn bit_range() -> (ExprNode, ExprNode) {
+    let u32_type = DataType::Scalar(ScalarType::U32);
+    let min =
+        |a: ExprNode, b: ExprNode| FnCallExpr::new("min", vec![a, b]).into_node(u32_type.clone());
+
+    let offset = min(
+        VarExpr::new("offset").into_node(u32_type.clone()),
+        Lit::U32(32).into(),
+    );
+
+    let count = min(
+        VarExpr::new("count").into_node(u32_type.clone()),
+        BinOpExpr::new(BinOp::Minus, Lit::U32(32), offset.clone()).into(),
+    );
+
+    (offset, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrapper(builtin_fn: BuiltinFn, data_type: DataType) -> String {
+        let decl = builtin("wrapper".to_owned(), builtin_fn, &data_type);
+        let mut output = String::new();
+        ast::writer::Writer::default()
+            .write_func(&mut output, &decl)
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn sqrt_takes_absolute_value() {
+        // sqrt(|x|)
+        assert_eq!(
+            wrapper(BuiltinFn::Sqrt, DataType::Scalar(ScalarType::F32)),
+            "fn wrapper(x: f32) -> f32 {\n    return sqrt(abs(x));\n}\n"
+        );
+    }
+
+    #[test]
+    fn pow_replaces_zero_base_with_one() {
+        // pow(x == 0 ? 1 : |x|, y), per component
+        assert_eq!(
+            wrapper(BuiltinFn::Pow, DataType::Vector(2, ScalarType::F32)),
+            "fn wrapper(x: vec2<f32>, y: vec2<f32>) -> vec2<f32> {\n    \
+                return pow(select(abs(x), vec2<f32>(1.0), x == vec2<f32>(0.0)), y);\n}\n"
+        );
+    }
+
+    #[test]
+    fn extract_bits_clamps_bit_range() {
+        // extractBits(e, min(offset, 32), min(count, 32 - min(offset, 32)))
+        assert_eq!(
+            wrapper(BuiltinFn::ExtractBits, DataType::Scalar(ScalarType::I32)),
+            "fn wrapper(e: i32, offset: u32, count: u32) -> i32 {\n    \
+                return extractBits(e, min(offset, 32u), min(count, 32u - min(offset, 32u)));\n}\n"
+        );
+    }
+}
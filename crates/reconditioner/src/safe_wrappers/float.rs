@@ -1,6 +1,6 @@
 use ast::{
-    BinOp, BinOpExpr, DataType, FnCallExpr, FnDecl, FnInput, FnOutput, Lit, ReturnStatement,
-    TypeConsExpr, VarExpr,
+    BinOp, BinOpExpr, DataType, FnCallExpr, FnDecl, FnInput, FnOutput, Lit, Postfix, PostfixExpr,
+    ReturnStatement, TypeConsExpr, VarExpr,
 };
 
 pub fn float(name: String, data_type: &DataType) -> FnDecl {
@@ -44,3 +44,33 @@ pub fn float(name: String, data_type: &DataType) -> FnDecl {
         .into()],
     }
 }
+
+/// Generates a float wrapper for a matrix type, which applies `column_wrapper` to each column.
+pub fn float_matrix(name: String, data_type: &DataType, column_wrapper: String) -> FnDecl {
+    let (columns, column_type) = match data_type {
+        DataType::Matrix(c, r, t) => (*c, DataType::Vector(*r, *t)),
+        _ => unreachable!("expected matrix type, got `{data_type}`"),
+    };
+
+    let args = (0..columns)
+        .map(|i| {
+            FnCallExpr::new(
+                column_wrapper.clone(),
+                vec![PostfixExpr::new(
+                    VarExpr::new("v").into_node(data_type.clone()),
+                    Postfix::index(Lit::I32(i as i32)),
+                )
+                .into()],
+            )
+            .into_node(column_type.clone())
+        })
+        .collect();
+
+    FnDecl {
+        attrs: vec![],
+        name,
+        inputs: vec![FnInput::new("v", data_type.clone())],
+        output: Some(FnOutput::new(data_type.clone())),
+        body: vec![ReturnStatement::new(TypeConsExpr::new(data_type.clone(), args)).into()],
+    }
+}
@@ -1,3 +1,4 @@
+mod builtin;
 mod clamp;
 mod divide;
 mod dot;
@@ -7,21 +8,24 @@ mod index;
 mod minus;
 mod modulo;
 mod plus;
+mod shift;
 mod times;
 
 use ast::{
     BinOp, BinOpExpr, DataType, ExprNode, FnCallExpr, Lit, Postfix, PostfixExpr, ScalarType,
 };
 
+pub use builtin::builtin;
 pub use clamp::clamp;
 pub use divide::divide;
 pub use dot::dot;
-pub use float::float;
+pub use float::{float, float_matrix};
 pub use float_divide::float_divide;
 pub use index::index;
 pub use minus::minus;
 pub use modulo::modulo;
 pub use plus::plus;
+pub use shift::shift;
 pub use times::times;
 
 /// Wraps the given expression in a call to `any()` if it is a vector.
@@ -0,0 +1,41 @@
+use ast::{
+    BinOp, BinOpExpr, DataType, ExprNode, FnDecl, FnInput, FnOutput, Lit, ReturnStatement,
+    ScalarType, TypeConsExpr, VarExpr,
+};
+
+/// Generates a wrapper for a shift operator, which masks the shift amount to the bit width of the
+/// operand in the same way that WGSL defines for runtime shifts.
+///
+/// Shifting by at least the bit width is a shader-creation error in a const-expression, and isn't
+/// handled consistently by all backends at runtime, so the shift is moved into a function where
+/// the mask is explicit.
+pub fn shift(name: String, op: BinOp, data_type: &DataType) -> FnDecl {
+    let shift_type = data_type.map(ScalarType::U32);
+
+    let mask: ExprNode = match shift_type {
+        DataType::Scalar(_) => Lit::U32(31).into(),
+        _ => TypeConsExpr::new(shift_type.clone(), vec![Lit::U32(31).into()]).into(),
+    };
+
+    let shift_value = BinOpExpr::new(
+        BinOp::BitAnd,
+        VarExpr::new("b").into_node(shift_type.clone()),
+        mask,
+    );
+
+    FnDecl {
+        attrs: vec![],
+        name,
+        inputs: vec![
+            FnInput::new("a", data_type.clone()),
+            FnInput::new("b", shift_type),
+        ],
+        output: Some(FnOutput::new(data_type.clone())),
+        body: vec![ReturnStatement::new(BinOpExpr::new(
+            op,
+            VarExpr::new("a").into_node(data_type.clone()),
+            shift_value,
+        ))
+        .into()],
+    }
+}
@@ -0,0 +1,518 @@
+//! Removal of the safety checks inserted by the reconditioner.
+//!
+//! Reconditioned shaders are hard to read, since most operations are replaced with calls to safe
+//! wrappers and every loop is limited by a counter in `LOOP_COUNTERS`. Each of these checks is a
+//! [`Site`] which can be removed independently, by replacing the wrapper call with the operation it
+//! guards or by deleting the statements that update the loop counter. This works on any shader
+//! containing the checks (e.g. a reduced test case) rather than relying on the exact output of the
+//! reconditioner.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use ast::fold::{self, Fold};
+use ast::visit::{self, Visit};
+use ast::*;
+
+/// A safety check which can be removed from a reconditioned module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Site {
+    /// A call to a safe wrapper, numbered as in [`sites`].
+    Wrapper(usize),
+    /// A loop which is limited by the counter with the given index.
+    Loop(u32),
+}
+
+/// Finds the safety checks in a reconditioned module.
+///
+/// Wrapper calls are numbered in the order that they appear in the module, with nested calls
+/// coming before the calls that contain them.
+pub fn sites(module: &Module) -> Vec<Site> {
+    let mut unreconditioner = Unreconditioner::new(None);
+    unreconditioner.fold_fns(module.functions.clone());
+
+    (0..unreconditioner.wrapper_calls)
+        .map(Site::Wrapper)
+        .chain(unreconditioner.loop_counters.into_iter().map(Site::Loop))
+        .collect()
+}
+
+/// Separates the name of a wrapper from a tag which the reconditioner adds to the calls it inserts,
+/// to identify them until they can be numbered as in [`sites`]. It can't appear in identifiers, so
+/// the tags must be removed by [`untag_wrapper_calls`].
+pub(crate) const TAG_SEPARATOR: char = '#';
+
+/// Removes the tags from the wrapper calls in `functions`, returning the tag of each call along
+/// with its index in [`sites`].
+pub(crate) fn untag_wrapper_calls(functions: Vec<FnDecl>) -> (Vec<FnDecl>, Vec<(usize, usize)>) {
+    let mut untag = Untag::default();
+
+    // Wrapper calls are visited in the same order as in `Unreconditioner`
+    let functions = functions
+        .into_iter()
+        .map(|decl| {
+            if guarded_op(&decl.name).is_some() {
+                decl
+            } else {
+                untag.fold_fn_decl(decl)
+            }
+        })
+        .collect();
+
+    (functions, untag.tags)
+}
+
+/// Removes the safety checks at the given sites, along with any wrapper functions and loop
+/// counters which are no longer used.
+pub fn unrecondition(mut module: Module, remove: &HashSet<Site>) -> Module {
+    let mut unreconditioner = Unreconditioner::new(Some(remove));
+    let mut functions = unreconditioner.fold_fns(module.functions);
+
+    // Wrappers are kept if they are reachable from the other functions. Functions can be declared
+    // in any order, so uses are collected from every reachable function until no more are found.
+    let mut uses = Uses::default();
+    let mut visited = HashSet::new();
+
+    loop {
+        let reachable = functions
+            .iter()
+            .filter(|it| guarded_op(&it.name).is_none() || uses.fns.contains(&it.name))
+            .filter(|it| !visited.contains(&it.name))
+            .collect::<Vec<_>>();
+
+        if reachable.is_empty() {
+            break;
+        }
+
+        for decl in reachable {
+            visited.insert(decl.name.clone());
+            uses.visit_fn_decl(decl);
+        }
+    }
+
+    functions.retain(|it| visited.contains(&it.name));
+    module.functions = functions;
+
+    if !uses.loop_counters {
+        module.vars.retain(|it| it.name != "LOOP_COUNTERS");
+    }
+
+    module
+}
+
+/// The operation that a safe wrapper stands in for.
+#[derive(Debug, PartialEq)]
+enum GuardedOp {
+    BinOp(BinOp),
+    /// The wrapper returns its first argument, or a safe value in its place.
+    Value,
+    /// The wrapper calls the builtin with the same name.
+    Builtin(String),
+}
+
+/// Recovers the operation guarded by a wrapper from its name (see `Display for Wrapper`), or
+/// returns `None` if `ident` isn't the name of a wrapper.
+fn guarded_op(ident: &str) -> Option<GuardedOp> {
+    let name = ident.strip_prefix("_wgslsmith_")?;
+
+    let (name, scalar) = name.rsplit_once('_')?;
+    if !matches!(scalar, "bool" | "i32" | "u32" | "f32") {
+        return None;
+    }
+
+    let name = match name.rsplit_once('_') {
+        Some((name, ty)) if ty.starts_with("vec") || ty.starts_with("mat") => name,
+        _ => name,
+    };
+
+    let op = match name {
+        "add" => GuardedOp::BinOp(BinOp::Plus),
+        "sub" => GuardedOp::BinOp(BinOp::Minus),
+        "mult" => GuardedOp::BinOp(BinOp::Times),
+        "div" => GuardedOp::BinOp(BinOp::Divide),
+        "mod" => GuardedOp::BinOp(BinOp::Mod),
+        "shl" => GuardedOp::BinOp(BinOp::LShift),
+        "shr" => GuardedOp::BinOp(BinOp::RShift),
+        "f_op" | "index" => GuardedOp::Value,
+        name if BuiltinFn::from_str(name).is_ok() => GuardedOp::Builtin(name.to_owned()),
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+impl GuardedOp {
+    fn apply(&self, mut args: Vec<ExprNode>, data_type: DataType) -> ExprNode {
+        match self {
+            GuardedOp::BinOp(op) => {
+                let r = args.pop().unwrap();
+                let l = args.pop().unwrap();
+                ExprNode {
+                    data_type,
+                    expr: BinOpExpr::new(*op, l, r).into(),
+                }
+            }
+            GuardedOp::Value => args.swap_remove(0),
+            GuardedOp::Builtin(name) => FnCallExpr::new(name, args).into_node(data_type),
+        }
+    }
+}
+
+/// Returns the index of the counter updated by a statement inserted to limit a loop.
+fn loop_counter(stmt: &Statement) -> Option<u32> {
+    fn counter_index(postfix: &Postfix) -> Option<u32> {
+        match postfix {
+            Postfix::Index(index) => match index.expr {
+                Expr::Lit(Lit::U32(counter)) => Some(counter),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    match stmt {
+        // if (LOOP_COUNTERS[i] >= n) { break; }
+        Statement::If(IfStatement {
+            condition,
+            body,
+            else_: None,
+        }) if matches!(body.as_slice(), [Statement::Break]) => match &condition.expr {
+            Expr::BinOp(BinOpExpr {
+                op: BinOp::GreaterEqual,
+                left,
+                ..
+            }) => match &left.expr {
+                Expr::Postfix(PostfixExpr { inner, postfix }) => match &inner.expr {
+                    Expr::Var(var) if var.ident == "LOOP_COUNTERS" => counter_index(postfix),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        },
+        // LOOP_COUNTERS[i] = ...;
+        Statement::Assignment(AssignmentStatement {
+            lhs: AssignmentLhs::Expr(lhs),
+            ..
+        }) => match &lhs.expr {
+            LhsExpr::Postfix(inner, postfix) => match &inner.expr {
+                LhsExpr::Ident(ident) if ident == "LOOP_COUNTERS" => counter_index(postfix),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+struct Unreconditioner<'a> {
+    /// Sites to remove, or `None` to remove all of them.
+    remove: Option<&'a HashSet<Site>>,
+    /// Number of wrapper calls seen so far.
+    wrapper_calls: usize,
+    /// Counters of the loops seen so far.
+    loop_counters: Vec<u32>,
+}
+
+impl<'a> Unreconditioner<'a> {
+    fn new(remove: Option<&'a HashSet<Site>>) -> Self {
+        Unreconditioner {
+            remove,
+            wrapper_calls: 0,
+            loop_counters: vec![],
+        }
+    }
+
+    fn fold_fns(&mut self, functions: Vec<FnDecl>) -> Vec<FnDecl> {
+        functions
+            .into_iter()
+            .map(|decl| {
+                // Wrappers may call other wrappers, which are left alone since they are removed
+                // along with their callers
+                if guarded_op(&decl.name).is_some() {
+                    decl
+                } else {
+                    self.fold_fn_decl(decl)
+                }
+            })
+            .collect()
+    }
+
+    fn removes(&self, site: Site) -> bool {
+        self.remove.is_none_or(|it| it.contains(&site))
+    }
+}
+
+impl<'a> Fold for Unreconditioner<'a> {
+    fn fold_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        let mut stmts = Vec::with_capacity(block.len());
+
+        for stmt in block {
+            if let Some(counter) = loop_counter(&stmt) {
+                if matches!(stmt, Statement::If(_)) {
+                    self.loop_counters.push(counter);
+                }
+
+                if self.removes(Site::Loop(counter)) {
+                    continue;
+                }
+            }
+
+            stmts.push(self.fold_stmt(stmt));
+        }
+
+        stmts
+    }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        let node = fold::walk_expr(self, node);
+
+        let Expr::FnCall(call) = &node.expr else {
+            return node;
+        };
+
+        let Some(op) = guarded_op(&call.ident) else {
+            return node;
+        };
+
+        let site = Site::Wrapper(self.wrapper_calls);
+        self.wrapper_calls += 1;
+
+        if self.removes(site) {
+            op.apply(call.args.clone(), node.data_type.clone())
+        } else {
+            node
+        }
+    }
+}
+
+#[derive(Default)]
+struct Untag {
+    /// Number of wrapper calls seen so far.
+    wrapper_calls: usize,
+    /// Site index and tag of each tagged call.
+    tags: Vec<(usize, usize)>,
+}
+
+impl Fold for Untag {
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        let mut node = fold::walk_expr(self, node);
+
+        if let Expr::FnCall(call) = &mut node.expr {
+            if let Some((ident, tag)) = call.ident.split_once(TAG_SEPARATOR) {
+                let tag = tag.parse().expect("wrapper call tag must be an index");
+                self.tags.push((self.wrapper_calls, tag));
+                call.ident = ident.to_owned();
+            }
+
+            if guarded_op(&call.ident).is_some() {
+                self.wrapper_calls += 1;
+            }
+        }
+
+        node
+    }
+}
+
+/// Functions and loop counters that are referenced by a module.
+#[derive(Default)]
+struct Uses {
+    fns: HashSet<String>,
+    loop_counters: bool,
+}
+
+impl Visit for Uses {
+    fn visit_lhs_expr(&mut self, node: &LhsExprNode) {
+        if matches!(&node.expr, LhsExpr::Ident(ident) if ident == "LOOP_COUNTERS") {
+            self.loop_counters = true;
+        }
+
+        visit::walk_lhs_expr(self, node)
+    }
+
+    fn visit_expr(&mut self, node: &ExprNode) {
+        match &node.expr {
+            Expr::Var(var) if var.ident == "LOOP_COUNTERS" => self.loop_counters = true,
+            Expr::FnCall(call) => {
+                self.fns.insert(call.ident.clone());
+            }
+            _ => {}
+        }
+
+        visit::walk_expr(self, node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+@group(0) @binding(0)
+var<storage, read_write> output: array<u32, 4>;
+
+@compute @workgroup_size(1)
+fn main() {
+    var x = 1u;
+    loop {
+        if (x >= 100u) {
+            break;
+        }
+        x = (x * 3u) + (x / 2u);
+    }
+    output[x % 4u] = x << 1u;
+}
+"#;
+
+    fn write(module: &Module) -> String {
+        let mut output = String::new();
+        ast::writer::Writer::default()
+            .write_module(&mut output, module)
+            .unwrap();
+        output
+    }
+
+    /// Returns the names of the wrapper calls in a module, in the order used to number them.
+    fn wrapper_calls(module: &Module) -> Vec<String> {
+        #[derive(Default)]
+        struct Calls(Vec<String>);
+
+        impl Visit for Calls {
+            fn visit_expr(&mut self, node: &ExprNode) {
+                visit::walk_expr(self, node);
+
+                if let Expr::FnCall(call) = &node.expr {
+                    if guarded_op(&call.ident).is_some() {
+                        self.0.push(call.ident.clone());
+                    }
+                }
+            }
+        }
+
+        let mut calls = Calls::default();
+        for decl in &module.functions {
+            if guarded_op(&decl.name).is_none() {
+                calls.visit_fn_decl(decl);
+            }
+        }
+
+        calls.0
+    }
+
+    #[test]
+    fn guarded_op_is_parsed_from_wrapper_name() {
+        let cases = [
+            ("_wgslsmith_add_u32", GuardedOp::BinOp(BinOp::Plus)),
+            ("_wgslsmith_div_vec3_i32", GuardedOp::BinOp(BinOp::Divide)),
+            ("_wgslsmith_shl_vec2_u32", GuardedOp::BinOp(BinOp::LShift)),
+            ("_wgslsmith_index_i32", GuardedOp::Value),
+            ("_wgslsmith_f_op_mat2x3_f32", GuardedOp::Value),
+            (
+                "_wgslsmith_clamp_i32",
+                GuardedOp::Builtin("clamp".to_owned()),
+            ),
+            (
+                "_wgslsmith_extractBits_vec4_u32",
+                GuardedOp::Builtin("extractBits".to_owned()),
+            ),
+        ];
+
+        for (ident, op) in cases {
+            assert_eq!(guarded_op(ident), Some(op), "{ident}");
+        }
+    }
+
+    #[test]
+    fn other_names_are_not_wrappers() {
+        let idents = [
+            "main",
+            "add_u32",
+            "_wgslsmith_add",
+            "_wgslsmith_add_f16",
+            "_wgslsmith_foo_u32",
+            "_wgslsmith_emi_0",
+        ];
+
+        for ident in idents {
+            assert_eq!(guarded_op(ident), None, "{ident}");
+        }
+    }
+
+    #[test]
+    fn removing_all_sites_restores_original() {
+        let original = parser::parse(SHADER);
+        let reconditioned = crate::recondition(original.clone());
+        let sites = sites(&reconditioned);
+
+        assert_eq!(sites.len(), 7);
+        assert!(sites.contains(&Site::Loop(0)));
+
+        let remove = sites.into_iter().collect();
+        let unreconditioned = unrecondition(reconditioned, &remove);
+
+        assert_eq!(write(&unreconditioned), write(&original));
+    }
+
+    #[test]
+    fn unused_wrappers_are_removed_with_their_site() {
+        let reconditioned = crate::recondition(parser::parse(SHADER));
+        let calls = wrapper_calls(&reconditioned);
+        let site = calls
+            .iter()
+            .position(|it| it == "_wgslsmith_add_u32")
+            .unwrap();
+
+        let remove = HashSet::from([Site::Wrapper(site)]);
+        let output = write(&unrecondition(reconditioned, &remove));
+
+        assert!(!output.contains("_wgslsmith_add_u32"), "{output}");
+        assert!(
+            output.contains("x = _wgslsmith_mult_u32(x, 3u) + _wgslsmith_div_u32(x, 2u);"),
+            "{output}"
+        );
+        assert!(output.contains("LOOP_COUNTERS"), "{output}");
+    }
+
+    #[test]
+    fn provenance_is_numbered_by_site() {
+        let module = parser::parse(
+            "var<private> a: array<u32, 4>;\n\
+            fn f() -> i32 {\n\
+                return 1;\n\
+            }\n\
+            @compute @workgroup_size(1)\n\
+            fn main() {\n\
+                var x = 5u;\n\
+                for (var i = 0u; i < 4u; a[f()] /= x) {\n\
+                    x = x + a[i];\n\
+                }\n\
+            }\n",
+        );
+
+        let options = crate::Options {
+            provenance: true,
+            ..Default::default()
+        };
+
+        let result = crate::recondition_with(module, options);
+        let provenance = result.provenance.unwrap();
+        let calls = wrapper_calls(&result.ast);
+
+        assert!(!write(&result.ast).contains(TAG_SEPARATOR));
+        assert_eq!(provenance.wrapper_calls.len(), sites(&result.ast).len() - 1);
+
+        for (i, call) in provenance.wrapper_calls.iter().enumerate() {
+            assert_eq!(call.site, i);
+            assert_eq!(call.wrapper, calls[i]);
+        }
+
+        // The target of the compound assignment is copied along with its index wrapper
+        let originals = provenance
+            .wrapper_calls
+            .iter()
+            .map(|it| it.original.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(originals, ["f()", "f()", "a[f()] / x", "i", "x + a[i]"]);
+    }
+}
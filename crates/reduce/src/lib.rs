@@ -0,0 +1,196 @@
+//! A test-case reducer which operates directly on the AST.
+//!
+//! Reduction proceeds in a number of passes, from coarse to fine: removing module-scope
+//! declarations, removing statements, unwrapping blocks, removing struct members and finally
+//! simplifying expressions. Each pass enumerates sites in the module and produces candidate
+//! programs which are a single step smaller than the current one. Candidates are rendered to
+//! WGSL and parsed again before being tested, so only programs that are accepted by our parser are
+//! ever passed to the interestingness test. The passes are repeated until none of them make any
+//! further progress.
+
+mod passes;
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use ast::Module;
+
+pub struct Options {
+    /// Maximum number of interestingness tests to run in parallel.
+    pub jobs: usize,
+}
+
+pub struct Reduction {
+    pub module: Module,
+    /// Number of times the interestingness test was run.
+    pub tests: usize,
+}
+
+/// Reduces a module while preserving the property checked by `is_interesting`.
+///
+/// The interestingness test is given the candidate program as WGSL source. It may be called from
+/// multiple threads at once.
+pub fn reduce(
+    module: Module,
+    options: Options,
+    is_interesting: impl Fn(&str) -> bool + Sync,
+) -> eyre::Result<Reduction> {
+    let jobs = options.jobs.max(1);
+
+    let mut current = module;
+    let source = render(&current);
+    let tests = AtomicUsize::new(1);
+
+    if !is_interesting(&source) {
+        return Err(eyre::eyre!("initial shader is not interesting"));
+    }
+
+    // Every candidate that has already been tried (including the current program), so that no-op
+    // edits and repeated candidates aren't tested again
+    let mut seen = HashSet::new();
+    seen.insert(source);
+
+    let passes = passes::all();
+
+    loop {
+        let mut progress = false;
+
+        for pass in &passes {
+            let mut site = 0;
+
+            while site < pass.sites(&current) {
+                let mut batch = vec![];
+                let mut next_site = site;
+
+                while batch.len() < jobs && next_site < pass.sites(&current) {
+                    for candidate in pass.candidates(&current, next_site) {
+                        let source = render(&candidate);
+                        if seen.insert(source.clone()) {
+                            batch.push((next_site, source));
+                        }
+                    }
+                    next_site += 1;
+                }
+
+                let results = thread::scope(|scope| {
+                    let handles = batch
+                        .iter()
+                        .map(|(_, source)| {
+                            let is_interesting = &is_interesting;
+                            let tests = &tests;
+                            scope.spawn(move || {
+                                if parser::try_parse(source).is_err() {
+                                    return false;
+                                }
+
+                                tests.fetch_add(1, Ordering::Relaxed);
+                                is_interesting(source)
+                            })
+                        })
+                        .collect::<Vec<_>>();
+
+                    handles
+                        .into_iter()
+                        .map(|it| it.join().unwrap_or(false))
+                        .collect::<Vec<_>>()
+                });
+
+                let accepted = batch
+                    .into_iter()
+                    .zip(results)
+                    .find_map(|(candidate, interesting)| interesting.then_some(candidate));
+
+                if let Some((accepted_site, source)) = accepted {
+                    current = parser::try_parse(&source)?;
+                    progress = true;
+                    // The site that was just reduced may now refer to a different part of the
+                    // program, so try it again
+                    site = accepted_site;
+                } else {
+                    site = next_site;
+                }
+            }
+        }
+
+        if !progress {
+            break;
+        }
+    }
+
+    Ok(Reduction {
+        module: current,
+        tests: tests.into_inner(),
+    })
+}
+
+fn render(module: &Module) -> String {
+    let mut source = String::new();
+
+    ast::writer::Writer::default()
+        .write_module(&mut source, module)
+        .unwrap();
+
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = r#"
+struct S {
+    a: i32,
+    b: u32,
+}
+
+fn f() -> i32 {
+    return 1;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var s: S = S(f(), 2u);
+    if (s.a > 0) {
+        s.b = 3u;
+    }
+    s.a = 4;
+}
+"#;
+
+    fn reduce(is_interesting: impl Fn(&str) -> bool + Sync) -> eyre::Result<Reduction> {
+        super::reduce(parser::parse(SRC), Options { jobs: 2 }, is_interesting)
+    }
+
+    #[test]
+    fn uninteresting_input_is_rejected() {
+        assert!(reduce(|_| false).is_err());
+    }
+
+    #[test]
+    fn stops_when_no_candidate_is_interesting() {
+        let original = render(&parser::parse(SRC));
+        let reduction = reduce(|source| source == original).unwrap();
+
+        assert_eq!(render(&reduction.module), original);
+        assert!(reduction.tests > 1);
+    }
+
+    #[test]
+    fn reduces_to_interesting_core() {
+        let reduction = reduce(|source| source.contains("= 3u;")).unwrap();
+        let expected = r#"
+struct S {
+    b: u32,
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var s: S = S();
+    s.b = 3u;
+}
+"#;
+
+        assert_eq!(render(&reduction.module), render(&parser::parse(expected)));
+    }
+}
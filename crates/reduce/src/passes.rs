@@ -0,0 +1,813 @@
+use std::collections::HashSet;
+
+use ast::visit_mut::{self, VisitMut};
+use ast::*;
+
+/// A family of reductions.
+pub trait Pass {
+    /// Returns the number of sites in the module at which this pass can try to make changes.
+    fn sites(&self, module: &Module) -> usize;
+
+    /// Returns the candidate modules produced by reducing the given site.
+    fn candidates(&self, module: &Module, site: usize) -> Vec<Module>;
+}
+
+pub fn all() -> Vec<Box<dyn Pass>> {
+    vec![
+        Box::new(RemoveDecls),
+        Box::new(RemoveStatements),
+        Box::new(UnwrapBlocks),
+        Box::new(InlineCompounds),
+        Box::new(RemoveStructMembers),
+        Box::new(SimplifyExprs),
+    ]
+}
+
+/// A module-scope declaration that can be removed.
+#[derive(Clone, Copy)]
+enum Decl {
+    Alias(usize),
+    Struct(usize),
+    Const(usize),
+    Override(usize),
+    Var(usize),
+    ConstAssert(usize),
+    Function(usize),
+}
+
+/// Removes type aliases, structs, constants, private and workgroup variables, and functions.
+///
+/// Removing a declaration that is still used produces a program that doesn't parse, so it will be
+/// rejected before testing.
+struct RemoveDecls;
+
+impl RemoveDecls {
+    fn decls(module: &Module) -> Vec<Decl> {
+        let mut decls = vec![];
+
+        decls.extend((0..module.aliases.len()).map(Decl::Alias));
+        decls.extend((0..module.structs.len()).map(Decl::Struct));
+        decls.extend((0..module.consts.len()).map(Decl::Const));
+        decls.extend((0..module.overrides.len()).map(Decl::Override));
+
+        // Resource variables are left alone since they must match the pipeline layout
+        decls.extend(
+            module
+                .vars
+                .iter()
+                .enumerate()
+                .filter(|(_, var)| !is_resource(var))
+                .map(|(i, _)| Decl::Var(i)),
+        );
+
+        decls.extend((0..module.const_asserts.len()).map(Decl::ConstAssert));
+
+        decls.extend(
+            module
+                .functions
+                .iter()
+                .enumerate()
+                .filter(|(_, decl)| decl.name != "main")
+                .map(|(i, _)| Decl::Function(i)),
+        );
+
+        decls
+    }
+}
+
+impl Pass for RemoveDecls {
+    fn sites(&self, module: &Module) -> usize {
+        RemoveDecls::decls(module).len()
+    }
+
+    fn candidates(&self, module: &Module, site: usize) -> Vec<Module> {
+        let mut module = module.clone();
+
+        match RemoveDecls::decls(&module)[site] {
+            Decl::Alias(i) => drop(module.aliases.remove(i)),
+            Decl::Struct(i) => drop(module.structs.remove(i)),
+            Decl::Const(i) => drop(module.consts.remove(i)),
+            Decl::Override(i) => drop(module.overrides.remove(i)),
+            Decl::Var(i) => drop(module.vars.remove(i)),
+            Decl::ConstAssert(i) => drop(module.const_asserts.remove(i)),
+            Decl::Function(i) => drop(module.functions.remove(i)),
+        }
+
+        vec![module]
+    }
+}
+
+/// Removes individual statements, together with any statements nested inside them.
+///
+/// Statements are visited in pre-order, so larger statements are tried before the statements
+/// nested inside them.
+struct RemoveStatements;
+
+impl Pass for RemoveStatements {
+    fn sites(&self, module: &Module) -> usize {
+        count_stmts(module)
+    }
+
+    fn candidates(&self, module: &Module, site: usize) -> Vec<Module> {
+        edit_stmt(module, site, |block, i| {
+            // Removing a return statement with a value makes the function invalid
+            if matches!(
+                &block[i],
+                Statement::Return(ReturnStatement { value: Some(_) })
+            ) {
+                return false;
+            }
+
+            block.remove(i);
+            true
+        })
+        .into_iter()
+        .collect()
+    }
+}
+
+/// Replaces control flow statements with one of their bodies, as a compound statement.
+struct UnwrapBlocks;
+
+impl UnwrapBlocks {
+    fn unwrappings(stmt: &Statement) -> Vec<Statement> {
+        let mut unwrappings = vec![];
+
+        match stmt {
+            Statement::If(stmt) => {
+                unwrappings.push(Statement::Compound(stmt.body.clone()));
+
+                if let Some(els) = &stmt.else_ {
+                    unwrappings.push(match els.as_ref() {
+                        Else::If(stmt) => Statement::If(stmt.clone()),
+                        Else::Else(body) => Statement::Compound(body.clone()),
+                    });
+
+                    unwrappings.push(Statement::If(IfStatement {
+                        else_: None,
+                        ..stmt.clone()
+                    }));
+                }
+            }
+            // Loop bodies can only be unwrapped if they don't rely on being inside a loop
+            Statement::Loop(stmt) if !has_break(&stmt.body) && !has_continue(&stmt.body) => {
+                unwrappings.push(Statement::Compound(stmt.body.clone()));
+            }
+            Statement::While(stmt) if !has_break(&stmt.body) && !has_continue(&stmt.body) => {
+                unwrappings.push(Statement::Compound(stmt.body.clone()));
+            }
+            Statement::ForLoop(stmt) if !has_break(&stmt.body) && !has_continue(&stmt.body) => {
+                let mut body = vec![];
+                if let Some(ForLoopInit::VarDecl(init)) = &stmt.header.init {
+                    body.push(Statement::VarDecl(init.clone()));
+                }
+                body.extend(stmt.body.iter().cloned());
+                unwrappings.push(Statement::Compound(body));
+            }
+            Statement::Switch(stmt) => {
+                let bodies = stmt.cases.iter().map(|it| &it.body).chain([&stmt.default]);
+
+                for body in bodies {
+                    // A continue statement would still refer to the enclosing loop, but breaks and
+                    // fallthroughs only make sense inside the switch
+                    let has_fallthrough =
+                        body.iter().any(|it| matches!(it, Statement::Fallthrough));
+                    if !has_break(body) && !has_fallthrough {
+                        unwrappings.push(Statement::Compound(body.clone()));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        unwrappings
+    }
+}
+
+impl Pass for UnwrapBlocks {
+    fn sites(&self, module: &Module) -> usize {
+        count_stmts(module)
+    }
+
+    fn candidates(&self, module: &Module, site: usize) -> Vec<Module> {
+        let Some(stmt) = stmt_at(module, site) else {
+            return vec![];
+        };
+
+        UnwrapBlocks::unwrappings(&stmt)
+            .into_iter()
+            .filter_map(|replacement| {
+                edit_stmt(module, site, |block, i| {
+                    block[i] = replacement;
+                    true
+                })
+            })
+            .collect()
+    }
+}
+
+/// Splices the contents of compound statements into the enclosing block.
+struct InlineCompounds;
+
+impl Pass for InlineCompounds {
+    fn sites(&self, module: &Module) -> usize {
+        count_stmts(module)
+    }
+
+    fn candidates(&self, module: &Module, site: usize) -> Vec<Module> {
+        edit_stmt(module, site, |block, i| {
+            let Statement::Compound(stmts) = &block[i] else {
+                return false;
+            };
+
+            // The inlined declarations must not conflict with declarations in the enclosing block
+            let enclosing = declared_names(block);
+            if declared_names(stmts)
+                .iter()
+                .any(|it| enclosing.contains(it))
+            {
+                return false;
+            }
+
+            let stmts = stmts.clone();
+            block.splice(i..=i, stmts);
+            true
+        })
+        .into_iter()
+        .collect()
+    }
+}
+
+/// Removes members from structs, along with the corresponding constructor arguments.
+struct RemoveStructMembers;
+
+impl RemoveStructMembers {
+    fn members(module: &Module) -> Vec<(usize, usize)> {
+        // Changing the layout of a buffer would make it incompatible with the input data
+        let mut resource_structs = HashSet::new();
+        for var in module.vars.iter().filter(|it| is_resource(it)) {
+            collect_struct_names(&var.data_type, &mut resource_structs);
+        }
+
+        let mut members = vec![];
+        for (i, decl) in module.structs.iter().enumerate() {
+            if decl.members.len() > 1 && !resource_structs.contains(&decl.name) {
+                members.extend((0..decl.members.len()).map(|j| (i, j)));
+            }
+        }
+
+        members
+    }
+}
+
+impl Pass for RemoveStructMembers {
+    fn sites(&self, module: &Module) -> usize {
+        RemoveStructMembers::members(module).len()
+    }
+
+    fn candidates(&self, module: &Module, site: usize) -> Vec<Module> {
+        struct ConstructorArgs<'a> {
+            name: &'a str,
+            index: usize,
+        }
+
+        impl VisitMut for ConstructorArgs<'_> {
+            fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+                visit_mut::walk_expr(self, node);
+
+                let args = match &mut node.expr {
+                    Expr::FnCall(expr) if expr.ident == self.name => &mut expr.args,
+                    Expr::TypeCons(TypeConsExpr {
+                        data_type: DataType::Struct(decl),
+                        args,
+                    }) if decl.name == self.name => args,
+                    _ => return,
+                };
+
+                if self.index < args.len() {
+                    args.remove(self.index);
+                }
+            }
+        }
+
+        let (i, j) = RemoveStructMembers::members(module)[site];
+        let mut module = module.clone();
+
+        let decl = &module.structs[i];
+        let mut members = decl.members.clone();
+        members.remove(j);
+        module.structs[i] = StructDecl::new(decl.name.clone(), members);
+
+        let name = module.structs[i].name.clone();
+        ConstructorArgs {
+            name: &name,
+            index: j,
+        }
+        .visit_module_mut(&mut module);
+
+        vec![module]
+    }
+}
+
+/// Replaces expressions with the zero value of their type, or with one of their operands of the
+/// same type.
+struct SimplifyExprs;
+
+impl SimplifyExprs {
+    fn replacements(node: &ExprNode) -> Vec<ExprNode> {
+        let mut replacements = vec![];
+        let ty = node.data_type.dereference();
+
+        let is_zero = match &node.expr {
+            Expr::Lit(lit) => match lit {
+                Lit::Bool(v) => !*v,
+                Lit::I32(v) => *v == 0,
+                Lit::U32(v) => *v == 0,
+                Lit::F32(v) => *v == 0.0,
+            },
+            Expr::TypeCons(expr) => expr.args.is_empty(),
+            _ => false,
+        };
+
+        if !is_zero {
+            replacements.extend(zero_value(ty));
+        }
+
+        let operands = match &node.expr {
+            Expr::UnOp(expr) => match expr.op {
+                UnOp::Neg | UnOp::Not | UnOp::BitNot => vec![expr.inner.as_ref()],
+                UnOp::AddressOf | UnOp::Deref => vec![],
+            },
+            Expr::BinOp(expr) => vec![expr.left.as_ref(), expr.right.as_ref()],
+            Expr::FnCall(FnCallExpr { args, .. }) | Expr::TypeCons(TypeConsExpr { args, .. }) => {
+                args.iter().collect()
+            }
+            _ => vec![],
+        };
+
+        replacements.extend(
+            operands
+                .into_iter()
+                .filter(|it| it.data_type.dereference() == ty)
+                .cloned(),
+        );
+
+        replacements
+    }
+}
+
+impl Pass for SimplifyExprs {
+    fn sites(&self, module: &Module) -> usize {
+        let mut module = module.clone();
+        let mut sites = ExprSites {
+            target: usize::MAX,
+            next: 0,
+            edit: None,
+        };
+
+        sites.visit_module_mut(&mut module);
+        sites.next
+    }
+
+    fn candidates(&self, module: &Module, site: usize) -> Vec<Module> {
+        let mut node = None;
+        edit_expr(module, site, |it| node = Some(it.clone()));
+
+        let Some(node) = node else {
+            return vec![];
+        };
+
+        SimplifyExprs::replacements(&node)
+            .into_iter()
+            .filter_map(|replacement| edit_expr(module, site, |it| *it = replacement))
+            .collect()
+    }
+}
+
+type StmtEdit<'a> = Box<dyn FnOnce(&mut Vec<Statement>, usize) -> bool + 'a>;
+
+/// Visits statements in pre-order, applying an edit to the block containing the target
+/// statement.
+struct StmtSites<'a> {
+    target: usize,
+    next: usize,
+    edit: Option<StmtEdit<'a>>,
+    applied: bool,
+}
+
+impl VisitMut for StmtSites<'_> {
+    fn visit_block_mut(&mut self, block: &mut Vec<Statement>) {
+        for i in 0..block.len() {
+            if self.next == self.target {
+                if let Some(edit) = self.edit.take() {
+                    self.applied = edit(block, i);
+                }
+                return;
+            }
+
+            self.next += 1;
+            self.visit_stmt_mut(&mut block[i]);
+        }
+    }
+
+    fn visit_expr_mut(&mut self, _: &mut ExprNode) {
+        // Expressions can't contain statements
+    }
+}
+
+fn count_stmts(module: &Module) -> usize {
+    let mut sites = StmtSites {
+        target: usize::MAX,
+        next: 0,
+        edit: None,
+        applied: false,
+    };
+
+    sites.visit_module_mut(&mut module.clone());
+    sites.next
+}
+
+fn stmt_at(module: &Module, site: usize) -> Option<Statement> {
+    let mut stmt = None;
+    edit_stmt(module, site, |block, i| {
+        stmt = Some(block[i].clone());
+        true
+    });
+    stmt
+}
+
+/// Applies an edit to a copy of the module, returning the result if the edit was applicable.
+fn edit_stmt<'a>(
+    module: &Module,
+    site: usize,
+    edit: impl FnOnce(&mut Vec<Statement>, usize) -> bool + 'a,
+) -> Option<Module> {
+    let mut module = module.clone();
+    let mut sites = StmtSites {
+        target: site,
+        next: 0,
+        edit: Some(Box::new(edit)),
+        applied: false,
+    };
+
+    sites.visit_module_mut(&mut module);
+    sites.applied.then_some(module)
+}
+
+type ExprEdit<'a> = Box<dyn FnOnce(&mut ExprNode) + 'a>;
+
+/// Visits expressions in pre-order, applying an edit to the target expression.
+struct ExprSites<'a> {
+    target: usize,
+    next: usize,
+    edit: Option<ExprEdit<'a>>,
+}
+
+impl VisitMut for ExprSites<'_> {
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        if self.next == self.target {
+            if let Some(edit) = self.edit.take() {
+                edit(node);
+            }
+            return;
+        }
+
+        self.next += 1;
+
+        // The operand of an address-of expression must remain a reference
+        if let Expr::UnOp(UnOpExpr {
+            op: UnOp::AddressOf,
+            ..
+        }) = &node.expr
+        {
+            return;
+        }
+
+        visit_mut::walk_expr(self, node);
+    }
+
+    fn visit_switch_case_mut(&mut self, case: &mut SwitchCase) {
+        // Case selectors are skipped since they must remain distinct
+        self.visit_block_mut(&mut case.body);
+    }
+}
+
+fn edit_expr<'a>(
+    module: &Module,
+    site: usize,
+    edit: impl FnOnce(&mut ExprNode) + 'a,
+) -> Option<Module> {
+    let mut module = module.clone();
+    let mut sites = ExprSites {
+        target: site,
+        next: 0,
+        edit: Some(Box::new(edit)),
+    };
+
+    sites.visit_module_mut(&mut module);
+    sites.edit.is_none().then_some(module)
+}
+
+fn is_resource(var: &GlobalVarDecl) -> bool {
+    matches!(
+        var.qualifier.as_ref().map(|it| it.storage_class),
+        Some(StorageClass::Uniform | StorageClass::Storage)
+    )
+}
+
+fn collect_struct_names(ty: &DataType, names: &mut HashSet<String>) {
+    match ty {
+        DataType::Array(inner, _) => collect_struct_names(inner, names),
+        DataType::Struct(decl) => {
+            names.insert(decl.name.clone());
+            for member in &decl.members {
+                collect_struct_names(&member.data_type, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the names declared by let and var statements directly inside a block.
+fn declared_names(block: &[Statement]) -> HashSet<&str> {
+    block
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::LetDecl(stmt) => Some(stmt.ident.as_str()),
+            Statement::VarDecl(stmt) => Some(stmt.ident.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns true if the block contains a break statement that would exit an enclosing loop or
+/// switch.
+fn has_break(block: &[Statement]) -> bool {
+    block.iter().any(|stmt| match stmt {
+        Statement::Break => true,
+        Statement::Compound(stmts) => has_break(stmts),
+        Statement::If(stmt) => if_has(stmt, has_break),
+        _ => false,
+    })
+}
+
+/// Returns true if the block contains a continue statement that would continue an enclosing
+/// loop.
+fn has_continue(block: &[Statement]) -> bool {
+    block.iter().any(|stmt| match stmt {
+        Statement::Continue => true,
+        Statement::Compound(stmts) => has_continue(stmts),
+        Statement::If(stmt) => if_has(stmt, has_continue),
+        Statement::Switch(stmt) => {
+            stmt.cases.iter().any(|it| has_continue(&it.body)) || has_continue(&stmt.default)
+        }
+        _ => false,
+    })
+}
+
+fn if_has(stmt: &IfStatement, f: fn(&[Statement]) -> bool) -> bool {
+    f(&stmt.body)
+        || match stmt.else_.as_deref() {
+            Some(Else::If(stmt)) => if_has(stmt, f),
+            Some(Else::Else(stmts)) => f(stmts),
+            None => false,
+        }
+}
+
+fn zero_value(ty: &DataType) -> Option<ExprNode> {
+    fn is_constructible(ty: &DataType) -> bool {
+        match ty {
+            DataType::Scalar(t) | DataType::Vector(_, t) | DataType::Matrix(_, _, t) => {
+                !matches!(t, ScalarType::AI32 | ScalarType::AU32)
+            }
+            DataType::Array(inner, n) => n.is_some() && is_constructible(inner),
+            DataType::Struct(decl) => decl
+                .members
+                .iter()
+                .all(|it| is_constructible(&it.data_type)),
+            DataType::Ptr(_) | DataType::Ref(_) => false,
+        }
+    }
+
+    if !is_constructible(ty) {
+        return None;
+    }
+
+    Some(match ty {
+        DataType::Scalar(t) => match t {
+            ScalarType::Bool => Lit::Bool(false).into(),
+            ScalarType::I32 => Lit::I32(0).into(),
+            ScalarType::U32 => Lit::U32(0).into(),
+            ScalarType::F32 => Lit::F32(0.0).into(),
+            ScalarType::AI32 | ScalarType::AU32 => unreachable!(),
+        },
+        ty => TypeConsExpr::new(ty.clone(), vec![]).into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    /// Returns every candidate produced by the pass, across all sites, which is still accepted by
+    /// the parser.
+    fn reductions(pass: &dyn Pass, source: &str) -> Vec<String> {
+        let module = parser::parse(source);
+
+        (0..pass.sites(&module))
+            .flat_map(|site| pass.candidates(&module, site))
+            .map(|candidate| render(&candidate))
+            .filter(|source| parser::try_parse(source).is_ok())
+            .collect()
+    }
+
+    fn assert_reduces_to(pass: &dyn Pass, source: &str, expected: &str) {
+        let expected = render(&parser::parse(expected));
+        let reductions = reductions(pass, source);
+
+        assert!(
+            reductions.contains(&expected),
+            "expected reduction:\n{expected}\nactual reductions:\n{}",
+            reductions.join("\n---\n")
+        );
+
+        assert!(expected.len() < render(&parser::parse(source)).len());
+    }
+
+    #[test]
+    fn remove_decls_removes_unused_function() {
+        assert_reduces_to(
+            &RemoveDecls,
+            r#"
+fn f() {}
+
+@compute @workgroup_size(1)
+fn main() {}
+"#,
+            r#"
+@compute @workgroup_size(1)
+fn main() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn remove_decls_keeps_resources_and_entry_point() {
+        let module = parser::parse(
+            r#"
+@group(0) @binding(0)
+var<storage, read_write> output: u32;
+
+@compute @workgroup_size(1)
+fn main() {}
+"#,
+        );
+
+        assert_eq!(RemoveDecls.sites(&module), 0);
+    }
+
+    #[test]
+    fn remove_statements_removes_assignment() {
+        assert_reduces_to(
+            &RemoveStatements,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+    x = 2;
+}
+"#,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn unwrap_blocks_replaces_if_with_body() {
+        assert_reduces_to(
+            &UnwrapBlocks,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+    if (x > 0) {
+        x = 2;
+    }
+}
+"#,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+    {
+        x = 2;
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn unwrap_blocks_keeps_loop_with_break() {
+        let source = r#"
+@compute @workgroup_size(1)
+fn main() {
+    loop {
+        break;
+    }
+}
+"#;
+
+        assert!(reductions(&UnwrapBlocks, source).is_empty());
+    }
+
+    #[test]
+    fn inline_compounds_splices_into_enclosing_block() {
+        assert_reduces_to(
+            &InlineCompounds,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+    {
+        x = 2;
+    }
+}
+"#,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+    x = 2;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn inline_compounds_avoids_name_conflicts() {
+        let source = r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+    {
+        var x: i32 = 2;
+    }
+}
+"#;
+
+        assert!(reductions(&InlineCompounds, source).is_empty());
+    }
+
+    #[test]
+    fn remove_struct_members_updates_constructors() {
+        assert_reduces_to(
+            &RemoveStructMembers,
+            r#"
+struct S {
+    a: i32,
+    b: u32,
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var s: S = S(1, 2u);
+}
+"#,
+            r#"
+struct S {
+    a: i32,
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var s: S = S(1);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn simplify_exprs_replaces_binop_with_operand() {
+        assert_reduces_to(
+            &SimplifyExprs,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1 + 2;
+}
+"#,
+            r#"
+@compute @workgroup_size(1)
+fn main() {
+    var x: i32 = 1;
+}
+"#,
+        );
+    }
+}
@@ -1,23 +1,14 @@
 use ast::{Module, StorageClass, VarQualifier};
+use common::AddressSpace;
 pub use types::{PipelineDescription, PipelineResource, ResourceData, ResourceKind};
 
-
-
-fn update_size(value: &ast::DataType, init: &Option<Vec<u8>>) -> ast::DataType {
-  match value {
-    ast::DataType::Array(inner, size) => {
-      ast::DataType::array (
-      inner.as_ref().clone(),
-      size.or(init.as_ref().map(|i| u32::try_from(i.len()/inner.size_bytes()).ok()).flatten()))
-    },
-    other => other.clone()
-  }
-}
-
+/// Returns the resources used by a module along with their types.
+///
+/// Returns an error if the type of a resource is not host-shareable in its address space.
 pub fn reflect(
     module: &Module,
     mut init: impl FnMut(ResourceData<'_>) -> Option<Vec<u8>>,
-) -> (PipelineDescription, Vec<common::Type>) {
+) -> Result<(PipelineDescription, Vec<common::Type>), String> {
     let mut resources = vec![];
     let mut types = vec![];
 
@@ -43,14 +34,17 @@ pub fn reflect(
                 binding,
             });
 
-            let data_type = update_size(&var.data_type, &buffer_init);
-
-            let type_desc =
-                common::Type::try_from(&data_type).expect("invalid type for pipeline resource");
+            // Runtime-sized arrays are sized to fit the initial data
+            let space = AddressSpace::from_storage_class(*storage_class).unwrap();
+            let type_desc = match &buffer_init {
+                Some(init) => common::Type::from_buffer_len(&var.data_type, space, init.len()),
+                None => common::Type::from_data_type(&var.data_type, space),
+            }
+            .map_err(|e| format!("invalid type for resource `{}`: {e}", var.name))?;
 
             let init = buffer_init.map(|mut init| {
-              init.resize(type_desc.buffer_size() as usize, 0);
-              init
+                init.resize(type_desc.buffer_size() as usize, 0);
+                init
             });
 
             resources.push(PipelineResource {
@@ -66,5 +60,5 @@ pub fn reflect(
         }
     }
 
-    (PipelineDescription { resources }, types)
+    Ok((PipelineDescription { resources }, types))
 }
@@ -27,7 +27,7 @@ pub fn run(options: Options) -> eyre::Result<()> {
     );
 
     let shader = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&shader);
+    let ast = parser::try_parse(&shader)?;
 
     // Toss the work to our library, then we write after
     // Not sure if we can change the block size here
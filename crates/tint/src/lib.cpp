@@ -80,3 +80,20 @@ std::unique_ptr<std::string> compile_shader_to_msl(const char* source) {
 
     return std::make_unique<std::string>(std::move(result.msl));
 }
+
+std::unique_ptr<std::vector<uint32_t>> compile_shader_to_spirv(const char* source) {
+    auto source_file = std::make_unique<tint::Source::File>("[memory]", source);
+    auto program = std::make_unique<tint::Program>(tint::reader::wgsl::Parse(source_file.get()));
+
+    if (!program->IsValid()) {
+        return nullptr;
+    }
+
+    tint::writer::spirv::Options gen_options;
+    auto result = tint::writer::spirv::Generate(program.get(), gen_options);
+    if (!result.success) {
+        return nullptr;
+    }
+
+    return std::make_unique<std::vector<uint32_t>>(std::move(result.spirv));
+}
@@ -7,6 +7,7 @@ mod ffi {
         unsafe fn validate_shader(source: *const c_char) -> bool;
         unsafe fn compile_shader_to_hlsl(source: *const c_char) -> UniquePtr<CxxString>;
         unsafe fn compile_shader_to_msl(source: *const c_char) -> UniquePtr<CxxString>;
+        unsafe fn compile_shader_to_spirv(source: *const c_char) -> UniquePtr<CxxVector<u32>>;
     }
 }
 
@@ -24,3 +25,9 @@ pub fn compile_shader_to_msl(source: &str) -> String {
     let source = CString::new(source).unwrap();
     unsafe { ffi::compile_shader_to_msl(source.as_ptr()) }.to_string()
 }
+
+pub fn compile_shader_to_spirv(source: &str) -> Option<Vec<u32>> {
+    let source = CString::new(source).unwrap();
+    let spirv = unsafe { ffi::compile_shader_to_spirv(source.as_ptr()) };
+    spirv.as_ref().map(|it| it.iter().copied().collect())
+}
@@ -8,9 +8,10 @@ use clap::Parser;
 
 use rand::seq::SliceRandom;
 use rand::thread_rng;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::path::Path;
 
+use common::InputData;
 use eyre::{eyre, Context};
 
 use std::io::{self, Cursor};
@@ -44,7 +45,7 @@ pub fn run(options: Options) -> eyre::Result<()> {
     eprintln!("Running shader...");
     // Grab necessary data from the command execution
     let shader = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&shader);
+    let ast = parser::try_parse(&shader)?;
 
     // Insert Flow and write AST to string
     //let flowed_ast = flow(ast.clone()); NOTE: Removed to keep the workflowpath
@@ -53,7 +54,7 @@ pub fn run(options: Options) -> eyre::Result<()> {
 
     let input_data = read_input_data(&options.input, options.input_data.as_deref())?;
     // Get the pipeline desc to run, and the runner config
-    let (pipeline_desc, _) = reflect_shader(shader.as_str(), input_data);
+    let (pipeline_desc, _) = reflect_shader(shader.as_str(), input_data)?;
     use types::Config;
     let Config {
         id: runner_config,
@@ -137,10 +138,7 @@ fn read_shader_from_path(path: &str) -> eyre::Result<String> {
     Ok(shader)
 }
 
-fn read_input_data(
-    shader: &str,
-    input_data: Option<&str>,
-) -> eyre::Result<HashMap<String, Vec<u8>>> {
+fn read_input_data(shader: &str, input_data: Option<&str>) -> eyre::Result<InputData> {
     match input_data {
         Some(input_data) => {
             match serde_json::from_str(input_data)
@@ -175,16 +173,22 @@ fn read_input_data(
 
 fn reflect_shader(
     shader: &str,
-    mut input_data: HashMap<String, Vec<u8>>,
-) -> (PipelineDescription, Vec<common::Type>) {
+    input_data: InputData,
+) -> eyre::Result<(PipelineDescription, Vec<common::Type>)> {
     // This is innefficient in this module, since we use the parser twice
     // TODO: Make this more efficient (for now I will leave it the same since it comes from
     // harness stuff
-    let module = parser::parse(shader);
+    let module = parser::try_parse(shader)?;
+
+    let mut input_data = input_data
+        .to_bytes(&module)
+        .map_err(|e| eyre!(e))
+        .wrap_err("failed to parse input data")?;
 
     let (mut pipeline_desc, type_descs) = reflection::reflect(&module, |resource| {
         input_data.remove(&format!("{}:{}", resource.group, resource.binding))
-    });
+    })
+    .map_err(|e| eyre!(e))?;
 
     let mut resource_vars = HashSet::new();
 
@@ -198,7 +202,7 @@ fn reflect_shader(
         .resources
         .retain(|resource| !resource_vars.contains(&resource.name));
 
-    (pipeline_desc, type_descs)
+    Ok((pipeline_desc, type_descs))
 }
 
 fn u8s_to_u32s(from: &Vec<u8>) -> Vec<u32> {
@@ -1,8 +1,10 @@
 pub mod cli;
 mod ub;
 
+use ast::fold::Fold;
 use ast::types::{DataType, ScalarType};
 use ast::*;
+use flow::BlockInstrumenter;
 use std::rc::Rc;
 use ub::generate_ub;
 
@@ -83,186 +85,20 @@ pub fn insert_ub(mut ast: Module, flow: Vec<u32>, size: usize) -> Module {
         initializer: None,
     });
 
-    let mut inserter = UBInserter::new(
-        Options { blocks: flow },
-        ub_struct.clone(),
-        ub_arr_type.clone(),
-    );
+    let options = Options { blocks: flow };
+    let mut inserter = BlockInstrumenter::new(|block| {
+        options
+            .blocks
+            .contains(&block)
+            .then(|| generate_ub(ub_struct.clone(), ub_arr_type.clone()))
+    });
     ast.functions = ast
         .functions
         .into_iter()
-        .map(|f| inserter.analyze_fn(f))
+        .map(|f| inserter.fold_fn_decl(f))
         .collect::<Vec<_>>();
 
     // check the reconditioner here
 
     ast
 }
-
-// May be more to keep in the state
-struct UBInserter {
-    block_count: u32,
-    blocks: Vec<u32>,
-    ub_struct: Rc<StructDecl>,
-    arr_type: DataType,
-}
-
-impl UBInserter {
-    fn new(options: Options, ub_struct: Rc<StructDecl>, arr_type: DataType) -> UBInserter {
-        UBInserter {
-            block_count: 0,
-            blocks: options.blocks,
-            ub_struct,
-            arr_type,
-        }
-    }
-
-    fn build_assign(&mut self) -> Option<Statement> {
-        if !self.blocks.contains(&self.block_count) {
-            self.block_count += 1;
-            return None;
-        }
-        self.block_count += 1;
-        Some(generate_ub(self.ub_struct.clone(), self.arr_type.clone()).into())
-    }
-
-    fn analyze_fn(&mut self, mut decl: FnDecl) -> FnDecl {
-        // Insert the assignment at the beginning of a function
-        if !decl.name.starts_with("_wgslsmith_") {
-            if let Some(insertion) = self.build_assign() {
-                decl.body.insert(0, insertion.into());
-            }
-        }
-
-        decl.body = decl
-            .body
-            .into_iter()
-            .map(|s| self.analyze_stmt(s))
-            .collect();
-
-        decl
-    }
-
-    fn analyze_else(&mut self, els: Else) -> Else {
-        match els {
-            Else::If(IfStatement {
-                condition,
-                body,
-                else_,
-            }) => {
-                let mod_body: Vec<Statement> =
-                    body.into_iter().map(|s| self.analyze_stmt(s)).collect();
-                let new_body = if let Some(insertion) = self.build_assign() {
-                    vec![Statement::Compound(vec![insertion.into(), mod_body.into()])]
-                } else {
-                    mod_body
-                };
-                Else::If(IfStatement {
-                    condition,
-                    body: new_body,
-                    else_: else_.map(|els| Box::new(self.analyze_else(*els))),
-                })
-            }
-            Else::Else(mut stmts) => {
-                if let Some(insertion) = self.build_assign() {
-                    stmts.insert(0, insertion);
-                }
-
-                Else::Else(stmts.into_iter().map(|s| self.analyze_stmt(s)).collect())
-            }
-        }
-    }
-
-    fn analyze_stmt(&mut self, stmt: Statement) -> Statement {
-        match stmt {
-            // The first few matches do nothing since we want to preserve
-            // the ast and need an exhaustive match
-            Statement::If(IfStatement {
-                condition,
-                body,
-                else_,
-            }) => {
-                let mod_body: Vec<Statement> =
-                    body.into_iter().map(|s| self.analyze_stmt(s)).collect();
-                let new_body = if let Some(insertion) = self.build_assign() {
-                    vec![Statement::Compound(vec![insertion.into(), mod_body.into()])]
-                } else {
-                    mod_body
-                };
-                IfStatement::new(condition, new_body)
-                    .with_else(else_.map(|els| self.analyze_else(*els)))
-                    .into()
-            }
-            Statement::Loop(LoopStatement { body }) => {
-                let mod_body: Vec<Statement> =
-                    body.into_iter().map(|s| self.analyze_stmt(s)).collect();
-                let new_body = if let Some(insertion) = self.build_assign() {
-                    vec![Statement::Compound(vec![insertion.into(), mod_body.into()])]
-                } else {
-                    mod_body
-                };
-                LoopStatement::new(new_body).into()
-            }
-            Statement::Break => Statement::Break,
-            Statement::Switch(SwitchStatement {
-                selector,
-                cases,
-                default,
-            }) => {
-                let new_cases = cases
-                    .into_iter()
-                    .map(|SwitchCase { selector, body }| {
-                        let mod_body: Vec<Statement> =
-                            body.into_iter().map(|it| self.analyze_stmt(it)).collect();
-                        let new_body = if let Some(insertion) = self.build_assign() {
-                            vec![Statement::Compound(vec![insertion.into(), mod_body.into()])]
-                        } else {
-                            mod_body
-                        };
-                        SwitchCase {
-                            selector,
-                            body: new_body,
-                        }
-                    })
-                    .collect();
-                let mod_default: Vec<Statement> = default
-                    .into_iter()
-                    .map(|it| self.analyze_stmt(it))
-                    .collect();
-                let new_default = if let Some(insertion) = self.build_assign() {
-                    vec![Statement::Compound(vec![
-                        insertion.into(),
-                        mod_default.into(),
-                    ])]
-                } else {
-                    mod_default
-                };
-                SwitchStatement::new(selector, new_cases, new_default).into()
-            }
-            Statement::ForLoop(ForLoopStatement { header, body }) => {
-                let stmt = Statement::ForLoop(ForLoopStatement::new(*header.clone(), body.clone()).into());
-                let mod_body: Vec<Statement> =
-                    body.clone().into_iter().map(|it| self.analyze_stmt(it)).collect();
-                let mut new_body = if let Some(insertion) = self.build_assign() {
-                    vec![Statement::Compound(vec![insertion.into(), mod_body.into()])]
-                } else {
-                    mod_body
-                };
-                if stmt.to_string().contains("_wgslsmith_ub") {
-                    println!("Ignoring");
-                    new_body = body;
-                }
-                ForLoopStatement::new(*header, new_body).into()
-            }
-            Statement::Continue => Statement::Continue,
-            Statement::Fallthrough => Statement::Fallthrough,
-            Statement::Compound(stmts) => {
-                Statement::Compound(stmts
-                    .into_iter()
-                    .map(|it| self.analyze_stmt(it))
-                    .collect())
-            }
-            _ => stmt,
-        }
-    }
-}
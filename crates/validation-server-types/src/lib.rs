@@ -1,9 +1,12 @@
 use bincode::{Decode, Encode};
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
 pub enum Backend {
     Hlsl,
     Msl,
+    /// SPIR-V assembly, in the format accepted by `spirv-as`.
+    Spirv,
+    Glsl,
 }
 
 #[derive(Debug, Encode, Decode)]
@@ -11,6 +14,8 @@ pub enum Request {
     GetCount,
     ResetCount,
     Validate { backend: Backend, source: String },
+    // New variants must be appended, since the discriminant is part of the wire format
+    GetBackends,
 }
 
 #[derive(Debug, Encode, Decode)]
@@ -18,8 +23,15 @@ pub struct GetCountResponse {
     pub count: u64,
 }
 
+#[derive(Debug, Encode, Decode)]
+pub struct GetBackendsResponse {
+    pub backends: Vec<Backend>,
+}
+
 #[derive(Debug, Encode, Decode)]
 pub enum ValidateResponse {
     Success,
     Failure(String),
+    /// The server isn't able to validate shaders for the requested backend.
+    Unsupported,
 }
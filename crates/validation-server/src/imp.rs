@@ -1,103 +1,43 @@
-use std::io::{BufReader, BufWriter, Write};
-use std::net::TcpListener;
+use std::io::Write;
 use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
 use std::{env, ptr};
 
-use bincode::Encode;
 use clap::Parser;
 use color_eyre::eyre;
 use tempfile::NamedTempFile;
-use threadpool::ThreadPool;
-use types::{GetCountResponse, Request, ValidateResponse};
+use types::{Backend, ValidateResponse};
 use windows::core::PCSTR;
 use windows::Win32::Graphics::Direct3D::Fxc::D3DCompile;
 
-#[derive(Parser)]
-pub struct Options {
-    /// Server bind address.
-    #[clap(short, long, default_value = "localhost:0")]
-    address: String,
+use crate::server::{self, Options, Validator};
 
-    /// Number of worker threads to use.
-    ///
-    /// Defaults to the number of available CPUs.
-    #[clap(long)]
-    parallelism: Option<usize>,
-
-    #[clap(short, long)]
+/// Validates HLSL with FXC and MSL with the Metal Developer Tools for Windows.
+struct WindowsValidator {
     quiet: bool,
 }
 
-pub fn run() -> eyre::Result<()> {
-    let options = Options::parse();
-    let parallelism = options
-        .parallelism
-        .unwrap_or_else(|| std::thread::available_parallelism().unwrap().get());
-
-    let pool = ThreadPool::new(parallelism);
-    println!("Using thread pool with {parallelism} threads");
-
-    let listener = TcpListener::bind(options.address).unwrap();
-    let address = listener.local_addr().unwrap();
-    println!("Server listening at {address}");
-
-    let quiet = options.quiet;
-    let counter = Arc::new(AtomicU64::new(0));
-
-    for stream in listener.incoming() {
-        let counter = counter.clone();
-        counter.fetch_add(1, Ordering::SeqCst);
-        pool.execute(move || {
-            let stream = stream.unwrap();
-
-            let mut reader = BufReader::new(&stream);
-            let mut writer = BufWriter::new(&stream);
-
-            let req: Request =
-                bincode::decode_from_std_read(&mut reader, bincode::config::standard()).unwrap();
-
-            enum Response {
-                GetCount(GetCountResponse),
-                Validate(ValidateResponse),
-            }
-
-            impl Encode for Response {
-                fn encode<E: bincode::enc::Encoder>(
-                    &self,
-                    encoder: &mut E,
-                ) -> Result<(), bincode::error::EncodeError> {
-                    match self {
-                        Response::GetCount(inner) => inner.encode(encoder),
-                        Response::Validate(inner) => inner.encode(encoder),
-                    }
-                }
-            }
-
-            let res = match req {
-                Request::GetCount => Response::GetCount(GetCountResponse {
-                    count: counter.load(Ordering::SeqCst),
-                }),
-                Request::ResetCount => {
-                    counter.store(0, Ordering::SeqCst);
-                    return;
-                }
-                Request::Validate { backend, source } => match backend {
-                    types::Backend::Hlsl => {
-                        Response::Validate(validate_hlsl(&source, quiet).unwrap())
-                    }
-                    types::Backend::Msl => {
-                        Response::Validate(validate_metal(&source, quiet).unwrap())
-                    }
-                },
-            };
+impl Validator for WindowsValidator {
+    fn backends(&self) -> Vec<Backend> {
+        vec![Backend::Hlsl, Backend::Msl]
+    }
 
-            bincode::encode_into_std_write(res, &mut writer, bincode::config::standard()).unwrap();
-        });
+    fn validate(&self, backend: Backend, source: &str) -> eyre::Result<ValidateResponse> {
+        match backend {
+            Backend::Hlsl => validate_hlsl(source, self.quiet),
+            Backend::Msl => validate_metal(source, self.quiet),
+            Backend::Spirv | Backend::Glsl => Ok(ValidateResponse::Unsupported),
+        }
     }
+}
 
-    Ok(())
+pub fn run() -> eyre::Result<()> {
+    let options = Options::parse();
+    server::run(
+        &options,
+        WindowsValidator {
+            quiet: options.quiet,
+        },
+    )
 }
 
 fn validate_hlsl(hlsl: &str, quiet: bool) -> eyre::Result<ValidateResponse> {
@@ -1,12 +1,13 @@
+mod server;
+
 #[cfg(target_os = "windows")]
 mod imp;
 
 #[cfg(not(target_os = "windows"))]
-mod imp {
-    pub fn run() -> color_eyre::Result<()> {
-        unimplemented!("fxc-server can only be used on Windows (or with Wine)");
-    }
-}
+mod unix;
+
+#[cfg(not(target_os = "windows"))]
+use unix as imp;
 
 fn main() -> color_eyre::Result<()> {
     imp::run()
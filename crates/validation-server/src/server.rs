@@ -0,0 +1,111 @@
+use std::io::{BufReader, BufWriter};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use bincode::Encode;
+use clap::Parser;
+use color_eyre::eyre;
+use threadpool::ThreadPool;
+use types::{Backend, GetBackendsResponse, GetCountResponse, Request, ValidateResponse};
+
+#[derive(Parser)]
+pub struct Options {
+    /// Server bind address.
+    #[clap(short, long, default_value = "localhost:0")]
+    pub address: String,
+
+    /// Number of worker threads to use.
+    ///
+    /// Defaults to the number of available CPUs.
+    #[clap(long)]
+    pub parallelism: Option<usize>,
+
+    #[clap(short, long)]
+    pub quiet: bool,
+}
+
+/// A set of shader compilers that can be used to validate shaders.
+pub trait Validator: Send + Sync + 'static {
+    /// Returns the backends that this validator supports.
+    fn backends(&self) -> Vec<Backend>;
+
+    /// Validates a shader for a backend returned by [`Validator::backends`].
+    fn validate(&self, backend: Backend, source: &str) -> eyre::Result<ValidateResponse>;
+}
+
+pub fn run(options: &Options, validator: impl Validator) -> eyre::Result<()> {
+    let parallelism = options
+        .parallelism
+        .unwrap_or_else(|| std::thread::available_parallelism().unwrap().get());
+
+    let pool = ThreadPool::new(parallelism);
+    println!("Using thread pool with {parallelism} threads");
+
+    let backends = validator.backends();
+    println!("Available backends: {backends:?}");
+
+    let listener = TcpListener::bind(&options.address).unwrap();
+    let address = listener.local_addr().unwrap();
+    println!("Server listening at {address}");
+
+    let validator = Arc::new(validator);
+    let counter = Arc::new(AtomicU64::new(0));
+
+    for stream in listener.incoming() {
+        let validator = validator.clone();
+        let backends = backends.clone();
+        let counter = counter.clone();
+        counter.fetch_add(1, Ordering::SeqCst);
+        pool.execute(move || {
+            let stream = stream.unwrap();
+
+            let mut reader = BufReader::new(&stream);
+            let mut writer = BufWriter::new(&stream);
+
+            let req: Request =
+                bincode::decode_from_std_read(&mut reader, bincode::config::standard()).unwrap();
+
+            enum Response {
+                GetCount(GetCountResponse),
+                GetBackends(GetBackendsResponse),
+                Validate(ValidateResponse),
+            }
+
+            impl Encode for Response {
+                fn encode<E: bincode::enc::Encoder>(
+                    &self,
+                    encoder: &mut E,
+                ) -> Result<(), bincode::error::EncodeError> {
+                    match self {
+                        Response::GetCount(inner) => inner.encode(encoder),
+                        Response::GetBackends(inner) => inner.encode(encoder),
+                        Response::Validate(inner) => inner.encode(encoder),
+                    }
+                }
+            }
+
+            let res = match req {
+                Request::GetCount => Response::GetCount(GetCountResponse {
+                    count: counter.load(Ordering::SeqCst),
+                }),
+                Request::ResetCount => {
+                    counter.store(0, Ordering::SeqCst);
+                    return;
+                }
+                Request::GetBackends => Response::GetBackends(GetBackendsResponse { backends }),
+                Request::Validate { backend, source } => {
+                    if backends.contains(&backend) {
+                        Response::Validate(validator.validate(backend, &source).unwrap())
+                    } else {
+                        Response::Validate(ValidateResponse::Unsupported)
+                    }
+                }
+            };
+
+            bincode::encode_into_std_write(res, &mut writer, bincode::config::standard()).unwrap();
+        });
+    }
+
+    Ok(())
+}
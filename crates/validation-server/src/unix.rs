@@ -0,0 +1,176 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use clap::Parser;
+use color_eyre::eyre;
+use tempfile::{Builder, NamedTempFile};
+use types::{Backend, ValidateResponse};
+
+use crate::server::{self, Validator};
+
+#[derive(Parser)]
+pub struct Options {
+    #[clap(flatten)]
+    server: server::Options,
+
+    #[clap(flatten)]
+    tools: Tools,
+}
+
+#[derive(Parser)]
+struct Tools {
+    /// Path to the DXC executable, used to validate HLSL.
+    #[clap(long, default_value = "dxc")]
+    dxc: PathBuf,
+
+    /// Path to the spirv-as executable, used to assemble SPIR-V before validation.
+    #[clap(long, default_value = "spirv-as")]
+    spirv_as: PathBuf,
+
+    /// Path to the spirv-val executable, used to validate SPIR-V.
+    #[clap(long, default_value = "spirv-val")]
+    spirv_val: PathBuf,
+
+    /// Path to the glslangValidator executable, used to validate GLSL.
+    #[clap(long, default_value = "glslangValidator")]
+    glslang: PathBuf,
+}
+
+/// Validates shaders using open-source toolchains that are installed locally.
+///
+/// Backends whose tools can't be found are not reported as available.
+struct UnixValidator {
+    tools: Tools,
+    quiet: bool,
+}
+
+impl Validator for UnixValidator {
+    fn backends(&self) -> Vec<Backend> {
+        let mut backends = vec![];
+
+        if is_available(&self.tools.dxc) {
+            backends.push(Backend::Hlsl);
+        }
+
+        if is_available(&self.tools.spirv_as) && is_available(&self.tools.spirv_val) {
+            backends.push(Backend::Spirv);
+        }
+
+        if is_available(&self.tools.glslang) {
+            backends.push(Backend::Glsl);
+        }
+
+        backends
+    }
+
+    fn validate(&self, backend: Backend, source: &str) -> eyre::Result<ValidateResponse> {
+        let response = match backend {
+            Backend::Hlsl => self.validate_hlsl(source)?,
+            Backend::Spirv => self.validate_spirv(source)?,
+            Backend::Glsl => self.validate_glsl(source)?,
+            Backend::Msl => ValidateResponse::Unsupported,
+        };
+
+        if let ValidateResponse::Failure(messages) = &response {
+            if !self.quiet {
+                println!("{messages}");
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+impl UnixValidator {
+    fn validate_hlsl(&self, hlsl: &str) -> eyre::Result<ValidateResponse> {
+        let file = temp_file(".hlsl", hlsl.as_bytes())?;
+
+        let output = Command::new(&self.tools.dxc)
+            .args(["-T", "cs_6_0"])
+            .args(["-E", "main"])
+            .arg(file.path())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()?;
+
+        Ok(response(output))
+    }
+
+    fn validate_spirv(&self, assembly: &str) -> eyre::Result<ValidateResponse> {
+        let file = temp_file(".spvasm", assembly.as_bytes())?;
+        let binary = Builder::new().suffix(".spv").tempfile()?;
+
+        let output = Command::new(&self.tools.spirv_as)
+            .arg(file.path())
+            .arg("-o")
+            .arg(binary.path())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()?;
+
+        if !output.status.success() {
+            return Ok(response(output));
+        }
+
+        let output = Command::new(&self.tools.spirv_val)
+            .arg(binary.path())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()?;
+
+        Ok(response(output))
+    }
+
+    fn validate_glsl(&self, glsl: &str) -> eyre::Result<ValidateResponse> {
+        // glslangValidator determines the shader stage from the file extension
+        let file = temp_file(".comp", glsl.as_bytes())?;
+
+        // Errors are written to stdout rather than stderr
+        let output = Command::new(&self.tools.glslang)
+            .arg(file.path())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()?;
+
+        Ok(response(output))
+    }
+}
+
+pub fn run() -> eyre::Result<()> {
+    let options = Options::parse();
+    server::run(
+        &options.server,
+        UnixValidator {
+            tools: options.tools,
+            quiet: options.server.quiet,
+        },
+    )
+}
+
+fn is_available(tool: &Path) -> bool {
+    Command::new(tool)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+fn temp_file(suffix: &str, contents: &[u8]) -> eyre::Result<NamedTempFile> {
+    let mut file = Builder::new().suffix(suffix).tempfile()?;
+    file.write_all(contents)?;
+    file.flush()?;
+    Ok(file)
+}
+
+fn response(output: Output) -> ValidateResponse {
+    if output.status.success() {
+        return ValidateResponse::Success;
+    }
+
+    let mut messages = String::from_utf8_lossy(&output.stdout).into_owned();
+    messages.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    ValidateResponse::Failure(messages)
+}
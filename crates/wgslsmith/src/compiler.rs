@@ -53,6 +53,34 @@ impl Compiler {
             Compiler::Naga => compile_naga(source, backend),
         }
     }
+
+    pub fn compile_spirv(&self, source: &str) -> eyre::Result<Vec<u32>> {
+        match self {
+            Compiler::Tint => compile_tint_spirv(source),
+            Compiler::Naga => compile_naga_spirv(source),
+        }
+    }
+}
+
+/// Disassembles a SPIR-V binary into its textual form.
+pub fn disassemble_spirv(words: &[u32]) -> eyre::Result<String> {
+    use rspirv::binary::Disassemble;
+    let module = rspirv::dr::load_words(words).map_err(|e| eyre!("invalid spirv binary: {e}"))?;
+    Ok(module.disassemble())
+}
+
+/// Validates a SPIR-V binary by parsing it with naga's SPIR-V frontend and validating the
+/// resulting module.
+pub fn validate_spirv(words: &[u32]) -> eyre::Result<()> {
+    use naga::front::spv;
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+    let bytes = words
+        .iter()
+        .flat_map(|it| it.to_le_bytes())
+        .collect::<Vec<_>>();
+    let module = spv::parse_u8_slice(&bytes, &spv::Options::default())?;
+    Validator::new(ValidationFlags::default(), Capabilities::all()).validate(&module)?;
+    Ok(())
 }
 
 fn validate_naga(source: &str) -> eyre::Result<()> {
@@ -70,7 +98,7 @@ fn validate_tint(source: &str) -> eyre::Result<()> {
 }
 
 fn compile_naga(source: &str, backend: Backend) -> eyre::Result<String> {
-    use naga::back::{hlsl, msl};
+    use naga::back::{hlsl, msl, spv};
     use naga::front::wgsl;
     use naga::valid::{Capabilities, ValidationFlags, Validator};
 
@@ -92,17 +120,41 @@ fn compile_naga(source: &str, backend: Backend) -> eyre::Result<String> {
                 &msl::PipelineOptions::default(),
             )?;
         }
-        Backend::Spirv => todo!(),
+        Backend::Spirv => {
+            let words = spv::write_vec(&module, &validation, &spv::Options::default(), None)?;
+            out = disassemble_spirv(&words)?;
+        }
     }
 
     Ok(out)
 }
 
+fn compile_naga_spirv(source: &str) -> eyre::Result<Vec<u32>> {
+    use naga::back::spv;
+    use naga::front::wgsl;
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+    let module = wgsl::parse_str(&source.replace("@stage(compute)", "@compute"))?;
+    let validation =
+        Validator::new(ValidationFlags::default(), Capabilities::all()).validate(&module)?;
+
+    Ok(spv::write_vec(
+        &module,
+        &validation,
+        &spv::Options::default(),
+        None,
+    )?)
+}
+
 fn compile_tint(source: &str, backend: Backend) -> eyre::Result<String> {
     let out = match backend {
         Backend::Hlsl => tint::compile_shader_to_hlsl(source),
         Backend::Msl => tint::compile_shader_to_msl(source),
-        Backend::Spirv => todo!(),
+        Backend::Spirv => disassemble_spirv(&compile_tint_spirv(source)?)?,
     };
     Ok(out)
 }
+
+fn compile_tint_spirv(source: &str) -> eyre::Result<Vec<u32>> {
+    tint::compile_shader_to_spirv(source).ok_or_else(|| eyre!("tint failed to compile shader"))
+}
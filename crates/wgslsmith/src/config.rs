@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::io;
 use std::path::{Path, PathBuf};
 
+use buffer_check::FloatPolicy;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 use color_eyre::Help;
 use directories::ProjectDirs;
@@ -28,6 +29,8 @@ pub struct Config {
 pub struct Harness {
     pub path: Option<PathBuf>,
     pub remote: Option<String>,
+    /// Policy for comparing floating point values when checking for mismatches.
+    pub float_compare: Option<FloatPolicy>,
 }
 
 #[derive(Default, Deserialize)]
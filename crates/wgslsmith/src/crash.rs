@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+use harness_types::ConfigId;
+use regex::Regex;
+
+static ANSI_ESCAPE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+static FRAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d+:\s+(.+?)(::h[0-9a-f]{16})?$").unwrap());
+static THREAD_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"thread '[^']*'").unwrap());
+static QUOTED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"`[^`]*`|"[^"]*""#).unwrap());
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"0x[0-9a-fA-F]+|[0-9]+").unwrap());
+
+/// A normalised description of a crash, used to group together findings that are likely to be
+/// caused by the same bug.
+#[derive(Debug)]
+pub struct Signature {
+    pub config: Option<ConfigId>,
+    pub message: String,
+    pub frames: Vec<String>,
+}
+
+impl Signature {
+    /// Computes a crash signature from the output of the harness.
+    ///
+    /// `config` should be provided if the harness was restricted to a specific configuration,
+    /// otherwise the failing configuration is taken from the last `executing` line in the output.
+    /// At most `max_frames` stack frames are included, ignoring frames from the standard library
+    /// and the panic machinery.
+    pub fn from_output(output: &str, config: Option<ConfigId>, max_frames: usize) -> Signature {
+        let output = ANSI_ESCAPE.replace_all(output, "");
+        let lines = output.lines().map(str::trim).collect::<Vec<_>>();
+
+        let config = config.or_else(|| {
+            lines
+                .iter()
+                .rev()
+                .find_map(|line| line.strip_prefix("executing "))
+                .and_then(|it| it.trim().parse().ok())
+        });
+
+        let message = match lines.iter().position(|line| line.contains("panicked at")) {
+            Some(index) => {
+                let line = lines[index];
+                // Newer versions of rust print the panic message on the line after the location
+                match lines.get(index + 1) {
+                    Some(next) if line.ends_with(':') => format!("{line} {next}"),
+                    _ => line.to_owned(),
+                }
+            }
+            None => lines
+                .iter()
+                .rev()
+                .find(|line| !line.is_empty())
+                .copied()
+                .unwrap_or_default()
+                .to_owned(),
+        };
+
+        let frames = lines
+            .iter()
+            .filter_map(|line| FRAME.captures(line))
+            .map(|captures| captures[1].to_owned())
+            .filter(|name| !is_runtime_frame(name))
+            .take(max_frames)
+            .map(|name| normalise(&name))
+            .collect();
+
+        Signature {
+            config,
+            message: normalise(&message),
+            frames,
+        }
+    }
+
+    /// Returns a stable hash of the signature, suitable for use as a directory name.
+    pub fn bucket(&self) -> String {
+        format!("{:016x}", fnv1a(self.to_string().as_bytes()))
+    }
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.config {
+            Some(config) => writeln!(f, "config: {config}")?,
+            None => writeln!(f, "config: unknown")?,
+        }
+
+        writeln!(f, "message: {}", self.message)?;
+        writeln!(f, "frames:")?;
+
+        for frame in &self.frames {
+            writeln!(f, "  {frame}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_runtime_frame(name: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "std::",
+        "core::",
+        "alloc::",
+        "<std::",
+        "<core::",
+        "<alloc::",
+        "rust_begin_unwind",
+        "rust_panic",
+        "__rust",
+        "__libc",
+        "_start",
+    ];
+
+    PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Strips details which vary between crashes of the same kind, such as thread names, quoted
+/// identifiers and numbers (which includes line numbers and generated names like `var_12`).
+fn normalise(text: &str) -> String {
+    let text = THREAD_NAME.replace_all(text, "thread '_'");
+    let text = QUOTED.replace_all(&text, "`_`");
+    let text = NUMBER.replace_all(&text, "N");
+
+    text.into_owned()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Tracks the number of saved examples for each crash bucket.
+pub struct Buckets {
+    max_examples: usize,
+    counts: Mutex<HashMap<String, usize>>,
+}
+
+pub struct Record {
+    /// Whether this is the first time the bucket has been seen.
+    pub new: bool,
+    /// Whether the example should be saved, taking into account the cap for the bucket.
+    pub save: bool,
+}
+
+impl Buckets {
+    pub fn new(max_examples: usize) -> Buckets {
+        Buckets {
+            max_examples,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a crash in `bucket`, where `dir` is the directory in which examples for the bucket
+    /// are saved. `save` indicates whether the caller would like to save this example.
+    ///
+    /// Examples left over from previous runs are taken into account when a bucket is first seen.
+    pub fn record(&self, bucket: &str, dir: &Path, save: bool) -> Record {
+        let mut counts = self.counts.lock().unwrap();
+
+        let new = !counts.contains_key(bucket);
+        let count = counts
+            .entry(bucket.to_owned())
+            .or_insert_with(|| count_examples(dir));
+
+        let save = save && *count < self.max_examples;
+        if save {
+            *count += 1;
+        }
+
+        Record { new, save }
+    }
+}
+
+fn count_examples(dir: &Path) -> usize {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// Writes the signature to `signature.txt` in the bucket directory, if it doesn't already exist.
+pub fn write_signature(dir: &Path, signature: &Signature) -> eyre::Result<()> {
+    let path = dir.join("signature.txt");
+    if !path.exists() {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(path, signature.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+executing wgpu:vk:1234
+executing dawn:vk:5678
+thread 'main' panicked at src/codegen.rs:120:9:
+invalid type `var_12` for binding 3
+stack backtrace:
+   0: rust_begin_unwind
+   1: core::panicking::panic_fmt
+   2: tint::writer::Writer::emit_type::h0123456789abcdef
+   3: tint::writer::Writer::emit_var::hfedcba9876543210
+   4: tint::writer::Writer::emit_module::h00000000000000ff
+";
+
+    #[test]
+    fn normalise_strips_varying_details() {
+        assert_eq!(
+            normalise("thread 'worker-3' panicked at src/lib.rs:12:5: value `x_1` is \"bad\""),
+            "thread '_' panicked at src/lib.rs:N:N: value `_` is `_`"
+        );
+
+        assert_eq!(
+            normalise("address 0x7ffd1234 out of range"),
+            "address N out of range"
+        );
+    }
+
+    #[test]
+    fn signature_from_panic() {
+        let signature = Signature::from_output(OUTPUT, None, 2);
+
+        assert_eq!(
+            signature.config.map(|it| it.to_string()).as_deref(),
+            Some("dawn:vk:5678")
+        );
+
+        assert_eq!(
+            signature.message,
+            "thread '_' panicked at src/codegen.rs:N:N: invalid type `_` for binding N"
+        );
+
+        // Runtime frames are skipped and hashes are stripped, before the frame limit is applied
+        assert_eq!(
+            signature.frames,
+            [
+                "tint::writer::Writer::emit_type",
+                "tint::writer::Writer::emit_var",
+            ]
+        );
+    }
+
+    #[test]
+    fn signature_prefers_explicit_config() {
+        let config = "wgpu:dx12:1".parse().unwrap();
+        let signature = Signature::from_output(OUTPUT, Some(config), 0);
+
+        assert_eq!(
+            signature.config.map(|it| it.to_string()).as_deref(),
+            Some("wgpu:dx12:1")
+        );
+        assert!(signature.frames.is_empty());
+    }
+
+    #[test]
+    fn signature_without_panic_uses_last_line() {
+        let output = "\x1b[31merror\x1b[0m: device lost (code 42)\n\n";
+        let signature = Signature::from_output(output, None, 8);
+
+        assert!(signature.config.is_none());
+        assert_eq!(signature.message, "error: device lost (code N)");
+    }
+
+    #[test]
+    fn equivalent_crashes_share_a_bucket() {
+        let other = OUTPUT
+            .replace("'main'", "'worker'")
+            .replace("120:9", "98:13")
+            .replace("var_12", "var_7")
+            .replace("h0123456789abcdef", "h1111111111111111");
+
+        assert_eq!(
+            Signature::from_output(OUTPUT, None, 8).bucket(),
+            Signature::from_output(&other, None, 8).bucket()
+        );
+
+        let different = OUTPUT.replace("emit_type", "emit_expr");
+
+        assert_ne!(
+            Signature::from_output(OUTPUT, None, 8).bucket(),
+            Signature::from_output(&different, None, 8).bucket()
+        );
+    }
+}
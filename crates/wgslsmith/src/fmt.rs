@@ -16,7 +16,7 @@ pub struct Options {
 
 pub fn run(options: Options) -> eyre::Result<()> {
     let source = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&source);
+    let ast = parser::try_parse(&source)?;
 
     struct Output(Box<dyn std::io::Write>);
 
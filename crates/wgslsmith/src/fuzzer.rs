@@ -1,9 +1,13 @@
-use std::io::{self, BufWriter, Write as _};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
+use ast::Module;
+use buffer_check::FloatPolicy;
 use clap::{Parser, ValueEnum};
 use crossbeam_channel::select;
 use crossterm::event::KeyCode;
@@ -11,10 +15,11 @@ use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
-use eyre::eyre;
+use generator::{FeatureSet, Generator, Profile};
 use harness_types::ConfigId;
+use rand::rngs::{OsRng, StdRng};
+use rand::{Rng, SeedableRng};
 use regex::Regex;
-use tap::Tap;
 use time::{format_description, OffsetDateTime, UtcOffset};
 use tui::backend::{Backend, CrosstermBackend};
 use tui::layout::Rect;
@@ -23,7 +28,9 @@ use tui::widgets::{Block, Borders, Paragraph};
 use tui::Terminal;
 
 use crate::config::Config;
+use crate::crash::{self, Buckets, Signature};
 use crate::harness_runner::{self, ExecutionResult, Harness};
+use crate::swarm::{Outcome, SwarmStats};
 
 #[derive(Copy, Clone, ValueEnum)]
 enum SaveStrategy {
@@ -59,6 +66,39 @@ pub struct Options {
     #[clap(long, action)]
     enable_pointers: bool,
 
+    /// Rewrite calls which may cause invalid pointer aliasing, instead of discarding the program.
+    #[clap(long, action)]
+    repair_aliasing: bool,
+
+    #[clap(long, action)]
+    enable_matrices: bool,
+
+    #[clap(long, action)]
+    enable_workgroup_memory: bool,
+
+    #[clap(long, action)]
+    enable_atomics: bool,
+
+    #[clap(long, action)]
+    enable_runtime_arrays: bool,
+
+    /// Fold all intermediate values into a checksum in the output buffer.
+    #[clap(long, action)]
+    checksum: bool,
+
+    /// Randomly disable a subset of generator features for each program (swarm testing).
+    ///
+    /// Crash and mismatch counts for programs with and without each feature are written to
+    /// `swarm.json` in the output directory.
+    #[clap(long, action)]
+    swarm: bool,
+
+    /// Generation profile to use, either the name of a shipped profile or a path to a TOML file.
+    ///
+    /// See `wgslsmith gen --help` for the list of shipped profiles.
+    #[clap(long, action)]
+    profile: Option<Profile>,
+
     /// Specific harness configuration to test.
     #[clap(long, action)]
     config: Option<ConfigId>,
@@ -72,49 +112,101 @@ pub struct Options {
     /// This is mostly for debugging.
     #[clap(long, action)]
     save_failures: bool,
-}
 
-fn gen_shader(options: &Options) -> eyre::Result<String> {
-    let output = Command::new(std::env::current_exe().unwrap())
-        .arg("gen")
-        .args(["--block-min-stmts", "1"])
-        .args(["--block-max-stmts", "1"])
-        .args(["--max-fns", "3"])
-        .tap_mut(|cmd| {
-            if options.enable_pointers {
-                cmd.arg("--enable-pointers");
-            }
-        })
-        .stdout(Stdio::piped())
-        .output()?;
+    /// Number of fuzzing workers to run in parallel.
+    ///
+    /// With more than one worker, mismatches and failures are saved in a separate subdirectory of
+    /// the output directory for each worker. Crashes are always saved in the output directory,
+    /// grouped by their bucket.
+    #[clap(short, long, action, default_value = "1")]
+    jobs: usize,
 
-    if !output.status.success() {
-        return Err(eyre!("wgslsmith command failed"));
-    }
+    /// Number of stack frames to include in crash signatures.
+    ///
+    /// Crashes are grouped into buckets by their signature, which is made up of the normalised
+    /// panic message, the top stack frames and the failing configuration.
+    #[clap(long, action, default_value = "5")]
+    signature_frames: usize,
 
-    Ok(String::from_utf8(output.stdout)?)
-}
+    /// Policy for comparing floating point values when checking for mismatches.
+    ///
+    /// Overrides the `harness.float_compare` setting in the config file.
+    #[clap(long, action)]
+    float_compare: Option<FloatPolicy>,
 
-fn recondition_shader(shader: &str) -> eyre::Result<String> {
-    let mut reconditioner = Command::new(std::env::current_exe().unwrap())
-        .arg("recondition")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
+    /// Maximum number of examples to save for each unique crash.
+    #[clap(long, action, default_value = "10")]
+    max_crashes_per_bucket: usize,
+}
 
-    {
-        let stdin = reconditioner.stdin.take().unwrap();
-        let mut writer = BufWriter::new(stdin);
-        write!(writer, "{shader}")?;
-        writer.flush()?;
+fn gen_options(options: &Options) -> generator::Options {
+    generator::Options {
+        seed: None,
+        debug: false,
+        enabled_fns: vec![],
+        enable_pointers: options.enable_pointers,
+        enable_matrices: options.enable_matrices,
+        enable_workgroup_memory: options.enable_workgroup_memory,
+        enable_atomics: options.enable_atomics,
+        enable_runtime_arrays: options.enable_runtime_arrays,
+        checksum: options.checksum,
+        // Pointer checks and reconditioning are done by the worker, not the generator
+        skip_pointer_checks: false,
+        repair_aliasing: false,
+        log: None,
+        fn_min_stmts: 5,
+        fn_max_stmts: 5,
+        block_min_stmts: 1,
+        block_max_stmts: 1,
+        max_block_depth: 3,
+        max_fns: 3,
+        min_structs: 1,
+        max_structs: 5,
+        min_struct_members: 1,
+        max_struct_members: 5,
+        swarm: options.swarm,
+        disabled_features: vec![],
+        profile: options.profile.clone(),
+        preset: None,
+        recondition: false,
+        flow: false,
+        output: "-".to_owned(),
     }
+}
+
+/// Generates a shader from a random seed, returning the seed, the module, the disabled features
+/// and the buffer initialization data as JSON.
+fn gen_shader(options: &Rc<generator::Options>) -> eyre::Result<(u64, Module, FeatureSet, String)> {
+    let seed = OsRng.gen();
+    let mut rng = StdRng::seed_from_u64(seed);
 
-    let output = reconditioner.wait_with_output()?;
-    if !output.status.success() {
-        return Err(eyre!("reconditioner command failed"));
+    let mut generator = Generator::new(&mut rng, options.clone());
+    let shader = generator.gen_module();
+    let features = generator.features().clone();
+    let metadata = serde_json::to_string(&generator::gen_init_data(&shader, &mut rng)?)?;
+
+    Ok((seed, shader, features, metadata))
+}
+
+fn recondition_shader(shader: &mut Module, repair_aliasing: bool) -> Option<Module> {
+    if repair_aliasing {
+        *shader = reconditioner::analysis::repair(shader.clone())?.ast;
+    } else if !reconditioner::analysis::analyse(shader) {
+        return None;
     }
 
-    Ok(String::from_utf8(output.stdout)?)
+    let shader = shader.clone();
+
+    // A bug in the reconditioner shouldn't bring down the worker
+    panic::catch_unwind(AssertUnwindSafe(|| reconditioner::recondition(shader))).ok()
+}
+
+fn write_shader(shader: &Module) -> String {
+    let mut out = String::new();
+    ast::writer::Writer::default()
+        .write_module(&mut out, shader)
+        .unwrap();
+    out
 }
 
 impl ExecutionResult {
@@ -151,7 +243,15 @@ fn save_shader(
         "[year]-[month]-[day]-[hour]-[minute]-[second]",
     )?)?;
 
-    let out = out.join(&timestamp);
+    // A worker can save more than one shader within a second, so add a suffix to avoid collisions
+    let mut path = out.join(&timestamp);
+    let mut n = 1;
+    while path.exists() {
+        path = out.join(format!("{timestamp}-{n}"));
+        n += 1;
+    }
+
+    let out = path;
 
     std::fs::create_dir_all(&out)?;
 
@@ -170,6 +270,7 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
     unsafe { UTC_OFFSET = Some(UtcOffset::current_local_offset()?) };
 
     let disable_tui = options.disable_tui;
+    let jobs = options.jobs.max(1);
     let harness = match options
         .server
         .as_deref()
@@ -186,28 +287,81 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
         ),
     };
 
-    let (worker_tx, worker_rx) = crossbeam_channel::bounded(1);
+    let (worker_tx, worker_rx) = crossbeam_channel::bounded(jobs);
+
+    let config = Arc::new(config);
+    let options = Arc::new(options);
+    let harness = Arc::new(harness);
+    let buckets = Arc::new(Buckets::new(options.max_crashes_per_bucket));
+    let swarm_stats_path = options.output.join("swarm.json");
+    let mut swarm_stats = options.swarm.then(SwarmStats::default);
+    let mut record_swarm_stats = |result: &WorkerResult| -> eyre::Result<()> {
+        if let (Some(stats), Some(outcome)) = (&mut swarm_stats, result.kind.outcome()) {
+            stats.record(&result.features, outcome);
+            if outcome != Outcome::Success || stats.count() % 100 == 0 {
+                stats.save(&swarm_stats_path)?;
+            }
+        }
+        Ok(())
+    };
+
+    for id in 0..jobs {
+        let config = config.clone();
+        let options = options.clone();
+        let harness = harness.clone();
+        let buckets = buckets.clone();
+        let worker_tx = worker_tx.clone();
+
+        let output = if jobs > 1 {
+            options.output.join(format!("worker-{id}"))
+        } else {
+            options.output.clone()
+        };
 
-    std::thread::spawn(move || {
-        worker(config, options, harness, &mut |result| {
-            worker_tx.send(result).unwrap()
-        })
-        .unwrap()
-    });
+        std::thread::spawn(move || {
+            let result = worker(
+                &config,
+                &options,
+                &harness,
+                &buckets,
+                &output,
+                &mut |result| worker_tx.send(result).unwrap(),
+            );
+
+            // Stop the run rather than silently continuing with fewer workers
+            if let Err(e) = result {
+                let _ = worker_tx.send(WorkerMessage::Error(e));
+            }
+        });
+    }
+
+    drop(worker_tx);
 
     if disable_tui {
         while let Ok(msg) = worker_rx.recv() {
             match msg {
                 WorkerMessage::Log(line) => println!("{line}"),
-                WorkerMessage::Result(result) => println!("saved: {}", result.saved),
+                WorkerMessage::Result(result) => {
+                    record_swarm_stats(&result)?;
+                    println!("saved: {}", result.saved)
+                }
+                WorkerMessage::Error(e) => return Err(e.wrap_err("fuzzing worker failed")),
             }
         }
+
+        Ok(())
     } else {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen)?;
         let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
-        let ui = Arc::new(Mutex::new(Ui::new(terminal, UiState::default())));
+        let ui = Arc::new(Mutex::new(Ui::new(
+            terminal,
+            UiState {
+                jobs,
+                ..UiState::default()
+            },
+        )));
 
         let (input_tx, input_rx) = crossbeam_channel::bounded(1);
 
@@ -215,13 +369,17 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
             input_tx.send(crossterm::event::read().unwrap()).unwrap();
         });
 
-        let on_result = |result: WorkerResult| {
+        let mut on_result = |result: WorkerResult| -> eyre::Result<()> {
+            record_swarm_stats(&result)?;
             let mut ui = ui.lock().unwrap();
             ui.state.total += 1;
             match result.kind {
                 WorkerResultKind::Success => ui.state.success += 1,
-                WorkerResultKind::Crash => {
+                WorkerResultKind::Crash { new_bucket } => {
                     ui.state.crashes += 1;
+                    if new_bucket {
+                        ui.state.unique_crashes += 1;
+                    }
                     if result.saved {
                         ui.state.saved_crashes += 1;
                     }
@@ -237,15 +395,16 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
                     ui.state.failures += 1
                 }
             }
+            Ok(())
         };
 
-        loop {
+        let result = loop {
             ui.lock().unwrap().render()?;
             select! {
                 recv(input_rx) -> msg => {
                     if let crossterm::event::Event::Key(key) = msg? {
                         if let KeyCode::Char('q') = key.code {
-                            break;
+                            break Ok(());
                         }
                     }
 
@@ -254,11 +413,12 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
                 recv(worker_rx) -> msg => {
                     match msg? {
                         WorkerMessage::Log(_line) => {},
-                        WorkerMessage::Result(result) => on_result(result),
+                        WorkerMessage::Result(result) => on_result(result)?,
+                        WorkerMessage::Error(e) => break Err(e.wrap_err("fuzzing worker failed")),
                     }
                 }
             }
-        }
+        };
 
         {
             disable_raw_mode()?;
@@ -266,39 +426,65 @@ pub fn run(config: Config, options: Options) -> eyre::Result<()> {
             execute!(ui.terminal.backend_mut(), LeaveAlternateScreen)?;
             ui.terminal.show_cursor()?;
         }
-    }
 
-    Ok(())
+        result
+    }
 }
 
 enum WorkerMessage {
     Log(String),
     Result(WorkerResult),
+    /// The worker has stopped because of an error.
+    Error(eyre::Report),
 }
 
 struct WorkerResult {
     kind: WorkerResultKind,
+    features: FeatureSet,
     saved: bool,
 }
 
 enum WorkerResultKind {
     Success,
-    Crash,
+    Crash { new_bucket: bool },
     Mismatch,
     // Timeout,
     ReconditionFailure,
     ExecutionFailure,
 }
 
+impl WorkerResultKind {
+    /// The outcome to record in the swarm stats, or `None` if the shader wasn't executed.
+    fn outcome(&self) -> Option<Outcome> {
+        match self {
+            WorkerResultKind::Success => Some(Outcome::Success),
+            WorkerResultKind::Crash { .. } => Some(Outcome::Crash),
+            WorkerResultKind::Mismatch => Some(Outcome::Mismatch),
+            WorkerResultKind::ReconditionFailure | WorkerResultKind::ExecutionFailure => None,
+        }
+    }
+}
+
 fn worker(
-    config: Config,
-    options: Options,
-    harness: Harness,
+    config: &Config,
+    options: &Options,
+    harness: &Harness,
+    buckets: &Buckets,
+    output: &Path,
     on_message: &mut dyn FnMut(WorkerMessage),
 ) -> eyre::Result<()> {
+    let gen_options = Rc::new(gen_options(options));
     loop {
         let mut logger = |line| on_message(WorkerMessage::Log(line));
-        let result = worker_iteration(&config, &options, &harness, &mut logger)?;
+        let result = worker_iteration(
+            config,
+            options,
+            &gen_options,
+            harness,
+            buckets,
+            output,
+            &mut logger,
+        )?;
         on_message(WorkerMessage::Result(result))
     }
 }
@@ -306,29 +492,42 @@ fn worker(
 fn worker_iteration(
     config: &Config,
     options: &Options,
+    gen_options: &Rc<generator::Options>,
     harness: &Harness,
+    buckets: &Buckets,
+    output_dir: &Path,
     logger: &mut dyn FnMut(String),
 ) -> eyre::Result<WorkerResult> {
-    let shader = gen_shader(options)?;
-    let (metadata, shader) = shader
-        .split_once('\n')
-        .ok_or_else(|| eyre!("expected first line of shader to be a JSON metadata comment"))?;
-
-    let metadata = metadata.trim_start_matches("//").trim();
-    let reconditioned = match recondition_shader(shader) {
-        Ok(reconditioned) => reconditioned,
-        Err(_) => {
-            eprintln!("reconditioner command failed, ignoring");
+    let (seed, mut shader, features, metadata) = gen_shader(gen_options)?;
+    let metadata = metadata.as_str();
+
+    // The saved shader should include any calls rewritten to avoid aliasing
+    let reconditioned = match recondition_shader(&mut shader, options.repair_aliasing) {
+        Some(reconditioned) => write_shader(&reconditioned),
+        None => {
+            logger("reconditioner failed, ignoring".to_owned());
             return Ok(WorkerResult {
                 kind: WorkerResultKind::ReconditionFailure,
+                features,
                 saved: false,
             });
         }
     };
 
+    let shader = if options.swarm {
+        format!(
+            "// Seed: {seed}\n// Disabled: {features}\n\n{}",
+            write_shader(&shader)
+        )
+    } else {
+        format!("// Seed: {seed}\n\n{}", write_shader(&shader))
+    };
+    let shader = shader.as_str();
+
     let exec_result = harness_runner::exec_shader(
         harness,
         options.config.clone(),
+        options.float_compare.or(config.harness.float_compare),
         &reconditioned,
         metadata,
         logger,
@@ -339,7 +538,7 @@ fn worker_iteration(
         Err(e) => {
             if options.save_failures {
                 save_shader(
-                    &options.output,
+                    output_dir,
                     shader,
                     &reconditioned,
                     metadata,
@@ -348,49 +547,68 @@ fn worker_iteration(
             }
             return Ok(WorkerResult {
                 kind: WorkerResultKind::ExecutionFailure,
+                features,
                 saved: false,
             });
         }
     };
 
-    let result_kind = match result {
-        ExecutionResult::Success => WorkerResultKind::Success,
-        ExecutionResult::Crash(_) => WorkerResultKind::Crash,
-        ExecutionResult::Mismatch => WorkerResultKind::Mismatch,
-        // ExecutionResult::Timeout => WorkerResultKind::Timeout,
-    };
-
-    let mut output = None;
-    if let ExecutionResult::Crash(out) = &result {
-        output = Some(out.as_str());
-    }
-
     let should_save = result.should_save(
         &options.strategy,
         options.ignore.iter().chain(&config.fuzzer.ignore),
     );
 
-    if should_save {
-        save_shader(&options.output, shader, &reconditioned, metadata, output)?;
-    }
+    let (kind, saved) = match &result {
+        ExecutionResult::Success => (WorkerResultKind::Success, false),
+        ExecutionResult::Crash(output) => {
+            let signature =
+                Signature::from_output(output, options.config.clone(), options.signature_frames);
+            let bucket = signature.bucket();
+            // Buckets are shared between workers, so they aren't saved in the worker's directory
+            let bucket_dir = options.output.join(&bucket);
+            let record = buckets.record(&bucket, &bucket_dir, should_save);
+
+            if record.save {
+                crash::write_signature(&bucket_dir, &signature)?;
+                save_shader(&bucket_dir, shader, &reconditioned, metadata, Some(output))?;
+            }
+
+            (
+                WorkerResultKind::Crash {
+                    new_bucket: record.new,
+                },
+                record.save,
+            )
+        }
+        ExecutionResult::Mismatch => {
+            if should_save {
+                save_shader(output_dir, shader, &reconditioned, metadata, None)?;
+            }
+            (WorkerResultKind::Mismatch, should_save)
+        } // ExecutionResult::Timeout => WorkerResultKind::Timeout,
+    };
 
     Ok(WorkerResult {
-        kind: result_kind,
-        saved: should_save,
+        kind,
+        features,
+        saved,
     })
 }
 
 struct Ui<B: Backend> {
     terminal: Terminal<B>,
     state: UiState,
+    start_time: Instant,
 }
 
 #[derive(Default)]
 struct UiState {
+    jobs: usize,
     total: usize,
     success: usize,
     timeouts: usize,
     crashes: usize,
+    unique_crashes: usize,
     saved_crashes: usize,
     mismatches: usize,
     saved_mismatches: usize,
@@ -399,7 +617,11 @@ struct UiState {
 
 impl<B: Backend> Ui<B> {
     fn new(terminal: Terminal<B>, state: UiState) -> Self {
-        Ui { terminal, state }
+        Ui {
+            terminal,
+            state,
+            start_time: Instant::now(),
+        }
     }
 
     fn render(&mut self) -> eyre::Result<()> {
@@ -411,21 +633,32 @@ impl<B: Backend> Ui<B> {
             }
         }
 
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+
         self.terminal.draw(|f| {
+            let jobs = self.state.jobs;
             let count = self.state.total;
             let success = self.state.success;
             let crashes = self.state.crashes;
+            let unique_crashes = self.state.unique_crashes;
             let saved_crashes = self.state.saved_crashes;
             let mismatches = self.state.mismatches;
             let saved_mismatches = self.state.saved_mismatches;
             let timeouts = self.state.timeouts;
             let failures = self.state.failures;
+            let throughput = if elapsed > 0.0 {
+                count as f64 / elapsed
+            } else {
+                0.0
+            };
 
             #[rustfmt::skip]
             let lines = vec![
+                Spans::from(format!("workers:    {jobs}")),
+                Spans::from(format!("throughput: {throughput:.2} shaders/s")),
                 Spans::from(format!("total:      {count}")),
                 Spans::from(format!("ok:         {success} ({:.2}%)", pc(success, count))),
-                Spans::from(format!("crashes:    {crashes} ({:.2}%)", pc(crashes, count))),
+                Spans::from(format!("crashes:    {crashes} ({:.2}%), unique: {unique_crashes}", pc(crashes, count))),
                 Spans::from(format!("  saved:    {saved_crashes} ({:.2}%)", pc(saved_crashes, crashes))),
                 Spans::from(format!("mismatches: {mismatches} ({:.2}%)", pc(mismatches, count))),
                 Spans::from(format!("  saved:    {saved_mismatches} ({:.2}%)", pc(saved_mismatches, mismatches))),
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use std::process::{Child, Command, ExitStatus, Stdio};
 use std::thread;
 
+use buffer_check::FloatPolicy;
 use eyre::eyre;
 use harness_types::ConfigId;
 use tap::Tap;
@@ -37,16 +38,25 @@ pub enum Harness {
 pub fn exec_shader(
     harness: &Harness,
     config: Option<ConfigId>,
+    float_compare: Option<FloatPolicy>,
     shader: &str,
     metadata: &str,
     mut logger: impl FnMut(String),
 ) -> eyre::Result<ExecutionResult> {
-    exec_shader_impl(harness, config, shader, metadata, &mut logger)
+    exec_shader_impl(
+        harness,
+        config,
+        float_compare,
+        shader,
+        metadata,
+        &mut logger,
+    )
 }
 
 fn exec_shader_impl(
     harness: &Harness,
     config: Option<ConfigId>,
+    float_compare: Option<FloatPolicy>,
     shader: &str,
     metadata: &str,
     logger: &mut dyn FnMut(String),
@@ -64,6 +74,10 @@ fn exec_shader_impl(
         cmd.args(["-c", &config.to_string()]);
     }
 
+    if let Some(float_compare) = float_compare {
+        cmd.args(["--float-compare", &float_compare.to_string()]);
+    }
+
     let mut harness = cmd
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
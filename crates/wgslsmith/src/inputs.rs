@@ -0,0 +1,49 @@
+use clap::{Parser, ValueEnum};
+use common::InputData;
+use eyre::eyre;
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Format {
+    /// Typed values keyed by variable name.
+    Typed,
+    /// Raw byte arrays keyed by `group:binding`.
+    Bytes,
+}
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to the wgsl shader program that the inputs are for.
+    #[clap(action)]
+    shader: String,
+
+    /// Input data to convert, either as a json string or a path to a json file.
+    ///
+    /// If not provided, this will be looked up in the same way as when running the shader.
+    #[clap(action)]
+    input_data: Option<String>,
+
+    /// Format to convert the input data to.
+    #[clap(long, action, default_value = "typed")]
+    to: Format,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let shader = harness_frontend::read_shader_from_path(&options.shader)?;
+    let module = parser::try_parse(&shader)?;
+
+    let input_data =
+        harness_frontend::read_input_data(&options.shader, options.input_data.as_deref())?;
+    let bytes = input_data.to_bytes(&module).map_err(|e| eyre!(e))?;
+
+    let output = match options.to {
+        Format::Typed => {
+            let typed = InputData::from_bytes(&module, &bytes).map_err(|e| eyre!(e))?;
+            serde_json::to_string_pretty(&typed)?
+        }
+        Format::Bytes => serde_json::to_string(&bytes)?,
+    };
+
+    println!("{output}");
+
+    Ok(())
+}
@@ -1,27 +1,27 @@
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 mod compiler;
 mod config;
+mod crash;
 mod fmt;
 mod fuzzer;
 mod harness_runner;
+mod inputs;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 mod reducer;
 mod remote;
+mod swarm;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 mod test;
+mod unrecondition;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 mod validator;
 
 use std::fs;
 use std::path::PathBuf;
-use std::time::Duration;
 
 use clap::Parser;
 use color_eyre::Help;
 use eyre::{eyre, Context};
-use harness_frontend::{ExecutionError, ExecutionEvent};
-use harness_types::ConfigId;
-use reflection_types::PipelineDescription;
 
 #[derive(Parser)]
 struct Options {
@@ -45,14 +45,20 @@ enum Cmd {
     DataRaceCoordinator(coordinator::cli::Options),
     /// Recondition a shader to add safety checks.
     Recondition(reconditioner::cli::Options),
+    /// Remove the safety checks from a reconditioned shader that don't affect its output.
+    Unrecondition(unrecondition::Options),
     /// Add Flow Analysis to a shader.
     Flow(flow::cli::Options),
     /// Insert Undefined Behavour into a shader.
     UB(ub::cli::Options),
     /// Make it parallel!
     Thread(thread::cli::Options),
+    /// Generate variants of a shader that are equivalent modulo its inputs.
+    Emi(emi::cli::Options),
     /// Format a shader.
     Fmt(fmt::Options),
+    /// Convert shader input data between the typed and byte formats.
+    Inputs(inputs::Options),
     Fuzz(fuzzer::Options),
     /// Reduce a shader.
     #[cfg(all(target_family = "unix", feature = "reducer"))]
@@ -116,10 +122,19 @@ fn main() -> eyre::Result<()> {
         Cmd::DataRaceRunner(options) => data_race_runner::cli::run(options),
         Cmd::DataRaceCoordinator(options) => coordinator::cli::run(options),
         Cmd::Recondition(options) => reconditioner::cli::run(options),
+        Cmd::Unrecondition(options) => unrecondition::run(&config, options),
         Cmd::Flow(options) => flow::cli::run(options),
         Cmd::UB(options) => ub::cli::run(options),
         Cmd::Thread(options) => thread::cli::run(options),
+        #[cfg(feature = "harness")]
+        Cmd::Emi(options) => {
+            let executor = harness::cli::Executor::<HarnessHost>::default();
+            emi::cli::run(options, Some(&executor))
+        }
+        #[cfg(not(feature = "harness"))]
+        Cmd::Emi(options) => emi::cli::run(options, None),
         Cmd::Fmt(options) => fmt::run(options),
+        Cmd::Inputs(options) => inputs::run(options),
         Cmd::Fuzz(options) => fuzzer::run(config, options),
         #[cfg(all(target_family = "unix", feature = "reducer"))]
         Cmd::Reduce(options) => reducer::run(config, options),
@@ -146,33 +161,7 @@ fn main() -> eyre::Result<()> {
                     Ok(())
                 }
                 RemoteCmd::Run(options) => {
-                    struct Executor<'a>(&'a str);
-
-                    impl harness_frontend::Executor for Executor<'_> {
-                        fn execute(
-                            &self,
-                            shader: &str,
-                            workgroups: u32,
-                            flow: bool,
-                            pipeline_desc: &PipelineDescription,
-                            configs: &[ConfigId],
-                            timeout: Option<Duration>,
-                            on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
-                        ) -> Result<(), ExecutionError> {
-                            remote::execute(
-                                self.0,
-                                shader.to_owned(),
-                                workgroups,
-                                flow,
-                                pipeline_desc.clone(),
-                                configs.to_owned(),
-                                timeout,
-                                on_event,
-                            )
-                        }
-                    }
-
-                    harness_frontend::cli::run(options, &Executor(address))
+                    harness_frontend::cli::run(options, &remote::RemoteExecutor(address))
                 }
             }
         }
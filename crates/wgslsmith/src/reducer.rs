@@ -2,11 +2,13 @@ use std::ffi::OsStr;
 use std::fs::Permissions;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
 use std::time::Instant;
 use std::{env, thread};
 
 use clap::{Parser, ValueEnum};
 use eyre::{eyre, Context};
+use harness_types::ConfigId;
 use nix::sys::signal::Signal;
 use nix::unistd::Pid;
 use regex::Regex;
@@ -15,6 +17,7 @@ use tap::Tap;
 
 use crate::compiler::{Backend, Compiler};
 use crate::config::Config;
+use crate::test::{self, CrashOptions};
 
 #[derive(ValueEnum, Clone)]
 pub enum ReductionKind {
@@ -76,11 +79,17 @@ pub struct Options {
     #[clap(short, long, action)]
     quiet: bool,
 
+    /// Reducer to use.
+    ///
+    /// Defaults to perses if it is configured in `wgslsmith.toml`, and creduce otherwise. The
+    /// built-in `native` reducer operates directly on the AST and doesn't require any external
+    /// tools.
     #[clap(long, action, action)]
     reducer: Option<Reducer>,
 
     /// This passed to the underlying reducer using the appropriate flag, to set how many threads it
-    /// should use.
+    /// should use. For the built-in reducer, this is the number of interestingness tests to run in
+    /// parallel.
     ///
     /// Can also be set in `wgslsmith.toml`, as `reducer.parallelism`.
     #[clap(long, action)]
@@ -89,6 +98,7 @@ pub struct Options {
 
 #[derive(ValueEnum, Clone, Debug)]
 pub enum Reducer {
+    Native,
     Creduce,
     Cvise,
     Perses,
@@ -118,6 +128,7 @@ impl Reducer {
         }
 
         match self {
+            Reducer::Native => Err(eyre!("native reducer does not run as an external command")),
             Reducer::Creduce => Ok(build_creduce(
                 config.reducer.creduce.path(),
                 shader,
@@ -169,32 +180,50 @@ impl Reducer {
 }
 
 pub fn run(config: Config, options: Options) -> eyre::Result<()> {
-    let pid = std::process::id();
-    std::env::set_var("WGSLREDUCE_PID", pid.to_string());
-
-    let worker = thread::spawn(move || {
-        let result = thread_main(&config, options);
-        nix::sys::signal::kill(Pid::from_raw(pid as i32), Signal::SIGUSR2).unwrap();
-        result
+    let reducer = options.reducer.clone().unwrap_or_else(|| {
+        if config.reducer.perses.jar.is_some() {
+            Reducer::Perses
+        } else {
+            Reducer::Creduce
+        }
     });
 
-    let mut count = 0;
+    // The native reducer runs interestingness tests in-process, so it can count them directly
+    // rather than waiting for signals from the test script
+    let count = if let Reducer::Native = reducer {
+        thread_main(&config, options, reducer)?.unwrap_or_default()
+    } else {
+        let pid = std::process::id();
+        std::env::set_var("WGSLREDUCE_PID", pid.to_string());
+
+        let worker = thread::spawn(move || {
+            let result = thread_main(&config, options, reducer);
+            nix::sys::signal::kill(Pid::from_raw(pid as i32), Signal::SIGUSR2).unwrap();
+            result
+        });
+
+        let mut count = 0;
 
-    for signal in &mut signal_hook::iterator::Signals::new([SIGUSR1, SIGUSR2]).unwrap() {
-        if signal == SIGUSR1 {
-            count += 1;
-        } else if signal == SIGUSR2 {
-            worker.join().unwrap()?;
-            break;
+        for signal in &mut signal_hook::iterator::Signals::new([SIGUSR1, SIGUSR2]).unwrap() {
+            if signal == SIGUSR1 {
+                count += 1;
+            } else if signal == SIGUSR2 {
+                worker.join().unwrap()?;
+                break;
+            }
         }
-    }
+
+        count
+    };
 
     println!("> {count} calls to interestingness test");
 
     Ok(())
 }
 
-fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
+/// Runs the reduction, returning the number of interestingness tests if they were counted
+/// in-process.
+fn thread_main(config: &Config, options: Options, reducer: Reducer) -> eyre::Result<Option<usize>> {
     let shader_path = Path::new(&options.shader);
     if !shader_path.exists() {
         return Err(eyre!("shader at {shader_path:?} does not exist"));
@@ -202,7 +231,7 @@ fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
 
     let shader_path = shader_path.canonicalize()?;
 
-    let input_path = if let Some(input_path) = options.input_data {
+    let input_path = if let Some(input_path) = options.input_data.clone() {
         input_path
     } else {
         let mut try_path = shader_path
@@ -230,7 +259,7 @@ fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
 
     let metadata_path = input_path.canonicalize()?;
 
-    let out_dir = options.output.unwrap_or_else(|| {
+    let out_dir = options.output.clone().unwrap_or_else(|| {
         let out_dir = options.shader.parent().unwrap().join("reduced");
         if out_dir.exists() {
             let mut n = 1;
@@ -246,15 +275,7 @@ fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
         }
     });
 
-    let shader_name = options.shader.file_name().unwrap();
-
-    let reducer = options.reducer.unwrap_or_else(|| {
-        if config.reducer.perses.jar.is_some() {
-            Reducer::Perses
-        } else {
-            Reducer::Creduce
-        }
-    });
+    let shader_name = options.shader.file_name().unwrap().to_owned();
 
     println!("> using reducer: {reducer:?}");
 
@@ -262,18 +283,145 @@ fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
 
     let harness_server = options
         .server
-        .as_deref()
-        .or_else(|| config.default_remote());
+        .clone()
+        .or_else(|| config.default_remote().map(str::to_owned));
 
     let parallelism = options
         .parallelism
         .or(config.reducer.parallelism)
         .unwrap_or(1);
 
+    let start_time = Instant::now();
+
+    let tests = if let Reducer::Native = reducer {
+        let tests = reduce_native(
+            config,
+            &options,
+            &shader_path,
+            &metadata_path,
+            &out_dir.join(&shader_name),
+            harness_server.as_deref(),
+            parallelism,
+        )?;
+
+        Some(tests)
+    } else {
+        run_external(
+            config,
+            options,
+            &reducer,
+            &shader_path,
+            &metadata_path,
+            &out_dir,
+            harness_server.as_deref(),
+            parallelism,
+        )?;
+
+        None
+    };
+
+    let end_time = Instant::now();
+    let duration = end_time - start_time;
+
+    println!("> reducer completed in {}s", duration.as_secs_f64());
+
+    let result_path = out_dir.join(&shader_name).to_str().unwrap().to_owned();
+    // let reconditioned_path = out_dir
+    //     .join("reconditioned.wgsl")
+    //     .to_str()
+    //     .unwrap()
+    //     .to_owned();
+
+    crate::fmt::run(crate::fmt::Options {
+        input: result_path.clone(),
+        output: result_path,
+    })?;
+
+    // crate::reconditioner::run(crate::reconditioner::Options {
+    //     input: result_path,
+    //     output: reconditioned_path,
+    // })?;
+
+    Ok(tests)
+}
+
+fn reduce_native(
+    config: &Config,
+    options: &Options,
+    shader_path: &Path,
+    metadata_path: &Path,
+    result_path: &Path,
+    harness_server: Option<&str>,
+    parallelism: u32,
+) -> eyre::Result<usize> {
+    let source = std::fs::read_to_string(shader_path)?;
+    let metadata = std::fs::read_to_string(metadata_path)?;
+    let module = parser::try_parse(&source)?;
+
+    let harness = test::harness(config, harness_server.map(str::to_owned))?;
+
+    let crash_options = CrashOptions {
+        config: options
+            .config
+            .as_deref()
+            .map(ConfigId::from_str)
+            .transpose()
+            .map_err(|e| eyre!("invalid config: {e}"))?,
+        compiler: options.compiler.clone(),
+        backend: options.backend,
+        regex: options.regex.clone(),
+        no_recondition: options.no_recondition,
+    };
+
+    let is_interesting = |source: &str| {
+        let result = match options.kind {
+            ReductionKind::Crash => {
+                test::reduce_crash(config, &crash_options, source, &metadata, &harness, true)
+            }
+            ReductionKind::Mismatch => {
+                test::reduce_mismatch(config, source, &metadata, &harness, true)
+            }
+        };
+
+        matches!(result, Ok(true))
+    };
+
+    let reduction = reduce::reduce(
+        module,
+        reduce::Options {
+            jobs: parallelism as usize,
+        },
+        is_interesting,
+    )?;
+
+    let mut reduced = String::new();
+
+    ast::writer::Writer::default()
+        .write_module(&mut reduced, &reduction.module)
+        .unwrap();
+
+    std::fs::write(result_path, reduced)?;
+
+    Ok(reduction.tests)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_external(
+    config: &Config,
+    options: Options,
+    reducer: &Reducer,
+    shader_path: &Path,
+    metadata_path: &Path,
+    out_dir: &Path,
+    harness_server: Option<&str>,
+    parallelism: u32,
+) -> eyre::Result<()> {
+    let shader_name = options.shader.file_name().unwrap().to_owned();
+
     let mut cmd = reducer
-        .cmd(config, parallelism, shader_name, "test.sh")?
+        .cmd(config, parallelism, &shader_name, "test.sh")?
         .tap_mut(|cmd| {
-            cmd.current_dir(&out_dir)
+            cmd.current_dir(out_dir)
                 .env("WGSLREDUCE_SHADER_NAME", shader_path.file_name().unwrap())
                 .env("WGSLREDUCE_METADATA_PATH", metadata_path);
 
@@ -309,34 +457,10 @@ fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
         }
     }
 
-    let start_time = Instant::now();
-
     if !cmd.status()?.success() {
         return Err(eyre!("reducer process did not exit successfully"));
     }
 
-    let end_time = Instant::now();
-    let duration = end_time - start_time;
-
-    println!("> reducer completed in {}s", duration.as_secs_f64());
-
-    let result_path = out_dir.join(shader_name).to_str().unwrap().to_owned();
-    // let reconditioned_path = out_dir
-    //     .join("reconditioned.wgsl")
-    //     .to_str()
-    //     .unwrap()
-    //     .to_owned();
-
-    crate::fmt::run(crate::fmt::Options {
-        input: result_path.clone(),
-        output: result_path,
-    })?;
-
-    // crate::reconditioner::run(crate::reconditioner::Options {
-    //     input: result_path,
-    //     output: reconditioned_path,
-    // })?;
-
     Ok(())
 }
 
@@ -352,6 +476,11 @@ fn setup_out_dir(out_dir: &Path, shader: &Path, reducer: &Reducer) -> eyre::Resu
     // Copy over the shader file
     std::fs::copy(shader, out_dir.join(shader.file_name().unwrap()))?;
 
+    // The native reducer doesn't need a test script
+    if let Reducer::Native = reducer {
+        return Ok(());
+    }
+
     // Generate the interestingness test script
     let test_path = out_dir.join("test.sh");
     std::fs::write(&test_path, reducer.gen_test_script())?;
@@ -4,7 +4,7 @@ use std::time::Duration;
 
 use bincode::Decode;
 use eyre::{eyre, Context};
-use harness_frontend::{ExecutionError, ExecutionEvent};
+use harness_frontend::{ExecutionError, ExecutionEvent, Executor};
 use harness_server_types::{ListResponse, Request, RunError, RunMessage, RunRequest};
 use harness_types::ConfigId;
 use reflection_types::PipelineDescription;
@@ -58,6 +58,33 @@ pub fn execute(
     }
 }
 
+/// Executes shaders on the harness server at the given address.
+pub struct RemoteExecutor<'a>(pub &'a str);
+
+impl Executor for RemoteExecutor<'_> {
+    fn execute(
+        &self,
+        shader: &str,
+        workgroups: u32,
+        flow: bool,
+        pipeline_desc: &PipelineDescription,
+        configs: &[ConfigId],
+        timeout: Option<Duration>,
+        on_event: &mut dyn FnMut(ExecutionEvent) -> Result<(), ExecutionError>,
+    ) -> Result<(), ExecutionError> {
+        execute(
+            self.0,
+            shader.to_owned(),
+            workgroups,
+            flow,
+            pipeline_desc.clone(),
+            configs.to_owned(),
+            timeout,
+            on_event,
+        )
+    }
+}
+
 fn req(server: &str, req: Request) -> eyre::Result<TcpStream> {
     let address = SocketAddr::from_str(server)?;
     let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(10))
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use generator::{Feature, FeatureSet};
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Crash,
+    Mismatch,
+}
+
+#[derive(Default, Serialize)]
+struct Counts {
+    total: usize,
+    crashes: usize,
+    mismatches: usize,
+}
+
+impl Counts {
+    fn record(&mut self, outcome: Outcome) {
+        self.total += 1;
+        match outcome {
+            Outcome::Success => {}
+            Outcome::Crash => self.crashes += 1,
+            Outcome::Mismatch => self.mismatches += 1,
+        }
+    }
+}
+
+#[derive(Default, Serialize)]
+struct FeatureCounts {
+    enabled: Counts,
+    disabled: Counts,
+}
+
+/// Counts crashes and mismatches for programs with each feature enabled or disabled.
+///
+/// Comparing the rates for a feature gives an indication of whether it is needed to trigger (or
+/// tends to suppress) a particular kind of failure.
+#[derive(Default, Serialize)]
+pub struct SwarmStats {
+    all: Counts,
+    features: BTreeMap<&'static str, FeatureCounts>,
+}
+
+impl SwarmStats {
+    pub fn record(&mut self, features: &FeatureSet, outcome: Outcome) {
+        self.all.record(outcome);
+
+        for feature in Feature::all() {
+            let counts = self.features.entry(feature.name()).or_default();
+            if features.is_enabled(feature) {
+                counts.enabled.record(outcome);
+            } else {
+                counts.disabled.record(outcome);
+            }
+        }
+    }
+
+    /// Returns the number of programs that have been recorded.
+    pub fn count(&self) -> usize {
+        self.all.total
+    }
+
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ use eyre::eyre;
 use harness_types::ConfigId;
 use regex::Regex;
 
-use crate::compiler::{Backend, Compiler};
+use crate::compiler::{self, Backend, Compiler};
 use crate::config::Config;
 use crate::harness_runner::{ExecutionResult, Harness};
 use crate::reducer::ReductionKind;
@@ -36,19 +36,19 @@ pub struct Options {
 #[derive(Parser)]
 pub struct CrashOptions {
     #[clap(long, action, conflicts_with("compiler"))]
-    config: Option<ConfigId>,
+    pub config: Option<ConfigId>,
 
     #[clap(long, value_enum, action, requires("backend"))]
-    compiler: Option<Compiler>,
+    pub compiler: Option<Compiler>,
 
     #[clap(long, value_enum, action)]
-    backend: Option<Backend>,
+    pub backend: Option<Backend>,
 
     #[clap(long, action, required_if_eq("kind", "crash"))]
-    regex: Option<Regex>,
+    pub regex: Option<Regex>,
 
     #[clap(long, action)]
-    no_recondition: bool,
+    pub no_recondition: bool,
 }
 
 pub fn run(config: &Config, options: Options) -> eyre::Result<()> {
@@ -89,29 +89,24 @@ pub fn run(config: &Config, options: Options) -> eyre::Result<()> {
 
     let metadata = std::fs::read_to_string(&input_path)?;
 
-    let harness = if let Some(server) = options.server {
-        Harness::Remote(server)
-    } else {
-        Harness::Local(
-            config
-                .harness
-                .path
-                .clone()
-                .map(Ok)
-                .unwrap_or_else(std::env::current_exe)?,
-        )
-    };
+    let harness = harness(config, options.server)?;
 
-    match options.kind {
+    let interesting = match options.kind {
         ReductionKind::Crash => reduce_crash(
             config,
-            options.crash_options,
-            source,
-            metadata,
+            &options.crash_options,
+            &source,
+            &metadata,
             &harness,
             options.quiet,
         )?,
-        ReductionKind::Mismatch => reduce_mismatch(source, metadata, &harness, options.quiet)?,
+        ReductionKind::Mismatch => {
+            reduce_mismatch(config, &source, &metadata, &harness, options.quiet)?
+        }
+    };
+
+    if !interesting {
+        return Err(eyre!("shader is not interesting"));
     }
 
     println!("interesting :)");
@@ -119,80 +114,107 @@ pub fn run(config: &Config, options: Options) -> eyre::Result<()> {
     Ok(())
 }
 
-fn reduce_crash(
+/// Returns the harness to execute shaders with, which is either the given server or a local
+/// harness executable.
+pub fn harness(config: &Config, server: Option<String>) -> eyre::Result<Harness> {
+    Ok(if let Some(server) = server {
+        Harness::Remote(server)
+    } else {
+        Harness::Local(
+            config
+                .harness
+                .path
+                .clone()
+                .map(Ok)
+                .unwrap_or_else(std::env::current_exe)?,
+        )
+    })
+}
+
+pub fn reduce_crash(
     config: &Config,
-    options: CrashOptions,
-    source: String,
-    metadata: String,
+    options: &CrashOptions,
+    source: &str,
+    metadata: &str,
     harness: &Harness,
     quiet: bool,
-) -> eyre::Result<()> {
-    let regex = options.regex.unwrap();
+) -> eyre::Result<bool> {
+    let regex = options
+        .regex
+        .as_ref()
+        .ok_or_else(|| eyre!("a regex is required to reduce a crash"))?;
+
     let should_recondition = !options.no_recondition;
 
     let source = if should_recondition {
-        recondition(parser::parse(&source))
+        recondition(parser::try_parse(source)?)
     } else {
-        source
+        source.to_owned()
     };
 
-    let interesting = if let Some(config) = options.config {
+    let interesting = if let Some(config) = options.config.clone() {
         let result =
-            harness_runner::exec_shader(harness, Some(config), &source, &metadata, |line| {
+            harness_runner::exec_shader(harness, Some(config), None, &source, metadata, |line| {
                 if !quiet {
                     println!("{line}");
                 }
             })?;
 
-        eprintln!("{result:?}");
+        if !quiet {
+            eprintln!("{result:?}");
+        }
 
         matches!(result, ExecutionResult::Crash(output) if regex.is_match(&output))
     } else {
-        let compiler = options.compiler.unwrap();
+        let compiler = options.compiler.clone().unwrap();
         let backend = options.backend.unwrap();
-        let compiled = compiler.compile(&source, backend)?;
 
         match backend {
             Backend::Hlsl => {
-                remote_validate(config, &compiled, validator::Backend::Hlsl, &regex, quiet)?
+                let compiled = compiler.compile(&source, backend)?;
+                remote_validate(config, &compiled, validator::Backend::Hlsl, regex, quiet)?
             }
             Backend::Msl => {
-                remote_validate(config, &compiled, validator::Backend::Msl, &regex, quiet)?
+                let compiled = compiler.compile(&source, backend)?;
+                remote_validate(config, &compiled, validator::Backend::Msl, regex, quiet)?
+            }
+            Backend::Spirv => {
+                let compiled = compiler.compile_spirv(&source)?;
+                local_validate_spirv(&compiled, regex, quiet)
             }
-            Backend::Spirv => todo!(),
         }
     };
 
-    if !interesting {
-        return Err(eyre!("shader is not interesting"));
-    }
-
-    Ok(())
+    Ok(interesting)
 }
 
-fn reduce_mismatch(
-    source: String,
-    metadata: String,
+pub fn reduce_mismatch(
+    config: &Config,
+    source: &str,
+    metadata: &str,
     harness: &Harness,
     quiet: bool,
-) -> eyre::Result<()> {
-    let module = parser::parse(&source);
+) -> eyre::Result<bool> {
+    let module = parser::try_parse(source)?;
     let reconditioned = recondition(module);
 
     Compiler::Naga.validate(&reconditioned)?;
     Compiler::Tint.validate(&reconditioned)?;
 
-    let result = harness_runner::exec_shader(harness, None, &reconditioned, &metadata, |line| {
-        if !quiet {
-            println!("{line}");
-        }
-    })?;
-
-    if result != ExecutionResult::Mismatch {
-        return Err(eyre!("shader is not interesting"));
-    }
+    let result = harness_runner::exec_shader(
+        harness,
+        None,
+        config.harness.float_compare,
+        &reconditioned,
+        metadata,
+        |line| {
+            if !quiet {
+                println!("{line}");
+            }
+        },
+    )?;
 
-    Ok(())
+    Ok(result == ExecutionResult::Mismatch)
 }
 
 fn recondition(module: Module) -> String {
@@ -230,7 +252,35 @@ fn remote_validate(
             }
             regex.is_match(&err)
         }
+        validator::ValidateResponse::Unsupported => {
+            let backends = validator::get_backends(server)?;
+            return Err(eyre!(
+                "validation server does not support {backend:?} (available backends: {backends:?})"
+            ));
+        }
     };
 
     Ok(is_interesting)
 }
+
+fn local_validate_spirv(spirv: &[u32], regex: &Regex, quiet: bool) -> bool {
+    if !quiet {
+        println!("[SOURCE]");
+        match compiler::disassemble_spirv(spirv) {
+            Ok(disassembly) => println!("{disassembly}"),
+            Err(e) => println!("{e}"),
+        }
+    }
+
+    match compiler::validate_spirv(spirv) {
+        Ok(()) => false,
+        Err(err) => {
+            let err = format!("{err:#}");
+            if !quiet {
+                println!("-----");
+                println!("{err}");
+            }
+            regex.is_match(&err)
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use clap::Parser;
+use common::InputData;
+use eyre::eyre;
+use harness_frontend::{ExecutionEvent, Executor};
+use harness_types::ConfigId;
+use reconditioner::unrecondition::{self, Site};
+
+use crate::config::Config;
+use crate::remote::RemoteExecutor;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to the reconditioned wgsl shader program (use '-' for stdin).
+    #[clap(action, default_value = "-")]
+    shader: String,
+
+    /// Input data for the shader, either as a json string or a path to a json file.
+    ///
+    /// If not provided, this will be looked up in the same way as when running the shader.
+    #[clap(action)]
+    input_data: Option<String>,
+
+    /// Config to run the shader on, whose output must be unchanged by removing a safety check.
+    #[clap(short, long, action)]
+    config: ConfigId,
+
+    /// Address of harness server to run the shader on, instead of running it locally.
+    #[clap(short, long, action)]
+    server: Option<String>,
+
+    /// Path at which to write output (use '-' for stdout).
+    #[clap(short, long, action, default_value = "-")]
+    output: String,
+
+    /// Timeout in seconds for each execution.
+    ///
+    /// Removing a loop counter may cause the shader to run forever, which will be stopped by the
+    /// timeout.
+    #[clap(long, action, default_value = "10")]
+    timeout: u64,
+
+    /// Number of workgroups.
+    #[clap(long, action, default_value = "1")]
+    workgroups: u32,
+}
+
+pub fn run(config: &Config, options: Options) -> eyre::Result<()> {
+    let shader = harness_frontend::read_shader_from_path(&options.shader)?;
+    let input_data =
+        harness_frontend::read_input_data(&options.shader, options.input_data.as_deref())?;
+    let module = parser::try_parse(&shader)?;
+
+    let server = options
+        .server
+        .as_deref()
+        .map(|server| config.resolve_remote(server));
+
+    // Without a local harness, fall back to the default remote
+    #[cfg(not(feature = "harness"))]
+    let server = server.or_else(|| config.default_remote());
+
+    let executor = executor(server)?;
+    let runner = Runner {
+        executor: executor.as_ref(),
+        input_data: &input_data,
+        config: &options.config,
+        workgroups: options.workgroups,
+        timeout: Duration::from_secs(options.timeout),
+    };
+
+    let expected = runner
+        .run(&shader)?
+        .ok_or_else(|| eyre!("shader failed to execute on {}", options.config))?;
+
+    let sites = unrecondition::sites(&module);
+    let is_unchanged = |remove: &HashSet<Site>| -> bool {
+        let candidate = unrecondition::unrecondition(module.clone(), remove);
+
+        // A candidate that can't be run is treated like one that changes the output, so the
+        // checks that were removed from it are kept
+        match runner.run(&render(&candidate)) {
+            Ok(output) => output.as_ref() == Some(&expected),
+            Err(e) => {
+                eprintln!("failed to run candidate: {e:#}");
+                false
+            }
+        }
+    };
+
+    // Removing everything at once is often enough, and saves running the shader once per site
+    let all = sites.iter().copied().collect::<HashSet<_>>();
+    let removed = if is_unchanged(&all) {
+        all
+    } else {
+        let mut removed = HashSet::new();
+
+        for site in &sites {
+            removed.insert(*site);
+            if !is_unchanged(&removed) {
+                removed.remove(site);
+            }
+        }
+
+        removed
+    };
+
+    eprintln!("removed {} of {} safety checks", removed.len(), sites.len());
+
+    let output = render(&unrecondition::unrecondition(module, &removed));
+
+    match options.output.as_str() {
+        "-" => print!("{output}"),
+        path => std::fs::write(path, output)?,
+    }
+
+    Ok(())
+}
+
+struct Runner<'a> {
+    executor: &'a dyn Executor,
+    input_data: &'a InputData,
+    config: &'a ConfigId,
+    workgroups: u32,
+    timeout: Duration,
+}
+
+impl<'a> Runner<'a> {
+    /// Executes the shader, returning its output buffers or `None` if it failed or timed out.
+    fn run(&self, shader: &str) -> eyre::Result<Option<Vec<Vec<u8>>>> {
+        let (pipeline_desc, _) = harness_frontend::reflect_shader(shader, self.input_data.clone())?;
+
+        let mut output = None;
+
+        self.executor.execute(
+            shader,
+            self.workgroups,
+            false,
+            &pipeline_desc,
+            std::slice::from_ref(self.config),
+            Some(self.timeout),
+            &mut |event| {
+                if let ExecutionEvent::Success(buffers, _) = event {
+                    output = Some(buffers);
+                }
+                Ok(())
+            },
+        )?;
+
+        Ok(output)
+    }
+}
+
+fn executor(server: Option<&str>) -> eyre::Result<Box<dyn Executor + '_>> {
+    match server {
+        Some(server) => Ok(Box::new(RemoteExecutor(server))),
+        #[cfg(feature = "harness")]
+        None => Ok(Box::new(
+            harness::cli::Executor::<crate::HarnessHost>::default(),
+        )),
+        #[cfg(not(feature = "harness"))]
+        None => Err(eyre!(
+            "no remote specified and wgslsmith was built without a local harness"
+        )),
+    }
+}
+
+fn render(module: &ast::Module) -> String {
+    let mut source = String::new();
+
+    ast::writer::Writer::default()
+        .write_module(&mut source, module)
+        .unwrap();
+
+    source
+}
@@ -9,6 +9,12 @@ pub fn validate(server: &str, backend: Backend, source: String) -> eyre::Result<
     req(&mut stream, Request::Validate { backend, source })
 }
 
+pub fn get_backends(server: &str) -> eyre::Result<Vec<Backend>> {
+    let mut stream = TcpStream::connect(server)?;
+    let res: GetBackendsResponse = req(&mut stream, Request::GetBackends)?;
+    Ok(res.backends)
+}
+
 fn req<T: Decode>(stream: &mut TcpStream, req: Request) -> eyre::Result<T> {
     bincode::encode_into_std_write(req, stream, bincode::config::standard())?;
     bincode::decode_from_std_read(stream, bincode::config::standard()).map_err(Into::into)